@@ -78,6 +78,9 @@ impl ControlPanel {
                 .service(endpoints::stats)
                 .service(endpoints::get_config)
                 .service(endpoints::set_config)
+                .service(endpoints::kill_switch)
+                .service(endpoints::kill_switch_status)
+                .service(endpoints::acknowledge_kill_switch)
                 .service(
                     actix_files::Files::new("/", webui_dir)
                         .use_last_modified(true)
@@ -41,3 +41,29 @@ pub(super) async fn set_config(body: web::Bytes, client: DataWebMmbRpcClient) ->
 pub(super) async fn stats(client: DataWebMmbRpcClient) -> impl Responder {
     send_request(client, |client| client.stats().boxed()).await
 }
+
+#[post("/kill_switch")]
+pub(super) async fn kill_switch(body: web::Bytes, client: DataWebMmbRpcClient) -> impl Responder {
+    let reason = String::from_utf8_lossy(&body).into_owned();
+    send_request(client, move |client| {
+        client.kill_switch(reason.clone()).boxed()
+    })
+    .await
+}
+
+#[get("/kill_switch")]
+pub(super) async fn kill_switch_status(client: DataWebMmbRpcClient) -> impl Responder {
+    send_request(client, |client| client.kill_switch_status().boxed()).await
+}
+
+#[post("/kill_switch/acknowledge")]
+pub(super) async fn acknowledge_kill_switch(
+    body: web::Bytes,
+    client: DataWebMmbRpcClient,
+) -> impl Responder {
+    let operator = String::from_utf8_lossy(&body).into_owned();
+    send_request(client, move |client| {
+        client.acknowledge_kill_switch(operator.clone()).boxed()
+    })
+    .await
+}
@@ -1,18 +1,24 @@
 use std::sync::Arc;
 
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use chrono::Duration;
 use futures::future::join_all;
 use itertools::Itertools;
+use mmb_database::postgres_db::events::{DbEvent, Event};
+use mmb_database::storage::Storage;
 use mmb_domain::market::MarketAccountId;
 use mmb_domain::order::snapshot::Amount;
 use mmb_utils::cancellation_token::CancellationToken;
+use mmb_utils::DateTime;
 use mockall_double::double;
 use parking_lot::Mutex;
 
 #[double]
 use crate::balance::manager::balance_manager::BalanceManager;
 #[double]
+use crate::misc::time::time_manager;
+#[double]
 use crate::services::usd_convertion::usd_converter::UsdConverter;
 
 use crate::balance::changes::{
@@ -70,26 +76,54 @@ impl BalanceChangeUsdPeriodicCalculator {
     }
 }
 
+fn parse_balance_changes(
+    events: Vec<DbEvent>,
+    from_date: DateTime,
+) -> Result<Vec<ProfitLossBalanceChange>> {
+    let mut balance_changes = events
+        .into_iter()
+        .map(|event| {
+            serde_json::from_value::<ProfitLossBalanceChange>(event.json)
+                .with_context(|| format!("Failed to parse balance change with id {}", event.id))
+        })
+        .filter_ok(|x| x.change_date >= from_date)
+        .collect::<Result<Vec<_>>>()?;
+
+    balance_changes.sort_by_key(|x| x.change_date);
+
+    Ok(balance_changes)
+}
+
 #[async_trait]
 impl BalanceChangeAccumulator for BalanceChangeUsdPeriodicCalculator {
-    // TODO: fix when DatabaseManager will be added
     async fn load_data(
         &self,
-        // database_manager: DatabaseManager,
-        _cancellation_token: CancellationToken,
-    ) {
-        //             await using var session = databaseManager.Sql;
-
-        //             var fromDate = _dateTimeService.UtcNow - Period;
-        //             var balanceChanges = await session.Set<ProfitLossBalanceChange>()
-        //                 .Where(x => x.DateTime >= fromDate)
-        //                 .OrderBy(x => x.DateTime)
-        //                 .ToListAsync(cancellationToken);
-
-        //             foreach (var balanceChange in balanceChanges)
-        //             {
-        //                 _balanceChangePeriodSelector.Add(balanceChange);
-        //             }
+        storage: &dyn Storage,
+        cancellation_token: CancellationToken,
+    ) -> Result<()> {
+        let from_date = time_manager::now() - self.period();
+
+        // Balance change is saved after it happened, so there are no items with
+        // change_date >= from_date among earlier inserted events
+        let events = tokio::select! {
+            events = storage.load_events_since(ProfitLossBalanceChange::TABLE_NAME, from_date) => events?,
+            _ = cancellation_token.when_cancelled() => return Ok(()),
+        };
+
+        let balance_changes = parse_balance_changes(events, from_date)?;
+
+        log::info!(
+            "Loaded {} balance changes for period {}",
+            balance_changes.len(),
+            self.period()
+        );
+
+        let mut balance_change_period_selector = self.balance_change_period_selector.lock();
+        for balance_change in &balance_changes {
+            balance_change_period_selector.add(balance_change);
+        }
+
+        Ok(())
     }
 
     fn add_balance_change(&self, balance_change: &ProfitLossBalanceChange) {
@@ -98,3 +132,72 @@ impl BalanceChangeAccumulator for BalanceChangeUsdPeriodicCalculator {
             .add(balance_change);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::balance::changes::profit_loss_stopper::test::create_balance_change_by_market_account_id;
+    use chrono::Utc;
+    use mmb_domain::market::{CurrencyPair, ExchangeAccountId};
+    use mmb_domain::order::snapshot::ClientOrderFillId;
+    use rust_decimal_macros::dec;
+
+    fn create_balance_change(
+        usd_balance_change: Amount,
+        change_date: DateTime,
+        client_order_fill_id: ClientOrderFillId,
+    ) -> ProfitLossBalanceChange {
+        let market_account_id = MarketAccountId::new(
+            ExchangeAccountId::new("Binance", 0),
+            CurrencyPair::from_codes("btc".into(), "eth".into()),
+        );
+        create_balance_change_by_market_account_id(
+            usd_balance_change,
+            change_date,
+            client_order_fill_id,
+            market_account_id,
+        )
+    }
+
+    fn to_db_event(id: u64, balance_change: &ProfitLossBalanceChange) -> DbEvent {
+        DbEvent {
+            id,
+            insert_time: Utc::now(),
+            version: 1,
+            json: balance_change.get_json().expect("in test"),
+        }
+    }
+
+    #[test]
+    fn parse_balance_changes_skips_outdated_and_sorts_by_change_date() {
+        let now = Utc::now();
+        let from_date = now - Duration::hours(1);
+
+        let outdated = create_balance_change(dec!(1), now - Duration::hours(2), "1".into());
+        let latest = create_balance_change(dec!(2), now, "2".into());
+        let earlier = create_balance_change(dec!(3), now - Duration::minutes(30), "3".into());
+
+        let events = [&outdated, &latest, &earlier]
+            .into_iter()
+            .enumerate()
+            .map(|(id, x)| to_db_event(id as u64, x))
+            .collect();
+
+        let balance_changes = parse_balance_changes(events, from_date).expect("in test");
+
+        assert_eq!(balance_changes, vec![earlier, latest]);
+    }
+
+    #[test]
+    fn parse_balance_changes_fails_on_broken_json() {
+        let events = vec![DbEvent {
+            id: 1,
+            insert_time: Utc::now(),
+            version: 1,
+            json: serde_json::json!({ "id": "broken" }),
+        }];
+
+        assert!(parse_balance_changes(events, Utc::now()).is_err());
+    }
+}
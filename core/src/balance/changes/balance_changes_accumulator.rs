@@ -1,5 +1,7 @@
+use anyhow::Result;
 use async_trait::async_trait;
 
+use mmb_database::storage::Storage;
 use mmb_utils::cancellation_token::CancellationToken;
 
 use super::profit_loss_balance_change::ProfitLossBalanceChange;
@@ -8,10 +10,10 @@ use super::profit_loss_balance_change::ProfitLossBalanceChange;
 pub(crate) trait BalanceChangeAccumulator {
     fn add_balance_change(&self, balance_change: &ProfitLossBalanceChange);
 
-    // TODO: fix me when DatabaseManager will be implemented
+    /// Restore balance changes saved before restart
     async fn load_data(
         &self,
-        // database_manage: DatabaseManager,
+        storage: &dyn Storage,
         cancellation_token: CancellationToken,
-    );
+    ) -> Result<()>;
 }
@@ -1,6 +1,8 @@
 use std::{sync::Arc, time::Duration};
 
 use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
+use futures::future::join_all;
+use mmb_database::storage::Storage;
 use mmb_domain::order::fill::OrderFill;
 use mmb_domain::order::snapshot::{ClientOrderFillId, OrderSnapshot};
 use mmb_utils::{
@@ -10,6 +12,7 @@ use mmb_utils::{
     DateTime,
 };
 use mockall_double::double;
+use parking_lot::Mutex;
 use tokio::sync::mpsc;
 
 #[double]
@@ -62,13 +65,15 @@ impl BalanceChange {
 
 pub struct BalanceChangesService {
     usd_converter: UsdConverter,
-    rx_event: mpsc::Receiver<BalanceChangeServiceEvent>,
+    rx_event: Mutex<Option<mpsc::Receiver<BalanceChangeServiceEvent>>>,
     tx_event: mpsc::Sender<BalanceChangeServiceEvent>,
     balance_changes_accumulators: Vec<Arc<dyn BalanceChangeAccumulator + Send + Sync>>,
     profit_loss_stopper_service: Arc<ProfitLossStopperService>,
     balance_changes_calculator: BalanceChangesCalculator,
     lifetime_manager: Arc<AppLifetimeManager>,
     event_recorder: Arc<EventRecorder>,
+    /// Source of balance changes saved before restart
+    storage: Option<Arc<dyn Storage>>,
 }
 
 impl BalanceChangesService {
@@ -78,6 +83,7 @@ impl BalanceChangesService {
         usd_converter: UsdConverter,
         lifetime_manager: Arc<AppLifetimeManager>,
         event_recorder: Arc<EventRecorder>,
+        storage: Option<Arc<dyn Storage>>,
     ) -> Arc<Self> {
         let (tx_event, rx_event) = mpsc::channel(20_000);
         let balance_changes_accumulators =
@@ -86,7 +92,7 @@ impl BalanceChangesService {
 
         let this = Arc::new(Self {
             usd_converter,
-            rx_event,
+            rx_event: Mutex::new(Some(rx_event)),
             tx_event,
             balance_changes_accumulators,
             profit_loss_stopper_service,
@@ -95,6 +101,7 @@ impl BalanceChangesService {
             ),
             lifetime_manager: lifetime_manager.clone(),
             event_recorder,
+            storage,
         });
 
         let on_timer_tick = {
@@ -127,17 +134,33 @@ impl BalanceChangesService {
         this
     }
 
-    pub async fn run(&mut self, cancellation_token: CancellationToken) {
-        // TODO: fix me when DatabaseManager/DataRecorder will be implemented
-        //             if (_databaseManager != null)
-        //             {
-        //                 await Task.WhenAll(_balanceChangeAccumulators.Select(x => x.LoadData(_databaseManager, cancellationToken)));
-        //                 await _profitLossStopperService.CheckForLimit(_usdConverter, cancellationToken);
-        //             }
+    pub async fn run(self: Arc<Self>, cancellation_token: CancellationToken) {
+        let mut rx_event = self
+            .rx_event
+            .lock()
+            .take()
+            .expect("BalanceChangesService::run() should be called only once");
+
+        if let Some(storage) = &self.storage {
+            let futures = self
+                .balance_changes_accumulators
+                .iter()
+                .map(|x| x.load_data(storage.as_ref(), cancellation_token.clone()));
+
+            for result in join_all(futures).await {
+                if let Err(err) = result {
+                    log::error!("Failed to load balance changes from database: {err:?}");
+                }
+            }
+
+            self.profit_loss_stopper_service
+                .check_for_limit(&self.usd_converter, cancellation_token.clone())
+                .await;
+        }
 
         loop {
             let new_event = tokio::select! {
-                event = self.rx_event.recv() => event,
+                event = rx_event.recv() => event,
                 _ = cancellation_token.when_cancelled() => return,
             }.expect("BalanceChangesService::run() the event channel is closed but cancellation hasn't been requested");
 
@@ -5,7 +5,7 @@ pub(crate) mod balance_changes_accumulator;
 pub(crate) mod balance_changes_calculator;
 pub(crate) mod balance_changes_service;
 pub(crate) mod profit_balance_changes_calculator;
-pub(crate) mod profit_loss_balance_change;
+pub mod profit_loss_balance_change;
 pub(crate) mod profit_loss_stopper;
 pub(crate) mod profit_loss_stopper_service;
 
@@ -17,9 +17,9 @@ use crate::service_configuration::configuration_descriptor::ConfigurationDescrip
 
 impl_u64_id!(ProfitLossBalanceChangeId);
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(dead_code)]
-pub(crate) struct ProfitLossBalanceChange {
+pub struct ProfitLossBalanceChange {
     pub id: ProfitLossBalanceChangeId,
     pub client_order_fill_id: ClientOrderFillId,
     pub change_date: DateTime,
@@ -15,6 +15,7 @@ use crate::exchanges::general::engine_api::EngineApi;
 #[double]
 use crate::services::usd_convertion::usd_converter::UsdConverter;
 
+use crate::services::kill_switch::{KillSwitch, KillSwitchTrigger};
 use crate::{
     exchanges::exchange_blocker::{BlockReason, BlockType},
     misc::position_helper,
@@ -31,6 +32,8 @@ pub(crate) struct ProfitLossStopper {
     exchange_blocker: Arc<ExchangeBlocker>,
     balance_manager: Option<Arc<Mutex<BalanceManager>>>,
     engine_api: Arc<EngineApi>,
+    /// Stop trading on all exchange accounts if the limit is exceeded
+    kill_switch: Option<Arc<KillSwitch>>,
 }
 
 impl ProfitLossStopper {
@@ -41,6 +44,7 @@ impl ProfitLossStopper {
         exchange_blocker: Arc<ExchangeBlocker>,
         balance_manager: Option<Arc<Mutex<BalanceManager>>>,
         engine_api: Arc<EngineApi>,
+        kill_switch: Option<Arc<KillSwitch>>,
     ) -> Self {
         Self {
             limit,
@@ -49,6 +53,7 @@ impl ProfitLossStopper {
             exchange_blocker,
             balance_manager,
             engine_api,
+            kill_switch,
         }
     }
 
@@ -77,6 +82,19 @@ impl ProfitLossStopper {
         let target_exchange_account_id = self.target_market_account_id.exchange_account_id;
 
         if usd_change <= -self.limit {
+            if let Some(kill_switch) = &self.kill_switch {
+                if !kill_switch.is_triggered() {
+                    let _ = kill_switch.trigger(KillSwitchTrigger::ProfitLoss {
+                        market: format!(
+                            "{}|{}",
+                            target_exchange_account_id, self.target_market_account_id.currency_pair
+                        ),
+                        usd_change,
+                        limit: self.limit,
+                    });
+                }
+            }
+
             let _ = position_helper::close_position_if_needed(
                 &self.target_market_account_id,
                 self.balance_manager.clone(),
@@ -271,6 +289,7 @@ pub(crate) mod test {
             exchange_blocker.clone(),
             Some(balance_manager.clone()),
             exchange,
+            None,
         );
 
         let (mut usd_converter, usd_converter_locker) = UsdConverter::init_mock();
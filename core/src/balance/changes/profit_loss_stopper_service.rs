@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
+use anyhow::Result;
 use async_trait::async_trait;
 use chrono::Duration;
 use futures::future::join_all;
+use mmb_database::storage::Storage;
 use mmb_domain::market::MarketAccountId;
 use mmb_utils::cancellation_token::CancellationToken;
 use mockall_double::double;
@@ -17,6 +19,7 @@ use crate::exchanges::general::engine_api::EngineApi;
 #[double]
 use crate::services::usd_convertion::usd_converter::UsdConverter;
 
+use crate::services::kill_switch::KillSwitch;
 use crate::{
     balance::changes::balance_changes_accumulator::BalanceChangeAccumulator,
     settings::{ProfitLossStopperSettings, TimePeriodKind},
@@ -31,6 +34,7 @@ pub struct ProfitLossStopperService {
     target_market_account_id: MarketAccountId,
     exchange_blocker: Arc<ExchangeBlocker>,
     engine_api: Arc<EngineApi>,
+    kill_switch: Option<Arc<KillSwitch>>,
     profit_loss_stoppers: Vec<ProfitLossStopper>,
     usd_periodic_calculators: Vec<Arc<BalanceChangeUsdPeriodicCalculator>>,
 }
@@ -42,11 +46,13 @@ impl ProfitLossStopperService {
         exchange_blocker: Arc<ExchangeBlocker>,
         balance_manager: Option<Arc<Mutex<BalanceManager>>>,
         engine_api: Arc<EngineApi>,
+        kill_switch: Option<Arc<KillSwitch>>,
     ) -> Self {
         let mut this = Self {
             target_market_account_id,
             exchange_blocker,
             engine_api,
+            kill_switch,
             profit_loss_stoppers: Vec::new(),
             usd_periodic_calculators: Vec::new(),
         };
@@ -76,6 +82,7 @@ impl ProfitLossStopperService {
                 self.exchange_blocker.clone(),
                 balance_manager.clone(),
                 self.engine_api.clone(),
+                self.kill_switch.clone(),
             );
 
             self.usd_periodic_calculators.push(usd_periodic_calculator);
@@ -109,20 +116,17 @@ impl ProfitLossStopperService {
 
 #[async_trait]
 impl BalanceChangeAccumulator for ProfitLossStopperService {
-    // TODO: Fix me when DatabaseManager will be implemented
     async fn load_data(
         &self,
-        // database_manager: DatabaseManager,
+        storage: &dyn Storage,
         cancellation_token: CancellationToken,
-    ) {
-        let futures = self.usd_periodic_calculators.iter().map(|x| {
-            x.load_data(
-                // database_manager: DatabaseManager,
-                cancellation_token.clone(),
-            )
-        });
+    ) -> Result<()> {
+        let futures = self
+            .usd_periodic_calculators
+            .iter()
+            .map(|x| x.load_data(storage, cancellation_token.clone()));
 
-        join_all(futures).await;
+        join_all(futures).await.into_iter().collect()
     }
 
     fn add_balance_change(&self, balance_change: &ProfitLossBalanceChange) {
@@ -169,6 +173,7 @@ mod test {
             Arc::new(ExchangeBlocker::default()),
             None,
             Arc::new(EngineApi::default()),
+            None,
         );
     }
 }
@@ -6,6 +6,7 @@ use crate::balance::changes::balance_changes_service::BalanceChangesService;
 use crate::balance::manager::balance_reservation::BalanceReservation;
 use crate::balance::manager::balances::Balances;
 use crate::balance::manager::position_change::PositionChange;
+use crate::database::events::audit::{AuditEvent, ReservationChange};
 use crate::exchanges::general::currency_pair_to_symbol_converter::CurrencyPairToSymbolConverter;
 use crate::explanation::Explanation;
 use crate::misc::reserve_parameters::ReserveParameters;
@@ -153,6 +154,11 @@ impl BalanceManager {
     pub fn unreserve(&mut self, reservation_id: ReservationId, amount: Amount) -> Result<()> {
         self.balance_reservation_manager
             .unreserve(reservation_id, amount, &None)?;
+        self.save_reservation_change(
+            reservation_id,
+            None,
+            ReservationChange::Unreserved { amount },
+        );
         self.save_balances();
         Ok(())
     }
@@ -166,12 +172,40 @@ impl BalanceManager {
         self.balance_reservation_manager.unreserve(
             reservation_id,
             amount,
-            &Some(client_order_id),
+            &Some(client_order_id.clone()),
         )?;
+        self.save_reservation_change(
+            reservation_id,
+            Some(client_order_id),
+            ReservationChange::Unreserved { amount },
+        );
         self.save_balances();
         Ok(())
     }
 
+    /// Record reservation change to audit log. Clones of `BalanceManager` are used for
+    /// calculations only, so their changes aren't recorded
+    fn save_reservation_change(
+        &self,
+        reservation_id: ReservationId,
+        client_order_id: Option<ClientOrderId>,
+        change: ReservationChange,
+    ) {
+        if self.balance_reservation_manager.is_call_from_clone {
+            return;
+        }
+
+        if let Some(event_recorder) = &self.event_recorder {
+            event_recorder
+                .save(AuditEvent::reservation(
+                    reservation_id,
+                    client_order_id,
+                    change,
+                ))
+                .expect("Failure save reservation audit event");
+        }
+    }
+
     fn save_balances(&mut self) {
         match &self.event_recorder {
             None => {}
@@ -689,6 +723,11 @@ impl BalanceManager {
                             reservation_id,
                             &order_snapshot.header.client_order_id,
                         );
+                    self.save_reservation_change(
+                        reservation_id,
+                        Some(order_snapshot.header.client_order_id.clone()),
+                        ReservationChange::ApprovedPartCanceled,
+                    );
                     self.save_balances();
                 }
             }
@@ -732,6 +771,16 @@ impl BalanceManager {
             .unreserve_expected(reservation_id_1, amount_1, &None);
         self.balance_reservation_manager
             .unreserve_expected(reservation_id_2, amount_2, &None);
+        self.save_reservation_change(
+            reservation_id_1,
+            None,
+            ReservationChange::Unreserved { amount: amount_1 },
+        );
+        self.save_reservation_change(
+            reservation_id_2,
+            None,
+            ReservationChange::Unreserved { amount: amount_2 },
+        );
         self.save_balances();
     }
 
@@ -750,6 +799,11 @@ impl BalanceManager {
                 ))
             });
 
+        self.save_reservation_change(
+            reservation_id,
+            Some(client_order_id.clone()),
+            ReservationChange::Approved { amount },
+        );
         self.save_balances();
     }
 
@@ -768,6 +822,14 @@ impl BalanceManager {
         ) {
             return false;
         }
+        self.save_reservation_change(
+            src_reservation_id,
+            client_order_id.clone(),
+            ReservationChange::Transferred {
+                dst_reservation_id,
+                amount,
+            },
+        );
         self.save_balances();
         true
     }
@@ -784,6 +846,11 @@ impl BalanceManager {
             return false;
         }
 
+        self.save_reservation_change(
+            reservation_id,
+            None,
+            ReservationChange::PriceUpdated { price: new_price },
+        );
         self.save_balances();
         true
     }
@@ -797,6 +864,11 @@ impl BalanceManager {
             .balance_reservation_manager
             .try_reserve(reserve_parameters, explanation)
         {
+            self.save_reservation_change(
+                reservation_id,
+                None,
+                ReservationChange::reserved(self.get_reservation_expected(reservation_id)),
+            );
             self.save_balances();
             return Some(reservation_id);
         }
@@ -808,10 +880,12 @@ impl BalanceManager {
         order1: ReserveParameters,
         order2: ReserveParameters,
     ) -> Option<(ReservationId, ReservationId)> {
+        let orders = [order1, order2];
         let reservations_id = self
             .balance_reservation_manager
-            .try_reserve_multiple(&[order1, order2], &mut None)?;
+            .try_reserve_multiple(&orders, &mut None)?;
         if reservations_id.len() == 2 {
+            self.save_reservations(&reservations_id);
             self.save_balances();
             return Some((reservations_id[0], reservations_id[1]));
         }
@@ -824,16 +898,28 @@ impl BalanceManager {
         order2: ReserveParameters,
         order3: ReserveParameters,
     ) -> Option<(ReservationId, ReservationId, ReservationId)> {
+        let orders = [order1, order2, order3];
         let reservations_id = self
             .balance_reservation_manager
-            .try_reserve_multiple(&[order1, order2, order3], &mut None)?;
+            .try_reserve_multiple(&orders, &mut None)?;
         if reservations_id.len() == 3 {
+            self.save_reservations(&reservations_id);
             self.save_balances();
             return Some((reservations_id[0], reservations_id[1], reservations_id[2]));
         }
         None
     }
 
+    fn save_reservations(&self, reservation_ids: &[ReservationId]) {
+        for &reservation_id in reservation_ids {
+            self.save_reservation_change(
+                reservation_id,
+                None,
+                ReservationChange::reserved(self.get_reservation_expected(reservation_id)),
+            );
+        }
+    }
+
     pub fn can_reserve(
         &self,
         reserve_parameters: &ReserveParameters,
@@ -3,8 +3,9 @@ pub mod balance_manager;
 pub(crate) mod balance_position_by_fill_amount;
 pub mod balance_request;
 pub(crate) mod balance_reservation;
-pub(crate) mod balances;
+pub mod balances;
 pub(crate) mod position_change;
+pub(crate) mod reservations_replay;
 
 #[cfg(test)]
 pub mod tests;
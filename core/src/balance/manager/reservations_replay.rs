@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use mmb_domain::order::snapshot::{Amount, ClientOrderId, Price, ReservationId};
+use mmb_utils::DateTime;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::balance::manager::approved_part::ApprovedPart;
+use crate::balance::manager::balance_request::BalanceRequest;
+use crate::balance::manager::balance_reservation::BalanceReservation;
+use crate::balance::manager::balances::Balances;
+use crate::balance::virtual_balance_holder::VirtualBalanceHolder;
+use crate::database::events::audit::ReservationChange;
+use crate::misc::service_value_tree::ServiceValueTree;
+
+/// Applies reservation changes from audit log to saved `Balances` the same way
+/// `BalanceReservationManager` applied them. Available balance checks are skipped
+/// because recorded changes had been already accepted by the engine
+pub(crate) struct ReservationsReplay {
+    balances: Balances,
+    reservations: HashMap<ReservationId, BalanceReservation>,
+    reserved_amount: ServiceValueTree,
+    virtual_balance_holder: VirtualBalanceHolder,
+}
+
+impl ReservationsReplay {
+    pub fn new(mut balances: Balances) -> Self {
+        let mut virtual_balance_holder = VirtualBalanceHolder::new(HashMap::new());
+        if let Some(virtual_diff_balances) = &balances.virtual_diff_balances {
+            for (request, diff) in virtual_diff_balances.get_as_balances() {
+                virtual_balance_holder.add_balance(&request, diff);
+            }
+        }
+
+        ReservationsReplay {
+            reservations: balances
+                .balance_reservations_by_reservation_id
+                .take()
+                .unwrap_or_default(),
+            reserved_amount: balances.reserved_amount.take().unwrap_or_default(),
+            virtual_balance_holder,
+            balances,
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        time: DateTime,
+        reservation_id: ReservationId,
+        client_order_id: &Option<ClientOrderId>,
+        change: &ReservationChange,
+    ) -> Result<()> {
+        match change {
+            ReservationChange::Reserved { reservation } => {
+                self.reserve(reservation_id, reservation.as_ref().clone())
+            }
+            ReservationChange::Approved { amount } => {
+                let client_order_id = client_order_id
+                    .as_ref()
+                    .context("Approved reservation change without client order id")?;
+                self.approve(time, reservation_id, client_order_id, *amount)?
+            }
+            ReservationChange::Unreserved { amount } => {
+                self.unreserve(reservation_id, client_order_id, *amount)?
+            }
+            ReservationChange::Transferred {
+                dst_reservation_id,
+                amount,
+            } => self.transfer(
+                time,
+                reservation_id,
+                *dst_reservation_id,
+                *amount,
+                client_order_id,
+            )?,
+            ReservationChange::PriceUpdated { price } => {
+                self.update_price(reservation_id, *price)?
+            }
+            ReservationChange::ApprovedPartCanceled => {
+                let client_order_id = client_order_id
+                    .as_ref()
+                    .context("ApprovedPartCanceled reservation change without client order id")?;
+                self.cancel_approved_part(reservation_id, client_order_id)?
+            }
+        }
+
+        self.balances.init_time = time;
+        Ok(())
+    }
+
+    pub fn into_balances(self) -> Balances {
+        let mut balances = self.balances;
+        balances.balance_reservations_by_reservation_id = Some(self.reservations);
+        balances.reserved_amount = Some(self.reserved_amount);
+        balances.virtual_diff_balances = Some(
+            self.virtual_balance_holder
+                .get_virtual_balance_diffs()
+                .clone(),
+        );
+        balances
+    }
+
+    fn reservation_mut(
+        &mut self,
+        reservation_id: ReservationId,
+    ) -> Result<&mut BalanceReservation> {
+        self.reservations
+            .get_mut(&reservation_id)
+            .with_context(|| format!("Can't find reservation {reservation_id}"))
+    }
+
+    /// Recorded reservation already contains reserved amount in `unreserved_amount`,
+    /// so only reserved amount and virtual balance should be updated
+    fn reserve(&mut self, reservation_id: ReservationId, reservation: BalanceReservation) {
+        let request = BalanceRequest::from_reservation(&reservation);
+        self.reserved_amount
+            .add_by_request(&request, reservation.unreserved_amount);
+        self.virtual_balance_holder.add_balance_by_symbol(
+            &request,
+            reservation.symbol.clone(),
+            -reservation.cost,
+            reservation.price,
+        );
+
+        let _ = self.reservations.insert(reservation_id, reservation);
+    }
+
+    fn approve(
+        &mut self,
+        time: DateTime,
+        reservation_id: ReservationId,
+        client_order_id: &ClientOrderId,
+        amount: Amount,
+    ) -> Result<()> {
+        let reservation = self.reservation_mut(reservation_id)?;
+        if reservation.approved_parts.contains_key(client_order_id) {
+            return Ok(());
+        }
+
+        reservation.not_approved_amount -= amount;
+        let _ = reservation.approved_parts.insert(
+            client_order_id.clone(),
+            ApprovedPart::new(time, client_order_id.clone(), amount),
+        );
+        Ok(())
+    }
+
+    fn unreserve(
+        &mut self,
+        reservation_id: ReservationId,
+        client_order_id: &Option<ClientOrderId>,
+        amount: Amount,
+    ) -> Result<()> {
+        let reservation = match self.reservations.get_mut(&reservation_id) {
+            Some(reservation) => reservation,
+            // reservation can be removed by previous unreserving within precision error
+            None if amount.is_zero() => return Ok(()),
+            None => bail!("Can't find reservation {reservation_id} to unreserve {amount}"),
+        };
+
+        let amount_to_unreserve = reservation
+            .symbol
+            .round_to_remove_amount_precision_error(amount);
+        if amount_to_unreserve.is_zero() && !reservation.amount.is_zero() {
+            return Ok(());
+        }
+
+        match client_order_id
+            .as_ref()
+            .and_then(|x| reservation.approved_parts.get_mut(x))
+        {
+            Some(approved_part) => approved_part.unreserved_amount -= amount_to_unreserve,
+            None => reservation.not_approved_amount -= amount_to_unreserve,
+        }
+
+        self.add_reserved_amount(reservation_id, -amount_to_unreserve, true)?;
+
+        let reservation = self.reservation_mut(reservation_id)?;
+        if reservation.unreserved_amount < dec!(0)
+            || reservation.is_amount_within_symbol_margin_error(reservation.unreserved_amount)
+        {
+            let amount_left = reservation.unreserved_amount;
+            if !amount_left.is_zero() {
+                self.add_reserved_amount(reservation_id, -amount_left, true)?;
+            }
+
+            let _ = self.reservations.remove(&reservation_id);
+        }
+
+        Ok(())
+    }
+
+    fn transfer(
+        &mut self,
+        time: DateTime,
+        src_reservation_id: ReservationId,
+        dst_reservation_id: ReservationId,
+        amount: Amount,
+        client_order_id: &Option<ClientOrderId>,
+    ) -> Result<()> {
+        let src_reservation = self.reservation_mut(src_reservation_id)?;
+        let amount_to_move = src_reservation
+            .symbol
+            .round_to_remove_amount_precision_error(amount);
+        let new_src_unreserved_amount = src_reservation.unreserved_amount - amount_to_move;
+        let src_cost_diff = self.update_unreserved_amount_for_transfer(
+            time,
+            src_reservation_id,
+            new_src_unreserved_amount,
+            client_order_id,
+            None,
+        )?;
+
+        let new_dst_unreserved_amount =
+            self.reservation_mut(dst_reservation_id)?.unreserved_amount + amount_to_move;
+        let _ = self.update_unreserved_amount_for_transfer(
+            time,
+            dst_reservation_id,
+            new_dst_unreserved_amount,
+            client_order_id,
+            Some(-src_cost_diff),
+        )?;
+
+        Ok(())
+    }
+
+    /// `target_cost_diff` is `None` for source reservation of transfer,
+    /// so cost diff is proportional to transferred amount
+    fn update_unreserved_amount_for_transfer(
+        &mut self,
+        time: DateTime,
+        reservation_id: ReservationId,
+        new_unreserved_amount: Amount,
+        client_order_id: &Option<ClientOrderId>,
+        target_cost_diff: Option<Decimal>,
+    ) -> Result<Decimal> {
+        let reservation = self.reservation_mut(reservation_id)?;
+        let reservation_amount_diff = new_unreserved_amount - reservation.unreserved_amount;
+        match client_order_id {
+            None => reservation.not_approved_amount += reservation_amount_diff,
+            Some(client_order_id) => match reservation.approved_parts.get_mut(client_order_id) {
+                Some(approved_part) => {
+                    let new_amount = approved_part.unreserved_amount + reservation_amount_diff;
+                    approved_part.unreserved_amount = new_amount;
+                    approved_part.amount += reservation_amount_diff;
+                    if reservation.is_amount_within_symbol_margin_error(new_amount) {
+                        let _ = reservation.approved_parts.remove(client_order_id);
+                    }
+                }
+                None => {
+                    let _ = reservation.approved_parts.insert(
+                        client_order_id.clone(),
+                        ApprovedPart::new(time, client_order_id.clone(), reservation_amount_diff),
+                    );
+                }
+            },
+        }
+
+        self.add_reserved_amount(reservation_id, reservation_amount_diff, false)?;
+
+        let reservation = self.reservation_mut(reservation_id)?;
+        let cost_diff = match target_cost_diff {
+            None => reservation.get_proportional_cost_amount(reservation_amount_diff)?,
+            Some(cost_diff) => cost_diff,
+        };
+
+        let request = BalanceRequest::from_reservation(reservation);
+        let (symbol, price) = (reservation.symbol.clone(), reservation.price);
+        reservation.cost += cost_diff;
+        reservation.amount += reservation_amount_diff;
+        let is_empty = reservation.is_amount_within_symbol_margin_error(new_unreserved_amount);
+
+        self.virtual_balance_holder
+            .add_balance_by_symbol(&request, symbol, -cost_diff, price);
+
+        if is_empty {
+            let _ = self.reservations.remove(&reservation_id);
+        }
+
+        Ok(cost_diff)
+    }
+
+    fn update_price(&mut self, reservation_id: ReservationId, new_price: Price) -> Result<()> {
+        let reservation = self.reservation_mut(reservation_id)?;
+
+        let approved_sum: Decimal = reservation
+            .approved_parts
+            .values()
+            .filter(|approved_part| approved_part.is_canceled)
+            .map(|approved_part| approved_part.unreserved_amount)
+            .sum();
+
+        let new_raw_rest_amount = reservation.amount - approved_sum;
+        let new_rest_amount_in_reservation_currency =
+            reservation.symbol.convert_amount_from_amount_currency_code(
+                reservation.reservation_currency_code,
+                new_raw_rest_amount,
+                new_price,
+            );
+        let not_approved_amount_in_reservation_currency =
+            reservation.convert_in_reservation_currency(reservation.not_approved_amount);
+
+        reservation.price = new_price;
+        let reservation_amount_diff = reservation.symbol.convert_amount_into_amount_currency_code(
+            reservation.reservation_currency_code,
+            new_rest_amount_in_reservation_currency - not_approved_amount_in_reservation_currency,
+            new_price,
+        );
+        // it will be compensated by adding reserved amount
+        reservation.unreserved_amount -= reservation_amount_diff;
+
+        self.add_reserved_amount(reservation_id, reservation_amount_diff, true)?;
+        self.reservation_mut(reservation_id)?.not_approved_amount = new_raw_rest_amount;
+
+        Ok(())
+    }
+
+    fn cancel_approved_part(
+        &mut self,
+        reservation_id: ReservationId,
+        client_order_id: &ClientOrderId,
+    ) -> Result<()> {
+        let reservation = self.reservation_mut(reservation_id)?;
+        let approved_part = reservation
+            .approved_parts
+            .get_mut(client_order_id)
+            .with_context(|| format!("There is no approved part for order {client_order_id}"))?;
+
+        approved_part.is_canceled = true;
+        reservation.not_approved_amount += approved_part.unreserved_amount;
+        Ok(())
+    }
+
+    fn add_reserved_amount(
+        &mut self,
+        reservation_id: ReservationId,
+        amount_diff_in_amount_currency: Amount,
+        update_balance: bool,
+    ) -> Result<()> {
+        let reservation = self.reservation_mut(reservation_id)?;
+        let request = BalanceRequest::from_reservation(reservation);
+
+        if update_balance {
+            let cost = reservation.get_proportional_cost_amount(amount_diff_in_amount_currency)?;
+            let (symbol, price) = (reservation.symbol.clone(), reservation.price);
+            self.virtual_balance_holder
+                .add_balance_by_symbol(&request, symbol, -cost, price);
+        }
+
+        self.reservation_mut(reservation_id)?.unreserved_amount += amount_diff_in_amount_currency;
+        self.reserved_amount
+            .add_by_request(&request, amount_diff_in_amount_currency);
+        Ok(())
+    }
+}
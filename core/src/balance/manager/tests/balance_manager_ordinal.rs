@@ -154,8 +154,11 @@ mod tests {
     use rust_decimal_macros::dec;
 
     use crate::balance::manager::balance_manager::BalanceManager;
+    use crate::balance::manager::balances::Balances;
     use crate::balance::manager::position_change::PositionChange;
+    use crate::balance::manager::reservations_replay::ReservationsReplay;
     use crate::balance::manager::tests::balance_manager_base::BalanceManagerBase;
+    use crate::database::events::audit::ReservationChange;
     use crate::exchanges::general::currency_pair_to_symbol_converter::CurrencyPairToSymbolConverter;
     use crate::misc::reserve_parameters::ReserveParameters;
     use mmb_domain::exchanges::symbol::{Precision, Symbol};
@@ -4384,6 +4387,57 @@ mod tests {
             .can_reserve(&reserve_parameters, &mut None));
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    pub async fn restore_reservations_from_saved_balances() {
+        init_logger_file_named("log.txt");
+        let mut test_object = create_test_obj_by_currency_code(BalanceManagerBase::btc(), dec!(0));
+        let (_, exchanges_by_id) = BalanceManagerOrdinal::create_balance_manager_ctor_parameters();
+
+        let currency_pair_to_symbol_converter = CurrencyPairToSymbolConverter::new(exchanges_by_id);
+
+        let balance_manager = BalanceManager::new(currency_pair_to_symbol_converter.clone(), None);
+
+        let exchange_account_id = test_object.balance_manager_base.exchange_account_id_1;
+
+        let mut balance_map: HashMap<CurrencyCode, Amount> = HashMap::new();
+        balance_map.insert(BalanceManagerBase::btc(), dec!(1));
+
+        BalanceManagerBase::update_balance(
+            &mut *balance_manager.lock(),
+            exchange_account_id,
+            balance_map,
+        );
+
+        let reserve_parameters = test_object.balance_manager_base.create_reserve_parameters(
+            OrderSide::Buy,
+            dec!(1),
+            dec!(0.08),
+        );
+        let reservation_id = balance_manager
+            .lock()
+            .try_reserve(&reserve_parameters, &mut None)
+            .expect("in test");
+
+        let saved_balances =
+            serde_json::to_value(balance_manager.lock().get_balances()).expect("in test");
+
+        test_object
+            .balance_manager_base
+            .set_balance_manager(BalanceManager::new(currency_pair_to_symbol_converter, None));
+
+        let balances: Balances = serde_json::from_value(saved_balances).expect("in test");
+        test_object
+            .balance_manager()
+            .restore_balance_state(&balances, false);
+
+        let balance_manager = test_object.balance_manager();
+        let reservation = balance_manager
+            .get_reservation(reservation_id)
+            .expect("in test");
+        assert_eq!(reservation.amount, dec!(0.08));
+        assert_eq!(reservation.unreserved_amount, dec!(0.08));
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     pub async fn restore_state_ctor() {
         init_logger_file_named("log.txt");
@@ -5319,4 +5373,116 @@ mod tests {
 
         assert_eq!(position, amount_position);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    pub async fn replay_reservation_changes_restores_balances() {
+        init_logger_file_named("log.txt");
+        let test_object = create_test_obj_by_currency_code(BalanceManagerBase::eth(), dec!(5));
+        let reserve_parameters = test_object.balance_manager_base.create_reserve_parameters(
+            OrderSide::Sell,
+            dec!(0.2),
+            dec!(2),
+        );
+        let client_order_id = ClientOrderId::unique_id();
+
+        let snapshot = test_object.balance_manager().get_balances();
+        let mut changes = Vec::new();
+        {
+            let mut balance_manager = test_object.balance_manager();
+
+            let reservation_id_1 = balance_manager
+                .try_reserve(&reserve_parameters, &mut None)
+                .expect("in test");
+            changes.push((
+                reservation_id_1,
+                None,
+                ReservationChange::reserved(
+                    balance_manager.get_reservation_expected(reservation_id_1),
+                ),
+            ));
+
+            let reservation_id_2 = balance_manager
+                .try_reserve(&reserve_parameters, &mut None)
+                .expect("in test");
+            changes.push((
+                reservation_id_2,
+                None,
+                ReservationChange::reserved(
+                    balance_manager.get_reservation_expected(reservation_id_2),
+                ),
+            ));
+
+            balance_manager.approve_reservation(reservation_id_1, &client_order_id, dec!(1));
+            changes.push((
+                reservation_id_1,
+                Some(client_order_id.clone()),
+                ReservationChange::Approved { amount: dec!(1) },
+            ));
+
+            assert!(balance_manager.try_transfer_reservation(
+                reservation_id_2,
+                reservation_id_1,
+                dec!(1),
+                &None
+            ));
+            changes.push((
+                reservation_id_2,
+                None,
+                ReservationChange::Transferred {
+                    dst_reservation_id: reservation_id_1,
+                    amount: dec!(1),
+                },
+            ));
+
+            assert!(balance_manager.try_update_reservation(reservation_id_2, dec!(0.3)));
+            changes.push((
+                reservation_id_2,
+                None,
+                ReservationChange::PriceUpdated { price: dec!(0.3) },
+            ));
+
+            balance_manager
+                .unreserve_by_client_order_id(reservation_id_1, client_order_id.clone(), dec!(1))
+                .expect("in test");
+            changes.push((
+                reservation_id_1,
+                Some(client_order_id.clone()),
+                ReservationChange::Unreserved { amount: dec!(1) },
+            ));
+
+            balance_manager
+                .unreserve(reservation_id_2, dec!(1))
+                .expect("in test");
+            changes.push((
+                reservation_id_2,
+                None,
+                ReservationChange::Unreserved { amount: dec!(1) },
+            ));
+        }
+        let expected = test_object.balance_manager().get_balances();
+
+        let mut replay = ReservationsReplay::new(snapshot.clone());
+        for (reservation_id, client_order_id, change) in &changes {
+            replay
+                .apply(
+                    super::time_manager::now(),
+                    *reservation_id,
+                    client_order_id,
+                    change,
+                )
+                .expect("in test");
+        }
+        let replayed = replay.into_balances();
+
+        let to_json = |balances: &Balances| {
+            serde_json::to_value((
+                &balances.balance_reservations_by_reservation_id,
+                &balances.reserved_amount,
+                &balances.virtual_diff_balances,
+            ))
+            .expect("in test")
+        };
+        assert_ne!(to_json(&snapshot), to_json(&expected));
+        assert_eq!(to_json(&replayed), to_json(&expected));
+    }
 }
@@ -2,6 +2,6 @@ pub(crate) mod balance_position_model;
 pub(crate) mod balance_reservation_manager;
 pub(crate) mod balance_reservation_preset;
 pub(crate) mod balance_reservation_storage;
-pub(crate) mod changes;
+pub mod changes;
 pub mod manager;
 pub(crate) mod virtual_balance_holder;
@@ -1,7 +1,10 @@
+use crate::connectivity::transport::Transport;
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use thiserror::Error;
 use url::Url;
 
+pub mod transport;
 mod websocket;
 mod websocket_connection;
 
@@ -39,11 +42,16 @@ impl Display for WebSocketRole {
 #[derive(Debug, Clone)]
 pub struct WebSocketParams {
     url: Url,
+    transport: Arc<Transport>,
 }
 
 impl WebSocketParams {
     pub fn new(url: Url) -> Self {
-        WebSocketParams { url }
+        Self::with_transport(url, Arc::new(Transport::default()))
+    }
+
+    pub fn with_transport(url: Url, transport: Arc<Transport>) -> Self {
+        WebSocketParams { url, transport }
     }
 }
 
@@ -8,8 +8,9 @@ use std::fmt::Formatter;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use tokio::time::{timeout, timeout_at, Duration, Instant};
-use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::error::UrlError;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{client_async_tls, MaybeTlsStream, WebSocketStream};
 use tokio_util::sync::CancellationToken;
 
 /// Time interval between heartbeat pings are sent
@@ -292,6 +293,23 @@ impl ReaderHandle {
     }
 }
 
+async fn connect(
+    params: &WebSocketParams,
+) -> tungstenite::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let url = &params.url;
+    let host = url
+        .host_str()
+        .ok_or(tungstenite::Error::Url(UrlError::NoHostName))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or(tungstenite::Error::Url(UrlError::UnsupportedUrlScheme))?;
+
+    let local_address = params.transport.next_local_address();
+    let stream = params.transport.connect(local_address, host, port).await?;
+    let (ws_stream, _) = client_async_tls(url.as_str(), stream).await?;
+    Ok(ws_stream)
+}
+
 /// Open WebSocket connection.
 ///
 /// Provided cancellation token can be used to shutdown service futures instantly.
@@ -307,7 +325,7 @@ pub async fn open_connection(
     mpsc::UnboundedSender<Message>,
     mpsc::UnboundedReceiver<String>,
 )> {
-    let (ws_stream, _) = connect_async(params.url.clone())
+    let ws_stream = connect(&params)
         .await
         .map_err(|e| ConnectivityError::FailedToConnect(role, params.url.to_string(), e))?;
 
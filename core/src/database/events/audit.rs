@@ -0,0 +1,324 @@
+use crate::balance::manager::balance_reservation::BalanceReservation;
+use crate::balance::manager::balances::Balances;
+use crate::balance::manager::reservations_replay::ReservationsReplay;
+use crate::database::events::recorder::EventRecorder;
+use crate::exchanges::exchange_blocker::{
+    ExchangeBlocker, ExchangeBlockerEvent, ExchangeBlockerMoment,
+};
+use crate::exchanges::general::handlers::handle_order_filled::FillEvent;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::FutureExt;
+use mmb_database::impl_event;
+use mmb_database::postgres_db::audit::{load_audit_events_between, load_order_audit_events};
+use mmb_database::postgres_db::events::{load_last_event_before, Event};
+use mmb_database::postgres_db::PgPool;
+use mmb_domain::market::ExchangeAccountId;
+use mmb_domain::order::event::OrderEventType;
+use mmb_domain::order::pool::OrderRef;
+use mmb_domain::order::snapshot::{Amount, ClientOrderId, OrderSnapshot, Price, ReservationId};
+use mmb_utils::DateTime;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Append-only record of everything that affects orders and balance reservations.
+/// It allows to explain every order sent by the engine after the fact, because
+/// `OrderStatusHistory` lives in memory only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub time: DateTime,
+    pub record: AuditRecord,
+}
+
+impl_event!(AuditEvent, "audit_events");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AuditRecord {
+    /// Snapshot of the order at the moment of the event
+    Order {
+        exchange_account_id: ExchangeAccountId,
+        event_type: OrderAuditEventType,
+        order: OrderSnapshot,
+    },
+    /// Fill as it was received from exchange before handling
+    Fill {
+        exchange_account_id: ExchangeAccountId,
+        fill: FillEvent,
+    },
+    Reservation {
+        reservation_id: ReservationId,
+        client_order_id: Option<ClientOrderId>,
+        change: ReservationChange,
+    },
+    ExchangeBlocker {
+        exchange_account_id: ExchangeAccountId,
+        reason: String,
+        moment: ExchangeBlockerMoment,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderAuditEventType {
+    CreateOrderSucceeded,
+    CreateOrderFailed,
+    OrderFilled,
+    OrderCompleted,
+    CancelOrderSucceeded,
+    CancelOrderFailed,
+}
+
+impl From<&OrderEventType> for OrderAuditEventType {
+    fn from(event_type: &OrderEventType) -> Self {
+        match event_type {
+            OrderEventType::CreateOrderSucceeded => Self::CreateOrderSucceeded,
+            OrderEventType::CreateOrderFailed => Self::CreateOrderFailed,
+            OrderEventType::OrderFilled { .. } => Self::OrderFilled,
+            OrderEventType::OrderCompleted { .. } => Self::OrderCompleted,
+            OrderEventType::CancelOrderSucceeded => Self::CancelOrderSucceeded,
+            OrderEventType::CancelOrderFailed => Self::CancelOrderFailed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReservationChange {
+    /// Reservation as it was created, so it can be restored by `load_balances_at`
+    Reserved {
+        reservation: Box<BalanceReservation>,
+    },
+    Approved {
+        amount: Amount,
+    },
+    Unreserved {
+        amount: Amount,
+    },
+    Transferred {
+        dst_reservation_id: ReservationId,
+        amount: Amount,
+    },
+    PriceUpdated {
+        price: Price,
+    },
+    ApprovedPartCanceled,
+}
+
+impl ReservationChange {
+    pub(crate) fn reserved(reservation: &BalanceReservation) -> Self {
+        ReservationChange::Reserved {
+            reservation: Box::new(reservation.clone()),
+        }
+    }
+}
+
+impl AuditEvent {
+    fn new(record: AuditRecord) -> Self {
+        AuditEvent {
+            time: Utc::now(),
+            record,
+        }
+    }
+
+    pub fn order(
+        exchange_account_id: ExchangeAccountId,
+        event_type: &OrderEventType,
+        order: &OrderRef,
+    ) -> Self {
+        Self::new(AuditRecord::Order {
+            exchange_account_id,
+            event_type: event_type.into(),
+            order: order.deep_clone(),
+        })
+    }
+
+    pub fn fill(exchange_account_id: ExchangeAccountId, fill: &FillEvent) -> Self {
+        Self::new(AuditRecord::Fill {
+            exchange_account_id,
+            fill: fill.clone(),
+        })
+    }
+
+    pub fn reservation(
+        reservation_id: ReservationId,
+        client_order_id: Option<ClientOrderId>,
+        change: ReservationChange,
+    ) -> Self {
+        Self::new(AuditRecord::Reservation {
+            reservation_id,
+            client_order_id,
+            change,
+        })
+    }
+
+    pub fn exchange_blocker(event: &ExchangeBlockerEvent) -> Self {
+        Self::new(AuditRecord::ExchangeBlocker {
+            exchange_account_id: event.exchange_account_id,
+            reason: event.reason.to_string(),
+            moment: event.moment,
+        })
+    }
+}
+
+/// Save every exchange blocker state change to audit log
+pub fn record_exchange_blocker_events(
+    exchange_blocker: &ExchangeBlocker,
+    event_recorder: Arc<EventRecorder>,
+) {
+    exchange_blocker.register_handler(Box::new(move |event, _| {
+        if let Err(err) = event_recorder.save(AuditEvent::exchange_blocker(&event)) {
+            log::error!("Failed to save exchange blocker audit event: {err:?}");
+        }
+
+        async {}.boxed()
+    }));
+}
+
+/// Load full timeline of the order from audit log: its events, fills and changes of its reservation
+pub async fn load_order_timeline(
+    pool: &PgPool,
+    client_order_id: &ClientOrderId,
+) -> Result<Vec<AuditEvent>> {
+    load_order_audit_events(pool, AuditEvent::TABLE_NAME, client_order_id.as_str())
+        .await
+        .context("loading order audit events")?
+        .into_iter()
+        .map(|event| serde_json::from_value(event.json).context("parsing audit event"))
+        .collect()
+}
+
+/// Load `BalanceManager` state at `time`: the last balances saved before `time`
+/// with reservation changes recorded after them up to `time`.
+/// It can be applied to `BalanceManager` with `restore_balance_state`
+pub async fn load_balances_at(pool: &PgPool, time: DateTime) -> Result<Option<Balances>> {
+    let balances: Balances = match load_last_event_before(pool, Balances::TABLE_NAME, time)
+        .await
+        .context("loading balances")?
+    {
+        None => return Ok(None),
+        Some(event) => serde_json::from_value(event.json).context("parsing balances")?,
+    };
+
+    let reservation_events = load_audit_events_between(
+        pool,
+        AuditEvent::TABLE_NAME,
+        "Reservation",
+        balances.init_time,
+        time,
+    )
+    .await
+    .context("loading reservation audit events")?
+    .into_iter()
+    .map(|event| serde_json::from_value(event.json).context("parsing audit event"))
+    .collect::<Result<Vec<AuditEvent>>>()?;
+
+    replay_reservation_changes(balances, &reservation_events).map(Some)
+}
+
+fn replay_reservation_changes(balances: Balances, events: &[AuditEvent]) -> Result<Balances> {
+    let mut replay = ReservationsReplay::new(balances);
+    for event in events {
+        if let AuditRecord::Reservation {
+            reservation_id,
+            client_order_id,
+            change,
+        } = &event.record
+        {
+            replay
+                .apply(event.time, *reservation_id, client_order_id, change)
+                .with_context(|| {
+                    format!("replaying reservation change {change:?} of {reservation_id}")
+                })?;
+        }
+    }
+
+    Ok(replay.into_balances())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::general::handlers::handle_order_filled::FillAmount;
+    use mmb_domain::market::CurrencyPair;
+    use mmb_domain::order::fill::{EventSourceType, OrderFillType};
+    use mmb_domain::order::snapshot::{OrderSide, OrderStatus, OrderType};
+    use rust_decimal_macros::dec;
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("Binance", 0)
+    }
+
+    #[test]
+    fn order_audit_event_round_trip() {
+        let mut order = OrderSnapshot::with_params(
+            ClientOrderId::unique_id(),
+            OrderType::Limit,
+            None,
+            exchange_account_id(),
+            CurrencyPair::from_codes("eth".into(), "btc".into()),
+            dec!(0.07),
+            dec!(1),
+            OrderSide::Sell,
+            Some(ReservationId::generate()),
+            "StrategyInUnitTests",
+        );
+        order.set_status(OrderStatus::Created, Utc::now());
+        let event = AuditEvent::new(AuditRecord::Order {
+            exchange_account_id: exchange_account_id(),
+            event_type: OrderAuditEventType::CreateOrderSucceeded,
+            order,
+        });
+
+        let json = event.get_json().expect("in test");
+        assert_eq!(json["record"]["type"], "Order");
+
+        let restored: AuditEvent = serde_json::from_value(json).expect("in test");
+        match restored.record {
+            AuditRecord::Order {
+                event_type, order, ..
+            } => {
+                assert_eq!(event_type, OrderAuditEventType::CreateOrderSucceeded);
+                assert_eq!(order.status(), OrderStatus::Created);
+            }
+            record => panic!("Unexpected audit record {record:?}"),
+        }
+    }
+
+    #[test]
+    fn fill_audit_event_round_trip() {
+        let fill = FillEvent {
+            source_type: EventSourceType::WebSocket,
+            trade_id: None,
+            client_order_id: Some(ClientOrderId::unique_id()),
+            exchange_order_id: "100".into(),
+            fill_price: dec!(0.07),
+            fill_amount: FillAmount::Incremental {
+                fill_amount: dec!(0.5),
+                total_filled_amount: None,
+            },
+            order_role: None,
+            commission_currency_code: None,
+            commission_rate: None,
+            commission_amount: None,
+            fill_type: OrderFillType::UserTrade,
+            special_order_data: None,
+            fill_date: None,
+        };
+        let event = AuditEvent::fill(exchange_account_id(), &fill);
+
+        let json = event.get_json().expect("in test");
+        let restored: AuditEvent = serde_json::from_value(json).expect("in test");
+
+        match restored.record {
+            AuditRecord::Fill {
+                exchange_account_id: restored_exchange_account_id,
+                fill: restored_fill,
+            } => {
+                assert_eq!(restored_exchange_account_id, exchange_account_id());
+                assert_eq!(restored_fill.client_order_id, fill.client_order_id);
+                assert_eq!(restored_fill.exchange_order_id, fill.exchange_order_id);
+                assert_eq!(restored_fill.fill_price, fill.fill_price);
+            }
+            record => panic!("Unexpected audit record {record:?}"),
+        }
+    }
+}
@@ -1,15 +1,19 @@
 use crate::database::events::recorder::save_batch;
 use crate::exchanges::timeouts::timeout_manager;
-use anyhow::{Context, Result};
+use crate::settings::EventRecorderSettings;
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDateTime, TimeZone, Utc};
 use itertools::Itertools;
 use mmb_database::postgres_db::events::InsertEvent;
-use mmb_database::postgres_db::PgPool;
+use mmb_database::storage::Storage;
 use mmb_utils::{nothing_to_do, DateTime};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::ffi::OsString;
-use std::fs::{create_dir_all, DirEntry, File};
-use std::io::{BufReader, BufWriter};
+use std::fs::{create_dir_all, DirEntry, File, OpenOptions};
+use std::io::{BufReader, ErrorKind, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{env, fs};
 use tokio::task::spawn_blocking;
@@ -17,6 +21,9 @@ use tokio::task::spawn_blocking;
 const BUFFER_SIZE: usize = 16384;
 const EVENTS_FILE_PREFIX: &str = "events_";
 const NOT_FINISHED_FILED_PREFIX: &str = "writing_yet_";
+const FILE_NAME_DATE_FORMAT: &str = "%Y.%m.%d_%H.%M.%S.%6f";
+const FILE_NAME_TABLE_SEPARATOR: char = '-';
+const QUOTA_WARNING_PERCENT: u64 = 80;
 
 fn get_postponed_events_dir(
     postponed_events_dir_from_settings: Option<PathBuf>,
@@ -66,14 +73,25 @@ impl PostponedEventsFileFormat {
 #[derive(Debug, Clone)]
 pub(crate) struct EventRecorderFallback {
     postponed_events_dir: Arc<Path>,
+    quota_bytes: Option<u64>,
+    table_priorities: Arc<HashMap<String, i32>>,
+    is_quota_warned: Arc<AtomicBool>,
 }
 
 impl EventRecorderFallback {
     /// EventRecorder's fallback handlers
     /// postponed_events_dir: postponed events director from settings if exists
-    pub fn new(postponed_events_dir: Option<PathBuf>) -> Result<Self> {
+    pub fn new(
+        postponed_events_dir: Option<PathBuf>,
+        settings: &EventRecorderSettings,
+    ) -> Result<Self> {
         Ok(Self {
             postponed_events_dir: init_postponed_events_dir(postponed_events_dir)?,
+            quota_bytes: settings
+                .postponed_events_quota_mb
+                .map(|quota_mb| quota_mb * 1024 * 1024),
+            table_priorities: Arc::new(settings.table_priorities.clone()),
+            is_quota_warned: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -82,36 +100,130 @@ impl EventRecorderFallback {
         table_name: String,
         not_written_events: Vec<InsertEvent>,
     ) -> Result<()> {
-        let postponed_events_dir = self.postponed_events_dir.clone();
-        spawn_blocking(move || -> Result<()> {
-            let now = timeout_manager::now();
-
-            let file_names = FileNames::from_date(now);
-            let not_finished_file_path = postponed_events_dir.join(&file_names.not_finished);
-
-            let file = File::create(not_finished_file_path.clone())
-                .context("can't create file for postponed events")?;
-            let mut buf_writer = BufWriter::with_capacity(BUFFER_SIZE, file);
-
-            let file_format = PostponedEventsFileFormat::new(table_name, not_written_events);
-            serde_json::to_writer(&mut buf_writer, &file_format).with_context(|| {
-                format!(
-                    "failed saving postponed events to file `{}`",
-                    not_finished_file_path.display()
-                )
-            })?;
-
-            let finished_file_path = postponed_events_dir.join(&file_names.finished);
-            fs::rename(not_finished_file_path, finished_file_path).with_context(|| {
-                format!(
-                    "can't rename from {} to {}",
-                    file_names.not_finished, file_names.finished,
-                )
-            })?;
-
-            Ok(())
-        })
-        .await??;
+        let fallback = self.clone();
+        spawn_blocking(move || fallback.save_to_file_blocking(table_name, not_written_events))
+            .await?
+    }
+
+    pub(crate) fn save_to_file_blocking(
+        &self,
+        table_name: String,
+        not_written_events: Vec<InsertEvent>,
+    ) -> Result<()> {
+        let events_count = not_written_events.len();
+
+        let file_format = PostponedEventsFileFormat::new(table_name, not_written_events);
+        let data =
+            serde_json::to_vec(&file_format).context("failed serialization of postponed events")?;
+        self.free_disk_quota(&file_format.table_name, events_count, data.len() as u64)?;
+
+        let (file_names, mut file) = self.create_not_finished_file(&file_format.table_name)?;
+        let not_finished_file_path = self.postponed_events_dir.join(&file_names.not_finished);
+        file.write_all(&data).with_context(|| {
+            format!(
+                "failed saving postponed events to file `{}`",
+                not_finished_file_path.display()
+            )
+        })?;
+
+        let finished_file_path = self.postponed_events_dir.join(&file_names.finished);
+        fs::rename(not_finished_file_path, finished_file_path).with_context(|| {
+            format!(
+                "can't rename from {} to {}",
+                file_names.not_finished, file_names.finished,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Batches of several tables can be saved at the same microsecond, so saving time is shifted
+    /// until file names become unique
+    fn create_not_finished_file(&self, table_name: &str) -> Result<(FileNames, File)> {
+        let mut time = timeout_manager::now();
+        loop {
+            let file_names = FileNames::new(time, table_name);
+            if !self
+                .postponed_events_dir
+                .join(&file_names.finished)
+                .exists()
+            {
+                let path = self.postponed_events_dir.join(&file_names.not_finished);
+                match OpenOptions::new().write(true).create_new(true).open(path) {
+                    Ok(file) => return Ok((file_names, file)),
+                    Err(err) if err.kind() == ErrorKind::AlreadyExists => nothing_to_do(),
+                    Err(err) => {
+                        return Err(err).context("can't create file for postponed events");
+                    }
+                }
+            }
+            time += chrono::Duration::microseconds(1);
+        }
+    }
+
+    fn priority(&self, table_name: &str) -> i32 {
+        self.table_priorities.get(table_name).copied().unwrap_or(0)
+    }
+
+    /// Removes the oldest files of tables with lower priority if a new file doesn't fit to quota
+    fn free_disk_quota(&self, table_name: &str, events_count: usize, size: u64) -> Result<()> {
+        let quota = match self.quota_bytes {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+
+        let mut files = fs::read_dir(&self.postponed_events_dir)?
+            .filter_map(select_events_file_names)
+            .map(|file_name| {
+                let path = self.postponed_events_dir.join(&file_name);
+                let file_size = fs::metadata(&path).map_or(0, |x| x.len());
+                (path, file_name, file_size)
+            })
+            .collect_vec();
+        let mut used = files.iter().map(|(_, _, file_size)| file_size).sum::<u64>();
+
+        let is_near_quota = (used + size) * 100 > quota * QUOTA_WARNING_PERCENT;
+        if !self.is_quota_warned.swap(is_near_quota, Ordering::Relaxed) && is_near_quota {
+            log::warn!(
+                "Postponed events files use {used} bytes of disk quota {quota} bytes in {}",
+                self.postponed_events_dir.display()
+            );
+        }
+
+        if used + size <= quota {
+            return Ok(());
+        }
+
+        log::error!(
+            "Postponed events files exceed disk quota {quota} bytes in {}",
+            self.postponed_events_dir.display()
+        );
+
+        // file names contain saving time, so the oldest files are first
+        files.sort();
+        let priority = self.priority(table_name);
+        for (path, file_name, file_size) in files {
+            if used + size <= quota {
+                break;
+            }
+
+            // Files saved by previous versions have no table name and get default priority
+            let file_name = file_name.to_string_lossy();
+            let file_table_name = FileNames::parse_table_name(&file_name).unwrap_or_default();
+            if self.priority(file_table_name) < priority {
+                fs::remove_file(&path)
+                    .with_context(|| format!("can't remove file {}", path.display()))?;
+                used -= file_size;
+                log::error!(
+                    "Removed postponed events file {} of table {file_table_name} because of disk quota",
+                    path.display()
+                );
+            }
+        }
+
+        if used + size > quota {
+            bail!("Disk quota {quota} bytes for postponed events is exceeded, {events_count} events of table {table_name} are dropped");
+        }
 
         Ok(())
     }
@@ -131,7 +243,7 @@ impl EventRecorderFallback {
 
     pub(crate) async fn try_restore_to_db_postponed_events(
         &self,
-        pool: &PgPool,
+        storage: &dyn Storage,
         file_names: &[OsString],
     ) {
         for file_name in file_names {
@@ -148,7 +260,7 @@ impl EventRecorderFallback {
                 }
             };
 
-            match save_batch(pool, &table_name, events, self).await {
+            match save_batch(storage, &table_name, events, self).await {
                 Err(err) => log::error!("failed resaving batch of events to db: {err}"),
                 Ok(()) => tokio::fs::remove_file(file_path)
                     .await
@@ -160,21 +272,83 @@ impl EventRecorderFallback {
     }
 }
 
+/// Events which were saved to file because they weren't saved to database
+#[derive(Debug)]
+pub struct PostponedEvents {
+    pub file_name: String,
+    pub saving_time: DateTime,
+    pub table_name: String,
+    pub events: Vec<InsertEvent>,
+}
+
+/// Read all finished postponed events files in directory ordered by saving time
+pub fn read_postponed_events(postponed_events_dir: &Path) -> Result<Vec<PostponedEvents>> {
+    let read_dir = fs::read_dir(postponed_events_dir).with_context(|| {
+        format!(
+            "can't read postponed events dir {}",
+            postponed_events_dir.display()
+        )
+    })?;
+
+    let mut postponed_events = Vec::new();
+    for file_name in read_dir.filter_map(select_events_file_names) {
+        let path = postponed_events_dir.join(&file_name);
+        let saving_time = FileNames::parse_date(&file_name.to_string_lossy())
+            .with_context(|| format!("can't parse time of file {}", path.display()))?;
+
+        let file = File::open(&path)
+            .with_context(|| format!("can't open postponed events file {}", path.display()))?;
+        let PostponedEventsFileFormat {
+            table_name, events, ..
+        } = serde_json::from_reader(BufReader::with_capacity(BUFFER_SIZE, file))
+            .with_context(|| format!("can't read postponed events file {}", path.display()))?;
+
+        postponed_events.push(PostponedEvents {
+            file_name: file_name.to_string_lossy().into_owned(),
+            saving_time,
+            table_name,
+            events,
+        });
+    }
+    postponed_events.sort_by_key(|x| x.saving_time);
+
+    Ok(postponed_events)
+}
+
 struct FileNames {
     not_finished: String,
     finished: String,
 }
 
 impl FileNames {
-    fn from_date(now: DateTime) -> FileNames {
-        let formatted_datetime = now.format("%Y.%m.%d_%H.%M.%S.%6f");
+    /// Table name is kept in file name, so quota can be checked without reading files
+    fn new(now: DateTime, table_name: &str) -> FileNames {
+        let formatted_datetime = now.format(FILE_NAME_DATE_FORMAT);
+        let finished = format!(
+            "{EVENTS_FILE_PREFIX}{formatted_datetime}{FILE_NAME_TABLE_SEPARATOR}{table_name}"
+        );
         FileNames {
-            not_finished: format!(
-                "{NOT_FINISHED_FILED_PREFIX}{EVENTS_FILE_PREFIX}{formatted_datetime}"
-            ),
-            finished: format!("{EVENTS_FILE_PREFIX}{formatted_datetime}"),
+            not_finished: format!("{NOT_FINISHED_FILED_PREFIX}{finished}"),
+            finished,
         }
     }
+
+    fn parse_date(finished_file_name: &str) -> Result<DateTime> {
+        let formatted_datetime = finished_file_name
+            .strip_prefix(EVENTS_FILE_PREFIX)
+            .context("unexpected prefix of events file")?
+            .split(FILE_NAME_TABLE_SEPARATOR)
+            .next()
+            .unwrap_or_default();
+        let datetime = NaiveDateTime::parse_from_str(formatted_datetime, FILE_NAME_DATE_FORMAT)?;
+        Ok(Utc.from_utc_datetime(&datetime))
+    }
+
+    fn parse_table_name(finished_file_name: &str) -> Option<&str> {
+        finished_file_name
+            .split_once(FILE_NAME_TABLE_SEPARATOR)
+            .map(|(_, table_name)| table_name)
+    }
 }
 
 fn select_events_file_names(entry: std::io::Result<DirEntry>) -> Option<OsString> {
@@ -220,19 +394,21 @@ async fn load_from_file(path: PathBuf) -> Result<PostponedEventsFileFormat> {
 #[cfg(test)]
 mod tests {
     use crate::database::events::recorder::fallback::{
-        load_from_file, EventRecorderFallback, PostponedEventsFileFormat,
+        load_from_file, read_postponed_events, EventRecorderFallback, FileNames,
+        PostponedEventsFileFormat,
     };
-    use bb8_postgres::bb8::PooledConnection;
-    use bb8_postgres::PostgresConnectionManager;
-    use chrono::Utc;
+    use crate::settings::EventRecorderSettings;
+    use chrono::{TimeZone, Utc};
     use mmb_database::impl_event;
     use mmb_database::postgres_db::events::{Event, InsertEvent};
     use mmb_database::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use mmb_database::postgres_db::PgConnection;
     use mmb_utils::DateTime;
     use scopeguard::defer;
     use serde::{Deserialize, Serialize};
-    use std::fs;
-    use tokio_postgres::NoTls;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::{env, fs};
 
     const TABLE_NAME: &str = "fallback_events";
 
@@ -255,9 +431,7 @@ mod tests {
         pool_mutex
     }
 
-    async fn recreate_table<'a>(
-        connection: &'a PooledConnection<'a, PostgresConnectionManager<NoTls>>,
-    ) {
+    async fn recreate_table(connection: &PgConnection<'_>) {
         let sql = include_str!(
             "../../../../../mmb_database/src/postgres_db/sql/create_or_truncate_table.sql"
         )
@@ -272,7 +446,8 @@ mod tests {
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn save_files_on_fallback() {
         // arrange
-        let fallback = EventRecorderFallback::new(None).expect("in test");
+        let fallback =
+            EventRecorderFallback::new(None, &EventRecorderSettings::default()).expect("in test");
         defer! {
             fs::remove_dir_all(fallback.clone().postponed_events_dir).expect("clear postponed events dir");
         };
@@ -315,16 +490,24 @@ mod tests {
         let expected = PostponedEventsFileFormat {
             version: 1,
             table_name: TABLE_NAME.to_string(),
-            events: vec![test_event_data],
+            events: vec![test_event_data.clone()],
         };
         pretty_assertions::assert_eq!(file_format, expected);
+
+        let postponed_events =
+            read_postponed_events(&fallback.postponed_events_dir).expect("in test");
+        assert_eq!(postponed_events.len(), 1);
+        assert_eq!(postponed_events[0].table_name, TABLE_NAME);
+        assert_eq!(postponed_events[0].events, vec![test_event_data]);
+        assert!(postponed_events[0].saving_time <= Utc::now());
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn restore_postponed_events_in_db() {
         let pool_mutex = init_test().await;
 
-        let fallback = EventRecorderFallback::new(None).expect("in test");
+        let fallback =
+            EventRecorderFallback::new(None, &EventRecorderSettings::default()).expect("in test");
         defer! {
             fs::remove_dir_all(fallback.clone().postponed_events_dir).expect("clear postponed events dir");
         };
@@ -358,7 +541,7 @@ mod tests {
             .expect("in test");
 
         fallback
-            .try_restore_to_db_postponed_events(&pool_mutex.pool, &file_names)
+            .try_restore_to_db_postponed_events(pool_mutex.storage().as_ref(), &file_names)
             .await;
 
         // check saved event to db
@@ -372,4 +555,77 @@ mod tests {
         let json: serde_json::Value = rows[0].get("json");
         pretty_assertions::assert_eq!(json, test_event_data.json);
     }
+
+    #[test]
+    fn parse_file_names() {
+        let time = Utc.ymd(2022, 10, 10).and_hms(12, 0, 0);
+        let file_names = FileNames::new(time, "fills");
+
+        assert_eq!(
+            file_names.finished,
+            "events_2022.10.10_12.00.00.000000-fills"
+        );
+        assert_eq!(
+            FileNames::parse_date(&file_names.finished).expect("in test"),
+            time
+        );
+        assert_eq!(
+            FileNames::parse_table_name(&file_names.finished),
+            Some("fills")
+        );
+
+        let legacy_file_name = "events_2022.10.10_12.00.00.000000";
+        assert_eq!(
+            FileNames::parse_date(legacy_file_name).expect("in test"),
+            time
+        );
+        assert_eq!(FileNames::parse_table_name(legacy_file_name), None);
+    }
+
+    #[test]
+    fn remove_files_of_tables_with_lower_priority_when_quota_exceeded() {
+        let settings = EventRecorderSettings {
+            table_priorities: HashMap::from([("fills".to_owned(), 1)]),
+            ..Default::default()
+        };
+        let dir = env::temp_dir().join("postponed_events_disk_quota");
+        let mut fallback =
+            EventRecorderFallback::new(Some(dir.clone()), &settings).expect("in test");
+        defer! {
+            fs::remove_dir_all(&dir).expect("clear postponed events dir");
+        };
+
+        let events = vec![InsertEvent {
+            version: 1,
+            json: json!({ "price": 1 }),
+        }];
+        let file_size = serde_json::to_vec(&PostponedEventsFileFormat::new(
+            "fills".to_owned(),
+            events.clone(),
+        ))
+        .expect("in test")
+        .len() as u64;
+        fallback.quota_bytes = Some(file_size * 5 / 2);
+        let save = |table_name: &str| {
+            fallback.save_to_file_blocking(table_name.to_owned(), events.clone())
+        };
+        let saved_tables = || {
+            read_postponed_events(&dir)
+                .expect("in test")
+                .into_iter()
+                .map(|x| x.table_name)
+                .collect::<Vec<_>>()
+        };
+
+        save("books").expect("in test");
+        save("books").expect("in test");
+        save("fills").expect("in test");
+        assert_eq!(saved_tables(), vec!["books", "fills"]);
+
+        assert!(save("books").is_err());
+        assert_eq!(saved_tables(), vec!["books", "fills"]);
+
+        save("fills").expect("in test");
+        assert_eq!(saved_tables(), vec!["fills", "fills"]);
+    }
 }
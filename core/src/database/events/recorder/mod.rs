@@ -1,12 +1,16 @@
-mod fallback;
+pub mod fallback;
+mod queue;
+
+pub use crate::database::events::recorder::queue::EventRecorderMetrics;
 
 use crate::database::events::recorder::fallback::EventRecorderFallback;
+use crate::database::events::recorder::queue::{EventsQueue, Overflow, QueuedEvent};
 use crate::infrastructure::spawn_future;
+use crate::settings::EventRecorderSettings;
 use anyhow::{bail, Context, Result};
-use mmb_database::postgres_db::events::{
-    save_events_batch, save_events_one_by_one, Event, InsertEvent, TableName,
-};
-use mmb_database::postgres_db::PgPool;
+use itertools::Itertools;
+use mmb_database::postgres_db::events::{Event, InsertEvent, TableName};
+use mmb_database::storage::Storage;
 use mmb_utils::infrastructure::SpawnFutureFlags;
 use mmb_utils::logger::print_info;
 use parking_lot::Mutex;
@@ -30,37 +34,51 @@ pub struct DbSettings {
 }
 
 pub struct EventRecorder {
-    data_tx: mpsc::Sender<(TableName, InsertEvent)>,
+    queue: Arc<EventsQueue>,
     shutdown_signal_tx: mpsc::UnboundedSender<()>,
     shutdown_rx: Mutex<Option<oneshot::Receiver<Result<()>>>>,
 }
 
 impl EventRecorder {
     pub async fn start(
-        pool: Option<PgPool>,
+        storage: Option<Arc<dyn Storage>>,
+        postponed_events_dir: Option<PathBuf>,
+    ) -> Result<Arc<EventRecorder>> {
+        Self::start_with_settings(
+            storage,
+            postponed_events_dir,
+            &EventRecorderSettings::default(),
+        )
+        .await
+    }
+
+    pub async fn start_with_settings(
+        storage: Option<Arc<dyn Storage>>,
         postponed_events_dir: Option<PathBuf>,
+        settings: &EventRecorderSettings,
     ) -> Result<Arc<EventRecorder>> {
-        let (data_tx, data_rx) = mpsc::channel(20_000);
+        let queue = Arc::new(EventsQueue::new(settings));
         let (shutdown_signal_tx, shutdown_signal_rx) = mpsc::unbounded_channel();
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
-        match pool {
+        match storage {
             None => {
+                queue.close();
                 let _ = shutdown_tx.send(Ok(()));
                 print_info(
                     "EventRecorder is not started because `database_url` is not set in settings",
                 );
             }
-            Some(pool) => {
-                let fallback = EventRecorderFallback::new(postponed_events_dir)
+            Some(storage) => {
+                let fallback = EventRecorderFallback::new(postponed_events_dir, settings)
                     .context("failed creation EventRecorderFallback")?;
 
                 let _ = spawn_future(
                     "start db event recorder",
                     SpawnFutureFlags::DENY_CANCELLATION | SpawnFutureFlags::STOP_BY_TOKEN,
                     start_db_event_recorder(
-                        pool.clone(),
-                        data_rx,
+                        storage.clone(),
+                        queue.clone(),
                         shutdown_signal_rx,
                         shutdown_tx,
                         fallback.clone(),
@@ -69,35 +87,40 @@ impl EventRecorder {
                 let _ = spawn_future(
                     "start postponed events restoring",
                     SpawnFutureFlags::DENY_CANCELLATION | SpawnFutureFlags::STOP_BY_TOKEN,
-                    start_postponed_events_restoring(pool, fallback),
+                    start_postponed_events_restoring(storage, fallback.clone()),
                 );
                 print_info("EventRecorder started");
             }
         }
 
         Ok(Arc::new(Self {
-            data_tx,
+            queue,
             shutdown_signal_tx,
             shutdown_rx: Mutex::new(Some(shutdown_rx)),
         }))
     }
 
     pub fn save<E: Event>(&self, event: E) -> Result<()> {
-        if !self.data_tx.is_closed() {
-            self.data_tx
-                .try_send((
-                    E::TABLE_NAME,
-                    InsertEvent {
-                        version: event.get_version(),
-                        json: event
-                            .get_json()
-                            .context("serialization to json in `EventRecorder::save()`")?,
-                    },
-                ))
-                .context("failed EventRecorder::save()")?
+        if self.queue.is_closed() {
+            return Ok(());
         }
 
-        Ok(())
+        let event = InsertEvent {
+            version: event.get_version(),
+            json: event
+                .get_json()
+                .context("serialization to json in `EventRecorder::save()`")?,
+        };
+        match self.queue.push(E::TABLE_NAME, event) {
+            None => Ok(()),
+            Some(Overflow::Dropped(table_name)) => {
+                bail!("EventRecorder queue is full, event of table {table_name} is dropped")
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> EventRecorderMetrics {
+        self.queue.metrics()
     }
 
     pub async fn flush_and_stop(&self) -> Result<()> {
@@ -113,7 +136,7 @@ impl EventRecorder {
 }
 
 async fn start_postponed_events_restoring(
-    pool: PgPool,
+    storage: Arc<dyn Storage>,
     fallback: EventRecorderFallback,
 ) -> Result<()> {
     let mut interval = tokio::time::interval(RESTORING_EVENTS_TIMEOUT);
@@ -130,20 +153,20 @@ async fn start_postponed_events_restoring(
             continue;
         }
 
-        if !pool.is_connection_health().await {
+        if !storage.is_connection_health().await {
             continue;
         }
 
         file_names.sort();
         fallback
-            .try_restore_to_db_postponed_events(&pool, &file_names)
+            .try_restore_to_db_postponed_events(storage.as_ref(), &file_names)
             .await;
     }
 }
 
 async fn start_db_event_recorder(
-    pool: PgPool,
-    mut data_rx: mpsc::Receiver<(TableName, InsertEvent)>,
+    storage: Arc<dyn Storage>,
+    queue: Arc<EventsQueue>,
     mut shutdown_signal_rx: mpsc::UnboundedReceiver<()>,
     shutdown_tx: oneshot::Sender<Result<()>>,
     fallback: EventRecorderFallback,
@@ -170,29 +193,25 @@ async fn start_db_event_recorder(
         let mut interval = tokio::time::interval(SAVING_TIMEOUT);
         tokio::select! {
             _ = shutdown_signal_rx.recv() => break, // in any case we should correctly finish
-            result = data_rx.recv() => {
-                match result {
-                    Some((table_name, event)) => {
-                        let EventsByTableName{ ref mut events, ref mut last_time_to_save } = events_map.entry(table_name).or_default();
-                        events.push(event);
-
-                        if last_time_to_save.elapsed() > SAVING_TIMEOUT ||
-                            events.len() >= BATCH_SIZE_TO_SAVE {
-
-                            let events = mem::replace(events, create_batch_size_vec());
-                            save_batch(&pool, table_name, events, &fallback).await.context("from `start_db_event_recorder` in `save_batch`")?;
-
-                            *last_time_to_save = Instant::now();
-                        }
-                    },
-                    None => break, // in any case we should correctly finish
+            (table_name, event) = queue.pop() => {
+                let EventsByTableName{ ref mut events, ref mut last_time_to_save } = events_map.entry(table_name).or_default();
+                events.push(event);
+
+                if last_time_to_save.elapsed() > SAVING_TIMEOUT ||
+                    events.len() >= BATCH_SIZE_TO_SAVE {
+
+                    let events = mem::replace(events, create_batch_size_vec());
+                    save_batch(storage.as_ref(), table_name, events, &fallback).await.context("from `start_db_event_recorder` in `save_batch`")?;
+
+                    *last_time_to_save = Instant::now();
                 }
             },
+            spilled_events = queue.pop_spilled() => spill_to_disk(spilled_events, &fallback).await,
             _ = interval.tick() => {
                 for (table_name, EventsByTableName { ref mut events, ref mut last_time_to_save }) in &mut events_map {
                     if last_time_to_save.elapsed() < SAVING_TIMEOUT {
                         let events = mem::replace(events, create_batch_size_vec());
-                        save_batch(&pool, table_name, events, &fallback).await.context("from `start_db_event_recorder` in `save_batch`")?;
+                        save_batch(storage.as_ref(), table_name, events, &fallback).await.context("from `start_db_event_recorder` in `save_batch`")?;
 
                         *last_time_to_save = Instant::now();
                     }
@@ -202,17 +221,20 @@ async fn start_db_event_recorder(
     }
 
     async fn flush_all_events(
-        pool: &PgPool,
-        mut data_rx: mpsc::Receiver<(TableName, InsertEvent)>,
+        storage: &dyn Storage,
+        queue: Arc<EventsQueue>,
         mut events_map: HashMap<TableName, EventsByTableName>,
         fallback: EventRecorderFallback,
     ) -> Result<()> {
-        while let Ok((table_name, event)) = data_rx.try_recv() {
+        // events saved after closing are ignored
+        queue.close();
+        while let Some((table_name, event)) = queue.try_pop() {
             events_map.entry(table_name).or_default().events.push(event);
         }
+        spill_to_disk(queue.take_spilled(), &fallback).await;
 
         for (table_name, EventsByTableName { events, .. }) in events_map {
-            save_batch(pool, table_name, events, &fallback)
+            save_batch(storage, table_name, events, &fallback)
                 .await
                 .context("from `flush_all_events` in `save_batch`")?;
         }
@@ -220,7 +242,7 @@ async fn start_db_event_recorder(
         Ok(())
     }
 
-    let flush_result = flush_all_events(&pool, data_rx, events_map, fallback).await;
+    let flush_result = flush_all_events(storage.as_ref(), queue, events_map, fallback).await;
 
     let _ = shutdown_tx.send(flush_result);
 
@@ -228,29 +250,43 @@ async fn start_db_event_recorder(
 }
 
 async fn save_batch(
-    pool: &PgPool,
+    storage: &dyn Storage,
     table_name: &'_ str,
     events: Vec<InsertEvent>,
     fallback: &EventRecorderFallback,
 ) -> Result<()> {
-    match save_events_batch(pool, table_name, &events).await {
+    match storage.save_events_batch(table_name, &events).await {
         Ok(()) => return Ok(()),
         Err(err) => log::error!("Failed to save batch of events with error: {err:?}"),
     }
 
     let (saving_result, not_written_events) =
-        save_events_one_by_one(pool, table_name, events).await;
-    match saving_result {
-        Ok(()) => if !not_written_events.is_empty() {},
-        Err(err) => {
-            log::error!("Failed to save events one by one with error: {err:?}");
-            save_to_file(table_name, not_written_events, fallback).await;
-        }
+        storage.save_events_one_by_one(table_name, events).await;
+    if let Err(err) = saving_result {
+        log::error!("Failed to save events one by one with error: {err:?}");
+    }
+    if !not_written_events.is_empty() {
+        save_to_file(table_name, not_written_events, fallback).await;
     }
 
     Ok(())
 }
 
+async fn spill_to_disk(events: Vec<QueuedEvent>, fallback: &EventRecorderFallback) {
+    if events.is_empty() {
+        return;
+    }
+
+    log::warn!(
+        "EventRecorder queue is full, {} events are spilled to disk",
+        events.len()
+    );
+
+    for (table_name, events) in events.into_iter().into_group_map() {
+        save_to_file(table_name, events, fallback).await;
+    }
+}
+
 async fn save_to_file(
     table_name: &str,
     not_written_events: Vec<InsertEvent>,
@@ -330,7 +366,7 @@ mod tests {
     async fn save_1_event() {
         let pool_mutex = init_test().await;
 
-        let event_recorder = EventRecorder::start(Some(pool_mutex.pool.clone()), None)
+        let event_recorder = EventRecorder::start(Some(pool_mutex.storage()), None)
             .await
             .expect("in test");
 
@@ -381,7 +417,7 @@ mod tests {
         let person = test_person();
 
         // act
-        let event_recorder = EventRecorder::start(Some(pool_mutex.pool.clone()), None)
+        let event_recorder = EventRecorder::start(Some(pool_mutex.storage()), None)
             .await
             .expect("in test");
         let connection = pool_mutex.pool.get_connection_expected().await;
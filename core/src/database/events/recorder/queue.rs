@@ -0,0 +1,326 @@
+use crate::settings::{EventRecorderOverflowPolicy, EventRecorderSettings};
+use mmb_database::postgres_db::events::{InsertEvent, TableName};
+use parking_lot::{Condvar, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+const DEFAULT_QUEUE_CAPACITY: usize = 20_000;
+const DEFAULT_BLOCK_TIMEOUT: Duration = Duration::from_millis(1_000);
+
+pub(crate) type QueuedEvent = (TableName, InsertEvent);
+
+/// Result of pushing event to the full queue
+#[derive(Debug)]
+pub(crate) enum Overflow {
+    Dropped(TableName),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventRecorderMetrics {
+    pub queue_depth: usize,
+    pub max_queue_depth: usize,
+    pub dropped_events: u64,
+    pub spilled_events: u64,
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<QueuedEvent>,
+    /// Events which should be written to postponed events files by the recorder task
+    spilled: Vec<QueuedEvent>,
+    is_closed: bool,
+}
+
+/// Bounded queue of events between `EventRecorder::save()` and the task saving them to database.
+/// Pushing blocks caller only with `block` policy and never touches disk, overflowed events are
+/// written to files by the recorder task
+pub(crate) struct EventsQueue {
+    state: Mutex<QueueState>,
+    has_events: Notify,
+    has_spilled: Notify,
+    has_space: Condvar,
+    capacity: usize,
+    policy: EventRecorderOverflowPolicy,
+    block_timeout: Duration,
+    table_priorities: HashMap<String, i32>,
+    max_depth: AtomicUsize,
+    dropped_events: AtomicU64,
+    spilled_events: AtomicU64,
+}
+
+impl EventsQueue {
+    pub fn new(settings: &EventRecorderSettings) -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            has_events: Notify::new(),
+            has_spilled: Notify::new(),
+            has_space: Condvar::new(),
+            capacity: settings
+                .queue_capacity
+                .unwrap_or(DEFAULT_QUEUE_CAPACITY)
+                .max(1),
+            policy: settings.overflow_policy,
+            block_timeout: settings
+                .block_timeout_ms
+                .map_or(DEFAULT_BLOCK_TIMEOUT, Duration::from_millis),
+            table_priorities: settings.table_priorities.clone(),
+            max_depth: AtomicUsize::new(0),
+            dropped_events: AtomicU64::new(0),
+            spilled_events: AtomicU64::new(0),
+        }
+    }
+
+    pub fn priority(&self, table_name: &str) -> i32 {
+        self.table_priorities.get(table_name).copied().unwrap_or(0)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().is_closed
+    }
+
+    /// Events pushed after closing are ignored
+    pub fn close(&self) {
+        self.state.lock().is_closed = true;
+        let _ = self.has_space.notify_all();
+    }
+
+    pub fn push(&self, table_name: TableName, event: InsertEvent) -> Option<Overflow> {
+        let mut state = self.state.lock();
+        if state.is_closed {
+            return None;
+        }
+
+        if state.events.len() >= self.capacity {
+            match self.policy {
+                EventRecorderOverflowPolicy::Block => {
+                    let deadline = Instant::now() + self.block_timeout;
+                    while state.events.len() >= self.capacity && !state.is_closed {
+                        if self.has_space.wait_until(&mut state, deadline).timed_out() {
+                            break;
+                        }
+                    }
+
+                    if state.is_closed {
+                        return None;
+                    }
+                    if state.events.len() >= self.capacity {
+                        self.spill(&mut state, (table_name, event));
+                        return None;
+                    }
+                }
+                EventRecorderOverflowPolicy::DropOldest => {
+                    if let Some(overflow) = self.drop_oldest(&mut state, table_name) {
+                        return Some(overflow);
+                    }
+                }
+                EventRecorderOverflowPolicy::SpillToDisk => {
+                    self.spill(&mut state, (table_name, event));
+                    return None;
+                }
+            }
+        }
+
+        state.events.push_back((table_name, event));
+        self.max_depth
+            .fetch_max(state.events.len(), Ordering::Relaxed);
+        drop(state);
+
+        self.has_events.notify_one();
+        None
+    }
+
+    /// Only the event which doesn't fit into the queue is spilled, so queued events are still
+    /// saved in order
+    fn spill(&self, state: &mut QueueState, event: QueuedEvent) {
+        let _ = self.spilled_events.fetch_add(1, Ordering::Relaxed);
+        state.spilled.push(event);
+
+        self.has_spilled.notify_one();
+    }
+
+    /// Frees place for new event by dropping the oldest event with the lowest priority.
+    /// Returns `Overflow::Dropped` if the new event itself should be dropped
+    fn drop_oldest(&self, state: &mut QueueState, table_name: TableName) -> Option<Overflow> {
+        self.dropped_events.fetch_add(1, Ordering::Relaxed);
+
+        let lowest = state
+            .events
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, (table_name, _))| (self.priority(table_name), *index))
+            .map(|(index, (table_name, _))| (index, *table_name));
+
+        match lowest {
+            Some((index, lowest_table_name))
+                if self.priority(lowest_table_name) <= self.priority(table_name) =>
+            {
+                let _ = state.events.remove(index);
+                log::warn!(
+                    "EventRecorder queue is full, dropped event of table {lowest_table_name}"
+                );
+                None
+            }
+            _ => Some(Overflow::Dropped(table_name)),
+        }
+    }
+
+    pub fn try_pop(&self) -> Option<QueuedEvent> {
+        let event = self.state.lock().events.pop_front();
+        if event.is_some() {
+            let _ = self.has_space.notify_one();
+        }
+        event
+    }
+
+    /// Waits for event. Cancel safe, events are not lost if the future is dropped
+    pub async fn pop(&self) -> QueuedEvent {
+        loop {
+            if let Some(event) = self.try_pop() {
+                return event;
+            }
+            self.has_events.notified().await;
+        }
+    }
+
+    pub fn take_spilled(&self) -> Vec<QueuedEvent> {
+        std::mem::take(&mut self.state.lock().spilled)
+    }
+
+    /// Waits for events which should be written to disk. Cancel safe
+    pub async fn pop_spilled(&self) -> Vec<QueuedEvent> {
+        loop {
+            let spilled = self.take_spilled();
+            if !spilled.is_empty() {
+                return spilled;
+            }
+            self.has_spilled.notified().await;
+        }
+    }
+
+    pub fn metrics(&self) -> EventRecorderMetrics {
+        EventRecorderMetrics {
+            queue_depth: self.state.lock().events.len(),
+            max_queue_depth: self.max_depth.load(Ordering::Relaxed),
+            dropped_events: self.dropped_events.load(Ordering::Relaxed),
+            spilled_events: self.spilled_events.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn event(number: u64) -> InsertEvent {
+        InsertEvent {
+            version: 1,
+            json: json!(number),
+        }
+    }
+
+    fn queue(policy: EventRecorderOverflowPolicy) -> EventsQueue {
+        EventsQueue::new(&EventRecorderSettings {
+            queue_capacity: Some(3),
+            overflow_policy: policy,
+            block_timeout_ms: Some(10),
+            table_priorities: HashMap::from([("fills".to_owned(), 10), ("books".to_owned(), -1)]),
+            postponed_events_quota_mb: None,
+        })
+    }
+
+    fn queued_events(queue: &EventsQueue) -> Vec<QueuedEvent> {
+        std::iter::from_fn(|| queue.try_pop()).collect()
+    }
+
+    #[test]
+    fn drop_oldest_event_with_lowest_priority() {
+        let queue = queue(EventRecorderOverflowPolicy::DropOldest);
+        assert!(queue.push("fills", event(1)).is_none());
+        assert!(queue.push("balances", event(2)).is_none());
+        assert!(queue.push("balances", event(3)).is_none());
+
+        assert!(queue.push("fills", event(4)).is_none());
+        assert!(matches!(
+            queue.push("books", event(5)),
+            Some(Overflow::Dropped("books"))
+        ));
+
+        assert_eq!(
+            queued_events(&queue),
+            vec![
+                ("fills", event(1)),
+                ("balances", event(3)),
+                ("fills", event(4))
+            ]
+        );
+        assert_eq!(queue.metrics().dropped_events, 2);
+    }
+
+    #[test]
+    fn spill_only_overflowed_events() {
+        let queue = queue(EventRecorderOverflowPolicy::SpillToDisk);
+        for number in 1..=3 {
+            assert!(queue.push("fills", event(number)).is_none());
+        }
+
+        assert!(queue.push("books", event(4)).is_none());
+
+        assert_eq!(queue.take_spilled(), vec![("books", event(4))]);
+        assert_eq!(
+            queue.metrics(),
+            EventRecorderMetrics {
+                queue_depth: 3,
+                max_queue_depth: 3,
+                dropped_events: 0,
+                spilled_events: 1,
+            }
+        );
+        assert_eq!(
+            queued_events(&queue),
+            (1..=3).map(|x| ("fills", event(x))).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn block_until_queue_has_space() {
+        let queue = Arc::new(queue(EventRecorderOverflowPolicy::Block));
+        for number in 1..=3 {
+            assert!(queue.push("fills", event(number)).is_none());
+        }
+
+        let consumer = std::thread::spawn({
+            let queue = queue.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(5));
+                queue.try_pop()
+            }
+        });
+        assert!(queue.push("fills", event(4)).is_none());
+
+        assert_eq!(consumer.join().expect("in test"), Some(("fills", event(1))));
+        assert!(queue.take_spilled().is_empty());
+        assert_eq!(
+            queued_events(&queue),
+            (2..=4).map(|x| ("fills", event(x))).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn spill_event_after_block_timeout() {
+        let queue = queue(EventRecorderOverflowPolicy::Block);
+        for number in 1..=3 {
+            assert!(queue.push("fills", event(number)).is_none());
+        }
+
+        let started = Instant::now();
+        assert!(queue.push("fills", event(4)).is_none());
+
+        assert!(started.elapsed() >= Duration::from_millis(10));
+        assert_eq!(queue.take_spilled(), vec![("fills", event(4))]);
+        assert_eq!(queue.metrics().queue_depth, 3);
+    }
+}
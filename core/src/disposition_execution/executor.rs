@@ -9,10 +9,12 @@ use mmb_utils::{nothing_to_do, DateTime};
 use parking_lot::Mutex;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use tokio::sync::{broadcast, oneshot};
+use tokio::sync::oneshot;
 
+use crate::disposition_execution::risk_checks::RiskCheckRejection;
 use crate::disposition_execution::strategy::DispositionStrategy;
 use crate::disposition_execution::trading_context_calculation::calculate_trading_context;
+use crate::exchanges::conflating_events_receiver::{ConflatingEventsReceiver, ReceivedEvent};
 use crate::exchanges::general::exchange::Exchange;
 use crate::exchanges::general::request_type::RequestType;
 use crate::explanation::{Explanation, WithExplanation};
@@ -40,6 +42,8 @@ use mmb_domain::order::snapshot::{
     ClientOrderId, OrderCreating, OrderExecutionType, OrderHeader, OrderSide, OrderSnapshot,
     OrderStatus, OrderType,
 };
+use mmb_domain::order_book::event::{EventType, OrderBookEvent};
+use mmb_domain::order_book::order_book_data::OrderBookData;
 use mmb_utils::cancellation_token::CancellationToken;
 
 static DISPOSITION_EXECUTOR: &str = "DispositionExecutor";
@@ -66,7 +70,7 @@ impl DispositionExecutorService {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         engine_ctx: Arc<EngineContext>,
-        events_receiver: broadcast::Receiver<ExchangeEvent>,
+        events_receiver: ConflatingEventsReceiver,
         local_snapshots_service: LocalSnapshotsService,
         exchange_account_id: ExchangeAccountId,
         currency_pair: CurrencyPair,
@@ -122,7 +126,7 @@ struct DispositionExecutor {
     engine_ctx: Arc<EngineContext>,
     exchange_account_id: ExchangeAccountId,
     symbol: Arc<Symbol>,
-    events_receiver: broadcast::Receiver<ExchangeEvent>,
+    events_receiver: ConflatingEventsReceiver,
     local_snapshots_service: LocalSnapshotsService,
     orders_state: OrdersState,
     strategy: Box<dyn DispositionStrategy>,
@@ -135,7 +139,7 @@ impl DispositionExecutor {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         engine_ctx: Arc<EngineContext>,
-        events_receiver: broadcast::Receiver<ExchangeEvent>,
+        events_receiver: ConflatingEventsReceiver,
         local_snapshots_service: LocalSnapshotsService,
         exchange_account_id: ExchangeAccountId,
         currency_pair: CurrencyPair,
@@ -170,29 +174,39 @@ impl DispositionExecutor {
 
         loop {
             let event = tokio::select! {
-                event_res = self.events_receiver.recv() => event_res.context("Error during receiving event in DispositionExecutor::start()")?,
+                event_res = self.events_receiver.recv() => event_res.context("Events channel closed in DispositionExecutor::start()")?,
                 _ = self.cancellation_token.when_cancelled() => {
                     let _ = self.work_finished_sender.take().ok_or_else(|| anyhow!("Can't take `work_finished_sender` in DispositionExecutor"))?.send(Ok(()));
                     return Ok(());
                 }
             };
 
-            self.handle_event(&event, &mut trading_context)?;
+            self.handle_event(event, &mut trading_context)?;
         }
     }
 
     fn handle_event(
         &mut self,
-        event: &ExchangeEvent,
+        received_event: ReceivedEvent,
         last_trading_context: &mut Option<TradingContext>,
     ) -> Result<()> {
+        let event = &match received_event {
+            ReceivedEvent::Exchange(event) => event,
+            ReceivedEvent::OrderBook {
+                market_account_id,
+                snapshot,
+            } => {
+                let event = order_book_changed_event(market_account_id, snapshot.last_update_time);
+                self.local_snapshots_service
+                    .set_shared_snapshot(market_account_id.market_id(), snapshot);
+                event
+            }
+        };
+
         let now = now();
         let need_recalculate_trading_context = self.prepare_estimate_trading_context(event, now);
 
         match event {
-            ExchangeEvent::OrderBookEvent(order_book_event) => {
-                let _ = self.local_snapshots_service.update(order_book_event);
-            }
             ExchangeEvent::OrderEvent(order_event) => {
                 let order = &order_event.order;
                 if order.fn_ref(|s| s.header.order_type.is_external_order()) {
@@ -380,6 +394,19 @@ impl DispositionExecutor {
             return Ok(());
         }
 
+        if !self
+            .exchange()
+            .is_order_book_ready(self.symbol.currency_pair())
+        {
+            self.start_cancelling_all_orders(
+                "target market order book is resynchronizing",
+                &mut composite_order.borrow_mut(),
+                explanation,
+            );
+
+            return Ok(());
+        }
+
         // TODO close position if needed
 
         let new_estimating = match new_estimating {
@@ -621,6 +648,8 @@ impl DispositionExecutor {
             );
         }
 
+        let exchange = self.exchange();
+
         let new_client_order_id = ClientOrderId::unique_id();
 
         let requests_group_id = self.engine_ctx.timeout_manager.try_reserve_group(
@@ -701,8 +730,6 @@ impl DispositionExecutor {
             );
         }
 
-        *price_slot.estimating.borrow_mut() = Some(Box::new(new_estimating.clone()));
-
         let new_order_header = OrderHeader::new(
             new_client_order_id.clone(),
             self.exchange_account_id,
@@ -716,7 +743,26 @@ impl DispositionExecutor {
             new_estimating.strategy_name.clone(),
         );
 
-        let exchange = self.exchange();
+        let order_creating = OrderCreating {
+            header: new_order_header.clone(),
+            price: new_price,
+        };
+        if !check_risks(&exchange, &order_creating, &self.statistics, explanation) {
+            self.engine_ctx
+                .balance_manager
+                .lock()
+                .unreserve_rest(reservation_id)
+                .with_expect(|| format!("DispositionExecutor::try_create_order() failed to unreserve_rest for: {reservation_id:?}"));
+
+            let _ = self
+                .engine_ctx
+                .timeout_manager
+                .remove_group(self.exchange_account_id, requests_group_id);
+
+            return Ok(());
+        }
+
+        *price_slot.estimating.borrow_mut() = Some(Box::new(new_estimating.clone()));
 
         let new_order = exchange.orders.add_simple_initial(
             new_order_header.clone(),
@@ -739,18 +785,25 @@ impl DispositionExecutor {
         {
             let new_client_order_id = new_client_order_id.clone();
             let cancellation_token = self.cancellation_token.clone();
+            let statistics = self.statistics.clone();
+            let market_account_id = new_disposition.market_account_id();
 
             let action = async move {
                 log::trace!("Begin create_order {new_client_order_id}");
 
-                let order_creating = OrderCreating {
-                    header: new_order_header,
-                    price: new_price,
-                };
-
-                exchange
+                if let Err(err) = exchange
                     .create_order(order_creating, Some(requests_group_id), cancellation_token)
-                    .await?;
+                    .await
+                {
+                    // State of checks could be changed since order was checked by executor
+                    if let Some(rejection) = err.downcast_ref::<RiskCheckRejection>() {
+                        statistics
+                            .register_risk_check_rejection(market_account_id, rejection.check_name);
+                        log::trace!("Order {new_client_order_id} wasn't created: {rejection}");
+                        return Ok(());
+                    }
+                    return Err(err);
+                }
 
                 log::trace!("Finished create_order {new_client_order_id}");
 
@@ -917,6 +970,22 @@ impl DispositionExecutor {
     }
 }
 
+/// Strategies are notified about changed order book by event without data,
+/// actual state of order book is taken from local snapshots service
+fn order_book_changed_event(
+    market_account_id: MarketAccountId,
+    update_time: DateTime,
+) -> ExchangeEvent {
+    ExchangeEvent::OrderBookEvent(OrderBookEvent::new(
+        update_time,
+        market_account_id.exchange_account_id,
+        market_account_id.currency_pair,
+        None,
+        EventType::Update,
+        Arc::new(OrderBookData::default()),
+    ))
+}
+
 fn estimate_trading_context(
     need_recalculate_trading_context: bool,
     event: &ExchangeEvent,
@@ -982,6 +1051,35 @@ fn now() -> DateTime {
 }
 
 #[inline(always)]
+/// Returns `false` and adds rejection reason to explanation if order doesn't pass risk checks
+fn check_risks(
+    exchange: &Exchange,
+    order_creating: &OrderCreating,
+    statistics: &StatisticService,
+    explanation: &mut Explanation,
+) -> bool {
+    let rejection = match exchange.check_risks(order_creating) {
+        Ok(()) => return true,
+        Err(rejection) => rejection,
+    };
+
+    let header = &order_creating.header;
+    statistics.register_risk_check_rejection(
+        MarketAccountId::new(header.exchange_account_id, header.currency_pair),
+        rejection.check_name,
+    );
+
+    let _ = log_trace(
+        format!(
+            "Finished `try_create_order` because order {} rejected by risk check {}: {}",
+            header.client_order_id, rejection.check_name, rejection.reason
+        ),
+        explanation,
+    );
+
+    false
+}
+
 fn log_trace(msg: impl AsRef<str>, explanation: &mut Explanation) -> Result<()> {
     let msg = msg.as_ref();
     log::trace!("{msg}");
@@ -989,3 +1087,75 @@ fn log_trace(msg: impl AsRef<str>, explanation: &mut Explanation) -> Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposition_execution::risk_checks::RiskChecks;
+    use crate::exchanges::general::test_helper::get_test_exchange;
+    use crate::infrastructure::init_lifetime_manager;
+    use crate::settings::{CoreSettings, ExchangeSettings, RiskChecksSettings};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn risk_check_rejection_is_explained() {
+        let _ = init_lifetime_manager();
+        let (exchange, _) = get_test_exchange(false);
+        let currency_pair = exchange
+            .symbols
+            .iter()
+            .next()
+            .expect("in test")
+            .currency_pair();
+        exchange.setup_risk_checks(Arc::new(RiskChecks::from_settings(&CoreSettings {
+            exchanges: vec![ExchangeSettings {
+                exchange_account_id: exchange.exchange_account_id,
+                risk_checks: Some(RiskChecksSettings {
+                    max_order_notional: Some(dec!(100)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })));
+
+        let order_creating = |amount| OrderCreating {
+            header: OrderHeader::new(
+                ClientOrderId::unique_id(),
+                exchange.exchange_account_id,
+                currency_pair,
+                OrderType::Limit,
+                OrderSide::Buy,
+                amount,
+                OrderExecutionType::MakerOnly,
+                None,
+                None,
+                "test_strategy".to_owned(),
+            ),
+            price: dec!(100),
+        };
+        let statistics = StatisticService::new();
+        let mut explanation = Explanation::default();
+
+        assert!(check_risks(
+            &exchange,
+            &order_creating(dec!(1)),
+            &statistics,
+            &mut explanation
+        ));
+        assert!(explanation.get_reasons().is_empty());
+
+        assert!(!check_risks(
+            &exchange,
+            &order_creating(dec!(2)),
+            &statistics,
+            &mut explanation
+        ));
+        let reasons = explanation.get_reasons();
+        assert_eq!(reasons.len(), 1);
+        assert!(
+            reasons[0].contains("rejected by risk check MaxOrderNotional"),
+            "{}",
+            reasons[0]
+        );
+    }
+}
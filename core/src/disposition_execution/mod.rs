@@ -1,7 +1,9 @@
 pub mod executor;
+pub mod risk_checks;
 pub mod strategy;
 pub mod trade_limit;
 mod trading_context_calculation;
+pub mod wasm_plugin;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -19,6 +21,9 @@ use mmb_domain::market::{CurrencyPair, ExchangeAccountId, ExchangeId, MarketAcco
 use mmb_domain::order::pool::OrderRef;
 use mmb_domain::order::snapshot::{ClientOrderId, OrderRole, OrderSide};
 
+/// Count of price slots per side in `DispositionExecutor`
+pub const PRICE_SLOTS_COUNT: usize = 1;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct SmallOrder {
     pub price: Price,
@@ -119,6 +124,31 @@ pub struct TradingContextBySide {
 }
 
 impl TradingContextBySide {
+    /// Context with trade cycles of strategy for price slots. Trade cycles over `PRICE_SLOTS_COUNT`
+    /// are ignored, remaining slots are left empty with `empty_slot_explanation`
+    pub fn from_trade_cycles(
+        max_amount: Amount,
+        trade_cycles: impl IntoIterator<Item = WithExplanation<Option<TradeCycle>>>,
+        empty_slot_explanation: &Explanation,
+    ) -> Self {
+        let mut estimating = trade_cycles
+            .into_iter()
+            .take(PRICE_SLOTS_COUNT)
+            .collect_vec();
+
+        while estimating.len() < PRICE_SLOTS_COUNT {
+            estimating.push(WithExplanation {
+                value: None,
+                explanation: empty_slot_explanation.clone(),
+            });
+        }
+
+        TradingContextBySide {
+            max_amount,
+            estimating,
+        }
+    }
+
     pub fn empty(slots_count: usize, explanation: Explanation) -> Self {
         TradingContextBySide {
             max_amount: dec!(0),
@@ -331,10 +361,11 @@ impl OrdersStateBySide {
         OrdersStateBySide {
             _side,
             // TODO create list of PriceSlots by config
-            slots: vec![PriceSlot::new(
-                PriceSlotId::new("PriceSlotId".into(), 0),
-                _side,
-            )],
+            slots: (0..PRICE_SLOTS_COUNT)
+                .map(|level_index| {
+                    PriceSlot::new(PriceSlotId::new("PriceSlotId".into(), level_index), _side)
+                })
+                .collect(),
         }
     }
 
@@ -0,0 +1,647 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use chrono::Duration;
+use mmb_domain::exchanges::symbol::{BeforeAfter, Symbol};
+use mmb_domain::market::{CurrencyCode, ExchangeAccountId, MarketAccountId};
+use mmb_domain::order::snapshot::{Amount, ClientOrderId, OrderSide, Price};
+use mmb_utils::DateTime;
+use parking_lot::{Mutex, RwLock};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::balance::manager::balance_manager::BalanceManager;
+use crate::exchanges::general::exchange::Exchange;
+use crate::settings::{CoreSettings, OrderRateSettings, RiskChecksSettings};
+
+/// Order which is going to be created and data for checking it
+pub struct OrderRiskContext<'a> {
+    pub client_order_id: &'a ClientOrderId,
+    pub market_account_id: MarketAccountId,
+    pub symbol: &'a Arc<Symbol>,
+    pub side: OrderSide,
+    pub price: Price,
+    pub amount: Amount,
+    pub strategy_name: &'a str,
+    pub now: DateTime,
+    pub exchange: &'a Exchange,
+    /// Is `None` if balance manager isn't set up for exchange
+    pub balance_manager: Option<&'a Mutex<BalanceManager>>,
+}
+
+impl OrderRiskContext<'_> {
+    fn notional(&self) -> Decimal {
+        self.symbol.convert_amount_from_amount_currency_code(
+            self.symbol.quote_currency_code(),
+            self.amount,
+            self.price,
+        )
+    }
+}
+
+/// Check of order before sending it to exchange
+pub trait RiskCheck: Send + Sync {
+    /// Name of check for explanations and statistics
+    fn name(&self) -> &'static str;
+
+    /// Returns rejection reason if order shouldn't be created
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String>;
+
+    /// Called when order passed all checks of pipeline and is going to be sent to exchange
+    fn order_approved(&self, _order: &OrderRiskContext) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Order rejected by risk check {check_name}: {reason}")]
+pub struct RiskCheckRejection {
+    pub check_name: &'static str,
+    pub reason: String,
+}
+
+/// Pipeline of pre-trade risk checks for every exchange account.
+/// Checks are configured by `ExchangeSettings::risk_checks` and can be extended with
+/// custom checks through `add_check`. Checks are run by `Exchange::create_order` for every order
+#[derive(Default)]
+pub struct RiskChecks {
+    checks: RwLock<HashMap<ExchangeAccountId, Vec<Box<dyn RiskCheck>>>>,
+}
+
+impl RiskChecks {
+    pub fn from_settings(core_settings: &CoreSettings) -> Self {
+        let checks = core_settings
+            .exchanges
+            .iter()
+            .filter_map(|exchange_settings| {
+                let settings = exchange_settings.risk_checks.as_ref()?;
+                Some((
+                    exchange_settings.exchange_account_id,
+                    create_checks(settings),
+                ))
+            })
+            .collect();
+
+        RiskChecks {
+            checks: RwLock::new(checks),
+        }
+    }
+
+    pub fn add_check(&self, exchange_account_id: ExchangeAccountId, check: Box<dyn RiskCheck>) {
+        self.checks
+            .write()
+            .entry(exchange_account_id)
+            .or_default()
+            .push(check);
+    }
+
+    /// Runs checks in configuration order and stops on the first rejection
+    pub fn check(&self, order: &OrderRiskContext) -> Result<(), RiskCheckRejection> {
+        let checks = self.checks.read();
+        let checks = match checks.get(&order.market_account_id.exchange_account_id) {
+            None => return Ok(()),
+            Some(checks) => checks,
+        };
+
+        for check in checks {
+            check.check(order).map_err(|reason| RiskCheckRejection {
+                check_name: check.name(),
+                reason,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Should be called only for orders which passed `check` and are actually going to be sent,
+    /// so rejected orders don't affect stateful checks like order rate
+    pub fn order_approved(&self, order: &OrderRiskContext) {
+        if let Some(checks) = self
+            .checks
+            .read()
+            .get(&order.market_account_id.exchange_account_id)
+        {
+            checks.iter().for_each(|check| check.order_approved(order));
+        }
+    }
+}
+
+fn create_checks(settings: &RiskChecksSettings) -> Vec<Box<dyn RiskCheck>> {
+    let mut checks: Vec<Box<dyn RiskCheck>> = vec![];
+
+    if let Some(max_notional) = settings.max_order_notional {
+        checks.push(Box::new(MaxOrderNotionalCheck { max_notional }));
+    }
+    if let Some(max_deviation) = settings.max_price_deviation_from_mid {
+        checks.push(Box::new(PriceDeviationCheck { max_deviation }));
+    }
+    if let Some(max_open_orders) = settings.max_open_orders_per_market {
+        checks.push(Box::new(MaxOpenOrdersCheck { max_open_orders }));
+    }
+    if !settings.max_positions.is_empty() {
+        checks.push(Box::new(MaxPositionCheck {
+            max_positions: settings.max_positions.clone(),
+        }));
+    }
+    if let Some(order_rate) = &settings.max_order_rate_per_strategy {
+        checks.push(Box::new(OrderRateCheck::new(order_rate)));
+    }
+
+    checks
+}
+
+/// Limits order cost in quote currency
+pub struct MaxOrderNotionalCheck {
+    max_notional: Decimal,
+}
+
+impl RiskCheck for MaxOrderNotionalCheck {
+    fn name(&self) -> &'static str {
+        "MaxOrderNotional"
+    }
+
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String> {
+        let notional = order.notional();
+        if notional > self.max_notional {
+            return Err(format!(
+                "Order notional {notional} is greater than max notional {}",
+                self.max_notional
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Fat-finger protection: rejects orders with price too far from middle price of order book
+pub struct PriceDeviationCheck {
+    /// Max relative deviation, e.g. 0.05 for 5%
+    max_deviation: Decimal,
+}
+
+impl RiskCheck for PriceDeviationCheck {
+    fn name(&self) -> &'static str {
+        "PriceDeviation"
+    }
+
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String> {
+        let currency_pair = order.market_account_id.currency_pair;
+        let mid_price = order
+            .exchange
+            .order_book_top
+            .get(&currency_pair)
+            .and_then(|top| match (&top.ask, &top.bid) {
+                (Some(ask), Some(bid)) => Some((ask.price + bid.price) / dec!(2)),
+                _ => None,
+            })
+            .ok_or_else(|| format!("There is no middle price for {currency_pair}"))?;
+
+        let deviation = ((order.price - mid_price) / mid_price).abs();
+        if deviation > self.max_deviation {
+            return Err(format!(
+                "Order price {} deviates from middle price {mid_price} by {deviation} that is greater than {}",
+                order.price, self.max_deviation
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct MaxOpenOrdersCheck {
+    max_open_orders: usize,
+}
+
+impl RiskCheck for MaxOpenOrdersCheck {
+    fn name(&self) -> &'static str {
+        "MaxOpenOrders"
+    }
+
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String> {
+        let currency_pair = order.market_account_id.currency_pair;
+        // Checked order is already added to orders pool, so it isn't counted as open
+        let open_orders_count = order
+            .exchange
+            .orders
+            .not_finished
+            .iter()
+            .filter(|x| x.key() != order.client_order_id && x.currency_pair() == currency_pair)
+            .count();
+
+        if open_orders_count >= self.max_open_orders {
+            return Err(format!(
+                "There are {open_orders_count} open orders for {currency_pair} when max is {}",
+                self.max_open_orders
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits balance of currency which will be received by order, including order amount
+pub struct MaxPositionCheck {
+    max_positions: HashMap<CurrencyCode, Amount>,
+}
+
+impl RiskCheck for MaxPositionCheck {
+    fn name(&self) -> &'static str {
+        "MaxPosition"
+    }
+
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String> {
+        let currency_code = order.symbol.get_trade_code(order.side, BeforeAfter::After);
+        let max_position = match self.max_positions.get(&currency_code) {
+            None => return Ok(()),
+            Some(max_position) => *max_position,
+        };
+
+        let balance_manager = order
+            .balance_manager
+            .ok_or_else(|| "There is no balance manager for checking position".to_owned())?;
+        let balance = balance_manager
+            .lock()
+            .get_exchange_balance(
+                order.market_account_id.exchange_account_id,
+                order.symbol.clone(),
+                currency_code,
+            )
+            .unwrap_or(dec!(0));
+
+        let position = balance
+            + order.symbol.convert_amount_from_amount_currency_code(
+                currency_code,
+                order.amount,
+                order.price,
+            );
+
+        if position > max_position {
+            return Err(format!(
+                "Position {position} {currency_code} after order would be greater than max position {max_position}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits count of created orders by every strategy in sliding time window
+pub struct OrderRateCheck {
+    max_orders_count: usize,
+    period: Duration,
+    created_orders: Mutex<HashMap<String, VecDeque<DateTime>>>,
+}
+
+impl OrderRateCheck {
+    pub fn new(settings: &OrderRateSettings) -> Self {
+        OrderRateCheck {
+            max_orders_count: settings.max_orders_count,
+            period: Duration::milliseconds(settings.period_ms as i64),
+            created_orders: Default::default(),
+        }
+    }
+}
+
+impl RiskCheck for OrderRateCheck {
+    fn name(&self) -> &'static str {
+        "OrderRate"
+    }
+
+    fn check(&self, order: &OrderRiskContext) -> Result<(), String> {
+        let mut created_orders = self.created_orders.lock();
+        let times = match created_orders.get_mut(order.strategy_name) {
+            None => return Ok(()),
+            Some(times) => times,
+        };
+
+        while matches!(times.front(), Some(&time) if time + self.period <= order.now) {
+            let _ = times.pop_front();
+        }
+
+        if times.len() >= self.max_orders_count {
+            return Err(format!(
+                "Strategy {} created {} orders during last {} ms",
+                order.strategy_name,
+                times.len(),
+                self.period.num_milliseconds()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn order_approved(&self, order: &OrderRiskContext) {
+        self.created_orders
+            .lock()
+            .entry(order.strategy_name.to_owned())
+            .or_default()
+            .push_back(order.now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::general::currency_pair_to_symbol_converter::CurrencyPairToSymbolConverter;
+    use crate::exchanges::general::exchange::{OrderBookTop, PriceLevel};
+    use crate::exchanges::general::test_helper::get_test_exchange;
+    use crate::infrastructure::init_lifetime_manager;
+    use crate::settings::ExchangeSettings;
+    use chrono::Utc;
+    use mmb_domain::events::{ExchangeBalance, ExchangeBalancesAndPositions};
+    use mmb_domain::order::snapshot::{OrderExecutionType, OrderHeader, OrderType};
+    use mmb_utils::hashmap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct TestOrder {
+        client_order_id: ClientOrderId,
+        exchange: Arc<Exchange>,
+        symbol: Arc<Symbol>,
+        balance_manager: Arc<Mutex<BalanceManager>>,
+    }
+
+    impl TestOrder {
+        fn new() -> Self {
+            let _ = init_lifetime_manager();
+            let (exchange, _) = get_test_exchange(false);
+            let symbol = exchange
+                .symbols
+                .iter()
+                .next()
+                .expect("in test")
+                .value()
+                .clone();
+
+            let level = |price| {
+                Some(PriceLevel {
+                    price,
+                    amount: dec!(1),
+                })
+            };
+            let _ = exchange.order_book_top.insert(
+                symbol.currency_pair(),
+                OrderBookTop {
+                    ask: level(dec!(101)),
+                    bid: level(dec!(99)),
+                },
+            );
+
+            let converter = CurrencyPairToSymbolConverter::new(
+                hashmap![exchange.exchange_account_id => exchange.clone()],
+            );
+
+            TestOrder {
+                client_order_id: "checked_order".into(),
+                exchange,
+                symbol,
+                balance_manager: BalanceManager::new(converter, None),
+            }
+        }
+
+        fn context(&self, price: Price, amount: Amount, now: DateTime) -> OrderRiskContext {
+            OrderRiskContext {
+                client_order_id: &self.client_order_id,
+                market_account_id: MarketAccountId::new(
+                    self.exchange.exchange_account_id,
+                    self.symbol.currency_pair(),
+                ),
+                symbol: &self.symbol,
+                side: OrderSide::Buy,
+                price,
+                amount,
+                strategy_name: "test_strategy",
+                now,
+                exchange: &self.exchange,
+                balance_manager: Some(&self.balance_manager),
+            }
+        }
+
+        /// Adds open order to orders pool the same way as `Exchange::create_order` does
+        fn add_open_order(&self, client_order_id: ClientOrderId) {
+            let header = OrderHeader::new(
+                client_order_id,
+                self.exchange.exchange_account_id,
+                self.symbol.currency_pair(),
+                OrderType::Limit,
+                OrderSide::Buy,
+                dec!(1),
+                OrderExecutionType::None,
+                None,
+                None,
+                "test_strategy".to_owned(),
+            );
+            let _ =
+                self.exchange
+                    .orders
+                    .add_simple_initial(header, Utc::now(), Some(dec!(100)), None);
+        }
+
+        fn risk_checks(&self, settings: RiskChecksSettings) -> RiskChecks {
+            RiskChecks::from_settings(&CoreSettings {
+                exchanges: vec![ExchangeSettings {
+                    exchange_account_id: self.exchange.exchange_account_id,
+                    risk_checks: Some(settings),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+        }
+    }
+
+    fn rejected_by(result: Result<(), RiskCheckRejection>) -> Option<&'static str> {
+        result.err().map(|x| x.check_name)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn without_settings_everything_is_allowed() {
+        let test_order = TestOrder::new();
+        let risk_checks = RiskChecks::default();
+
+        let result = risk_checks.check(&test_order.context(dec!(1000), dec!(1000), Utc::now()));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn max_order_notional() {
+        let test_order = TestOrder::new();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_order_notional: Some(dec!(200)),
+            ..Default::default()
+        });
+
+        let now = Utc::now();
+        assert_eq!(
+            risk_checks.check(&test_order.context(dec!(100), dec!(2), now)),
+            Ok(())
+        );
+        assert_eq!(
+            rejected_by(risk_checks.check(&test_order.context(dec!(100), dec!(2.1), now))),
+            Some("MaxOrderNotional")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn price_deviation_from_mid() {
+        let test_order = TestOrder::new();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_price_deviation_from_mid: Some(dec!(0.05)),
+            ..Default::default()
+        });
+
+        let now = Utc::now();
+        assert_eq!(
+            risk_checks.check(&test_order.context(dec!(95), dec!(1), now)),
+            Ok(())
+        );
+        assert_eq!(
+            rejected_by(risk_checks.check(&test_order.context(dec!(94.9), dec!(1), now))),
+            Some("PriceDeviation")
+        );
+        assert_eq!(
+            rejected_by(risk_checks.check(&test_order.context(dec!(1000), dec!(1), now))),
+            Some("PriceDeviation")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn price_deviation_without_order_book() {
+        let test_order = TestOrder::new();
+        test_order.exchange.order_book_top.clear();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_price_deviation_from_mid: Some(dec!(0.05)),
+            ..Default::default()
+        });
+
+        let result = risk_checks.check(&test_order.context(dec!(100), dec!(1), Utc::now()));
+
+        assert_eq!(rejected_by(result), Some("PriceDeviation"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn max_open_orders_per_market() {
+        let test_order = TestOrder::new();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_open_orders_per_market: Some(2),
+            ..Default::default()
+        });
+
+        test_order.add_open_order("open_order_1".into());
+        test_order.add_open_order(test_order.client_order_id.clone());
+        assert_eq!(
+            risk_checks.check(&test_order.context(dec!(100), dec!(1), Utc::now())),
+            Ok(())
+        );
+
+        test_order.add_open_order("open_order_2".into());
+        let result = risk_checks.check(&test_order.context(dec!(100), dec!(1), Utc::now()));
+        assert_eq!(rejected_by(result), Some("MaxOpenOrders"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn max_position() {
+        let test_order = TestOrder::new();
+        let base_currency_code = test_order.symbol.base_currency_code();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_positions: hashmap![base_currency_code => dec!(5)],
+            ..Default::default()
+        });
+        test_order
+            .balance_manager
+            .lock()
+            .update_exchange_balance(
+                test_order.exchange.exchange_account_id,
+                &ExchangeBalancesAndPositions {
+                    balances: vec![ExchangeBalance {
+                        currency_code: base_currency_code,
+                        balance: dec!(3),
+                    }],
+                    positions: None,
+                },
+            )
+            .expect("in test");
+
+        let now = Utc::now();
+        assert_eq!(
+            risk_checks.check(&test_order.context(dec!(100), dec!(2), now)),
+            Ok(())
+        );
+        assert_eq!(
+            rejected_by(risk_checks.check(&test_order.context(dec!(100), dec!(2.1), now))),
+            Some("MaxPosition")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn order_rate_per_strategy() {
+        let test_order = TestOrder::new();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_order_rate_per_strategy: Some(OrderRateSettings {
+                max_orders_count: 2,
+                period_ms: 1000,
+            }),
+            ..Default::default()
+        });
+
+        let now = Utc::now();
+        let check_at = |time| {
+            let order = test_order.context(dec!(100), dec!(1), time);
+            let result = risk_checks.check(&order);
+            if result.is_ok() {
+                risk_checks.order_approved(&order);
+            }
+            result
+        };
+
+        assert_eq!(check_at(now), Ok(()));
+        assert_eq!(check_at(now + Duration::milliseconds(100)), Ok(()));
+        assert_eq!(
+            rejected_by(check_at(now + Duration::milliseconds(200))),
+            Some("OrderRate")
+        );
+        assert_eq!(check_at(now + Duration::milliseconds(1000)), Ok(()));
+    }
+
+    struct CountingCheck {
+        approved_count: Arc<AtomicUsize>,
+    }
+
+    impl RiskCheck for CountingCheck {
+        fn name(&self) -> &'static str {
+            "Counting"
+        }
+
+        fn check(&self, _order: &OrderRiskContext) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn order_approved(&self, _order: &OrderRiskContext) {
+            let _ = self.approved_count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn custom_check_is_notified_only_about_approved_orders() {
+        let test_order = TestOrder::new();
+        let risk_checks = test_order.risk_checks(RiskChecksSettings {
+            max_order_notional: Some(dec!(200)),
+            ..Default::default()
+        });
+        let approved_count = Arc::new(AtomicUsize::new(0));
+        risk_checks.add_check(
+            test_order.exchange.exchange_account_id,
+            Box::new(CountingCheck {
+                approved_count: approved_count.clone(),
+            }),
+        );
+
+        let now = Utc::now();
+        let approved_order = test_order.context(dec!(100), dec!(1), now);
+        assert_eq!(risk_checks.check(&approved_order), Ok(()));
+        assert!(risk_checks
+            .check(&test_order.context(dec!(100), dec!(3), now))
+            .is_err());
+        assert_eq!(approved_count.load(Ordering::SeqCst), 0);
+
+        risk_checks.order_approved(&approved_order);
+        assert_eq!(approved_count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,156 @@
+//! Data types passed between the engine and WebAssembly strategy plugins.
+//!
+//! Plugin ABI version 1. A plugin module should export:
+//! * `memory` - linear memory with declared maximum size;
+//! * `mmb_abi_version() -> i32` - returns [`ABI_VERSION`];
+//! * `mmb_alloc(len: i32) -> i32` - allocates `len` bytes and returns pointer to them;
+//! * `mmb_dealloc(ptr: i32, len: i32)` - frees memory allocated by `mmb_alloc`;
+//! * `mmb_calculate_trading_context(ptr: i32, len: i32) -> i64` - receives JSON
+//!   [`CalculateTradingContextRequest`] and returns packed pointer to JSON
+//!   [`CalculateTradingContextResponse`] or 0 if there is no trading context;
+//! * `mmb_handle_order_fill(ptr: i32, len: i32) -> i64` - receives JSON [`OrderFillRequest`] and
+//!   returns 0 on success or packed pointer to UTF-8 error message.
+//!
+//! Packed pointer is `(ptr << 32) | len`. Memory with request is allocated by host through
+//! `mmb_alloc` and freed by plugin. Memory with response is allocated by plugin and freed by host
+//! through `mmb_dealloc`.
+//!
+//! Plugin can import `mmb.log(level: i32, ptr: i32, len: i32)` to write UTF-8 message to engine
+//! log. Levels: 1 - error, 2 - warn, 3 - info, 4 - debug, 5 - trace.
+//!
+//! Decimal values are serialized as strings. Types of the engine aren't passed to plugin directly,
+//! so changes of their serialization don't break plugins.
+
+use mmb_domain::market::{CurrencyPair, ExchangeAccountId};
+use mmb_domain::order::snapshot::{Amount, OrderSide, Price};
+use serde::{Deserialize, Serialize};
+
+pub const ABI_VERSION: i32 = 1;
+
+/// Exchange account and currency pair
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Market {
+    /// Exchange name like `Binance`
+    pub exchange_id: String,
+    pub account_number: u8,
+    /// Currency code in lowercase like `btc`
+    pub base_currency: String,
+    pub quote_currency: String,
+}
+
+impl Market {
+    pub fn new(exchange_account_id: ExchangeAccountId, currency_pair: CurrencyPair) -> Self {
+        let codes = currency_pair.to_codes();
+        Market {
+            exchange_id: exchange_account_id.exchange_id.as_str().to_owned(),
+            account_number: exchange_account_id.account_number,
+            base_currency: codes.base.as_str().to_owned(),
+            quote_currency: codes.quote.as_str().to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl From<OrderSide> for Side {
+    fn from(side: OrderSide) -> Self {
+        match side {
+            OrderSide::Buy => Side::Buy,
+            OrderSide::Sell => Side::Sell,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    OrderBook,
+    Order,
+    BalanceUpdate,
+    LiquidationPrice,
+    Trades,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub price: Price,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderBookView {
+    /// Asks starting from the lowest price
+    pub asks: Vec<PriceLevel>,
+    /// Bids starting from the highest price
+    pub bids: Vec<PriceLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalculateTradingContextRequest {
+    /// Unix time in milliseconds
+    pub now: i64,
+    pub event: EventKind,
+    /// Market of strategy
+    pub market: Market,
+    /// Local order book snapshot of the strategy market if it was received
+    pub order_book: Option<OrderBookView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginPriceLevel {
+    pub price: Price,
+    pub amount: Amount,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalculateTradingContextResponse {
+    #[serde(default)]
+    pub buy: Vec<PluginPriceLevel>,
+    #[serde(default)]
+    pub sell: Vec<PluginPriceLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderFillRequest {
+    pub client_order_id: String,
+    pub exchange_order_id: Option<String>,
+    pub market: Market,
+    pub side: Side,
+    pub amount: Amount,
+    pub filled_amount: Amount,
+    pub last_fill_price: Option<Price>,
+    pub last_fill_amount: Option<Amount>,
+    /// Exchange account of strategy in format `<exchange id>_<account number>`
+    pub target_exchange_account_id: String,
+    pub price_slot_strategy_name: String,
+    pub price_slot_level_index: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market_serialization() {
+        let market = Market::new(
+            ExchangeAccountId::new("Binance", 1),
+            CurrencyPair::from_codes("btc".into(), "usdt".into()),
+        );
+
+        assert_eq!(
+            serde_json::to_string(&market).expect("in test"),
+            r#"{"exchange_id":"Binance","account_number":1,"base_currency":"btc","quote_currency":"usdt"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Side::from(OrderSide::Sell)).expect("in test"),
+            r#""sell""#
+        );
+    }
+}
@@ -0,0 +1,326 @@
+pub mod abi;
+pub mod runtime;
+
+use crate::disposition_execution::strategy::DispositionStrategy;
+use crate::disposition_execution::wasm_plugin::abi::{
+    CalculateTradingContextRequest, CalculateTradingContextResponse, EventKind, Market,
+    OrderBookView, OrderFillRequest, PluginPriceLevel, PriceLevel,
+};
+use crate::disposition_execution::wasm_plugin::runtime::{PluginLimits, WasmPlugin};
+use crate::disposition_execution::{
+    PriceSlot, TradeCycle, TradeDisposition, TradingContext, TradingContextBySide,
+    PRICE_SLOTS_COUNT,
+};
+use crate::explanation::{Explanation, WithExplanation};
+use crate::lifecycle::trading_engine::EngineContext;
+use crate::order_book::local_snapshot_service::LocalSnapshotsService;
+use crate::service_configuration::configuration_descriptor::ConfigurationDescriptor;
+use crate::settings::{BaseStrategySettings, CurrencyPairSetting};
+use anyhow::{Context, Result};
+use mmb_domain::events::ExchangeEvent;
+use mmb_domain::exchanges::symbol::{Round, Symbol};
+use mmb_domain::market::{CurrencyPair, ExchangeAccountId, MarketAccountId};
+use mmb_domain::order::snapshot::{Amount, OrderRole, OrderSide, OrderSnapshot, Price};
+use mmb_domain::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
+use mmb_utils::cancellation_token::CancellationToken;
+use mmb_utils::infrastructure::WithExpect;
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const DEFAULT_ORDER_BOOK_DEPTH: usize = 20;
+const DEFAULT_FUEL_PER_CALL: u64 = 10_000_000;
+const DEFAULT_MAX_MEMORY_BYTES: u32 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WasmStrategySettings {
+    pub currency_pair: CurrencyPairSetting,
+    pub max_amount: Amount,
+    pub exchange_account_id: ExchangeAccountId,
+    /// Path to `.wasm` file with strategy plugin
+    pub plugin_path: PathBuf,
+    /// Count of order book levels by side passed to plugin
+    pub order_book_depth: Option<usize>,
+    /// Fuel for one plugin call. Approximately equals to count of executed wasm instructions
+    pub fuel_per_call: Option<u64>,
+    /// Max size of plugin memory in bytes
+    pub max_memory_bytes: Option<u32>,
+}
+
+impl BaseStrategySettings for WasmStrategySettings {
+    fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange_account_id
+    }
+
+    fn currency_pair(&self) -> CurrencyPair {
+        match self.currency_pair {
+            CurrencyPairSetting::Ordinary { base, quote } => CurrencyPair::from_codes(base, quote),
+            CurrencyPairSetting::Specific(_) => panic!(
+                "Incorrect currency pair setting enum type {:?}",
+                self.currency_pair
+            ),
+        }
+    }
+
+    fn max_amount(&self) -> Amount {
+        self.max_amount
+    }
+}
+
+/// `DispositionStrategy` which delegates calculations to WebAssembly plugin loaded at runtime,
+/// so a strategy can be built out of tree without linking with `mmb_core`.
+/// See [`abi`] for the interface which plugin should implement
+pub struct WasmStrategy {
+    target_eai: ExchangeAccountId,
+    currency_pair: CurrencyPair,
+    symbol: Arc<Symbol>,
+    market: Market,
+    configuration_descriptor: ConfigurationDescriptor,
+    max_amount: Amount,
+    order_book_depth: usize,
+    plugin: Mutex<WasmPlugin>,
+}
+
+impl WasmStrategy {
+    pub fn new(
+        settings: &WasmStrategySettings,
+        engine_context: Arc<EngineContext>,
+    ) -> Result<Box<Self>> {
+        let target_eai = settings.exchange_account_id();
+        let currency_pair = settings.currency_pair();
+        let max_amount = settings.max_amount;
+
+        let wasm = fs::read(&settings.plugin_path).with_context(|| {
+            format!(
+                "Failed to read wasm plugin {}",
+                settings.plugin_path.display()
+            )
+        })?;
+
+        let plugin_name = settings.plugin_path.display().to_string();
+        let limits = PluginLimits {
+            fuel_per_call: settings.fuel_per_call.unwrap_or(DEFAULT_FUEL_PER_CALL),
+            max_memory_bytes: settings
+                .max_memory_bytes
+                .unwrap_or(DEFAULT_MAX_MEMORY_BYTES),
+        };
+        let plugin = WasmPlugin::load(plugin_name, &wasm, limits)?;
+
+        let configuration_descriptor = ConfigurationDescriptor::new(
+            "WasmStrategy".into(),
+            format!("{target_eai};{currency_pair}").as_str().into(),
+        );
+
+        let symbol = engine_context
+            .exchanges
+            .get(&target_eai)
+            .with_expect(|| format!("failed to get exchange from trading_engine for {target_eai}"))
+            .get_symbol(currency_pair)
+            .with_expect(|| format!("failed to get symbol from exchange for {currency_pair}"));
+
+        // the same limit as in ExampleStrategy: an order can change a position from a limit by
+        // sells to a limit by buys
+        let amount_limit = max_amount * dec!(0.5);
+        engine_context
+            .balance_manager
+            .lock()
+            .set_target_amount_limit(
+                configuration_descriptor,
+                target_eai,
+                symbol.clone(),
+                amount_limit,
+            );
+
+        Ok(Box::new(WasmStrategy {
+            target_eai,
+            currency_pair,
+            symbol,
+            market: Market::new(target_eai, currency_pair),
+            configuration_descriptor,
+            max_amount,
+            order_book_depth: settings
+                .order_book_depth
+                .unwrap_or(DEFAULT_ORDER_BOOK_DEPTH),
+            plugin: Mutex::new(plugin),
+        }))
+    }
+
+    fn market_account_id(&self) -> MarketAccountId {
+        MarketAccountId::new(self.target_eai, self.currency_pair)
+    }
+
+    fn to_order_book_view(&self, snapshot: &LocalOrderBookSnapshot) -> OrderBookView {
+        let to_levels = |levels: &mut dyn Iterator<Item = (&Price, &Amount)>| {
+            levels
+                .take(self.order_book_depth)
+                .map(|(&price, &amount)| PriceLevel { price, amount })
+                .collect()
+        };
+
+        OrderBookView {
+            asks: to_levels(&mut snapshot.get_asks_price_levels()),
+            bids: to_levels(&mut snapshot.get_bids_price_levels()),
+        }
+    }
+
+    fn to_trading_context_by_side(
+        &self,
+        side: OrderSide,
+        levels: Vec<PluginPriceLevel>,
+        plugin_name: &str,
+        explanation: &Explanation,
+    ) -> TradingContextBySide {
+        let trade_cycles = levels.into_iter().take(PRICE_SLOTS_COUNT).map(|level| {
+            let price_round = match side {
+                OrderSide::Buy => Round::Floor,
+                OrderSide::Sell => Round::Ceiling,
+            };
+            let price = self.symbol.price_round(level.price, price_round);
+            let amount = self.symbol.amount_round(level.amount, Round::Floor);
+
+            let mut explanation = explanation.clone();
+            explanation.add_reason(format!(
+                "Wasm plugin {plugin_name} returned price {} and amount {}",
+                level.price, level.amount
+            ));
+            explanation.add_reason(level.reason);
+
+            WithExplanation {
+                value: Some(TradeCycle {
+                    order_role: OrderRole::Maker,
+                    strategy_name: self.configuration_descriptor.service_name.to_string(),
+                    disposition: TradeDisposition::new(
+                        self.market_account_id(),
+                        side,
+                        price,
+                        amount,
+                    ),
+                }),
+                explanation,
+            }
+        });
+
+        let mut empty_slot_explanation = explanation.clone();
+        empty_slot_explanation.add_reason(format!(
+            "Wasm plugin {plugin_name} didn't return price level for slot"
+        ));
+
+        TradingContextBySide::from_trade_cycles(
+            self.max_amount,
+            trade_cycles,
+            &empty_slot_explanation,
+        )
+    }
+}
+
+fn event_kind(event: &ExchangeEvent) -> EventKind {
+    match event {
+        ExchangeEvent::OrderBookEvent(_) => EventKind::OrderBook,
+        ExchangeEvent::OrderEvent(_) => EventKind::Order,
+        ExchangeEvent::BalanceUpdate(_) => EventKind::BalanceUpdate,
+        ExchangeEvent::LiquidationPrice(_) => EventKind::LiquidationPrice,
+        ExchangeEvent::Trades(_) => EventKind::Trades,
+    }
+}
+
+/// Calls plugin and recreates its instance after failure because plugin state can be broken by trap
+fn call_plugin<T>(
+    plugin: &mut WasmPlugin,
+    call: impl FnOnce(&mut WasmPlugin) -> Result<T>,
+) -> Result<T> {
+    let result = call(plugin);
+    if result.is_err() {
+        if let Err(err) = plugin.reset() {
+            log::error!("Failed to reset wasm plugin {}: {err:?}", plugin.name());
+        }
+    }
+    result
+}
+
+impl DispositionStrategy for WasmStrategy {
+    fn calculate_trading_context(
+        &mut self,
+        event: &ExchangeEvent,
+        now: DateTime,
+        local_snapshots_service: &LocalSnapshotsService,
+        explanation: &mut Explanation,
+    ) -> Option<TradingContext> {
+        let request = CalculateTradingContextRequest {
+            now: now.timestamp_millis(),
+            event: event_kind(event),
+            market: self.market.clone(),
+            order_book: local_snapshots_service
+                .get_snapshot(self.market_account_id().market_id())
+                .map(|x| self.to_order_book_view(x)),
+        };
+
+        let plugin = &mut *self.plugin.lock();
+        let plugin_name = plugin.name().to_owned();
+        let response = call_plugin(plugin, |plugin| {
+            plugin.calculate_trading_context::<_, CalculateTradingContextResponse>(&request)
+        });
+
+        let response = match response {
+            Ok(response) => response?,
+            Err(err) => {
+                log::error!("Failed to calculate trading context by wasm plugin: {err:?}");
+                return None;
+            }
+        };
+
+        Some(TradingContext::new(
+            self.to_trading_context_by_side(
+                OrderSide::Buy,
+                response.buy,
+                &plugin_name,
+                explanation,
+            ),
+            self.to_trading_context_by_side(
+                OrderSide::Sell,
+                response.sell,
+                &plugin_name,
+                explanation,
+            ),
+        ))
+    }
+
+    fn handle_order_fill(
+        &self,
+        cloned_order: &Arc<OrderSnapshot>,
+        price_slot: &PriceSlot,
+        target_eai: ExchangeAccountId,
+        _cancellation_token: CancellationToken,
+    ) -> Result<()> {
+        let header = &cloned_order.header;
+        let last_fill = cloned_order.fills.fills.last();
+        let request = OrderFillRequest {
+            client_order_id: header.client_order_id.to_string(),
+            exchange_order_id: cloned_order
+                .props
+                .exchange_order_id
+                .as_ref()
+                .map(|x| x.to_string()),
+            market: Market::new(header.exchange_account_id, header.currency_pair),
+            side: header.side.into(),
+            amount: header.amount,
+            filled_amount: cloned_order.fills.filled_amount,
+            last_fill_price: last_fill.map(|x| x.price()),
+            last_fill_amount: last_fill.map(|x| x.amount()),
+            target_exchange_account_id: target_eai.to_string(),
+            price_slot_strategy_name: price_slot.id.strategy_name.clone(),
+            price_slot_level_index: price_slot.id.level_index,
+        };
+
+        call_plugin(&mut self.plugin.lock(), |plugin| {
+            plugin.handle_order_fill(&request)
+        })
+    }
+
+    fn configuration_descriptor(&self) -> ConfigurationDescriptor {
+        self.configuration_descriptor
+    }
+}
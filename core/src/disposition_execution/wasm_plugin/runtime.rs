@@ -0,0 +1,447 @@
+use crate::disposition_execution::wasm_plugin::abi::ABI_VERSION;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasmi::{Caller, Config, Engine, Func, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+const LOG_IMPORT_MODULE: &str = "mmb";
+const WASM_PAGE_SIZE: u32 = 64 * 1024;
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Limits of resources which plugin can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginLimits {
+    /// Fuel available for one call of plugin function. Approximately equals to count of
+    /// executed wasm instructions
+    pub fuel_per_call: u64,
+    /// Max size of plugin linear memory in bytes. Plugin should declare maximum of its memory
+    /// not greater than this value (e.g. with `-C link-arg=--max-memory=<bytes>` for Rust plugins)
+    pub max_memory_bytes: u32,
+}
+
+struct PluginExports {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    dealloc: TypedFunc<(i32, i32), ()>,
+    calculate_trading_context: TypedFunc<(i32, i32), i64>,
+    handle_order_fill: TypedFunc<(i32, i32), i64>,
+}
+
+/// Instantiated WebAssembly strategy plugin. All calls are executed with limited fuel, so a plugin
+/// with infinite loop traps instead of stalling the caller
+pub struct WasmPlugin {
+    name: String,
+    module: Module,
+    limits: PluginLimits,
+    store: Store<()>,
+    instance: Instance,
+    exports: PluginExports,
+    added_fuel: u64,
+}
+
+impl WasmPlugin {
+    pub fn load(name: String, wasm: &[u8], limits: PluginLimits) -> Result<Self> {
+        let mut config = Config::default();
+        let _ = config.consume_fuel(true);
+        let engine = Engine::new(&config);
+
+        let module = Module::new(&engine, wasm)
+            .map_err(|err| anyhow!("Failed to compile wasm plugin {name}: {err}"))?;
+
+        let (store, instance, exports) = instantiate(&name, &module, limits)?;
+
+        let mut plugin = WasmPlugin {
+            name,
+            module,
+            limits,
+            store,
+            instance,
+            exports,
+            added_fuel: limits.fuel_per_call,
+        };
+        plugin.check_abi_version()?;
+
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Recreate plugin instance with clean state. Should be called after a trap because
+    /// state of plugin memory can be inconsistent
+    pub fn reset(&mut self) -> Result<()> {
+        let (store, instance, exports) = instantiate(&self.name, &self.module, self.limits)?;
+        self.store = store;
+        self.instance = instance;
+        self.exports = exports;
+        self.added_fuel = self.limits.fuel_per_call;
+        Ok(())
+    }
+
+    /// Call `mmb_calculate_trading_context`. Returns `None` if plugin returned null pointer
+    pub fn calculate_trading_context<Req, Resp>(&mut self, request: &Req) -> Result<Option<Resp>>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let func = self.exports.calculate_trading_context;
+        let response = self.call_with_json(func, request)?;
+        response
+            .map(|bytes| serde_json::from_slice(&bytes))
+            .transpose()
+            .with_context(|| {
+                format!(
+                    "Failed to deserialize trading context from wasm plugin {}",
+                    self.name
+                )
+            })
+    }
+
+    /// Call `mmb_handle_order_fill`. Returns error with message from plugin if it wasn't handled
+    pub fn handle_order_fill<Req: Serialize>(&mut self, request: &Req) -> Result<()> {
+        let func = self.exports.handle_order_fill;
+        match self.call_with_json(func, request)? {
+            None => Ok(()),
+            Some(error) => bail!(
+                "Wasm plugin {} failed to handle order fill: {}",
+                self.name,
+                String::from_utf8_lossy(&error)
+            ),
+        }
+    }
+
+    fn check_abi_version(&mut self) -> Result<()> {
+        self.refuel()?;
+        let version =
+            get_typed_func::<(), i32>(&self.store, self.instance, &self.name, "mmb_abi_version")?
+                .call(&mut self.store, ())
+                .map_err(|err| anyhow!("Failed to get ABI version of {}: {err}", self.name))?;
+
+        if version != ABI_VERSION {
+            bail!(
+                "Wasm plugin {} has ABI version {version}, but engine supports version {ABI_VERSION}",
+                self.name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Top up fuel so that exactly `fuel_per_call` is available for the next call
+    fn refuel(&mut self) -> Result<()> {
+        let consumed = self.store.fuel_consumed().unwrap_or_default();
+        let remaining = self.added_fuel.saturating_sub(consumed);
+        let delta = self.limits.fuel_per_call.saturating_sub(remaining);
+
+        self.store
+            .add_fuel(delta)
+            .map_err(|err| anyhow!("Failed to add fuel for wasm plugin {}: {err}", self.name))?;
+        self.added_fuel += delta;
+        Ok(())
+    }
+
+    fn call_with_json<Req: Serialize>(
+        &mut self,
+        func: TypedFunc<(i32, i32), i64>,
+        request: &Req,
+    ) -> Result<Option<Vec<u8>>> {
+        let request = serde_json::to_vec(request)?;
+        let len = i32::try_from(request.len()).context("Request for wasm plugin is too long")?;
+
+        self.refuel()?;
+        let ptr = self
+            .exports
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|err| anyhow!("Failed to allocate memory in wasm plugin: {err}"))?;
+        self.exports
+            .memory
+            .write(&mut self.store, to_offset(ptr)?, &request)
+            .map_err(|err| anyhow!("Failed to write request to wasm plugin memory: {err}"))?;
+
+        let packed = func
+            .call(&mut self.store, (ptr, len))
+            .map_err(|err| anyhow!("Wasm plugin {} trapped: {err}", self.name))?;
+
+        if packed == 0 {
+            return Ok(None);
+        }
+
+        let (ptr, len) = unpack_ptr(packed);
+        let response = self.read_response(ptr, len)?;
+
+        self.exports
+            .dealloc
+            .call(&mut self.store, (ptr, len))
+            .map_err(|err| anyhow!("Failed to deallocate memory in wasm plugin: {err}"))?;
+
+        Ok(Some(response))
+    }
+
+    /// Copy response from plugin memory. Pointer and length are validated before allocation
+    /// on the host side because they are returned by plugin
+    fn read_response(&self, ptr: i32, len: i32) -> Result<Vec<u8>> {
+        let start = to_offset(ptr)?;
+        let len = to_offset(len)?;
+        if len > MAX_RESPONSE_BYTES {
+            bail!(
+                "Wasm plugin {} returned response of {len} bytes, but max size is {MAX_RESPONSE_BYTES} bytes",
+                self.name
+            );
+        }
+
+        let data = self.exports.memory.data(&self.store);
+        let response = start
+            .checked_add(len)
+            .and_then(|end| data.get(start..end))
+            .with_context(|| {
+                format!(
+                    "Wasm plugin {} returned response at {start}..{start}+{len} outside of its memory of {} bytes",
+                    self.name,
+                    data.len()
+                )
+            })?;
+
+        Ok(response.to_vec())
+    }
+}
+
+fn to_offset(value: i32) -> Result<usize> {
+    usize::try_from(value).with_context(|| format!("Invalid wasm plugin memory offset {value}"))
+}
+
+fn unpack_ptr(packed: i64) -> (i32, i32) {
+    ((packed >> 32) as i32, packed as i32)
+}
+
+fn instantiate(
+    name: &str,
+    module: &Module,
+    limits: PluginLimits,
+) -> Result<(Store<()>, Instance, PluginExports)> {
+    let mut store = Store::new(module.engine(), ());
+    let _ = store.add_fuel(limits.fuel_per_call);
+
+    let log = Func::wrap(
+        &mut store,
+        |caller: Caller<'_, ()>, level: i32, ptr: i32, len: i32| {
+            let message = caller
+                .get_export("memory")
+                .and_then(|x| x.into_memory())
+                .and_then(|memory| {
+                    let start = usize::try_from(ptr).ok()?;
+                    let end = start.checked_add(usize::try_from(len).ok()?)?;
+                    memory
+                        .data(&caller)
+                        .get(start..end)
+                        .map(|x| String::from_utf8_lossy(x).to_string())
+                });
+
+            let message = message.unwrap_or_else(|| "<invalid message pointer>".to_owned());
+            let level = match level {
+                1 => log::Level::Error,
+                2 => log::Level::Warn,
+                3 => log::Level::Info,
+                4 => log::Level::Debug,
+                _ => log::Level::Trace,
+            };
+            log::log!(level, "Wasm plugin: {message}");
+        },
+    );
+
+    let mut linker = Linker::<()>::new();
+    let _ = linker.define(LOG_IMPORT_MODULE, "log", log)?;
+
+    let instance = linker
+        .instantiate(&mut store, module)
+        .and_then(|x| x.start(&mut store))
+        .map_err(|err| anyhow!("Failed to instantiate wasm plugin {name}: {err}"))?;
+
+    let memory = instance
+        .get_memory(&store, "memory")
+        .with_context(|| format!("Wasm plugin {name} doesn't export 'memory'"))?;
+
+    let max_pages = limits.max_memory_bytes / WASM_PAGE_SIZE;
+    match memory.ty(&store).maximum_pages() {
+        Some(pages) if u32::from(pages) <= max_pages => (),
+        declared => bail!(
+            "Wasm plugin {name} should declare maximum memory size not greater than {} bytes, but declared {:?} pages",
+            limits.max_memory_bytes,
+            declared.map(u32::from)
+        ),
+    }
+
+    let exports = PluginExports {
+        memory,
+        alloc: get_typed_func(&store, instance, name, "mmb_alloc")?,
+        dealloc: get_typed_func(&store, instance, name, "mmb_dealloc")?,
+        calculate_trading_context: get_typed_func(
+            &store,
+            instance,
+            name,
+            "mmb_calculate_trading_context",
+        )?,
+        handle_order_fill: get_typed_func(&store, instance, name, "mmb_handle_order_fill")?,
+    };
+
+    Ok((store, instance, exports))
+}
+
+fn get_typed_func<Params, Results>(
+    store: &Store<()>,
+    instance: Instance,
+    plugin_name: &str,
+    func_name: &str,
+) -> Result<TypedFunc<Params, Results>>
+where
+    Params: wasmi::WasmParams,
+    Results: wasmi::WasmResults,
+{
+    instance
+        .get_typed_func(store, func_name)
+        .map_err(|err| anyhow!("Wasm plugin {plugin_name} doesn't export '{func_name}': {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposition_execution::wasm_plugin::abi::{
+        CalculateTradingContextResponse, PluginPriceLevel,
+    };
+    use rust_decimal_macros::dec;
+
+    const LIMITS: PluginLimits = PluginLimits {
+        fuel_per_call: 100_000,
+        max_memory_bytes: 4 * WASM_PAGE_SIZE,
+    };
+
+    const RESPONSE: &str =
+        r#"{"buy":[{"price":"99.5","amount":"1.5","reason":"from plugin"}],"sell":[]}"#;
+    const RESPONSE_OFFSET: i64 = 16;
+
+    fn plugin_wat(memory: &str, abi_version: i32, calculate_body: &str) -> Vec<u8> {
+        let wat = format!(
+            r#"(module
+                (import "mmb" "log" (func $log (param i32 i32 i32)))
+                (memory (export "memory") {memory})
+                (global $next (mut i32) (i32.const 1024))
+                (data (i32.const {RESPONSE_OFFSET}) "{}")
+                (func (export "mmb_abi_version") (result i32) i32.const {abi_version})
+                (func (export "mmb_alloc") (param $len i32) (result i32) (local $ptr i32)
+                    global.get $next
+                    local.set $ptr
+                    global.get $next
+                    local.get $len
+                    i32.add
+                    global.set $next
+                    local.get $ptr)
+                (func (export "mmb_dealloc") (param i32 i32))
+                (func (export "mmb_calculate_trading_context") (param i32 i32) (result i64)
+                    {calculate_body})
+                (func (export "mmb_handle_order_fill") (param i32 i32) (result i64)
+                    i32.const 3
+                    local.get 0
+                    local.get 1
+                    call $log
+                    i64.const 0)
+            )"#,
+            RESPONSE.replace('"', "\\\"")
+        );
+
+        wat::parse_str(wat).expect("in test")
+    }
+
+    fn response_ptr() -> String {
+        let packed = (RESPONSE_OFFSET << 32) | RESPONSE.len() as i64;
+        format!("i64.const {packed}")
+    }
+
+    #[test]
+    fn calculate_trading_context() {
+        let wasm = plugin_wat("1 4", ABI_VERSION, &response_ptr());
+        let mut plugin = WasmPlugin::load("test".to_owned(), &wasm, LIMITS).expect("in test");
+
+        let response = plugin
+            .calculate_trading_context::<_, CalculateTradingContextResponse>(&"request")
+            .expect("in test");
+
+        assert_eq!(
+            response,
+            Some(CalculateTradingContextResponse {
+                buy: vec![PluginPriceLevel {
+                    price: dec!(99.5),
+                    amount: dec!(1.5),
+                    reason: Some("from plugin".to_owned()),
+                }],
+                sell: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn no_trading_context() {
+        let wasm = plugin_wat("1 4", ABI_VERSION, "i64.const 0");
+        let mut plugin = WasmPlugin::load("test".to_owned(), &wasm, LIMITS).expect("in test");
+
+        let response = plugin
+            .calculate_trading_context::<_, CalculateTradingContextResponse>(&"request")
+            .expect("in test");
+
+        assert_eq!(response, None);
+        plugin.handle_order_fill(&"fill").expect("in test");
+    }
+
+    #[test]
+    fn infinite_loop_runs_out_of_fuel() {
+        let wasm = plugin_wat("1 4", ABI_VERSION, "(loop $l (br $l)) i64.const 0");
+        let mut plugin = WasmPlugin::load("test".to_owned(), &wasm, LIMITS).expect("in test");
+
+        for _ in 0..3 {
+            let result =
+                plugin.calculate_trading_context::<_, CalculateTradingContextResponse>(&"request");
+            assert!(result.is_err());
+        }
+
+        // other functions still get fuel after trap
+        plugin.reset().expect("in test");
+        plugin.handle_order_fill(&"fill").expect("in test");
+    }
+
+    #[test]
+    fn invalid_response_pointer_is_rejected() {
+        let too_long = (RESPONSE_OFFSET << 32) | i64::from(i32::MAX);
+        let outside_of_memory = (i64::from(WASM_PAGE_SIZE) << 32) | 16;
+
+        for packed in [too_long, outside_of_memory] {
+            let wasm = plugin_wat("1 4", ABI_VERSION, &format!("i64.const {packed}"));
+            let mut plugin = WasmPlugin::load("test".to_owned(), &wasm, LIMITS).expect("in test");
+
+            let result =
+                plugin.calculate_trading_context::<_, CalculateTradingContextResponse>(&"request");
+
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn memory_without_maximum_is_rejected() {
+        let wasm = plugin_wat("1", ABI_VERSION, "i64.const 0");
+
+        assert!(WasmPlugin::load("test".to_owned(), &wasm, LIMITS).is_err());
+    }
+
+    #[test]
+    fn too_big_memory_maximum_is_rejected() {
+        let wasm = plugin_wat("1 5", ABI_VERSION, "i64.const 0");
+
+        assert!(WasmPlugin::load("test".to_owned(), &wasm, LIMITS).is_err());
+    }
+
+    #[test]
+    fn unsupported_abi_version_is_rejected() {
+        let wasm = plugin_wat("1 4", ABI_VERSION + 1, "i64.const 0");
+
+        assert!(WasmPlugin::load("test".to_owned(), &wasm, LIMITS).is_err());
+    }
+}
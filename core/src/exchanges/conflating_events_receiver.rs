@@ -0,0 +1,461 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::Result;
+use mmb_domain::events::{ExchangeEvent, CHANNEL_MAX_EVENTS_COUNT};
+use mmb_domain::market::{MarketAccountId, MarketId};
+use mmb_domain::order_book::event::OrderBookEvent;
+use mmb_domain::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
+use mmb_utils::infrastructure::SpawnFutureFlags;
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Notify};
+
+use crate::exchanges::timeouts::timeout_manager;
+use crate::infrastructure::spawn_future;
+use crate::order_book::local_snapshot_service::LocalSnapshotsService;
+use crate::statistic_service::StatisticService;
+
+/// Order, balance and liquidation price events are delivered before any market data
+fn is_priority_event(event: &ExchangeEvent) -> bool {
+    matches!(
+        event,
+        ExchangeEvent::OrderEvent(_)
+            | ExchangeEvent::BalanceUpdate(_)
+            | ExchangeEvent::LiquidationPrice(_)
+    )
+}
+
+pub enum ReceivedEvent {
+    Exchange(ExchangeEvent),
+    /// Order book of market is changed since its previous delivery. Snapshot is shared with
+    /// receiver, so order book isn't copied on delivery
+    OrderBook {
+        market_account_id: MarketAccountId,
+        snapshot: Arc<LocalOrderBookSnapshot>,
+    },
+}
+
+struct QueuedEvent {
+    event: ExchangeEvent,
+    receipt_time: DateTime,
+}
+
+struct DeliveredEvent {
+    event: ReceivedEvent,
+    receipt_time: DateTime,
+    is_priority: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PushResult {
+    Queued,
+    /// Order book event is merged into state of market which is still waiting for delivery
+    Conflated,
+    /// Order book event can't be applied to local snapshot, e.g. it's already included into
+    /// snapshot or snapshot is resynchronized
+    Dropped,
+    /// Queue of ordinary events is full, so the oldest one is dropped
+    Overflowed,
+}
+
+/// Events received from events channel and not delivered to subscriber yet
+struct PendingEvents {
+    priority_events: VecDeque<QueuedEvent>,
+    ordinary_events: VecDeque<QueuedEvent>,
+    max_ordinary_events_count: usize,
+    order_books: LocalSnapshotsService,
+    /// Markets with changed order book in order of the first change since last delivery
+    changed_order_books: VecDeque<(MarketAccountId, DateTime)>,
+    is_closed: bool,
+}
+
+impl PendingEvents {
+    fn new(max_ordinary_events_count: usize) -> Self {
+        Self {
+            priority_events: VecDeque::new(),
+            ordinary_events: VecDeque::new(),
+            max_ordinary_events_count,
+            order_books: LocalSnapshotsService::default(),
+            changed_order_books: VecDeque::new(),
+            is_closed: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.priority_events.len() + self.ordinary_events.len() + self.changed_order_books.len()
+    }
+
+    fn push(&mut self, event: ExchangeEvent, receipt_time: DateTime) -> PushResult {
+        let queued_event = QueuedEvent {
+            event,
+            receipt_time,
+        };
+
+        match &queued_event.event {
+            ExchangeEvent::OrderBookEvent(order_book_event) => {
+                self.push_order_book_event(order_book_event, receipt_time)
+            }
+            event if is_priority_event(event) => {
+                self.priority_events.push_back(queued_event);
+                PushResult::Queued
+            }
+            _ => {
+                let is_overflowed = self.ordinary_events.len() >= self.max_ordinary_events_count;
+                if is_overflowed {
+                    let _ = self.ordinary_events.pop_front();
+                }
+                self.ordinary_events.push_back(queued_event);
+
+                match is_overflowed {
+                    true => PushResult::Overflowed,
+                    false => PushResult::Queued,
+                }
+            }
+        }
+    }
+
+    /// Order book event is applied to local snapshot immediately and the market is delivered once
+    /// with its latest state. Events which can't be applied (e.g. while resynchronization) are
+    /// dropped: subscriber gets the market after the next successful update
+    fn push_order_book_event(
+        &mut self,
+        event: &OrderBookEvent,
+        receipt_time: DateTime,
+    ) -> PushResult {
+        let market_account_id = match self.order_books.update(event) {
+            Some(market_account_id) => market_account_id,
+            None => return PushResult::Dropped,
+        };
+
+        let market_id = market_account_id.market_id();
+        match self
+            .changed_order_books
+            .iter_mut()
+            .find(|(x, _)| x.market_id() == market_id)
+        {
+            Some((pending_market_account_id, _)) => {
+                *pending_market_account_id = market_account_id;
+                PushResult::Conflated
+            }
+            None => {
+                self.changed_order_books
+                    .push_back((market_account_id, receipt_time));
+                PushResult::Queued
+            }
+        }
+    }
+
+    /// Priority events go first, other events and order books are delivered in order of receipt
+    fn pop(&mut self) -> Option<DeliveredEvent> {
+        if let Some(QueuedEvent {
+            event,
+            receipt_time,
+        }) = self.priority_events.pop_front()
+        {
+            return Some(DeliveredEvent {
+                event: ReceivedEvent::Exchange(event),
+                receipt_time,
+                is_priority: true,
+            });
+        }
+
+        loop {
+            let is_order_book_first = match (
+                self.ordinary_events.front(),
+                self.changed_order_books.front(),
+            ) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some(event), Some((_, receipt_time))) => *receipt_time < event.receipt_time,
+            };
+
+            if !is_order_book_first {
+                return self.ordinary_events.pop_front().map(|x| DeliveredEvent {
+                    event: ReceivedEvent::Exchange(x.event),
+                    receipt_time: x.receipt_time,
+                    is_priority: false,
+                });
+            }
+
+            let (market_account_id, receipt_time) = self.changed_order_books.pop_front()?;
+            if let Some(event) = self.order_book_state(market_account_id) {
+                return Some(DeliveredEvent {
+                    event,
+                    receipt_time,
+                    is_priority: false,
+                });
+            }
+        }
+    }
+
+    /// Latest state of market, `None` if snapshot was discarded for resync
+    fn order_book_state(&self, market_account_id: MarketAccountId) -> Option<ReceivedEvent> {
+        let market_id: MarketId = market_account_id.market_id();
+        if self.order_books.is_resyncing(market_id) {
+            return None;
+        }
+
+        Some(ReceivedEvent::OrderBook {
+            market_account_id,
+            snapshot: self.order_books.get_shared_snapshot(market_id)?,
+        })
+    }
+}
+
+/// Subscriber of events channel which never lags behind it. Order book events are conflated:
+/// only the latest state of each market is delivered, and order events are delivered before
+/// any market data, so bursts of market data don't delay handling of fills
+pub struct ConflatingEventsReceiver {
+    name: String,
+    pending_events: Arc<Mutex<PendingEvents>>,
+    notify: Arc<Notify>,
+    statistics: Arc<StatisticService>,
+}
+
+impl ConflatingEventsReceiver {
+    /// Starts reading of events channel in background
+    pub fn new(
+        name: &str,
+        events_receiver: broadcast::Receiver<ExchangeEvent>,
+        statistics: Arc<StatisticService>,
+    ) -> Self {
+        let receiver = Self {
+            name: name.to_owned(),
+            pending_events: Arc::new(Mutex::new(PendingEvents::new(CHANNEL_MAX_EVENTS_COUNT))),
+            notify: Arc::new(Notify::new()),
+            statistics,
+        };
+
+        let _ = spawn_future(
+            &format!("Receiving events for {name}"),
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            receive_events(
+                receiver.name.clone(),
+                events_receiver,
+                receiver.pending_events.clone(),
+                receiver.notify.clone(),
+                receiver.statistics.clone(),
+            ),
+        );
+
+        receiver
+    }
+
+    /// Waits for the next event. Returns `None` when events channel is closed
+    /// and all received events are delivered
+    pub async fn recv(&self) -> Option<ReceivedEvent> {
+        loop {
+            let notified = self.notify.notified();
+
+            {
+                let mut pending_events = self.pending_events.lock();
+                if let Some(delivered) = pending_events.pop() {
+                    self.statistics.register_delivered_event(
+                        &self.name,
+                        delivered.is_priority,
+                        timeout_manager::now() - delivered.receipt_time,
+                        pending_events.len(),
+                    );
+                    return Some(delivered.event);
+                }
+
+                if pending_events.is_closed {
+                    return None;
+                }
+            }
+
+            notified.await;
+        }
+    }
+}
+
+async fn receive_events(
+    name: String,
+    mut events_receiver: broadcast::Receiver<ExchangeEvent>,
+    pending_events: Arc<Mutex<PendingEvents>>,
+    notify: Arc<Notify>,
+    statistics: Arc<StatisticService>,
+) -> Result<()> {
+    loop {
+        match events_receiver.recv().await {
+            Ok(event) => {
+                let (push_result, pending_events_count) = {
+                    let mut pending_events = pending_events.lock();
+                    let push_result = pending_events.push(event, timeout_manager::now());
+                    (push_result, pending_events.len())
+                };
+                notify.notify_one();
+
+                statistics.register_received_event(&name, push_result, pending_events_count);
+            }
+            Err(RecvError::Lagged(skipped_count)) => {
+                // Skipped events are unknown, so order and balance events could be lost too
+                log::error!("{name} lagged behind events channel by {skipped_count} events, order and balance events can be lost");
+                statistics.register_lagged_events(&name, skipped_count);
+            }
+            Err(RecvError::Closed) => {
+                pending_events.lock().is_closed = true;
+                notify.notify_one();
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use mmb_domain::events::{BalanceUpdateEvent, ExchangeBalancesAndPositions, TradesEvent};
+    use mmb_domain::market::{CurrencyPair, ExchangeAccountId};
+    use mmb_domain::order::snapshot::Price;
+    use mmb_domain::order_book::event::{EventType, OrderBookSequence};
+    use mmb_domain::order_book_data;
+    use rust_decimal_macros::dec;
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("exchange_test_id", 0)
+    }
+
+    fn currency_pair() -> CurrencyPair {
+        CurrencyPair::from_codes("btc".into(), "usdt".into())
+    }
+
+    fn order_book_event(top_bid: Price) -> ExchangeEvent {
+        ExchangeEvent::OrderBookEvent(OrderBookEvent::new(
+            Utc::now(),
+            exchange_account_id(),
+            currency_pair(),
+            None,
+            EventType::Snapshot,
+            Arc::new(order_book_data![dec!(200) => dec!(1), ; top_bid => dec!(1),]),
+        ))
+    }
+
+    fn trades_event() -> ExchangeEvent {
+        ExchangeEvent::Trades(TradesEvent {
+            exchange_account_id: exchange_account_id(),
+            currency_pair: currency_pair(),
+            trades: Vec::new(),
+            receipt_time: Utc::now(),
+        })
+    }
+
+    fn balance_update_event() -> ExchangeEvent {
+        ExchangeEvent::BalanceUpdate(BalanceUpdateEvent {
+            exchange_account_id: exchange_account_id(),
+            balances_and_positions: ExchangeBalancesAndPositions {
+                balances: Vec::new(),
+                positions: None,
+            },
+        })
+    }
+
+    fn event_kind(delivered: &DeliveredEvent) -> &'static str {
+        match &delivered.event {
+            ReceivedEvent::OrderBook { .. } => "order_book",
+            ReceivedEvent::Exchange(ExchangeEvent::BalanceUpdate(_)) => "balance",
+            ReceivedEvent::Exchange(ExchangeEvent::Trades(_)) => "trades",
+            ReceivedEvent::Exchange(_) => "other",
+        }
+    }
+
+    #[test]
+    fn order_book_events_are_conflated() {
+        let mut pending_events = PendingEvents::new(10);
+        let now = Utc::now();
+
+        assert_eq!(
+            pending_events.push(order_book_event(dec!(100)), now),
+            PushResult::Queued
+        );
+        assert_eq!(
+            pending_events.push(order_book_event(dec!(101)), now),
+            PushResult::Conflated
+        );
+        assert_eq!(
+            pending_events.push(order_book_event(dec!(102)), now),
+            PushResult::Conflated
+        );
+        assert_eq!(pending_events.len(), 1);
+
+        let delivered = pending_events.pop().expect("in test");
+        match delivered.event {
+            ReceivedEvent::OrderBook {
+                market_account_id,
+                snapshot,
+            } => {
+                assert_eq!(market_account_id.currency_pair, currency_pair());
+                assert_eq!(snapshot.get_top_bid(), Some((dec!(102), dec!(1))));
+            }
+            _ => panic!("Expected order book event"),
+        }
+        assert!(pending_events.pop().is_none());
+    }
+
+    #[test]
+    fn not_applied_order_book_events_are_dropped() {
+        let mut pending_events = PendingEvents::new(10);
+        let now = Utc::now();
+        let order_book_event = |event_type, sequence| {
+            ExchangeEvent::OrderBookEvent(OrderBookEvent::new(
+                now,
+                exchange_account_id(),
+                currency_pair(),
+                Some(sequence),
+                event_type,
+                Arc::new(order_book_data![dec!(200) => dec!(1), ; dec!(100) => dec!(1),]),
+            ))
+        };
+
+        assert_eq!(
+            pending_events.push(
+                order_book_event(EventType::Snapshot, OrderBookSequence::snapshot(10)),
+                now
+            ),
+            PushResult::Queued
+        );
+        // update is already included into snapshot
+        assert_eq!(
+            pending_events.push(
+                order_book_event(EventType::Update, OrderBookSequence::new(5, 8)),
+                now
+            ),
+            PushResult::Dropped
+        );
+        assert_eq!(pending_events.len(), 1);
+    }
+
+    #[test]
+    fn priority_events_are_delivered_first() {
+        let mut pending_events = PendingEvents::new(2);
+        let start = Utc::now();
+
+        let _ = pending_events.push(trades_event(), start);
+        let _ = pending_events.push(order_book_event(dec!(100)), start + Duration::seconds(1));
+        let _ = pending_events.push(trades_event(), start + Duration::seconds(2));
+        assert_eq!(
+            pending_events.push(trades_event(), start + Duration::seconds(3)),
+            PushResult::Overflowed
+        );
+        let _ = pending_events.push(balance_update_event(), start + Duration::seconds(4));
+
+        let mut delivered = Vec::new();
+        while let Some(event) = pending_events.pop() {
+            delivered.push((event_kind(&event), event.is_priority));
+        }
+
+        assert_eq!(
+            delivered,
+            vec![
+                ("balance", true),
+                ("order_book", false),
+                ("trades", false),
+                ("trades", false),
+            ]
+        );
+    }
+}
@@ -8,6 +8,7 @@ use mmb_utils::{
 };
 use mmb_utils::{impl_mock_initializer, nothing_to_do};
 use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
@@ -27,7 +28,7 @@ use mockall::automock;
 const EXPECTED_EAI_SHOULD_BE_CREATED: &str =
     "Should exists because locks created for all exchange accounts in constructor";
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum ExchangeBlockerMoment {
     Blocked,
     BeforeUnblocked,
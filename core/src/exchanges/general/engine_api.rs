@@ -16,6 +16,12 @@ pub struct EngineApi {
     exchange: Arc<Exchange>,
 }
 
+impl EngineApi {
+    pub fn new(exchange: Arc<Exchange>) -> Self {
+        EngineApi { exchange }
+    }
+}
+
 #[cfg_attr(test, automock)]
 impl EngineApi {
     pub async fn close_active_positions(
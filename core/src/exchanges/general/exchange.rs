@@ -1,15 +1,20 @@
+use super::feed_liveness::{Feed, FeedKind, FeedLiveness};
 use super::polling_timeout_manager::PollingTimeoutManager;
 use crate::balance::manager::balance_manager::BalanceManager;
+use crate::connectivity::transport::Transport;
 use crate::connectivity::{
     websocket_open, ConnectivityError, WebSocketParams, WebSocketRole, WsSender,
 };
+use crate::database::events::audit::AuditEvent;
 use crate::database::events::recorder::EventRecorder;
+use crate::disposition_execution::risk_checks::RiskChecks;
 use crate::exchanges::block_reasons::WEBSOCKET_DISCONNECTED;
 use crate::exchanges::exchange_blocker::{BlockType, ExchangeBlocker};
 use crate::exchanges::general::features::{BalancePositionOption, ExchangeFeatures};
 use crate::exchanges::general::order::cancel::CancelOrderResult;
 use crate::exchanges::general::order::create::CreateOrderResult;
 use crate::exchanges::general::request_type::RequestType;
+use crate::exchanges::server_clock::ServerClock;
 use crate::exchanges::timeouts::requests_timeout_manager_factory::RequestTimeoutArguments;
 use crate::exchanges::timeouts::timeout_manager::TimeoutManager;
 use crate::exchanges::traits::{ExchangeClient, ExchangeError};
@@ -18,11 +23,13 @@ use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
 use crate::misc::time::time_manager;
 use crate::orders::buffered_fills::buffered_canceled_orders_manager::BufferedCanceledOrdersManager;
 use crate::orders::buffered_fills::buffered_fills_manager::BufferedFillsManager;
+use crate::services::kill_switch::KillSwitch;
 use anyhow::{bail, Context, Result};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use function_name::named;
 use itertools::Itertools;
 use mmb_database::impl_event;
+use mmb_domain::candle::Candle;
 use mmb_domain::events::{
     BalanceUpdateEvent, ExchangeBalance, ExchangeBalancesAndPositions, ExchangeEvent,
     LiquidationPriceEvent, Trade,
@@ -30,7 +37,8 @@ use mmb_domain::events::{
 use mmb_domain::exchanges::commission::Commission;
 use mmb_domain::exchanges::symbol::Symbol;
 use mmb_domain::market::{
-    CurrencyCode, CurrencyPair, ExchangeAccountId, MarketId, SpecificCurrencyPair,
+    CurrencyCode, CurrencyPair, ExchangeAccountId, ExchangeErrorType, MarketId,
+    SpecificCurrencyPair,
 };
 use mmb_domain::order::event::OrderEvent;
 use mmb_domain::order::event::OrderEventType;
@@ -48,6 +56,7 @@ use parking_lot::Mutex;
 use rust_decimal::Decimal;
 use serde::Serialize;
 use std::fmt::Debug;
+use std::iter;
 use std::ops::DerefMut;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
@@ -94,6 +103,7 @@ pub struct Exchange {
     pub currencies: Mutex<Vec<CurrencyCode>>,
     pub leverage_by_currency_pair: DashMap<CurrencyPair, Decimal>,
     pub order_book_top: DashMap<CurrencyPair, OrderBookTop>,
+    pub(super) order_book_resyncs: DashSet<CurrencyPair>,
     pub exchange_client: BoxExchangeClient,
     pub(super) features: ExchangeFeatures,
     pub(super) events_channel: broadcast::Sender<ExchangeEvent>,
@@ -107,8 +117,11 @@ pub struct Exchange {
     pub(super) orders_created_events: DashMap<ClientOrderId, oneshot::Sender<()>>,
     pub(super) last_trades_update_time: DashMap<MarketId, DateTime>,
     pub(super) last_trades: DashMap<MarketId, Trade>,
+    pub feed_liveness: FeedLiveness,
     pub(super) timeout_manager: Arc<TimeoutManager>,
     pub(crate) balance_manager: Mutex<Option<Weak<Mutex<BalanceManager>>>>,
+    pub(super) risk_checks: Mutex<Option<Arc<RiskChecks>>>,
+    pub(super) kill_switch: Mutex<Option<Weak<KillSwitch>>>,
     pub(super) buffered_fills_manager: Mutex<BufferedFillsManager>,
     pub(super) buffered_canceled_orders_manager: Mutex<BufferedCanceledOrdersManager>,
     // It allows to send and receive notification about event in websocket channel
@@ -131,6 +144,7 @@ pub struct Exchange {
     >,
     exchange_blocker: Weak<ExchangeBlocker>,
     ws_sender: Mutex<Option<WsSender>>,
+    transport: Arc<Transport>,
     auto_reconnect: AtomicBool,
 
     // Temporary fix before integration ExchangeBlocker to wait_order_finish/wait_cancel_order fallbacks #641
@@ -154,10 +168,12 @@ impl Exchange {
         exchange_blocker: Weak<ExchangeBlocker>,
         commission: Commission,
         event_recorder: Arc<EventRecorder>,
-    ) -> Arc<Self> {
+    ) -> Result<Arc<Self>> {
         let polling_timeout_manager = PollingTimeoutManager::new(timeout_arguments);
+        let transport = Transport::new(exchange_client.get_settings().connectivity.as_ref())
+            .context("Invalid connectivity settings")?;
 
-        Arc::new_cyclic(move |e| {
+        Ok(Arc::new_cyclic(move |e| {
             Self::setup_exchange_client(e.clone(), exchange_client.as_mut());
 
             let timeout = timeout_manager.get_period_duration(exchange_account_id);
@@ -166,6 +182,7 @@ impl Exchange {
                 exchange_client,
                 orders,
                 ws_sender: Default::default(),
+                transport: Arc::new(transport),
                 order_creation_events: DashMap::new(),
                 order_cancellation_events: DashMap::new(),
                 lifetime_manager,
@@ -176,6 +193,7 @@ impl Exchange {
                 symbols: Default::default(),
                 currencies: Default::default(),
                 order_book_top: Default::default(),
+                order_book_resyncs: Default::default(),
                 wait_cancel_order: DashMap::new(),
                 wait_finish_order: DashMap::new(),
                 polling_trades_counts: DashMap::new(),
@@ -185,7 +203,10 @@ impl Exchange {
                 leverage_by_currency_pair: DashMap::new(),
                 last_trades_update_time: DashMap::new(),
                 last_trades: DashMap::new(),
+                feed_liveness: Default::default(),
                 balance_manager: Mutex::new(None),
+                risk_checks: Mutex::new(None),
+                kill_switch: Mutex::new(None),
                 buffered_fills_manager: Default::default(),
                 exchange_blocker,
                 buffered_canceled_orders_manager: Default::default(),
@@ -193,7 +214,7 @@ impl Exchange {
                 timeout,
                 event_recorder,
             }
-        })
+        }))
     }
 
     fn setup_exchange_client(
@@ -252,6 +273,7 @@ impl Exchange {
     }
 
     fn on_websocket_message(&self, msg: &str) {
+        self.feed_liveness.register_message(Feed::websocket());
         self.maybe_log_websocket_message(msg);
 
         if let Err(error) = self.exchange_client.on_websocket_message(msg) {
@@ -283,6 +305,15 @@ impl Exchange {
             exchange_blocker.unblock(self.exchange_account_id, WEBSOCKET_DISCONNECTED);
         }
 
+        let market_feeds = self.subscribed_currency_pairs().into_iter().flat_map(|x| {
+            [
+                Feed::market(FeedKind::OrderBook, x),
+                Feed::market(FeedKind::Trades, x),
+            ]
+        });
+        self.feed_liveness
+            .register_subscriptions(iter::once(Feed::websocket()).chain(market_feeds));
+
         let callback_outcome = self.exchange_client.on_connected();
         if let Err(error) = callback_outcome {
             log::warn!(
@@ -343,6 +374,34 @@ impl Exchange {
         *self.balance_manager.lock() = Some(Arc::downgrade(&balance_manager));
     }
 
+    /// Risk checks are run for every order created through `create_order`
+    pub fn setup_risk_checks(&self, risk_checks: Arc<RiskChecks>) {
+        *self.risk_checks.lock() = Some(risk_checks);
+    }
+
+    /// Failed exchange requests are counted by kill switch errors trigger
+    pub fn setup_kill_switch(&self, kill_switch: &Arc<KillSwitch>) {
+        *self.kill_switch.lock() = Some(Arc::downgrade(kill_switch));
+    }
+
+    pub(super) fn register_request_error(&self, request_name: &str, error: &ExchangeError) {
+        // Order already completed or missing on exchange is an expected race, not a failure
+        if matches!(
+            error.error_type,
+            ExchangeErrorType::OrderNotFound | ExchangeErrorType::OrderCompleted
+        ) {
+            return;
+        }
+
+        let kill_switch = self.kill_switch.lock().as_ref().and_then(Weak::upgrade);
+        if let Some(kill_switch) = kill_switch {
+            kill_switch.register_error(&format!(
+                "{request_name} failed on {}: {error:?}",
+                self.exchange_account_id
+            ));
+        }
+    }
+
     pub async fn reconnect_ws(self: &Arc<Self>) -> Result<()> {
         self.disconnect_ws().await;
         self.connect_ws().await
@@ -469,12 +528,84 @@ impl Exchange {
         Ok(())
     }
 
+    pub fn is_cancel_all_after_supported(&self) -> bool {
+        self.features.order_features.supports_cancel_all_after
+    }
+
+    /// Arm exchange-side dead man's switch which cancels all orders on exchange if it isn't
+    /// rearmed within `timeout`
+    pub async fn cancel_all_orders_after(&self, timeout: Duration) -> Result<()> {
+        if !self.is_cancel_all_after_supported() {
+            bail!(
+                "Cancel all orders after timeout isn't supported by {}",
+                self.exchange_account_id
+            );
+        }
+
+        self.exchange_client.cancel_all_orders_after(timeout).await
+    }
+
+    pub fn is_server_time_supported(&self) -> bool {
+        self.features.supports_server_time
+    }
+
+    pub async fn get_server_time(&self) -> Result<DateTime> {
+        if !self.is_server_time_supported() {
+            bail!(
+                "Requesting server time isn't supported by {}",
+                self.exchange_account_id
+            );
+        }
+
+        self.exchange_client.get_server_time().await
+    }
+
+    pub fn is_klines_supported(&self) -> bool {
+        self.features.supports_klines
+    }
+
+    /// Last closed candles of currency pair from exchange, oldest first
+    pub async fn get_candles(
+        &self,
+        currency_pair: CurrencyPair,
+        interval_secs: u32,
+        limit: u32,
+    ) -> Result<Vec<Candle>> {
+        if !self.is_klines_supported() {
+            bail!(
+                "Requesting candles isn't supported by {}",
+                self.exchange_account_id
+            );
+        }
+
+        self.timeout_manager
+            .reserve_when_available(
+                self.exchange_account_id,
+                RequestType::GetCancelStick,
+                None,
+                self.lifetime_manager.stop_token(),
+            )
+            .await
+            .into_result()?;
+
+        self.exchange_client
+            .get_candles(currency_pair, interval_secs, limit)
+            .await
+    }
+
+    pub fn server_clock(&self) -> Arc<ServerClock> {
+        self.timeout_manager.server_clock(self.exchange_account_id)
+    }
+
     pub async fn get_websocket_params(
         self: &Arc<Self>,
         role: WebSocketRole,
     ) -> Result<WebSocketParams> {
         let ws_url = self.exchange_client.create_ws_url(role).await?;
-        Ok(WebSocketParams::new(ws_url))
+        Ok(WebSocketParams::with_transport(
+            ws_url,
+            self.transport.clone(),
+        ))
     }
 
     pub(crate) fn add_event_on_order_change(
@@ -491,6 +622,14 @@ impl Exchange {
             let _ = self.orders.not_finished.remove(&client_order_id);
         }
 
+        self.event_recorder
+            .save(AuditEvent::order(
+                self.exchange_account_id,
+                &event_type,
+                order_ref,
+            ))
+            .context("Unable to save order audit event")?;
+
         let event = ExchangeEvent::OrderEvent(OrderEvent::new(order_ref.clone(), event_type));
         self.events_channel
             .send(event)
@@ -529,6 +668,38 @@ impl Exchange {
         }
     }
 
+    /// Cancels opened orders of specified currency pair or all opened orders if it isn't specified
+    pub async fn cancel_opened_orders_by_currency_pair(
+        self: Arc<Self>,
+        currency_pair: Option<CurrencyPair>,
+        cancellation_token: CancellationToken,
+    ) {
+        let currency_pair = match currency_pair {
+            Some(currency_pair) => currency_pair,
+            None => return self.cancel_opened_orders(cancellation_token, true).await,
+        };
+
+        match self.get_open_orders(true).await {
+            Err(error) => {
+                log::error!(
+                    "Unable to get opened order for {} {currency_pair}: {error:?}",
+                    self.exchange_account_id
+                );
+            }
+            Ok(orders) => {
+                let orders = orders
+                    .into_iter()
+                    .filter(|x| x.currency_pair == currency_pair)
+                    .collect_vec();
+                self.cancel_orders(orders, cancellation_token).await;
+            }
+        }
+    }
+
+    pub fn commission(&self) -> &Commission {
+        &self.commission
+    }
+
     pub fn get_balance_reservation_currency_code(
         &self,
         symbol: Arc<Symbol>,
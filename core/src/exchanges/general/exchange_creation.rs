@@ -13,6 +13,7 @@ use crate::{
     },
     settings::CoreSettings,
 };
+use anyhow::Result;
 use mmb_domain::events::ExchangeEvent;
 use mmb_domain::exchanges::commission::Commission;
 use mmb_domain::order::pool::OrdersPool;
@@ -26,6 +27,7 @@ pub fn create_timeout_manager(
         .exchanges
         .iter()
         .map(|exchange_settings| {
+            // Limits per source IP are checked by `Transport`
             let timeout_arguments = build_settings.supported_exchange_clients
                 [&exchange_settings.exchange_account_id.exchange_id]
                 .get_timeout_arguments();
@@ -51,7 +53,7 @@ pub async fn create_exchange(
     timeout_manager: Arc<TimeoutManager>,
     exchange_blocker: Weak<ExchangeBlocker>,
     event_recorder: Arc<EventRecorder>,
-) -> Arc<Exchange> {
+) -> Result<Arc<Exchange>> {
     let exchange_account_id = user_settings.exchange_account_id;
     let exchange_client_builder =
         &build_settings.supported_exchange_clients[&exchange_account_id.exchange_id];
@@ -63,7 +65,7 @@ pub async fn create_exchange(
         lifetime_manager.clone(),
         timeout_manager.clone(),
         orders.clone(),
-    );
+    )?;
 
     let exchange = Exchange::new(
         exchange_account_id,
@@ -77,10 +79,10 @@ pub async fn create_exchange(
         exchange_blocker,
         Commission::default(),
         event_recorder,
-    );
+    )?;
 
     exchange.build_symbols(&user_settings.currency_pairs).await;
     exchange.exchange_client.initialized(exchange.clone()).await;
 
-    exchange
+    Ok(exchange)
 }
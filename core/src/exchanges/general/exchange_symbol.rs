@@ -8,7 +8,7 @@ use std::sync::Arc;
 
 use crate::settings::CurrencyPairSetting;
 use mmb_domain::exchanges::symbol::Symbol;
-use mmb_domain::market::{CurrencyId, ExchangeAccountId};
+use mmb_domain::market::{CurrencyId, CurrencyPair, ExchangeAccountId};
 
 use super::exchange::Exchange;
 
@@ -38,6 +38,23 @@ impl Exchange {
         ));
     }
 
+    /// Currency pairs from exchange settings which market data is received for
+    pub fn subscribed_currency_pairs(&self) -> Vec<CurrencyPair> {
+        let settings = self.exchange_client.get_settings();
+        if !settings.subscribe_to_market_data {
+            return Vec::new();
+        }
+
+        let symbols = self.symbols.iter().map(|x| x.value().clone()).collect_vec();
+        settings
+            .currency_pairs
+            .iter()
+            .flatten()
+            .filter_map(|x| get_matched_currency_pair(x, &symbols, self.exchange_account_id))
+            .map(|x| x.currency_pair())
+            .collect()
+    }
+
     async fn request_symbols_with_retries(&self) -> Vec<Arc<Symbol>> {
         const MAX_RETRIES: u8 = 5;
         for retry in 0..=MAX_RETRIES {
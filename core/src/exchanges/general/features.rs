@@ -66,9 +66,12 @@ pub struct OrderFeatures {
     pub order_was_completed_error_for_cancellation: bool,
     pub supports_already_cancelled_order: bool,
     pub supports_stop_loss_order: bool,
+    /// Exchange can cancel all orders by itself when timer armed by client isn't refreshed in time
+    pub supports_cancel_all_after: bool,
 }
 
 impl OrderFeatures {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         maker_only: bool,
         supports_get_order_info_by_client_order_id: bool,
@@ -77,6 +80,7 @@ impl OrderFeatures {
         order_was_completed_error_for_cancellation: bool,
         supports_already_cancelled_order: bool,
         supports_stop_loss_order: bool,
+        supports_cancel_all_after: bool,
     ) -> Self {
         Self {
             maker_only,
@@ -86,6 +90,7 @@ impl OrderFeatures {
             order_was_completed_error_for_cancellation,
             supports_already_cancelled_order,
             supports_stop_loss_order,
+            supports_cancel_all_after,
         }
     }
 }
@@ -116,6 +121,10 @@ pub struct ExchangeFeatures {
     pub websocket_options: WebSocketOptions,
     pub empty_response_is_ok: bool,
     pub balance_position_option: BalancePositionOption,
+    /// Exchange client is able to request server time for clock synchronization
+    pub supports_server_time: bool,
+    /// Exchange client is able to request history of candles
+    pub supports_klines: bool,
 
     // used only for debug
     pub allowed_create_event_source_type: AllowedEventSourceType,
@@ -149,6 +158,8 @@ impl ExchangeFeatures {
             allowed_fill_event_source_type,
             allowed_cancel_event_source_type,
             balance_position_option: BalancePositionOption::NonDerivative,
+            supports_server_time: false,
+            supports_klines: false,
         }
     }
 }
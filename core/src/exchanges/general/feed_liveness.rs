@@ -0,0 +1,116 @@
+use crate::exchanges::timeouts::timeout_manager;
+use dashmap::DashMap;
+use mmb_domain::market::CurrencyPair;
+use mmb_utils::DateTime;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeedKind {
+    /// Any message of websocket
+    Websocket,
+    OrderBook,
+    Trades,
+}
+
+/// Stream of data from exchange. Websocket feed isn't related to currency pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Feed {
+    pub kind: FeedKind,
+    pub currency_pair: Option<CurrencyPair>,
+}
+
+impl Feed {
+    pub fn websocket() -> Self {
+        Self {
+            kind: FeedKind::Websocket,
+            currency_pair: None,
+        }
+    }
+
+    pub fn market(kind: FeedKind, currency_pair: CurrencyPair) -> Self {
+        Self {
+            kind,
+            currency_pair: Some(currency_pair),
+        }
+    }
+}
+
+impl Display for Feed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.currency_pair {
+            None => write!(f, "{:?}", self.kind),
+            Some(currency_pair) => write!(f, "{:?} {currency_pair}", self.kind),
+        }
+    }
+}
+
+/// Time of the last received message for each feed of exchange. Feeds are tracked since their
+/// subscription, so feeds which never receive messages are considered stale too, while markets
+/// without subscription aren't
+#[derive(Default)]
+pub struct FeedLiveness {
+    last_message_times: DashMap<Feed, DateTime>,
+}
+
+impl FeedLiveness {
+    pub fn register_message(&self, feed: Feed) {
+        let _ = self.last_message_times.insert(feed, timeout_manager::now());
+    }
+
+    /// Start tracking feeds from now if they haven't received messages yet
+    pub fn register_subscriptions(&self, feeds: impl IntoIterator<Item = Feed>) {
+        let now = timeout_manager::now();
+        for feed in feeds {
+            let _ = self.last_message_times.entry(feed).or_insert(now);
+        }
+    }
+
+    pub fn last_message_times(&self) -> Vec<(Feed, DateTime)> {
+        self.last_message_times
+            .iter()
+            .map(|x| (*x.key(), *x.value()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_message_time(feed_liveness: &FeedLiveness, feed: Feed) -> Option<DateTime> {
+        feed_liveness
+            .last_message_times()
+            .into_iter()
+            .find(|(x, _)| *x == feed)
+            .map(|(_, time)| time)
+    }
+
+    #[test]
+    fn track_feeds_since_subscription() {
+        let feed_liveness = FeedLiveness::default();
+        let currency_pair = CurrencyPair::from_codes("BTC".into(), "USDT".into());
+        let order_book = Feed::market(FeedKind::OrderBook, currency_pair);
+        let trades = Feed::market(FeedKind::Trades, currency_pair);
+
+        feed_liveness.register_subscriptions([Feed::websocket(), order_book]);
+
+        assert!(last_message_time(&feed_liveness, Feed::websocket()).is_some());
+        assert!(last_message_time(&feed_liveness, order_book).is_some());
+        assert_eq!(last_message_time(&feed_liveness, trades), None);
+    }
+
+    #[test]
+    fn keep_last_message_time_on_subscription() {
+        let feed_liveness = FeedLiveness::default();
+        feed_liveness.register_message(Feed::websocket());
+        let message_time = last_message_time(&feed_liveness, Feed::websocket());
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        feed_liveness.register_subscriptions([Feed::websocket()]);
+
+        assert_eq!(
+            last_message_time(&feed_liveness, Feed::websocket()),
+            message_time
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::database::events::audit::AuditEvent;
 use crate::exchanges::general::handlers::should_ignore_event;
 use crate::{exchanges::general::exchange::Exchange, math::ConvertPercentToRate};
 use chrono::Utc;
@@ -18,6 +19,7 @@ use mmb_utils::DateTime;
 use parking_lot::RwLock;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -30,7 +32,7 @@ type ArgsToLog = (
     EventSourceType,
 );
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum FillAmount {
     Incremental {
         // Volume of order fill for current event
@@ -58,7 +60,7 @@ impl FillAmount {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpecialOrderData {
     // For ClosePosition order currency pair can be empty string
     pub currency_pair: CurrencyPair,
@@ -66,7 +68,7 @@ pub struct SpecialOrderData {
     pub order_amount: Amount,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FillEvent {
     pub source_type: EventSourceType,
     pub trade_id: Option<TradeId>,
@@ -88,6 +90,10 @@ impl Exchange {
     pub fn handle_order_filled(&self, fill_event: &mut FillEvent) {
         log::trace!(concat!("started ", function_name!(), " {:?}"), fill_event);
 
+        self.event_recorder
+            .save(AuditEvent::fill(self.exchange_account_id, fill_event))
+            .expect("Failure save fill audit event");
+
         let args_to_log = (
             self.exchange_account_id,
             fill_event.trade_id.clone(),
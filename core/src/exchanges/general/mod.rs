@@ -4,8 +4,10 @@ pub mod exchange;
 pub mod exchange_creation;
 pub mod exchange_symbol;
 pub mod features;
+pub mod feed_liveness;
 pub mod handlers;
 pub mod order;
+pub mod order_book_resync;
 pub mod polling_timeout_manager;
 pub mod request_type;
 
@@ -70,6 +70,9 @@ impl Exchange {
             }
             _ => {
                 order.fn_mut(|order| order.set_status(OrderStatus::Canceling, time_manager::now()));
+                self.event_recorder
+                    .save(order.clone())
+                    .expect("Failure save order");
 
                 log::info!(
                     "Submitting order cancellation {client_order_id} {exchange_order_id:?} on {}",
@@ -109,6 +112,7 @@ impl Exchange {
                     cancel_outcome.source_type,
                 ),
                 RequestResult::Error(error) => {
+                    self.register_request_error("cancel_order", error);
                     if error.error_type != ExchangeErrorType::ParsingError {
                         self.handle_cancel_order_failed(
                             &exchange_order_id,
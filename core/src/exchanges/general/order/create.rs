@@ -1,3 +1,4 @@
+use crate::disposition_execution::risk_checks::{OrderRiskContext, RiskCheckRejection};
 use crate::exchanges::general::exchange::RequestResult::{Error, Success};
 use crate::exchanges::general::handlers::should_ignore_event;
 use crate::exchanges::general::request_type::RequestType;
@@ -10,7 +11,7 @@ use chrono::Utc;
 use function_name::named;
 use futures::pin_mut;
 use mmb_domain::events::AllowedEventSourceType;
-use mmb_domain::market::{ExchangeAccountId, ExchangeErrorType};
+use mmb_domain::market::{ExchangeAccountId, ExchangeErrorType, MarketAccountId};
 use mmb_domain::order::event::OrderEventType;
 use mmb_domain::order::fill::EventSourceType;
 use mmb_domain::order::pool::OrderRef;
@@ -65,6 +66,20 @@ impl Exchange {
             self.exchange_client.get_initial_extension_data(),
         );
 
+        if let Err(rejection) = self.run_risk_checks(&order_to_create, true) {
+            let exchange_error =
+                ExchangeError::new(ExchangeErrorType::InvalidOrder, rejection.to_string(), None);
+            let args_to_log = (self.exchange_account_id, &order.client_order_id(), &None);
+            self.react_on_status_when_failed(
+                &order,
+                args_to_log,
+                EventSourceType::Rest,
+                &exchange_error,
+            )?;
+
+            return Err(rejection.into());
+        }
+
         let linked_ct = cancellation_token.create_linked_token();
 
         let create_order_fut = self.create_order_base(&order, linked_ct.clone());
@@ -186,6 +201,57 @@ impl Exchange {
         Ok(order)
     }
 
+    /// Returns rejection if order doesn't pass risk checks. Checks aren't notified about order,
+    /// so strategy can check order before creating it to explain rejection
+    pub fn check_risks(&self, order_to_create: &OrderCreating) -> Result<(), RiskCheckRejection> {
+        self.run_risk_checks(order_to_create, false)
+    }
+
+    /// Returns rejection if order doesn't pass risk checks. Stateful checks are notified only
+    /// about orders which are going to be sent
+    fn run_risk_checks(
+        &self,
+        order_to_create: &OrderCreating,
+        is_going_to_be_sent: bool,
+    ) -> Result<(), RiskCheckRejection> {
+        let risk_checks = match self.risk_checks.lock().clone() {
+            None => return Ok(()),
+            Some(risk_checks) => risk_checks,
+        };
+
+        let header = &order_to_create.header;
+        let symbol = match self.symbols.get(&header.currency_pair) {
+            // ClosePosition orders can be without currency pair
+            None => return Ok(()),
+            Some(symbol) => symbol.value().clone(),
+        };
+        let balance_manager = self
+            .balance_manager
+            .lock()
+            .as_ref()
+            .and_then(|x| x.upgrade());
+
+        let order = OrderRiskContext {
+            client_order_id: &header.client_order_id,
+            market_account_id: MarketAccountId::new(self.exchange_account_id, header.currency_pair),
+            symbol: &symbol,
+            side: header.side,
+            price: order_to_create.price,
+            amount: header.amount,
+            strategy_name: &header.strategy_name,
+            now: time_manager::now(),
+            exchange: self,
+            balance_manager: balance_manager.as_deref(),
+        };
+
+        risk_checks.check(&order)?;
+        if is_going_to_be_sent {
+            risk_checks.order_approved(&order);
+        }
+
+        Ok(())
+    }
+
     async fn handle_created_order(
         &self,
         order: &OrderRef,
@@ -501,6 +567,7 @@ impl Exchange {
                     )?;
                 }
                 Error(exchange_error) => {
+                    self.register_request_error("create_order", exchange_error);
                     if exchange_error.error_type != ExchangeErrorType::ParsingError {
                         self.handle_create_order_failed(
                             &client_order_id,
@@ -810,3 +877,76 @@ impl Exchange {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposition_execution::risk_checks::RiskChecks;
+    use crate::exchanges::general::test_helper::get_test_exchange;
+    use crate::infrastructure::init_lifetime_manager;
+    use crate::settings::{CoreSettings, ExchangeSettings, RiskChecksSettings};
+    use mmb_domain::order::snapshot::{OrderExecutionType, OrderHeader, OrderSide};
+    use rust_decimal_macros::dec;
+    use std::sync::Arc;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn order_rejected_by_risk_checks_is_not_sent_to_exchange() {
+        let _ = init_lifetime_manager();
+        // TestClient panics on creating order, so test fails if order is sent
+        let (exchange, _rx) = get_test_exchange(false);
+        let currency_pair = exchange
+            .symbols
+            .iter()
+            .next()
+            .expect("in test")
+            .currency_pair();
+        exchange.setup_risk_checks(Arc::new(RiskChecks::from_settings(&CoreSettings {
+            exchanges: vec![ExchangeSettings {
+                exchange_account_id: exchange.exchange_account_id,
+                risk_checks: Some(RiskChecksSettings {
+                    max_order_notional: Some(dec!(1)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })));
+
+        let client_order_id = ClientOrderId::unique_id();
+        let header = OrderHeader::new(
+            client_order_id.clone(),
+            exchange.exchange_account_id,
+            currency_pair,
+            OrderType::Limit,
+            OrderSide::Buy,
+            dec!(10),
+            OrderExecutionType::None,
+            None,
+            None,
+            "test_strategy".to_owned(),
+        );
+
+        let err = exchange
+            .create_order(
+                OrderCreating {
+                    header,
+                    price: dec!(1),
+                },
+                None,
+                CancellationToken::default(),
+            )
+            .await
+            .expect_err("in test");
+
+        let rejection = err.downcast_ref::<RiskCheckRejection>().expect("in test");
+        assert_eq!(rejection.check_name, "MaxOrderNotional");
+        let order = exchange
+            .orders
+            .cache_by_client_id
+            .get(&client_order_id)
+            .expect("in test")
+            .clone();
+        assert_eq!(order.status(), OrderStatus::FailedToCreate);
+        assert!(!exchange.orders.not_finished.contains_key(&client_order_id));
+    }
+}
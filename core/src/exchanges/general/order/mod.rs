@@ -4,5 +4,6 @@ pub mod create_websocket_based;
 pub mod get_info;
 pub mod get_open_orders;
 pub mod get_order_trades;
+pub mod recover;
 pub mod wait_cancel;
 pub mod wait_finish;
@@ -0,0 +1,298 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use itertools::Itertools;
+use mmb_domain::order::event::OrderEventType;
+use mmb_domain::order::fill::{EventSourceType, OrderFill};
+use mmb_domain::order::pool::OrderRef;
+use mmb_domain::order::snapshot::{OrderInfo, OrderSnapshot, OrderStatus};
+use mmb_utils::cancellation_token::CancellationToken;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::general::features::RestFillsType;
+use crate::exchanges::general::request_type::RequestType;
+
+/// Order restored from database after engine restart
+pub struct RecoveredOrder {
+    pub order: OrderRef,
+    /// Fills which happened on exchange while engine wasn't running
+    pub missed_fills: Vec<OrderFill>,
+}
+
+impl Exchange {
+    /// Add orders of the exchange which weren't finished before engine restart to orders pool and reconcile them
+    /// with exchange: missed fills are replayed, orders which aren't open anymore are finished,
+    /// open orders unknown to the engine are added as missed ones
+    pub async fn recover_orders(
+        &self,
+        snapshots: Vec<OrderSnapshot>,
+        cancellation_token: CancellationToken,
+    ) -> Result<Vec<RecoveredOrder>> {
+        let orders = snapshots
+            .into_iter()
+            .filter_map(|snapshot| self.add_recovered_order(snapshot))
+            .collect_vec();
+
+        if orders.is_empty() {
+            return Ok(vec![]);
+        }
+
+        log::info!(
+            "Recovering {} orders on {}",
+            orders.len(),
+            self.exchange_account_id
+        );
+
+        let open_orders = self
+            .get_open_orders(false)
+            .await
+            .context("getting open orders for orders recovery")?;
+
+        let mut recovered_orders = Vec::with_capacity(orders.len());
+        for order in orders {
+            let open_order = find_open_order(&order, &open_orders);
+            if let Some(open_order) = open_order {
+                self.restore_open_order_props(&order, open_order);
+            }
+
+            let fills_count = order.fn_ref(|x| x.fills.fills.len());
+
+            match order.exchange_order_id() {
+                None => self.finish_not_created_order(&order),
+                Some(exchange_order_id) => {
+                    let is_fills_reconciled = self
+                        .replay_missed_fills(&order, cancellation_token.clone())
+                        .await?;
+
+                    if open_order.is_none() && !order.is_finished() && !is_fills_reconciled {
+                        // Finishing the order here would lose fills which happened while engine wasn't running
+                        log::warn!(
+                            "Recovered order {} {exchange_order_id} isn't open on {} anymore but its fills weren't reconciled so it is left unfinished",
+                            order.client_order_id(),
+                            self.exchange_account_id
+                        );
+                    } else if open_order.is_none() && !order.is_finished() {
+                        log::info!(
+                            "Recovered order {} {exchange_order_id} isn't open on {} anymore",
+                            order.client_order_id(),
+                            self.exchange_account_id
+                        );
+
+                        self.handle_cancel_order_succeeded(
+                            Some(&order.client_order_id()),
+                            &exchange_order_id,
+                            Some(order.filled_amount()),
+                            EventSourceType::RestFallback,
+                        );
+                    }
+                }
+            }
+
+            let missed_fills = order.fn_ref(|x| x.fills.fills[fills_count..].to_vec());
+            recovered_orders.push(RecoveredOrder {
+                order,
+                missed_fills,
+            });
+        }
+
+        self.add_missing_open_orders(&open_orders);
+
+        Ok(recovered_orders)
+    }
+
+    fn add_recovered_order(&self, snapshot: OrderSnapshot) -> Option<OrderRef> {
+        let client_order_id = snapshot.header.client_order_id.clone();
+        if self
+            .orders
+            .cache_by_client_id
+            .contains_key(&client_order_id)
+        {
+            log::warn!(
+                "Recovered order {client_order_id} already exists on {}",
+                self.exchange_account_id
+            );
+            return None;
+        }
+
+        let exchange_order_id = snapshot.exchange_order_id();
+        let order = self
+            .orders
+            .add_snapshot_initial(Arc::new(RwLock::new(snapshot)));
+        if let Some(exchange_order_id) = exchange_order_id {
+            self.orders
+                .cache_by_exchange_id
+                .insert(exchange_order_id, order.clone());
+        }
+
+        Some(order)
+    }
+
+    fn restore_open_order_props(&self, order: &OrderRef, open_order: &OrderInfo) {
+        if order.exchange_order_id().is_none() {
+            // Creation response was lost because of restart
+            order
+                .fn_mut(|x| x.props.exchange_order_id = Some(open_order.exchange_order_id.clone()));
+            self.orders
+                .cache_by_exchange_id
+                .insert(open_order.exchange_order_id.clone(), order.clone());
+        }
+
+        // Cancellation was interrupted by restart so order should be cancelled again if needed
+        if matches!(
+            order.status(),
+            OrderStatus::Creating | OrderStatus::Canceling
+        ) {
+            order.fn_mut(|x| x.set_status(OrderStatus::Created, Utc::now()));
+        }
+    }
+
+    /// Request fills of the order which happened while engine wasn't running.
+    /// Exchanges without REST fills support are reconciled by the order info request
+    /// Returns `false` if filled amount of the order can't be reconciled with exchange
+    async fn replay_missed_fills(
+        &self,
+        order: &OrderRef,
+        cancellation_token: CancellationToken,
+    ) -> Result<bool> {
+        let request_type = match self.features.rest_fills_features.fills_type {
+            RestFillsType::MyTrades => RequestType::GetOrderTrades,
+            RestFillsType::None | RestFillsType::GetOrderInfo => RequestType::GetOrderInfo,
+        };
+
+        let symbol = self.get_symbol(order.currency_pair())?;
+        let result = self
+            .check_order_fills_using_request_type(
+                order,
+                &symbol,
+                request_type,
+                None,
+                cancellation_token,
+            )
+            .await?;
+
+        match result.get_error() {
+            None => Ok(true),
+            Some(error) => {
+                log::warn!(
+                    "Unable to get fills for recovered order {} on {}: {error:?}",
+                    order.client_order_id(),
+                    self.exchange_account_id
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    fn finish_not_created_order(&self, order: &OrderRef) {
+        log::info!(
+            "Recovered order {} wasn't created on {}",
+            order.client_order_id(),
+            self.exchange_account_id
+        );
+
+        order.fn_mut(|x| x.set_status(OrderStatus::FailedToCreate, Utc::now()));
+        if let Err(err) = self.add_event_on_order_change(order, OrderEventType::CreateOrderFailed) {
+            log::error!("Failed to add CreateOrderFailed event for recovered order: {err:?}");
+        }
+
+        self.event_recorder
+            .save(order.clone())
+            .expect("Failure save order");
+    }
+}
+
+fn find_open_order<'a>(order: &OrderRef, open_orders: &'a [OrderInfo]) -> Option<&'a OrderInfo> {
+    let (client_order_id, exchange_order_id) = order.order_ids();
+    open_orders.iter().find(|x| match &exchange_order_id {
+        Some(exchange_order_id) => x.exchange_order_id == *exchange_order_id,
+        None => x.client_order_id == client_order_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::general::test_helper::get_test_exchange;
+    use mmb_domain::market::CurrencyPair;
+    use mmb_domain::order::snapshot::{ClientOrderId, ExchangeOrderId, OrderSide, OrderType};
+    use rust_decimal_macros::dec;
+
+    fn order_snapshot(exchange: &Exchange, exchange_order_id: Option<&str>) -> OrderSnapshot {
+        let mut snapshot = OrderSnapshot::with_params(
+            ClientOrderId::unique_id(),
+            OrderType::Limit,
+            None,
+            exchange.exchange_account_id,
+            CurrencyPair::from_codes("phb".into(), "btc".into()),
+            dec!(0.8),
+            dec!(12),
+            OrderSide::Buy,
+            None,
+            "StrategyInUnitTests",
+        );
+        snapshot.props.exchange_order_id = exchange_order_id.map(|x| x.into());
+        snapshot
+    }
+
+    fn open_order(snapshot: &OrderSnapshot, exchange_order_id: &str) -> OrderInfo {
+        OrderInfo::new(
+            snapshot.currency_pair(),
+            exchange_order_id.into(),
+            snapshot.client_order_id(),
+            snapshot.side(),
+            OrderStatus::Created,
+            snapshot.price(),
+            snapshot.amount(),
+            dec!(0),
+            dec!(0),
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn add_recovered_order_to_pool() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let snapshot = order_snapshot(&exchange, Some("exchange_order_id"));
+        let client_order_id = snapshot.client_order_id();
+
+        let order = exchange.add_recovered_order(snapshot.clone());
+        let duplicate = exchange.add_recovered_order(snapshot);
+
+        assert!(order.is_some());
+        assert!(duplicate.is_none());
+        assert!(exchange
+            .orders
+            .cache_by_client_id
+            .contains_key(&client_order_id));
+        assert!(exchange.orders.not_finished.contains_key(&client_order_id));
+        assert!(exchange
+            .orders
+            .cache_by_exchange_id
+            .contains_key(&ExchangeOrderId::from("exchange_order_id")));
+    }
+
+    #[tokio::test]
+    async fn restore_exchange_order_id_of_creating_order() {
+        let (exchange, _rx) = get_test_exchange(false);
+        let snapshot = order_snapshot(&exchange, None);
+        let open_orders = vec![
+            open_order(&order_snapshot(&exchange, None), "other_order"),
+            open_order(&snapshot, "exchange_order_id"),
+        ];
+        let order = exchange.add_recovered_order(snapshot).expect("in test");
+
+        let open_order = find_open_order(&order, &open_orders).expect("in test");
+        exchange.restore_open_order_props(&order, open_order);
+
+        let exchange_order_id = ExchangeOrderId::from("exchange_order_id");
+        assert_eq!(order.exchange_order_id(), Some(exchange_order_id.clone()));
+        assert_eq!(order.status(), OrderStatus::Created);
+        assert!(exchange
+            .orders
+            .cache_by_exchange_id
+            .contains_key(&exchange_order_id));
+    }
+}
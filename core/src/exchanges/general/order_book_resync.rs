@@ -0,0 +1,97 @@
+use crate::exchanges::common::send_event;
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::general::request_type::RequestType;
+use crate::infrastructure::spawn_future;
+use anyhow::Result;
+use mmb_domain::events::ExchangeEvent;
+use mmb_domain::market::CurrencyPair;
+use mmb_utils::infrastructure::SpawnFutureFlags;
+use std::sync::Arc;
+use std::time::Duration;
+
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+impl Exchange {
+    /// Market isn't ready for trading while its local order book is resynchronized
+    pub fn is_order_book_ready(&self, currency_pair: CurrencyPair) -> bool {
+        !self.order_book_resyncs.contains(&currency_pair)
+    }
+
+    /// Requests full order book snapshot via REST until success and sends it as `OrderBookEvent`.
+    /// Does nothing if resynchronization of the currency pair is already in progress
+    pub fn resync_order_book(self: &Arc<Self>, currency_pair: CurrencyPair) {
+        if !self.order_book_resyncs.insert(currency_pair) {
+            return;
+        }
+
+        let _ = self.order_book_top.remove(&currency_pair);
+
+        log::info!(
+            "Order book resynchronization of {} {currency_pair} started",
+            self.exchange_account_id
+        );
+
+        let action = format!(
+            "Order book resynchronization of {} {currency_pair}",
+            self.exchange_account_id
+        );
+        let future = self.clone().request_order_book_snapshot(currency_pair);
+        let _ = spawn_future(
+            &action,
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            future,
+        );
+    }
+
+    /// Should be called when snapshot is received, so market is ready again
+    pub fn finish_order_book_resync(&self, currency_pair: CurrencyPair) {
+        if self.order_book_resyncs.remove(&currency_pair).is_some() {
+            log::info!(
+                "Order book resynchronization of {} {currency_pair} finished",
+                self.exchange_account_id
+            );
+        }
+    }
+
+    async fn request_order_book_snapshot(
+        self: Arc<Self>,
+        currency_pair: CurrencyPair,
+    ) -> Result<()> {
+        let cancellation_token = self.lifetime_manager.stop_token();
+        loop {
+            self.timeout_manager
+                .reserve_when_available(
+                    self.exchange_account_id,
+                    RequestType::GetOrderBook,
+                    None,
+                    cancellation_token.clone(),
+                )
+                .await
+                .into_result()?;
+
+            match self
+                .exchange_client
+                .get_order_book_snapshot(currency_pair)
+                .await
+            {
+                Ok(order_book_event) => {
+                    return send_event(
+                        &self.events_channel,
+                        self.lifetime_manager.clone(),
+                        self.exchange_account_id,
+                        ExchangeEvent::OrderBookEvent(order_book_event),
+                    );
+                }
+                Err(err) => log::warn!(
+                    "Unable to get order book snapshot of {} {currency_pair}: {err:?}",
+                    self.exchange_account_id
+                ),
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(RETRY_DELAY) => {}
+                _ = cancellation_token.when_cancelled() => return Ok(()),
+            }
+        }
+    }
+}
@@ -27,6 +27,7 @@ use async_trait::async_trait;
 use chrono::Duration;
 use dashmap::DashMap;
 use futures::executor::block_on;
+use mmb_domain::candle::Candle;
 use mmb_domain::events::{AllowedEventSourceType, ExchangeBalancesAndPositions, ExchangeEvent};
 use mmb_domain::exchanges::commission::{Commission, CommissionForType};
 use mmb_domain::exchanges::symbol::{BeforeAfter, Precision, Symbol};
@@ -38,6 +39,7 @@ use mmb_domain::order::snapshot::{Amount, Price};
 use mmb_domain::order::snapshot::{
     ClientOrderId, OrderCancelling, OrderInfo, OrderRole, OrderSide, OrderSnapshot, OrderType,
 };
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::position::{ActivePosition, ClosedPosition};
 use parking_lot::RwLock;
 use rust_decimal_macros::dec;
@@ -55,7 +57,9 @@ use mmb_utils::{cancellation_token::CancellationToken, hashmap, DateTime};
 
 use super::order::get_order_trades::OrderTrade;
 
-pub struct TestClient;
+pub struct TestClient {
+    settings: ExchangeSettings,
+}
 
 #[async_trait]
 impl ExchangeClient for TestClient {
@@ -71,6 +75,10 @@ impl ExchangeClient for TestClient {
         unimplemented!("doesn't need in UT")
     }
 
+    async fn cancel_all_orders_after(&self, _timeout: std::time::Duration) -> Result<()> {
+        unimplemented!("doesn't need in UT")
+    }
+
     async fn get_open_orders(&self) -> Result<Vec<OrderInfo>> {
         unimplemented!("doesn't need in UT")
     }
@@ -117,6 +125,26 @@ impl ExchangeClient for TestClient {
     async fn build_all_symbols(&self) -> Result<Vec<Arc<Symbol>>> {
         unimplemented!("doesn't need in UT")
     }
+
+    async fn get_server_time(&self) -> Result<DateTime> {
+        unimplemented!("doesn't need in UT")
+    }
+
+    async fn get_order_book_snapshot(
+        &self,
+        _currency_pair: CurrencyPair,
+    ) -> Result<OrderBookEvent> {
+        unimplemented!("doesn't need in UT")
+    }
+
+    async fn get_candles(
+        &self,
+        _currency_pair: CurrencyPair,
+        _interval_secs: u32,
+        _limit: u32,
+    ) -> Result<Vec<Candle>> {
+        unimplemented!("doesn't need in UT")
+    }
 }
 
 #[async_trait]
@@ -185,7 +213,7 @@ impl Support for TestClient {
     }
 
     fn get_settings(&self) -> &ExchangeSettings {
-        unimplemented!("doesn't need in UT")
+        &self.settings
     }
 }
 
@@ -254,7 +282,12 @@ pub(crate) fn get_test_exchange_with_symbol_and_id(
     let lifetime_manager = AppLifetimeManager::new(CancellationToken::new());
     let (tx, rx) = broadcast::channel(10);
 
-    let exchange_client = Box::new(TestClient);
+    let exchange_client = Box::new(TestClient {
+        settings: ExchangeSettings {
+            exchange_account_id,
+            ..ExchangeSettings::default()
+        },
+    });
     let referral_reward = dec!(40);
     let commission = Commission::new(
         CommissionForType::new(dec!(0.1), referral_reward),
@@ -297,7 +330,8 @@ pub(crate) fn get_test_exchange_with_symbol_and_id(
         Arc::downgrade(&exchange_blocker),
         commission,
         event_recorder,
-    );
+    )
+    .expect("in test");
 
     exchange
         .leverage_by_currency_pair
@@ -1,13 +1,120 @@
-#[derive(Clone)]
+use crate::settings::ExchangeSettings;
+use anyhow::{bail, Result};
+use itertools::Itertools;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Hosts {
-    pub web_socket_host: &'static str,
+    pub web_socket_host: String,
     // Some exchanges have two websockets, for public and private data
+    pub web_socket2_host: String,
+    pub rest_host: String,
+}
+
+/// Named set of hosts of exchange like `mainnet` or `testnet`
+pub struct HostsEnvironment {
+    pub name: &'static str,
+    pub web_socket_host: &'static str,
     pub web_socket2_host: &'static str,
     pub rest_host: &'static str,
 }
 
+pub const MAINNET: &str = "mainnet";
+pub const TESTNET: &str = "testnet";
+
 impl Hosts {
+    /// Host of REST API for `UriBuilder`. Scheme is kept if it isn't `https`, e.g. for mock servers
     pub fn rest_uri_host(&self) -> &str {
-        &self.rest_host[8..]
+        self.rest_host
+            .strip_prefix("https://")
+            .unwrap_or(&self.rest_host)
+    }
+
+    /// Plain `http` is allowed only if it is explicitly configured in hosts overrides
+    pub fn is_http_rest_host(&self) -> bool {
+        self.rest_host.starts_with("http://")
+    }
+
+    /// Hosts of environment selected in exchange settings with overridden hosts from settings.
+    /// The first environment is used if it isn't specified in settings
+    pub fn from_settings(
+        settings: &ExchangeSettings,
+        environments: &[HostsEnvironment],
+    ) -> Result<Hosts> {
+        let environment = match &settings.environment {
+            None => environments.first(),
+            Some(name) => environments.iter().find(|x| x.name == name),
+        };
+        let environment = match environment {
+            Some(environment) => environment,
+            None => bail!(
+                "Unknown environment {:?} for exchange {}, available environments: {}",
+                settings.environment,
+                settings.exchange_account_id,
+                environments.iter().map(|x| x.name).join(", ")
+            ),
+        };
+
+        let overrides = settings.hosts.clone().unwrap_or_default();
+        Ok(Hosts {
+            web_socket_host: overrides
+                .web_socket_host
+                .unwrap_or_else(|| environment.web_socket_host.to_owned()),
+            web_socket2_host: overrides
+                .web_socket2_host
+                .unwrap_or_else(|| environment.web_socket2_host.to_owned()),
+            rest_host: overrides
+                .rest_host
+                .unwrap_or_else(|| environment.rest_host.to_owned()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::HostsSettings;
+
+    const ENVIRONMENTS: &[HostsEnvironment] = &[
+        HostsEnvironment {
+            name: MAINNET,
+            web_socket_host: "wss://stream.exchange.com",
+            web_socket2_host: "wss://stream2.exchange.com",
+            rest_host: "https://api.exchange.com",
+        },
+        HostsEnvironment {
+            name: TESTNET,
+            web_socket_host: "wss://testnet.exchange.com",
+            web_socket2_host: "wss://testnet.exchange.com",
+            rest_host: "https://testnet.exchange.com",
+        },
+    ];
+
+    #[test]
+    fn select_environment_and_override_hosts() {
+        let mut settings = ExchangeSettings::default();
+        let hosts = Hosts::from_settings(&settings, ENVIRONMENTS).expect("in test");
+        assert_eq!(hosts.rest_host, "https://api.exchange.com");
+        assert_eq!(hosts.rest_uri_host(), "api.exchange.com");
+        assert!(!hosts.is_http_rest_host());
+
+        settings.environment = Some(TESTNET.to_owned());
+        settings.hosts = Some(HostsSettings {
+            rest_host: Some("http://127.0.0.1:8080".to_owned()),
+            ..HostsSettings::default()
+        });
+        let hosts = Hosts::from_settings(&settings, ENVIRONMENTS).expect("in test");
+        assert_eq!(
+            hosts,
+            Hosts {
+                web_socket_host: "wss://testnet.exchange.com".to_owned(),
+                web_socket2_host: "wss://testnet.exchange.com".to_owned(),
+                rest_host: "http://127.0.0.1:8080".to_owned(),
+            }
+        );
+        assert_eq!(hosts.rest_uri_host(), "http://127.0.0.1:8080");
+        assert!(hosts.is_http_rest_host());
+
+        settings.environment = Some("unknown".to_owned());
+        assert!(Hosts::from_settings(&settings, ENVIRONMENTS).is_err());
     }
 }
@@ -9,13 +9,14 @@ use parking_lot::Mutex;
 use tokio::sync::{broadcast, oneshot};
 
 use crate::exchanges::general::exchange::{Exchange, OrderBookTop, PriceLevel};
+use crate::exchanges::general::feed_liveness::{Feed, FeedKind};
 use crate::lifecycle::trading_engine::Service;
 use crate::order_book::local_snapshot_service::LocalSnapshotsService;
 use mmb_domain::events::ExchangeEvent;
 use mmb_domain::market::ExchangeAccountId;
 use mmb_domain::order::event::OrderEventType;
 use mmb_domain::order::snapshot::OrderType;
-use mmb_domain::order_book::event::OrderBookEvent;
+use mmb_domain::order_book::event::{EventType, OrderBookEvent};
 
 pub(crate) struct InternalEventsLoop {
     work_finished_receiver: Mutex<Option<oneshot::Receiver<Result<()>>>>,
@@ -49,11 +50,17 @@ impl InternalEventsLoop {
 
             match event {
                 ExchangeEvent::OrderBookEvent(ref order_book_event) => {
+                    register_feed_message(
+                        &exchanges_map,
+                        order_book_event.exchange_account_id,
+                        Feed::market(FeedKind::OrderBook, order_book_event.currency_pair),
+                    );
                     update_order_book_top_for_exchange(
                         order_book_event,
                         &mut local_snapshots_service,
                         &exchanges_map,
-                    )
+                    );
+                    sync_order_book(order_book_event, &local_snapshots_service, &exchanges_map);
                 }
                 ExchangeEvent::OrderEvent(order_event) => {
                     let target_eai = order_event.order.exchange_account_id();
@@ -81,12 +88,26 @@ impl InternalEventsLoop {
                 }
                 ExchangeEvent::BalanceUpdate(_) => {}
                 ExchangeEvent::LiquidationPrice(_) => {}
-                ExchangeEvent::Trades(_) => {}
+                ExchangeEvent::Trades(trades_event) => register_feed_message(
+                    &exchanges_map,
+                    trades_event.exchange_account_id,
+                    Feed::market(FeedKind::Trades, trades_event.currency_pair),
+                ),
             }
         }
     }
 }
 
+fn register_feed_message(
+    exchanges_map: &HashMap<ExchangeAccountId, Arc<Exchange>>,
+    exchange_account_id: ExchangeAccountId,
+    feed: Feed,
+) {
+    if let Some(exchange) = exchanges_map.get(&exchange_account_id) {
+        exchange.feed_liveness.register_message(feed);
+    }
+}
+
 fn update_order_book_top_for_exchange(
     order_book_event: &OrderBookEvent,
     local_snapshots_service: &mut LocalSnapshotsService,
@@ -115,6 +136,25 @@ fn update_order_book_top_for_exchange(
     }
 }
 
+/// Requests order book snapshot if local snapshot is discarded because of missed updates
+fn sync_order_book(
+    order_book_event: &OrderBookEvent,
+    local_snapshots_service: &LocalSnapshotsService,
+    exchanges_map: &HashMap<ExchangeAccountId, Arc<Exchange>>,
+) {
+    let exchange = match exchanges_map.get(&order_book_event.exchange_account_id) {
+        Some(exchange) => exchange,
+        None => return,
+    };
+
+    let market_id = order_book_event.market_account_id().market_id();
+    if local_snapshots_service.is_resyncing(market_id) {
+        exchange.resync_order_book(order_book_event.currency_pair);
+    } else if let EventType::Snapshot = order_book_event.event_type {
+        exchange.finish_order_book_resync(order_book_event.currency_pair);
+    }
+}
+
 impl Service for InternalEventsLoop {
     fn name(&self) -> &str {
         "InternalEventsLoop"
@@ -1,9 +1,11 @@
 pub mod block_reasons;
 pub mod common;
+pub mod conflating_events_receiver;
 pub mod exchange_blocker;
 pub mod general;
 pub mod hosts;
 pub(crate) mod internal_events_loop;
 pub mod rest_client;
+pub mod server_clock;
 pub mod timeouts;
 pub mod traits;
@@ -1,10 +1,10 @@
+use crate::connectivity::transport::{Transport, TransportConnector};
 use crate::exchanges::traits::ExchangeError;
 use anyhow::Result;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use hyper::client::HttpConnector;
 use hyper::http::request::Builder;
 use hyper::http::uri::{Parts, PathAndQuery};
-use hyper::{Body, Client, Error, Method, Request, Response, StatusCode, Uri};
+use hyper::{Body, Client, Error, HeaderMap, Method, Request, Response, StatusCode, Uri};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use log::log;
 use mmb_domain::market::*;
@@ -13,6 +13,8 @@ use std::borrow::Cow;
 use std::convert::TryInto;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter, Write};
+use std::net::IpAddr;
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub type QueryKey = &'static str;
@@ -191,7 +193,9 @@ pub struct RestClient<
     ErrHandler: ErrorHandler + Send + Sync + 'static,
     SpecHeaders: RestHeaders + Send + Sync + 'static,
 > {
-    client: Client<HttpsConnector<HttpConnector>>,
+    // Client per local address, transport selects address for every request
+    clients: Vec<Client<HttpsConnector<TransportConnector>>>,
+    transport: Arc<Transport>,
     error_handler: ErrorHandlerData<ErrHandler>,
     headers: SpecHeaders,
 }
@@ -204,13 +208,45 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
     RestClient<ErrHandler, SpecHeaders>
 {
     pub fn new(error_handler: ErrorHandlerData<ErrHandler>, headers: SpecHeaders) -> Self {
+        Self::with_transport(
+            error_handler,
+            headers,
+            Arc::new(Transport::default()),
+            false,
+        )
+    }
+
+    /// `is_http_allowed` should be set only for explicitly configured `http://` hosts (e.g. mock servers)
+    /// because signed requests contain API keys
+    pub fn with_transport(
+        error_handler: ErrorHandlerData<ErrHandler>,
+        headers: SpecHeaders,
+        transport: Arc<Transport>,
+        is_http_allowed: bool,
+    ) -> Self {
+        let clients = match transport.local_addresses() {
+            [] => vec![create_client(transport.clone(), None, is_http_allowed)],
+            local_addresses => local_addresses
+                .iter()
+                .map(|local_address| {
+                    create_client(transport.clone(), Some(*local_address), is_http_allowed)
+                })
+                .collect(),
+        };
+
         Self {
-            client: create_client(),
+            clients,
+            transport,
             error_handler,
             headers,
         }
     }
 
+    fn client(&self) -> &Client<HttpsConnector<TransportConnector>> {
+        let index = self.transport.next_request_address_index();
+        &self.clients[index % self.clients.len()]
+    }
+
     pub async fn get(
         &self,
         uri: Uri,
@@ -232,7 +268,7 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
                 format!("Error during creation of http {request_type} request {request_id}")
             });
 
-        let response = self.client.request(req).await;
+        let response = self.client().request(req).await;
 
         self.handle_response(
             response,
@@ -265,7 +301,7 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
                 format!("Error during creation of http {request_type} request {request_id}")
             });
 
-        let response = self.client.request(req).await;
+        let response = self.client().request(req).await;
 
         self.handle_response(
             response,
@@ -302,7 +338,7 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
                 format!("Error during creation of http {request_type} request {request_id}")
             });
 
-        let response = self.client.request(req).await;
+        let response = self.client().request(req).await;
 
         self.handle_response(
             response,
@@ -335,7 +371,7 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
                 format!("Error during creation of http {request_type} request {request_id}")
             });
 
-        let response = self.client.request(req).await;
+        let response = self.client().request(req).await;
 
         self.handle_response(
             response,
@@ -358,18 +394,22 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
         let response = response.with_expect(|| {
             format!("Unable to send {rest_action} request, request_id: {request_id}")
         });
-        let status = response.status();
-        let request_bytes = hyper::body::to_bytes(response.into_body())
-            .await
-            .with_expect(|| {
-                format!("Unable to convert response body to bytes, request_id: {request_id}")
-            });
+        let (parts, body) = response.into_parts();
+        let status = parts.status;
+        let headers = parts.headers;
+        let request_bytes = hyper::body::to_bytes(body).await.with_expect(|| {
+            format!("Unable to convert response body to bytes, request_id: {request_id}")
+        });
 
         let content = std::str::from_utf8(&request_bytes)
             .with_expect(|| format!("Unable to convert response content from utf8: {request_bytes:?}, request_id: {request_id}"))
             .to_owned();
 
-        let request_outcome = RestResponse { status, content };
+        let request_outcome = RestResponse {
+            status,
+            content,
+            headers,
+        };
 
         let err_handler_data = &self.error_handler;
         err_handler_data.response_log(action_name, &log_args, &request_outcome, &request_id);
@@ -379,13 +419,20 @@ impl<ErrHandler: ErrorHandler + Send + Sync + 'static, SpecHeaders: RestHeaders
     }
 }
 
-fn create_client() -> Client<HttpsConnector<HttpConnector>> {
-    let https = HttpsConnectorBuilder::new()
-        .with_native_roots()
-        .https_only()
+fn create_client(
+    transport: Arc<Transport>,
+    local_address: Option<IpAddr>,
+    is_http_allowed: bool,
+) -> Client<HttpsConnector<TransportConnector>> {
+    let builder = HttpsConnectorBuilder::new().with_native_roots();
+    let builder = match is_http_allowed {
+        true => builder.https_or_http(),
+        false => builder.https_only(),
+    };
+    let https = builder
         .enable_http1()
         .enable_http2()
-        .build();
+        .wrap_connector(TransportConnector::new(transport, local_address));
     Client::builder().build::<_, Body>(https)
 }
 
@@ -452,9 +499,16 @@ impl UriBuilder {
         let path_and_query = PathAndQuery::from_maybe_shared(path_and_query)
             .expect("Unable create PathAndQuery from UriQueryBuilder");
 
+        // host can contain scheme, e.g. `http://127.0.0.1:8080` of mock server
+        let (scheme, authority) = host.split_once("://").unwrap_or(("https", host));
+
         let mut parts = Parts::default();
-        parts.scheme = Some("https".try_into().expect("Unable build scheme for url"));
-        parts.authority = Some(host.try_into().expect("Unable build authority for url"));
+        parts.scheme = Some(scheme.try_into().expect("Unable build scheme for url"));
+        parts.authority = Some(
+            authority
+                .try_into()
+                .expect("Unable build authority for url"),
+        );
         parts.path_and_query = Some(path_and_query);
 
         let uri = Uri::from_parts(parts).expect("Unable build url from parts");
@@ -509,6 +563,17 @@ mod tests {
         )
     }
 
+    #[test]
+    pub fn build_uri_with_scheme_in_host() {
+        let mut builder = UriBuilder::from_path("/path");
+        builder.add_kv("key", "value");
+
+        assert_eq!(
+            builder.build_uri("http://127.0.0.1:8080", true),
+            Uri::from_static("http://127.0.0.1:8080/path?key=value")
+        )
+    }
+
     #[test]
     pub fn build_uri_without_query_by_builder() {
         let host = "host.com";
@@ -549,6 +614,7 @@ pub enum RestRequestError {
 pub struct RestResponse {
     pub status: StatusCode,
     pub content: String,
+    pub headers: HeaderMap,
 }
 
 impl Debug for RestResponse {
@@ -565,7 +631,11 @@ impl Debug for RestResponse {
 
 impl RestResponse {
     pub fn new(content: String, status: StatusCode) -> Self {
-        Self { content, status }
+        Self {
+            content,
+            status,
+            headers: HeaderMap::new(),
+        }
     }
 }
 
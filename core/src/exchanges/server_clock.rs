@@ -0,0 +1,138 @@
+use chrono::{Duration, Utc};
+use mmb_domain::market::ExchangeAccountId;
+use mmb_utils::DateTime;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Measurement of exchange server time by request. Server time is supposed to be taken in the
+/// middle of request round trip like in NTP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    pub offset: Duration,
+    pub round_trip: Duration,
+}
+
+impl ClockSample {
+    pub fn new(request_time: DateTime, server_time: DateTime, response_time: DateTime) -> Self {
+        let round_trip = (response_time - request_time).max(Duration::zero());
+        Self {
+            offset: server_time - (request_time + round_trip / 2),
+            round_trip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerClockState {
+    /// Server time minus local time
+    pub offset_ms: i64,
+    pub round_trip_ms: i64,
+    /// Change of offset since previous synchronization
+    pub drift_ms: i64,
+    /// Local time of last synchronization, `None` if clock was never synchronized
+    pub last_sync_time: Option<DateTime>,
+}
+
+/// Estimation of exchange server clock. Until the first synchronization it matches local clock
+pub struct ServerClock {
+    exchange_account_id: ExchangeAccountId,
+    state: RwLock<ServerClockState>,
+}
+
+impl ServerClock {
+    pub fn new(exchange_account_id: ExchangeAccountId) -> Arc<Self> {
+        Arc::new(Self {
+            exchange_account_id,
+            state: Default::default(),
+        })
+    }
+
+    pub fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange_account_id
+    }
+
+    /// Current time of exchange server
+    pub fn now(&self) -> DateTime {
+        self.to_server_time(Utc::now())
+    }
+
+    pub fn to_server_time(&self, local_time: DateTime) -> DateTime {
+        local_time + Duration::milliseconds(self.state.read().offset_ms)
+    }
+
+    pub fn state(&self) -> ServerClockState {
+        *self.state.read()
+    }
+
+    /// Updates offset by the sample with the least round trip because it is the most accurate one.
+    /// Returns `None` if there are no samples
+    pub fn synchronize(
+        &self,
+        samples: &[ClockSample],
+        sync_time: DateTime,
+    ) -> Option<ServerClockState> {
+        let best_sample = samples.iter().min_by_key(|x| x.round_trip)?;
+        let offset_ms = best_sample.offset.num_milliseconds();
+
+        let mut state = self.state.write();
+        let drift_ms = match state.last_sync_time {
+            Some(_) => offset_ms - state.offset_ms,
+            None => 0,
+        };
+        *state = ServerClockState {
+            offset_ms,
+            round_trip_ms: best_sample.round_trip.num_milliseconds(),
+            drift_ms,
+            last_sync_time: Some(sync_time),
+        };
+
+        Some(*state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time(millis: i64) -> DateTime {
+        Utc.timestamp_millis(millis)
+    }
+
+    #[test]
+    fn offset_of_sample_is_taken_in_the_middle_of_round_trip() {
+        let sample = ClockSample::new(time(1_000), time(1_550), time(1_100));
+
+        assert_eq!(sample.round_trip, Duration::milliseconds(100));
+        assert_eq!(sample.offset, Duration::milliseconds(500));
+    }
+
+    #[test]
+    fn synchronize_by_sample_with_least_round_trip() {
+        let clock = ServerClock::new(ExchangeAccountId::new("Binance", 0));
+        assert_eq!(clock.to_server_time(time(10_000)), time(10_000));
+        assert_eq!(clock.synchronize(&[], time(0)), None);
+
+        let samples = [
+            ClockSample::new(time(1_000), time(1_800), time(1_400)),
+            ClockSample::new(time(2_000), time(2_520), time(2_040)),
+        ];
+        let state = clock.synchronize(&samples, time(2_040)).expect("in test");
+        assert_eq!(
+            state,
+            ServerClockState {
+                offset_ms: 500,
+                round_trip_ms: 40,
+                drift_ms: 0,
+                last_sync_time: Some(time(2_040)),
+            }
+        );
+        assert_eq!(clock.to_server_time(time(10_000)), time(10_500));
+
+        let samples = [ClockSample::new(time(3_000), time(3_530), time(3_020))];
+        let state = clock.synchronize(&samples, time(3_020)).expect("in test");
+        assert_eq!(state.offset_ms, 520);
+        assert_eq!(state.drift_ms, 20);
+    }
+}
@@ -15,6 +15,7 @@ use anyhow::Result;
 use chrono::Utc;
 
 use crate::exchanges::general::request_type::RequestType;
+use crate::exchanges::server_clock::ServerClock;
 use crate::exchanges::timeouts::requests_timeout_manager::{
     RequestGroupId, RequestsTimeoutManager,
 };
@@ -24,24 +25,49 @@ pub type BoxFuture = Box<dyn Future<Output = Result<()>> + Sync + Send>;
 
 pub struct TimeoutManager {
     inner: HashMap<ExchangeAccountId, Arc<RequestsTimeoutManager>>,
+    server_clocks: HashMap<ExchangeAccountId, Arc<ServerClock>>,
 }
 
 impl TimeoutManager {
     pub fn new(
         timeout_managers: HashMap<ExchangeAccountId, Arc<RequestsTimeoutManager>>,
     ) -> Arc<Self> {
+        let server_clocks = timeout_managers
+            .keys()
+            .map(|&exchange_account_id| {
+                (exchange_account_id, ServerClock::new(exchange_account_id))
+            })
+            .collect();
+
         Arc::new(TimeoutManager {
             inner: timeout_managers,
+            server_clocks,
         })
     }
 
+    /// Clock of exchange server. Requests limits of exchange are counted by server time
+    pub fn server_clock(&self, exchange_account_id: ExchangeAccountId) -> Arc<ServerClock> {
+        self.server_clocks
+            .get(&exchange_account_id)
+            .with_expect(|| format!("Can't find server clock for {exchange_account_id}"))
+            .clone()
+    }
+
+    fn now(&self, exchange_account_id: ExchangeAccountId) -> DateTime {
+        self.server_clocks[&exchange_account_id].now()
+    }
+
     pub fn try_reserve_group(
         &self,
         exchange_account_id: ExchangeAccountId,
         requests_count: usize,
         group_type: String,
     ) -> Option<RequestGroupId> {
-        self.inner[&exchange_account_id].try_reserve_group(group_type, now(), requests_count)
+        self.inner[&exchange_account_id].try_reserve_group(
+            group_type,
+            self.now(exchange_account_id),
+            requests_count,
+        )
     }
 
     pub fn remove_group(
@@ -49,7 +75,7 @@ impl TimeoutManager {
         exchange_account_id: ExchangeAccountId,
         group_id: RequestGroupId,
     ) -> bool {
-        self.inner[&exchange_account_id].remove_group(group_id, now())
+        self.inner[&exchange_account_id].remove_group(group_id, self.now(exchange_account_id))
     }
 
     pub fn try_reserve_instant(
@@ -57,7 +83,11 @@ impl TimeoutManager {
         exchange_account_id: ExchangeAccountId,
         request_type: RequestType,
     ) -> bool {
-        self.inner[&exchange_account_id].try_reserve_instant(request_type, now(), None)
+        self.inner[&exchange_account_id].try_reserve_instant(
+            request_type,
+            self.now(exchange_account_id),
+            None,
+        )
     }
 
     pub fn try_reserve_group_instant(
@@ -68,7 +98,7 @@ impl TimeoutManager {
     ) -> bool {
         self.inner[&exchange_account_id].try_reserve_instant(
             request_type,
-            now(),
+            self.now(exchange_account_id),
             pre_reserved_group_id,
         )
     }
@@ -97,7 +127,7 @@ impl TimeoutManager {
             })
         };
 
-        let now = now();
+        let now = self.now(exchange_account_id);
         if pre_reservation_group_id.is_none() {
             let result = inner.reserve_when_available(request_type, now, cancellation_token);
             return Either::Left(convert(result.0));
@@ -15,6 +15,7 @@ use crate::settings::ExchangeSettings;
 use anyhow::Result;
 use async_trait::async_trait;
 use dashmap::DashMap;
+use mmb_domain::candle::Candle;
 use mmb_domain::events::ExchangeBalancesAndPositions;
 use mmb_domain::events::{ExchangeEvent, Trade};
 use mmb_domain::exchanges::symbol::{BeforeAfter, Symbol};
@@ -29,6 +30,7 @@ use mmb_domain::order::snapshot::Price;
 use mmb_domain::order::snapshot::{
     ClientOrderId, ExchangeOrderId, OrderCancelling, OrderInfo, OrderInfoExtensionData, OrderSide,
 };
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::position::{ActivePosition, ClosedPosition};
 use mmb_utils::DateTime;
 use serde::{Deserialize, Serialize};
@@ -95,6 +97,11 @@ pub trait ExchangeClient: Support {
 
     async fn cancel_all_orders(&self, currency_pair: CurrencyPair) -> Result<()>;
 
+    /// Arm exchange-side timer which cancels all orders if it isn't rearmed within `timeout`.
+    /// Zero `timeout` disarms the timer.
+    /// Called only if `OrderFeatures::supports_cancel_all_after` is set
+    async fn cancel_all_orders_after(&self, timeout: Duration) -> Result<()>;
+
     async fn get_open_orders(&self) -> Result<Vec<OrderInfo>>;
 
     async fn get_open_orders_by_currency_pair(
@@ -126,6 +133,24 @@ pub trait ExchangeClient: Support {
     ) -> RequestResult<Vec<OrderTrade>>;
 
     async fn build_all_symbols(&self) -> Result<Vec<Arc<Symbol>>>;
+
+    /// Current time of exchange server for clock synchronization.
+    /// Called only if `ExchangeFeatures::supports_server_time` is set
+    async fn get_server_time(&self) -> Result<DateTime>;
+
+    /// Full order book snapshot for resynchronization of local order book after missed updates.
+    /// Called only for exchanges which specify `OrderBookSequence` or `OrderBookChecksum` in order
+    /// book events
+    async fn get_order_book_snapshot(&self, currency_pair: CurrencyPair) -> Result<OrderBookEvent>;
+
+    /// Last closed candles of currency pair with interval `interval_secs`, oldest first.
+    /// Called only if `ExchangeFeatures::supports_klines` is set
+    async fn get_candles(
+        &self,
+        currency_pair: CurrencyPair,
+        interval_secs: u32,
+        limit: u32,
+    ) -> Result<Vec<Candle>>;
 }
 
 pub type OrderCreatedCb =
@@ -205,7 +230,7 @@ pub trait ExchangeClientBuilder {
         lifetime_manager: Arc<AppLifetimeManager>,
         timeout_manager: Arc<TimeoutManager>,
         orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult;
+    ) -> Result<ExchangeClientBuilderResult>;
 
     fn get_timeout_arguments(&self) -> RequestTimeoutArguments;
 
@@ -1,5 +1,6 @@
 use crate::balance::manager::balance_manager::BalanceManager;
 use crate::config::{load_pretty_settings, try_load_settings};
+use crate::database::events::audit::record_exchange_blocker_events;
 use crate::database::events::recorder::EventRecorder;
 use crate::exchanges::exchange_blocker::ExchangeBlocker;
 use crate::exchanges::general::currency_pair_to_symbol_converter::CurrencyPairToSymbolConverter;
@@ -12,24 +13,34 @@ use crate::exchanges::traits::ExchangeClientBuilder;
 use crate::infrastructure::spawn_future;
 use crate::infrastructure::{init_lifetime_manager, spawn_by_timer, spawn_future_ok};
 use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
+#[double]
+use crate::lifecycle::profit_loss_services::ProfitLossServices;
 use crate::lifecycle::trading_engine::{EngineContext, TradingEngine};
 use crate::rpc::config_waiter::ConfigWaiter;
 use crate::rpc::core_api::CoreApi;
 use crate::services::cleanup_orders::CleanupOrdersService;
+use crate::services::clock_sync::ClockSyncService;
+use crate::services::dead_man_switch::DeadManSwitchService;
+use crate::services::event_tables_maintenance::EventTablesMaintenanceService;
+use crate::services::order_recovery::recover_orders_state;
+use crate::services::stale_feed::StaleFeedService;
+use crate::services::usd_convertion::prices_sources_saver::PriceSourcesSaver;
+use crate::services::usd_convertion::usd_denominator::UsdDenominator;
 use crate::settings::{AppSettings, BaseStrategySettings, CoreSettings};
 use anyhow::{anyhow, bail, Context, Result};
 use core::fmt::Debug;
 use dashmap::DashMap;
-use futures::{future::join_all, FutureExt};
+use futures::{future::try_join_all, FutureExt};
 use itertools::Itertools;
-use mmb_database::postgres_db::migrator::apply_migrations;
-use mmb_database::postgres_db::PgPool;
+use mmb_database::storage::{open_storage, Storage};
 use mmb_domain::events::{ExchangeEvent, ExchangeEvents, CHANNEL_MAX_EVENTS_COUNT};
 use mmb_domain::market::ExchangeAccountId;
 use mmb_domain::market::ExchangeId;
+use mmb_domain::market::MarketAccountId;
 use mmb_utils::infrastructure::{init_infrastructure, SpawnFutureFlags};
 use mmb_utils::logger::print_info;
 use mmb_utils::nothing_to_do;
+use mockall_double::double;
 use parking_lot::Mutex;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -127,7 +138,7 @@ async fn before_engine_context_init<StrategySettings>(
     DashMap<ExchangeAccountId, Arc<Exchange>>,
     Arc<EngineContext>,
     oneshot::Receiver<ActionAfterGracefulShutdown>,
-    Option<PgPool>,
+    Option<Arc<dyn Storage>>,
 )>
 where
     StrategySettings: BaseStrategySettings + Clone + Debug + DeserializeOwned + Serialize,
@@ -165,23 +176,40 @@ where
 
     let exchange_blocker = ExchangeBlocker::new(exchange_account_ids);
 
-    let (pool, postponed_events_dir) = if let Some(db) = &settings.core.database {
-        apply_migrations(&db.url, db.migrations.clone())
+    let (storage, postponed_events_dir) = if let Some(db) = &settings.core.database {
+        let storage = open_storage(&db.url, &db.pool)
             .await
-            .context("unable apply db migrations")?;
+            .with_context(|| format!("from `launcher` with connection_string: {}", &db.url))?;
+
+        if db.maintenance.is_some() && storage.postgres_pool().is_none() {
+            bail!("Event tables maintenance is supported for Postgres only, remove `maintenance` from database settings for {}", db.url);
+        }
 
-        let pool = PgPool::create(&db.url, 5)
+        storage
+            .apply_migrations(db.migrations.clone())
             .await
-            .with_context(|| format!("from `launcher` with connection_string: {}", &db.url))?;
+            .context("unable apply db migrations")?;
 
-        (Some(pool), db.postponed_events_dir.clone())
+        (Some(storage), db.postponed_events_dir.clone())
     } else {
         (None, None)
     };
 
-    let event_recorder = EventRecorder::start(pool.clone(), postponed_events_dir)
-        .await
-        .expect("can't start EventRecorder");
+    let event_recorder_settings = settings
+        .core
+        .database
+        .as_ref()
+        .map(|db| db.event_recorder.clone())
+        .unwrap_or_default();
+    let event_recorder = EventRecorder::start_with_settings(
+        storage.clone(),
+        postponed_events_dir,
+        &event_recorder_settings,
+    )
+    .await
+    .expect("can't start EventRecorder");
+
+    record_exchange_blocker_events(&exchange_blocker, event_recorder.clone());
 
     let exchanges = create_exchanges(
         &settings.core,
@@ -192,7 +220,7 @@ where
         Arc::downgrade(&exchange_blocker),
         event_recorder.clone(),
     )
-    .await;
+    .await?;
 
     let exchanges_map: DashMap<_, _> = exchanges
         .into_iter()
@@ -204,8 +232,23 @@ where
 
     let currency_pair_to_symbol_converter = CurrencyPairToSymbolConverter::new(exchanges_hashmap);
 
+    let price_source_service = ProfitLossServices::create_price_source_service(
+        &settings.core,
+        &currency_pair_to_symbol_converter,
+        &storage,
+    );
+
+    let usd_denominator = match &settings.core.usd_price_providers {
+        None => None,
+        Some(providers_settings) => Some(
+            UsdDenominator::create_with_providers(providers_settings, lifetime_manager.clone())
+                .await
+                .context("Failed to create USD price providers")?,
+        ),
+    };
+
     let balance_manager = BalanceManager::new(
-        currency_pair_to_symbol_converter,
+        currency_pair_to_symbol_converter.clone(),
         Some(event_recorder.clone()),
     );
 
@@ -221,8 +264,32 @@ where
             .setup_balance_manager(balance_manager.clone())
     }
 
+    if let Some(storage) = &storage {
+        if let Err(err) = recover_orders_state(
+            storage.as_ref(),
+            &exchanges_map,
+            &balance_manager,
+            lifetime_manager.stop_token(),
+        )
+        .await
+        {
+            log::error!("Failed to recover orders state: {err:?}");
+        }
+
+        // Exchange balances already contain missed fills, so reset virtual diffs added on their replaying
+        BalanceManager::update_balances_for_exchanges(
+            balance_manager.clone(),
+            lifetime_manager.stop_token(),
+        )
+        .await;
+    }
+
     start_updating_balances(&lifetime_manager, &balance_manager);
 
+    if let Some(storage) = &storage {
+        start_logging_db_metrics(storage.clone(), event_recorder.clone());
+    }
+
     let (finish_graceful_shutdown_tx, finish_graceful_shutdown_rx) = oneshot::channel();
 
     let engine_context = EngineContext::new(
@@ -235,15 +302,28 @@ where
         lifetime_manager.clone(),
         balance_manager,
         event_recorder,
+        price_source_service,
+        usd_denominator,
     );
 
+    ProfitLossServices::start_profit_loss_stopper(
+        &settings.core,
+        MarketAccountId::new(
+            settings.strategy.exchange_account_id(),
+            settings.strategy.currency_pair(),
+        ),
+        &engine_context,
+        currency_pair_to_symbol_converter,
+        storage.clone(),
+    )?;
+
     Ok((
         events_receiver,
         settings,
         exchanges_map,
         engine_context,
         finish_graceful_shutdown_rx,
-        pool,
+        storage,
     ))
 }
 
@@ -269,6 +349,35 @@ fn start_updating_balances(
     );
 }
 
+fn create_event_tables_maintenance_service(
+    settings: &CoreSettings,
+    storage: Option<&dyn Storage>,
+) -> Option<Arc<EventTablesMaintenanceService>> {
+    let maintenance_settings = settings.database.as_ref()?.maintenance.clone()?;
+    // Non-Postgres storage with maintenance settings is rejected on start
+    let pool = storage?.postgres_pool()?;
+    Some(Arc::new(EventTablesMaintenanceService::new(
+        pool.clone(),
+        maintenance_settings,
+    )))
+}
+
+fn start_logging_db_metrics(storage: Arc<dyn Storage>, event_recorder: Arc<EventRecorder>) {
+    let _ = spawn_by_timer(
+        "Log database metrics",
+        Duration::from_secs(600),
+        Duration::from_secs(600), // 10 minutes
+        SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+        move || {
+            if let Some(pool) = storage.postgres_pool() {
+                log::info!("Postgres pool metrics: {:?}", pool.metrics());
+            }
+            log::info!("EventRecorder metrics: {:?}", event_recorder.metrics());
+            futures::future::ready(())
+        },
+    );
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_services<'a, StrategySettings>(
     engine_context: Arc<EngineContext>,
@@ -279,6 +388,7 @@ fn run_services<'a, StrategySettings>(
     finish_graceful_shutdown_rx: oneshot::Receiver<ActionAfterGracefulShutdown>,
     cleanup_orders_service: Arc<CleanupOrdersService>,
     live_ranges_service: Option<Arc<LiveRangesService>>,
+    event_tables_maintenance_service: Option<Arc<EventTablesMaintenanceService>>,
 ) -> TradingEngine<StrategySettings>
 where
     StrategySettings: BaseStrategySettings + Clone + Debug + Deserialize<'a> + Serialize,
@@ -292,6 +402,7 @@ where
         engine_context.lifetime_manager.clone(),
         load_pretty_settings(init_user_settings),
         engine_context.statistic_service.clone(),
+        engine_context.kill_switch.clone(),
     )
     .expect("Unable to start control panel");
     engine_context
@@ -312,6 +423,19 @@ where
         ),
     );
 
+    let _ = spawn_future(
+        "Kill switch trigger file watching",
+        SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+        engine_context.kill_switch.clone().watch_trigger_file(),
+    );
+
+    #[cfg(unix)]
+    let _ = spawn_future(
+        "Kill switch signal watching",
+        SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+        engine_context.kill_switch.clone().watch_signal(),
+    );
+
     let _ = spawn_by_timer(
         "cleanup_outdated_orders",
         Duration::ZERO,
@@ -320,6 +444,111 @@ where
         move || cleanup_orders_service.clone().cleanup_outdated_orders(),
     );
 
+    let clock_sync_services = ClockSyncService::create_for_exchanges(
+        &engine_context.exchanges,
+        engine_context.statistic_service.clone(),
+    );
+    for clock_sync_service in clock_sync_services {
+        engine_context
+            .shutdown_service
+            .register_core_service(clock_sync_service.clone());
+
+        let _ = spawn_by_timer(
+            &format!(
+                "Clock synchronization for {}",
+                clock_sync_service.exchange_account_id()
+            ),
+            Duration::ZERO,
+            clock_sync_service.interval(),
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || clock_sync_service.clone().sync(),
+        );
+    }
+
+    let dead_man_switch_services = DeadManSwitchService::create_for_exchanges(
+        &engine_context.exchanges,
+        engine_context.kill_switch.clone(),
+    );
+    for dead_man_switch_service in dead_man_switch_services {
+        engine_context
+            .shutdown_service
+            .register_core_service(dead_man_switch_service.clone());
+
+        let _ = spawn_by_timer(
+            &format!(
+                "Dead man's switch refreshing for {}",
+                dead_man_switch_service.exchange_account_id()
+            ),
+            Duration::ZERO,
+            dead_man_switch_service.refresh_interval(),
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || dead_man_switch_service.clone().refresh(),
+        );
+    }
+
+    let stale_feed_services = StaleFeedService::create_for_exchanges(
+        &engine_context.exchanges,
+        engine_context.exchange_blocker.clone(),
+        engine_context.lifetime_manager.clone(),
+    );
+    for stale_feed_service in stale_feed_services {
+        engine_context
+            .shutdown_service
+            .register_core_service(stale_feed_service.clone());
+
+        let _ = spawn_by_timer(
+            &format!(
+                "Stale feeds checking for {}",
+                stale_feed_service.exchange_account_id()
+            ),
+            Duration::ZERO,
+            stale_feed_service.check_interval(),
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || stale_feed_service.clone().check(),
+        );
+    }
+
+    if let Some(candles_service) = engine_context.candles_service.clone() {
+        engine_context
+            .shutdown_service
+            .register_core_service(candles_service.clone());
+
+        let _ = spawn_future(
+            "Candles building",
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            candles_service
+                .clone()
+                .start(engine_context.get_events_channel()),
+        );
+
+        let _ = spawn_future(
+            "Candles history loading",
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            candles_service
+                .clone()
+                .load_history(engine_context.exchanges.clone()),
+        );
+
+        let _ = spawn_by_timer(
+            "Candles closing",
+            Duration::ZERO,
+            Duration::from_secs(1),
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || candles_service.clone().close_expired_candles(),
+        );
+    }
+
+    if let Some(price_source_service) = engine_context.price_source_service.clone() {
+        let price_sources_saver = PriceSourcesSaver::new(engine_context.event_recorder.clone());
+        let events_receiver = engine_context.get_events_channel();
+        let stop_token = engine_context.lifetime_manager.stop_token();
+        let _ = spawn_future_ok(
+            "Price sources updating",
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            price_source_service.start(price_sources_saver, events_receiver, stop_token),
+        );
+    }
+
     if let Some(live_ranges_service) = live_ranges_service {
         engine_context
             .shutdown_service
@@ -334,6 +563,20 @@ where
         );
     }
 
+    if let Some(event_tables_maintenance_service) = event_tables_maintenance_service {
+        engine_context
+            .shutdown_service
+            .register_core_service(event_tables_maintenance_service.clone());
+
+        let _ = spawn_by_timer(
+            "Event tables maintenance",
+            Duration::ZERO,
+            Duration::from_secs(3600), // 1 hour
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || event_tables_maintenance_service.clone().maintain(),
+        );
+    }
+
     log::info!("TradingEngine started");
     TradingEngine::new(engine_context, settings, finish_graceful_shutdown_rx)
 }
@@ -415,7 +658,7 @@ where
         exchanges_map,
         engine_context,
         finish_graceful_shutdown_rx,
-        pool,
+        storage,
     ) = unwrap_or_handle_panic(action_outcome, message_template, None)??;
 
     let cloned_lifetime_manager = engine_context.lifetime_manager.clone();
@@ -435,11 +678,14 @@ where
     let cleanup_orders_service =
         Arc::new(CleanupOrdersService::new(engine_context.exchanges.clone()));
 
-    let live_ranges_service = match pool {
+    let event_tables_maintenance_service =
+        create_event_tables_maintenance_service(&settings.core, storage.as_deref());
+
+    let live_ranges_service = match storage {
         None => None,
-        Some(pool) => {
+        Some(storage) => {
             let session_id = Uuid::new_v4().to_string();
-            Some(Arc::new(LiveRangesService::new(session_id, pool)))
+            Some(Arc::new(LiveRangesService::new(session_id, storage)))
         }
     };
 
@@ -453,6 +699,7 @@ where
             finish_graceful_shutdown_rx,
             cleanup_orders_service,
             live_ranges_service,
+            event_tables_maintenance_service,
         )
     }));
 
@@ -476,8 +723,8 @@ pub async fn create_exchanges(
     timeout_manager: &Arc<TimeoutManager>,
     exchange_blocker: Weak<ExchangeBlocker>,
     event_recorder: Arc<EventRecorder>,
-) -> Vec<Arc<Exchange>> {
-    join_all(core_settings.exchanges.iter().map(|x| {
+) -> Result<Vec<Arc<Exchange>>> {
+    try_join_all(core_settings.exchanges.iter().map(|x| {
         create_exchange(
             x,
             build_settings,
@@ -1,4 +1,5 @@
 pub mod app_lifetime_manager;
 pub mod launcher;
+pub mod profit_loss_services;
 pub mod shutdown;
 pub mod trading_engine;
@@ -0,0 +1,141 @@
+use crate::exchanges::general::currency_pair_to_symbol_converter::CurrencyPairToSymbolConverter;
+use crate::lifecycle::trading_engine::EngineContext;
+use crate::services::usd_convertion::price_source_service::PriceSourceService;
+use crate::settings::CoreSettings;
+use anyhow::Result;
+use mmb_database::storage::Storage;
+use mmb_domain::market::MarketAccountId;
+use std::sync::Arc;
+
+#[cfg(not(test))]
+pub use real::ProfitLossServices;
+
+#[cfg(not(test))]
+mod real {
+    use super::*;
+    use crate::balance::changes::balance_changes_service::BalanceChangesService;
+    use crate::balance::changes::profit_loss_stopper_service::ProfitLossStopperService;
+    use crate::exchanges::general::engine_api::EngineApi;
+    use crate::infrastructure::spawn_future_ok;
+    use crate::services::usd_convertion::price_sources_loader::PriceSourcesLoader;
+    use crate::services::usd_convertion::usd_converter::UsdConverter;
+    use anyhow::{bail, Context};
+    use itertools::Itertools;
+    use mmb_utils::infrastructure::SpawnFutureFlags;
+
+    /// Creation of price sources and profit loss services from engine services.
+    /// These services keep mocks of engine services in unit tests, so they can't be created
+    /// from real engine services there and `MockProfitLossServices` is used instead
+    pub struct ProfitLossServices;
+
+    impl ProfitLossServices {
+        pub fn create_price_source_service(
+            settings: &CoreSettings,
+            currency_pair_to_symbol_converter: &Arc<CurrencyPairToSymbolConverter>,
+            storage: &Option<Arc<dyn Storage>>,
+        ) -> Option<Arc<PriceSourceService>> {
+            if settings.price_sources.is_empty() {
+                return None;
+            }
+
+            Some(PriceSourceService::new(
+                currency_pair_to_symbol_converter.clone(),
+                &settings.price_sources,
+                PriceSourcesLoader::new(storage.clone()),
+            ))
+        }
+
+        pub fn start_profit_loss_stopper(
+            settings: &CoreSettings,
+            target_market_account_id: MarketAccountId,
+            engine_context: &Arc<EngineContext>,
+            currency_pair_to_symbol_converter: Arc<CurrencyPairToSymbolConverter>,
+            storage: Option<Arc<dyn Storage>>,
+        ) -> Result<()> {
+            let stopper_settings = match &settings.profit_loss_stopper {
+                None => return Ok(()),
+                Some(stopper_settings) => stopper_settings,
+            };
+
+            let (price_source_service, usd_denominator) = match (
+                &engine_context.price_source_service,
+                &engine_context.usd_denominator,
+            ) {
+                (Some(price_source_service), Some(usd_denominator)) => {
+                    (price_source_service.clone(), usd_denominator.clone())
+                }
+                _ => bail!("`price_sources` and `usd_price_providers` should be configured for `profit_loss_stopper`"),
+            };
+
+            let target_exchange = engine_context
+                .exchanges
+                .get(&target_market_account_id.exchange_account_id)
+                .with_context(|| {
+                    format!(
+                        "Exchange of strategy {} isn't configured",
+                        target_market_account_id.exchange_account_id
+                    )
+                })?
+                .clone();
+
+            let kill_switch = settings
+                .kill_switch
+                .as_ref()
+                .filter(|x| x.trigger_on_profit_loss)
+                .map(|_| engine_context.kill_switch.clone());
+            let profit_loss_stopper_service = Arc::new(ProfitLossStopperService::new(
+                target_market_account_id,
+                stopper_settings,
+                engine_context.exchange_blocker.clone(),
+                Some(engine_context.balance_manager.clone()),
+                Arc::new(EngineApi::new(target_exchange)),
+                kill_switch,
+            ));
+
+            let usd_currencies = settings
+                .price_sources
+                .iter()
+                .map(|x| x.end_currency_code)
+                .collect_vec();
+            let balance_changes_service = BalanceChangesService::new(
+                currency_pair_to_symbol_converter,
+                profit_loss_stopper_service,
+                UsdConverter::new(&usd_currencies, price_source_service, usd_denominator),
+                engine_context.lifetime_manager.clone(),
+                engine_context.event_recorder.clone(),
+                storage,
+            );
+            engine_context
+                .balance_manager
+                .lock()
+                .set_balance_changes_service(balance_changes_service.clone());
+
+            let _ = spawn_future_ok(
+                "Balance changes handling",
+                SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+                balance_changes_service.run(engine_context.lifetime_manager.stop_token()),
+            );
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub ProfitLossServices {
+        pub fn create_price_source_service(
+            settings: &CoreSettings,
+            currency_pair_to_symbol_converter: &Arc<CurrencyPairToSymbolConverter>,
+            storage: &Option<Arc<dyn Storage>>,
+        ) -> Option<Arc<PriceSourceService>>;
+
+        pub fn start_profit_loss_stopper(
+            settings: &CoreSettings,
+            target_market_account_id: MarketAccountId,
+            engine_context: &Arc<EngineContext>,
+            currency_pair_to_symbol_converter: Arc<CurrencyPairToSymbolConverter>,
+            storage: Option<Arc<dyn Storage>>,
+        ) -> Result<()>;
+    }
+}
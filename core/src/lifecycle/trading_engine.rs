@@ -2,8 +2,10 @@ use super::launcher::unwrap_or_handle_panic;
 use crate::balance::manager::balance_manager::BalanceManager;
 use crate::database::events::recorder::EventRecorder;
 use crate::disposition_execution::executor::DispositionExecutorService;
+use crate::disposition_execution::risk_checks::RiskChecks;
 use crate::disposition_execution::strategy::DispositionStrategy;
 use crate::exchanges::block_reasons;
+use crate::exchanges::conflating_events_receiver::ConflatingEventsReceiver;
 use crate::exchanges::exchange_blocker::BlockType;
 use crate::exchanges::exchange_blocker::ExchangeBlocker;
 use crate::exchanges::general::exchange::Exchange;
@@ -12,7 +14,12 @@ use crate::infrastructure::unset_lifetime_manager;
 use crate::lifecycle::app_lifetime_manager::ActionAfterGracefulShutdown;
 use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
 use crate::lifecycle::shutdown::ShutdownService;
+use crate::order_book::consolidated_order_book::ConsolidatedOrderBookService;
 use crate::order_book::local_snapshot_service::LocalSnapshotsService;
+use crate::services::candles::CandlesService;
+use crate::services::kill_switch::KillSwitch;
+use crate::services::usd_convertion::price_source_service::PriceSourceService;
+use crate::services::usd_convertion::usd_denominator::UsdDenominator;
 use crate::settings::BaseStrategySettings;
 use crate::settings::{AppSettings, CoreSettings};
 use crate::statistic_service::{StatisticEventHandler, StatisticService};
@@ -54,6 +61,16 @@ pub struct EngineContext {
     pub balance_manager: Arc<Mutex<BalanceManager>>,
     pub event_recorder: Arc<EventRecorder>,
     pub statistic_service: Arc<StatisticService>,
+    pub risk_checks: Arc<RiskChecks>,
+    pub kill_switch: Arc<KillSwitch>,
+    /// Exists if candles are configured in `CoreSettings`
+    pub candles_service: Option<Arc<CandlesService>>,
+    /// Merges order books of the same currency pair across all exchanges
+    pub consolidated_order_book_service: Arc<ConsolidatedOrderBookService>,
+    /// Exists if price sources are configured in `CoreSettings`
+    pub price_source_service: Option<Arc<PriceSourceService>>,
+    /// Exists if USD price providers are configured in `CoreSettings`
+    pub usd_denominator: Option<Arc<UsdDenominator>>,
     is_graceful_shutdown_started: AtomicBool,
     exchange_events: ExchangeEvents,
     finish_graceful_shutdown_sender: Mutex<Option<oneshot::Sender<ActionAfterGracefulShutdown>>>,
@@ -71,8 +88,28 @@ impl EngineContext {
         lifetime_manager: Arc<AppLifetimeManager>,
         balance_manager: Arc<Mutex<BalanceManager>>,
         event_recorder: Arc<EventRecorder>,
+        price_source_service: Option<Arc<PriceSourceService>>,
+        usd_denominator: Option<Arc<UsdDenominator>>,
     ) -> Arc<Self> {
         let statistic_service = StatisticService::new();
+        let risk_checks = Arc::new(RiskChecks::from_settings(&core_settings));
+        for exchange in exchanges.iter() {
+            exchange.setup_risk_checks(risk_checks.clone());
+        }
+        let kill_switch = KillSwitch::new(
+            &core_settings,
+            exchanges.clone(),
+            exchange_blocker.clone(),
+            lifetime_manager.stop_token(),
+        );
+        for exchange in exchanges.iter() {
+            exchange.setup_kill_switch(&kill_switch);
+        }
+        let candles_service = core_settings.candles.as_ref().map(|settings| {
+            CandlesService::new(settings, event_recorder.clone(), timeout_manager.clone())
+        });
+        let consolidated_order_book_service =
+            Arc::new(ConsolidatedOrderBookService::from_exchanges(&exchanges));
         let engine_context = Arc::new(EngineContext {
             core_settings,
             exchanges,
@@ -83,6 +120,12 @@ impl EngineContext {
             balance_manager,
             event_recorder,
             statistic_service,
+            risk_checks,
+            kill_switch,
+            candles_service,
+            consolidated_order_book_service,
+            price_source_service,
+            usd_denominator,
             is_graceful_shutdown_started: Default::default(),
             exchange_events,
             finish_graceful_shutdown_sender: Mutex::new(Some(finish_graceful_shutdown_sender)),
@@ -172,6 +215,16 @@ impl EngineContext {
     pub fn get_events_channel(&self) -> broadcast::Receiver<ExchangeEvent> {
         self.exchange_events.get_events_channel()
     }
+
+    /// Events channel for consumers which can't keep up with bursts of market data.
+    /// Lag of the receiver is reported to statistics under `name`
+    pub fn get_conflating_events_channel(&self, name: &str) -> ConflatingEventsReceiver {
+        ConflatingEventsReceiver::new(
+            name,
+            self.get_events_channel(),
+            self.statistic_service.clone(),
+        )
+    }
 }
 
 async fn cancel_opened_orders(
@@ -243,13 +296,16 @@ impl<StrategySettings: BaseStrategySettings + Clone> TradingEngine<StrategySetti
         let ctx = self.context();
         let settings = self.settings();
 
-        let statistics =
-            StatisticEventHandler::new(ctx.get_events_channel(), ctx.statistic_service.clone());
+        let statistics = StatisticEventHandler::new(
+            ctx.get_events_channel(),
+            ctx.statistic_service.clone(),
+            ctx.timeout_manager.clone(),
+        );
 
         let base_settings = &settings.strategy;
         let disposition_executor_service = DispositionExecutorService::new(
             ctx.clone(),
-            ctx.get_events_channel(),
+            ctx.get_conflating_events_channel("DispositionExecutor"),
             LocalSnapshotsService::default(),
             base_settings.exchange_account_id(),
             base_settings.currency_pair(),
@@ -3,9 +3,9 @@ use mmb_domain::market::CurrencyPair;
 use mmb_domain::market::ExchangeId;
 use mmb_domain::order::snapshot::Price;
 use mmb_utils::DateTime;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct PriceSourceModel {
     pub init_time: DateTime,
     pub exchange_id: ExchangeId,
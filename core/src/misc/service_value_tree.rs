@@ -5,7 +5,7 @@ use crate::service_configuration::configuration_descriptor::{
     ConfigurationDescriptor, ServiceConfigurationKey, ServiceName,
 };
 use mmb_domain::market::{CurrencyCode, CurrencyPair, ExchangeAccountId};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use mmb_domain::order::snapshot::Amount;
 use mmb_utils::hashmap;
@@ -25,7 +25,7 @@ pub(crate) type ValueByCurrencyCode = HashMap<CurrencyCode, Amount>;
 ///     NOTE: there is storing all balances by ServiceNames(strategy name),
 ///     that will contain several configuration keys for strategies, next layer is one or more accounts for
 ///     selected ServiceName and here stored CurrencyCodes by CurrencyPairs and amount for every currency code.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ServiceValueTree {
     tree: ConfigurationKeyByServiceName,
 }
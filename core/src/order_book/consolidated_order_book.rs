@@ -0,0 +1,309 @@
+use crate::exchanges::general::exchange::Exchange;
+use crate::math::ConvertPercentToRate;
+use crate::order_book::local_snapshot_service::LocalSnapshotsService;
+use dashmap::DashMap;
+use itertools::Itertools;
+use mmb_domain::market::{CurrencyPair, ExchangeAccountId, MarketId};
+use mmb_domain::order::snapshot::{Amount, OrderRole, OrderSide, Price};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::Arc;
+
+/// Exchange account which liquidity is included in consolidated order book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Venue {
+    pub exchange_account_id: ExchangeAccountId,
+    /// Fee rate of taking liquidity, e.g. 0.001 for 0.1%
+    pub taker_fee_rate: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolidatedPriceLevel {
+    pub exchange_account_id: ExchangeAccountId,
+    pub price: Price,
+    pub amount: Amount,
+    /// Price including taker fee: higher than `price` for asks and lower for bids
+    pub fee_adjusted_price: Price,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueFill {
+    pub exchange_account_id: ExchangeAccountId,
+    pub price: Price,
+    pub amount: Amount,
+}
+
+/// Best execution of order across venues
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    pub fills: Vec<VenueFill>,
+    pub filled_amount: Amount,
+    /// Quote amount including fees: paid for buying or received for selling
+    pub cost: Decimal,
+}
+
+impl ExecutionPlan {
+    /// Average fee-adjusted price, `None` if nothing can be filled
+    pub fn average_price(&self) -> Option<Price> {
+        (!self.filled_amount.is_zero()).then(|| self.cost / self.filled_amount)
+    }
+}
+
+/// Order books of the same currency pair on several venues merged into one.
+/// Levels are sorted from the best fee-adjusted price
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolidatedOrderBook {
+    pub currency_pair: CurrencyPair,
+    pub asks: Vec<ConsolidatedPriceLevel>,
+    pub bids: Vec<ConsolidatedPriceLevel>,
+}
+
+impl ConsolidatedOrderBook {
+    /// Return levels of asks or bids
+    pub fn get_levels(&self, book_side: OrderSide) -> &[ConsolidatedPriceLevel] {
+        match book_side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        }
+    }
+
+    /// Return the best fee-adjusted level of asks or bids
+    pub fn get_top(&self, book_side: OrderSide) -> Option<&ConsolidatedPriceLevel> {
+        self.get_levels(book_side).first()
+    }
+
+    /// Split order with taker side `order_side` between venues starting from the best
+    /// fee-adjusted price. Plan is partial if there isn't enough liquidity for `amount`
+    pub fn get_execution_plan(&self, order_side: OrderSide, amount: Amount) -> ExecutionPlan {
+        let mut plan = ExecutionPlan::default();
+
+        for level in self.get_levels(order_side.change_side()) {
+            let remaining_amount = amount - plan.filled_amount;
+            if remaining_amount <= Decimal::ZERO {
+                break;
+            }
+
+            let fill_amount = remaining_amount.min(level.amount);
+            plan.filled_amount += fill_amount;
+            plan.cost += fill_amount * level.fee_adjusted_price;
+            plan.fills.push(VenueFill {
+                exchange_account_id: level.exchange_account_id,
+                price: level.price,
+                amount: fill_amount,
+            });
+        }
+
+        plan
+    }
+}
+
+/// Builds consolidated order books from local snapshots of venues
+pub struct ConsolidatedOrderBookService {
+    venues: Vec<Venue>,
+}
+
+impl ConsolidatedOrderBookService {
+    pub fn new(venues: Vec<Venue>) -> Self {
+        Self { venues }
+    }
+
+    /// Venues with taker fees of exchanges. Only one account of each exchange is taken because
+    /// accounts of the same exchange share order book
+    pub fn from_exchanges(exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>) -> Self {
+        let venues = exchanges
+            .iter()
+            .map(|exchange| Venue {
+                exchange_account_id: exchange.exchange_account_id,
+                taker_fee_rate: exchange
+                    .commission()
+                    .get_commission(OrderRole::Taker)
+                    .fee
+                    .percent_to_rate(),
+            })
+            .sorted_by(|a, b| {
+                let (a, b) = (a.exchange_account_id, b.exchange_account_id);
+                a.exchange_id
+                    .as_str()
+                    .cmp(b.exchange_id.as_str())
+                    .then(a.account_number.cmp(&b.account_number))
+            })
+            .unique_by(|venue| venue.exchange_account_id.exchange_id)
+            .collect();
+
+        Self::new(venues)
+    }
+
+    pub fn venues(&self) -> &[Venue] {
+        &self.venues
+    }
+
+    /// Venues without snapshot of currency pair (e.g. while it is resynchronized) are skipped
+    pub fn get_consolidated_order_book(
+        &self,
+        local_snapshots_service: &LocalSnapshotsService,
+        currency_pair: CurrencyPair,
+    ) -> ConsolidatedOrderBook {
+        let mut asks = Vec::new();
+        let mut bids = Vec::new();
+
+        for venue in &self.venues {
+            let market_id = MarketId::new(venue.exchange_account_id.exchange_id, currency_pair);
+            let snapshot = match local_snapshots_service.get_snapshot(market_id) {
+                Some(snapshot) => snapshot,
+                None => continue,
+            };
+
+            let create_level = |(&price, &amount): (&Price, &Amount), fee_multiplier: Decimal| {
+                ConsolidatedPriceLevel {
+                    exchange_account_id: venue.exchange_account_id,
+                    price,
+                    amount,
+                    fee_adjusted_price: price * fee_multiplier,
+                }
+            };
+
+            asks.extend(
+                snapshot
+                    .get_asks_price_levels()
+                    .map(|level| create_level(level, dec!(1) + venue.taker_fee_rate)),
+            );
+            bids.extend(
+                snapshot
+                    .get_bids_price_levels()
+                    .map(|level| create_level(level, dec!(1) - venue.taker_fee_rate)),
+            );
+        }
+
+        asks.sort_by(|a, b| {
+            a.fee_adjusted_price
+                .cmp(&b.fee_adjusted_price)
+                .then(a.price.cmp(&b.price))
+        });
+        bids.sort_by(|a, b| {
+            b.fee_adjusted_price
+                .cmp(&a.fee_adjusted_price)
+                .then(b.price.cmp(&a.price))
+        });
+
+        ConsolidatedOrderBook {
+            currency_pair,
+            asks,
+            bids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmb_domain::order_book_data;
+    use std::collections::HashMap;
+
+    fn currency_pair() -> CurrencyPair {
+        CurrencyPair::from_codes("btc".into(), "usdt".into())
+    }
+
+    fn service() -> (ConsolidatedOrderBookService, LocalSnapshotsService) {
+        let first = ExchangeAccountId::new("first", 0);
+        let second = ExchangeAccountId::new("second", 0);
+
+        let local_snapshots = HashMap::from([
+            (
+                MarketId::new(first.exchange_id, currency_pair()),
+                order_book_data![
+                    dec!(100) => dec!(1),
+                    dec!(101) => dec!(2),
+                    ;
+                    dec!(99) => dec!(1),
+                ]
+                .to_local_order_book_snapshot(),
+            ),
+            (
+                MarketId::new(second.exchange_id, currency_pair()),
+                order_book_data![
+                    dec!(100.5) => dec!(3),
+                    ;
+                    dec!(99.5) => dec!(2),
+                ]
+                .to_local_order_book_snapshot(),
+            ),
+        ]);
+
+        let service = ConsolidatedOrderBookService::new(vec![
+            Venue {
+                exchange_account_id: first,
+                taker_fee_rate: dec!(0.01),
+            },
+            Venue {
+                exchange_account_id: second,
+                taker_fee_rate: dec!(0),
+            },
+        ]);
+
+        (service, LocalSnapshotsService::new(local_snapshots))
+    }
+
+    #[test]
+    fn levels_are_sorted_by_fee_adjusted_price() {
+        let (service, snapshots) = service();
+
+        let order_book = service.get_consolidated_order_book(&snapshots, currency_pair());
+
+        let asks = order_book
+            .asks
+            .iter()
+            .map(|x| {
+                (
+                    x.exchange_account_id.exchange_id.as_str(),
+                    x.fee_adjusted_price,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            asks,
+            vec![
+                ("second", dec!(100.5)),
+                ("first", dec!(101)),
+                ("first", dec!(102.01))
+            ]
+        );
+
+        let top_bid = order_book.get_top(OrderSide::Buy).expect("in test");
+        assert_eq!(top_bid.exchange_account_id.exchange_id.as_str(), "second");
+        assert_eq!(top_bid.price, dec!(99.5));
+        assert_eq!(order_book.bids[1].fee_adjusted_price, dec!(98.01));
+    }
+
+    #[test]
+    fn execution_plan_across_venues() {
+        let (service, snapshots) = service();
+        let order_book = service.get_consolidated_order_book(&snapshots, currency_pair());
+
+        let plan = order_book.get_execution_plan(OrderSide::Buy, dec!(3.5));
+        let fills = plan
+            .fills
+            .iter()
+            .map(|x| {
+                (
+                    x.exchange_account_id.exchange_id.as_str(),
+                    x.price,
+                    x.amount,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            fills,
+            vec![
+                ("second", dec!(100.5), dec!(3)),
+                ("first", dec!(100), dec!(0.5))
+            ]
+        );
+        assert_eq!(plan.filled_amount, dec!(3.5));
+        assert_eq!(plan.cost, dec!(352));
+
+        let plan = order_book.get_execution_plan(OrderSide::Sell, dec!(10));
+        assert_eq!(plan.filled_amount, dec!(3));
+        assert_eq!(plan.cost, dec!(297.01));
+        assert_eq!(plan.average_price(), Some(dec!(297.01) / dec!(3)));
+    }
+}
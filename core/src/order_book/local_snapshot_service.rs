@@ -2,28 +2,75 @@ use mmb_domain::market::{MarketAccountId, MarketId};
 use mmb_domain::order_book::event;
 use mmb_domain::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
 use mmb_utils::infrastructure::WithExpect;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Max count of updates buffered while snapshot is resynchronized. The oldest updates are dropped
+/// because they are likely covered by requested snapshot
+const MAX_BUFFERED_UPDATES: usize = 10_000;
+
+/// Synchronization state of order book with exchange update ids
+enum SequenceState {
+    Synced {
+        last_update_id: u64,
+    },
+    /// Snapshot is discarded and updates are buffered until a new snapshot arrives
+    Resyncing {
+        buffered_updates: VecDeque<event::OrderBookEvent>,
+    },
+}
 
 /// Produce and actualize current logical state of order book snapshot according to logical time of handled order book events
+/// Snapshots are shared with their readers and copied on the next update only if they are still read
 pub struct LocalSnapshotsService {
-    local_snapshots: HashMap<MarketId, LocalOrderBookSnapshot>,
+    local_snapshots: HashMap<MarketId, Arc<LocalOrderBookSnapshot>>,
+    sequences: HashMap<MarketId, SequenceState>,
 }
 
 impl LocalSnapshotsService {
     pub fn new(local_snapshots: HashMap<MarketId, LocalOrderBookSnapshot>) -> Self {
-        Self { local_snapshots }
+        Self {
+            local_snapshots: local_snapshots
+                .into_iter()
+                .map(|(market_id, snapshot)| (market_id, Arc::new(snapshot)))
+                .collect(),
+            sequences: HashMap::new(),
+        }
     }
 
     pub fn get_snapshot(&self, market_id: MarketId) -> Option<&LocalOrderBookSnapshot> {
-        self.local_snapshots.get(&market_id)
+        self.local_snapshots.get(&market_id).map(|x| x.as_ref())
     }
 
     pub fn get_snapshot_expected(&self, market_id: MarketId) -> &LocalOrderBookSnapshot {
-        self.local_snapshots
-            .get(&market_id)
+        self.get_snapshot(market_id)
             .with_expect(|| format!("Can't get snapshot for {:?}", market_id))
     }
 
+    /// Snapshot which can be kept by reader without copying
+    pub fn get_shared_snapshot(&self, market_id: MarketId) -> Option<Arc<LocalOrderBookSnapshot>> {
+        self.local_snapshots.get(&market_id).cloned()
+    }
+
+    /// Replace snapshot by the one maintained by other service, e.g. by events receiver
+    pub fn set_shared_snapshot(
+        &mut self,
+        market_id: MarketId,
+        snapshot: Arc<LocalOrderBookSnapshot>,
+    ) {
+        let _ = self.sequences.remove(&market_id);
+        let _ = self.local_snapshots.insert(market_id, snapshot);
+    }
+
+    /// Snapshot of market is discarded because of missed updates or checksum mismatch and
+    /// should be requested again. Market isn't ready for trading until that
+    pub fn is_resyncing(&self, market_id: MarketId) -> bool {
+        matches!(
+            self.sequences.get(&market_id),
+            Some(SequenceState::Resyncing { .. })
+        )
+    }
+
     /// Create snapshot if it does not exist
     /// Update snapshot if suitable data arrive
     /// Returns `Some(MarketAccountId)` if snapshot update succeeded, otherwise `None`
@@ -33,20 +80,127 @@ impl LocalSnapshotsService {
 
         match event.event_type {
             event::EventType::Snapshot => {
+                let _ = self.local_snapshots.insert(
+                    market_id,
+                    Arc::new(event.data.to_local_order_book_snapshot()),
+                );
+
+                let buffered_updates = match event.sequence {
+                    None => {
+                        let _ = self.sequences.remove(&market_id);
+                        VecDeque::new()
+                    }
+                    Some(sequence) => {
+                        let previous_state = self.sequences.insert(
+                            market_id,
+                            SequenceState::Synced {
+                                last_update_id: sequence.last_update_id,
+                            },
+                        );
+                        match previous_state {
+                            Some(SequenceState::Resyncing { buffered_updates }) => buffered_updates,
+                            _ => VecDeque::new(),
+                        }
+                    }
+                };
+
+                if !self.is_checksum_valid(market_id, event) {
+                    return None;
+                }
+
+                for update in buffered_updates {
+                    let _ = self.apply_update(market_id, &update);
+                }
+
                 self.local_snapshots
-                    .insert(market_id, event.data.to_local_order_book_snapshot());
-                Some(market_account_id)
+                    .contains_key(&market_id)
+                    .then_some(market_account_id)
             }
-            event::EventType::Update => {
-                self.local_snapshots
-                    .get_mut(&market_id)
-                    .map(move |snapshot| {
-                        snapshot.apply_update(&event.data, event.creation_time);
-                        market_account_id
-                    })
+            event::EventType::Update => self
+                .apply_update(market_id, event)
+                .then_some(market_account_id),
+        }
+    }
+
+    /// Returns `true` if snapshot is changed
+    fn apply_update(&mut self, market_id: MarketId, event: &event::OrderBookEvent) -> bool {
+        match (event.sequence, self.sequences.get_mut(&market_id)) {
+            (_, Some(SequenceState::Resyncing { buffered_updates })) => {
+                if event.sequence.is_some() {
+                    if buffered_updates.len() >= MAX_BUFFERED_UPDATES {
+                        let _ = buffered_updates.pop_front();
+                    }
+                    buffered_updates.push_back(event.clone());
+                }
+                return false;
+            }
+            (Some(sequence), Some(SequenceState::Synced { last_update_id })) => {
+                if sequence.last_update_id <= *last_update_id {
+                    // Update is already included in snapshot
+                    return false;
+                }
+
+                if sequence.first_update_id > *last_update_id + 1 {
+                    log::warn!(
+                        "Order book updates of {} {} are missed: expected update id {}, but received {}. Snapshot is discarded",
+                        event.exchange_account_id,
+                        event.currency_pair,
+                        *last_update_id + 1,
+                        sequence.first_update_id
+                    );
+                    self.start_resync(market_id, VecDeque::from([event.clone()]));
+                    return false;
+                }
+
+                *last_update_id = sequence.last_update_id;
+            }
+            (Some(_), None) => {
+                // Snapshot with update ids isn't received yet
+                self.start_resync(market_id, VecDeque::from([event.clone()]));
+                return false;
+            }
+            (None, _) => {}
+        }
+
+        match self.local_snapshots.get_mut(&market_id) {
+            None => false,
+            Some(snapshot) => {
+                Arc::make_mut(snapshot).apply_update(&event.data, event.creation_time);
+                self.is_checksum_valid(market_id, event)
             }
         }
     }
+
+    fn is_checksum_valid(&mut self, market_id: MarketId, event: &event::OrderBookEvent) -> bool {
+        let checksum = match &event.checksum {
+            None => return true,
+            Some(checksum) => checksum,
+        };
+
+        let snapshot = self.get_snapshot_expected(market_id);
+        if checksum.is_valid(snapshot) {
+            return true;
+        }
+
+        log::warn!(
+            "Checksum of order book {} {} mismatched. Snapshot is discarded",
+            event.exchange_account_id,
+            event.currency_pair
+        );
+        self.start_resync(market_id, VecDeque::new());
+        false
+    }
+
+    fn start_resync(
+        &mut self,
+        market_id: MarketId,
+        buffered_updates: VecDeque<event::OrderBookEvent>,
+    ) {
+        let _ = self.local_snapshots.remove(&market_id);
+        let _ = self
+            .sequences
+            .insert(market_id, SequenceState::Resyncing { buffered_updates });
+    }
 }
 
 impl Default for LocalSnapshotsService {
@@ -75,7 +229,7 @@ mod tests {
             Utc::now(),
             ExchangeAccountId::new(exchange_id, 0),
             currency_pair,
-            "".to_string(),
+            None,
             event_type,
             Arc::new(order_book_data),
         )
@@ -109,12 +263,12 @@ mod tests {
         let updated_asks = &snapshot_service
             .get_snapshot(market_account_id.market_id())
             .expect("in test")
-            .asks;
+            .get_asks();
 
         let updated_bids = &snapshot_service
             .get_snapshot(market_account_id.market_id())
             .expect("in test")
-            .bids;
+            .get_bids();
 
         // Check all snapshot returned values
         assert_eq!(updated_asks.get(&dec!(1.0)), Some(&dec!(2.1)));
@@ -200,12 +354,12 @@ mod tests {
         let updated_asks = &snapshot_service
             .get_snapshot(market_id)
             .expect("in test")
-            .asks;
+            .get_asks();
 
         let updated_bids = &snapshot_service
             .get_snapshot(market_id)
             .expect("in test")
-            .bids;
+            .get_bids();
 
         // Check all snapshot returned values
         assert_eq!(
@@ -229,4 +383,131 @@ mod tests {
             None
         );
     }
+
+    fn create_sequenced_event(
+        event_type: event::EventType,
+        first_update_id: u64,
+        last_update_id: u64,
+        order_book_data: order_book_data::OrderBookData,
+    ) -> event::OrderBookEvent {
+        event::OrderBookEvent::new(
+            Utc::now(),
+            ExchangeAccountId::new("exchange_id", 0),
+            CurrencyPair::from_codes("base".into(), "quote".into()),
+            Some(event::OrderBookSequence::new(
+                first_update_id,
+                last_update_id,
+            )),
+            event_type,
+            Arc::new(order_book_data),
+        )
+    }
+
+    #[test]
+    fn gap_in_updates_discards_snapshot_until_new_snapshot() {
+        let mut snapshot_service = LocalSnapshotsService::default();
+
+        let snapshot = create_sequenced_event(
+            event::EventType::Snapshot,
+            10,
+            10,
+            order_book_data![dec!(1.0) => dec!(1.0), ; dec!(0.9) => dec!(1.0),],
+        );
+        let market_id = snapshot.market_account_id().market_id();
+        assert!(snapshot_service.update(&snapshot).is_some());
+
+        // Already included in snapshot
+        let outdated_update = create_sequenced_event(
+            event::EventType::Update,
+            8,
+            10,
+            order_book_data![dec!(1.0) => dec!(5.0), ;],
+        );
+        assert!(snapshot_service.update(&outdated_update).is_none());
+
+        let update = create_sequenced_event(
+            event::EventType::Update,
+            9,
+            12,
+            order_book_data![dec!(1.0) => dec!(2.0), ;],
+        );
+        assert!(snapshot_service.update(&update).is_some());
+
+        let update_after_gap = create_sequenced_event(
+            event::EventType::Update,
+            15,
+            16,
+            order_book_data![dec!(1.1) => dec!(3.0), ;],
+        );
+        assert!(snapshot_service.update(&update_after_gap).is_none());
+        assert!(snapshot_service.is_resyncing(market_id));
+        assert!(snapshot_service.get_snapshot(market_id).is_none());
+
+        let next_update = create_sequenced_event(
+            event::EventType::Update,
+            17,
+            17,
+            order_book_data![; dec!(0.9) => dec!(0),],
+        );
+        assert!(snapshot_service.update(&next_update).is_none());
+
+        // Buffered updates are applied after snapshot, outdated ones are skipped
+        let new_snapshot = create_sequenced_event(
+            event::EventType::Snapshot,
+            15,
+            15,
+            order_book_data![dec!(1.0) => dec!(4.0), ; dec!(0.9) => dec!(1.0),],
+        );
+        assert!(snapshot_service.update(&new_snapshot).is_some());
+        assert!(!snapshot_service.is_resyncing(market_id));
+
+        let snapshot = snapshot_service.get_snapshot_expected(market_id);
+        assert_eq!(snapshot.get_asks().get(&dec!(1.0)), Some(&dec!(4.0)));
+        assert_eq!(snapshot.get_asks().get(&dec!(1.1)), Some(&dec!(3.0)));
+        assert_eq!(snapshot.get_bids().get(&dec!(0.9)), None);
+    }
+
+    #[test]
+    fn checksum_mismatch_discards_snapshot() {
+        fn asks_count(snapshot: &LocalOrderBookSnapshot) -> u32 {
+            snapshot.get_asks().len() as u32
+        }
+
+        let mut snapshot_service = LocalSnapshotsService::default();
+
+        let snapshot = create_sequenced_event(
+            event::EventType::Snapshot,
+            1,
+            1,
+            order_book_data![dec!(1.0) => dec!(1.0), ;],
+        );
+        let market_id = snapshot.market_account_id().market_id();
+        assert!(snapshot_service.update(&snapshot).is_some());
+
+        let update = create_sequenced_event(
+            event::EventType::Update,
+            2,
+            2,
+            order_book_data![dec!(1.1) => dec!(1.0), ;],
+        )
+        .with_checksum(event::OrderBookChecksum {
+            expected: 2,
+            calculator: asks_count,
+        });
+        assert!(snapshot_service.update(&update).is_some());
+
+        let update = create_sequenced_event(
+            event::EventType::Update,
+            3,
+            3,
+            order_book_data![dec!(1.2) => dec!(1.0), ;],
+        )
+        .with_checksum(event::OrderBookChecksum {
+            expected: 2,
+            calculator: asks_count,
+        });
+        assert!(snapshot_service.update(&update).is_none());
+        assert!(snapshot_service.is_resyncing(market_id));
+        assert!(snapshot_service.get_snapshot(market_id).is_none());
+    }
 }
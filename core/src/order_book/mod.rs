@@ -1 +1,2 @@
+pub mod consolidated_order_book;
 pub mod local_snapshot_service;
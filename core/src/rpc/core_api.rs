@@ -5,6 +5,7 @@ use tokio::sync::{mpsc, oneshot};
 use crate::lifecycle::app_lifetime_manager::{ActionAfterGracefulShutdown, AppLifetimeManager};
 use std::sync::Arc;
 
+use crate::services::kill_switch::KillSwitch;
 use crate::{lifecycle::trading_engine::Service, statistic_service::StatisticService};
 
 use super::{
@@ -27,6 +28,7 @@ impl CoreApi {
         lifetime_manager: Arc<AppLifetimeManager>,
         engine_settings: String,
         statistics: Arc<StatisticService>,
+        kill_switch: Arc<KillSwitch>,
     ) -> Result<Arc<Self>> {
         let (server_stopper_tx, server_stopper_rx) =
             mpsc::channel::<ActionAfterGracefulShutdown>(10);
@@ -39,6 +41,7 @@ impl CoreApi {
             server_stopper_tx.clone(),
             statistics,
             engine_settings,
+            kill_switch,
         ));
 
         spawn_server_stopping_action(
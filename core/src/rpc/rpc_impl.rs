@@ -1,4 +1,4 @@
-use jsonrpc_core::Result;
+use jsonrpc_core::{BoxFuture, Result};
 use mmb_rpc::rest_api::server_side_error;
 use mmb_rpc::rest_api::MmbRpc;
 use parking_lot::Mutex;
@@ -7,6 +7,7 @@ use tokio::sync::mpsc;
 use std::sync::Arc;
 
 use crate::lifecycle::app_lifetime_manager::ActionAfterGracefulShutdown;
+use crate::services::kill_switch::{KillSwitch, KillSwitchTrigger};
 use crate::statistic_service::StatisticService;
 use mmb_rpc::rest_api::ErrorCode;
 
@@ -18,6 +19,7 @@ pub struct RpcImpl {
     server_stopper_tx: Arc<Mutex<Option<mpsc::Sender<ActionAfterGracefulShutdown>>>>,
     statistics: Arc<StatisticService>,
     engine_settings: String,
+    kill_switch: Arc<KillSwitch>,
 }
 
 impl RpcImpl {
@@ -25,11 +27,13 @@ impl RpcImpl {
         server_stopper_tx: Arc<Mutex<Option<mpsc::Sender<ActionAfterGracefulShutdown>>>>,
         statistics: Arc<StatisticService>,
         engine_settings: String,
+        kill_switch: Arc<KillSwitch>,
     ) -> Self {
         Self {
             server_stopper_tx,
             statistics,
             engine_settings,
+            kill_switch,
         }
     }
 }
@@ -66,4 +70,35 @@ impl MmbRpc for RpcImpl {
 
         Ok(json_statistic)
     }
+
+    fn kill_switch(&self, reason: String) -> Result<String> {
+        if self.kill_switch.trigger(KillSwitchTrigger::Rpc { reason }) {
+            Ok("Kill switch is triggered. Trading is stopped until acknowledge".into())
+        } else {
+            Ok("Kill switch has already been triggered".into())
+        }
+    }
+
+    fn kill_switch_status(&self) -> Result<String> {
+        serde_json::to_string(&self.kill_switch.status()).map_err(|err| {
+            log::warn!("Failed to convert kill switch status to string: {err}");
+            server_side_error(ErrorCode::FailedToSerializeResponse)
+        })
+    }
+
+    fn acknowledge_kill_switch(&self, operator: String) -> BoxFuture<Result<String>> {
+        let kill_switch = self.kill_switch.clone();
+        Box::pin(async move {
+            match kill_switch.acknowledge(&operator).await {
+                Ok(status) => Ok(format!(
+                    "Kill switch triggered by {} is acknowledged. Trading is unblocked",
+                    status.trigger
+                )),
+                Err(err) => {
+                    log::warn!("Failed to acknowledge kill switch: {err:?}");
+                    Err(server_side_error(ErrorCode::KillSwitchAcknowledgeRejected))
+                }
+            }
+        })
+    }
 }
@@ -1,4 +1,4 @@
-use jsonrpc_core::Result;
+use jsonrpc_core::{BoxFuture, Result};
 use mmb_rpc::rest_api::MmbRpc;
 use mmb_utils::send_expected::SendExpectedByRef;
 use parking_lot::Mutex;
@@ -52,4 +52,16 @@ impl MmbRpc for RpcImplNoConfig {
     fn stats(&self) -> Result<String> {
         Ok(CONFIG_IS_NOT_SET.into())
     }
+
+    fn kill_switch(&self, _reason: String) -> Result<String> {
+        Ok(CONFIG_IS_NOT_SET.into())
+    }
+
+    fn kill_switch_status(&self) -> Result<String> {
+        Ok(CONFIG_IS_NOT_SET.into())
+    }
+
+    fn acknowledge_kill_switch(&self, _operator: String) -> BoxFuture<Result<String>> {
+        Box::pin(async { Ok(CONFIG_IS_NOT_SET.into()) })
+    }
 }
@@ -0,0 +1,554 @@
+use crate::database::events::recorder::EventRecorder;
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::timeouts::timeout_manager::TimeoutManager;
+use crate::lifecycle::trading_engine::Service;
+use crate::settings::CandlesSettings;
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+use dashmap::DashMap;
+use itertools::Itertools;
+use mmb_domain::candle::Candle;
+use mmb_domain::events::{ExchangeEvent, TradesEvent};
+use mmb_domain::market::{ExchangeAccountId, MarketId};
+use mmb_domain::order::snapshot::{Amount, Price};
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::oneshot::Receiver;
+
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Candles are closed a bit later than their intervals end, so trades delivered with a delay
+/// still get into them
+const CLOSING_DELAY_SECS: i64 = 2;
+
+type CandleKey = (MarketId, u32);
+
+/// Start of interval which contains `time`
+fn get_open_time(time: DateTime, interval_secs: u32) -> DateTime {
+    let timestamp = time.timestamp();
+    Utc.timestamp(timestamp - timestamp.rem_euclid(interval_secs.into()), 0)
+}
+
+/// Current and closed candles of markets for all configured intervals
+struct CandlesBuilder {
+    intervals_secs: Vec<u32>,
+    history_size: usize,
+    current: HashMap<CandleKey, Candle>,
+    history: HashMap<CandleKey, VecDeque<Candle>>,
+    /// Trades which are older than all candles in history, so there is no candle to amend
+    dropped_trades_count: u64,
+    /// Accounts of the same exchange receive the same trades, so trades of market are taken from
+    /// the first account which delivered them
+    trade_sources: HashMap<MarketId, ExchangeAccountId>,
+}
+
+impl CandlesBuilder {
+    fn new(settings: &CandlesSettings) -> Self {
+        Self {
+            intervals_secs: settings.intervals_secs.iter().copied().unique().collect(),
+            history_size: settings.history_size.unwrap_or(DEFAULT_HISTORY_SIZE),
+            current: HashMap::new(),
+            history: HashMap::new(),
+            dropped_trades_count: 0,
+            trade_sources: HashMap::new(),
+        }
+    }
+
+    /// Returns `false` if trades of market are taken from another account of the exchange
+    fn is_trade_source(
+        &mut self,
+        market_id: MarketId,
+        exchange_account_id: ExchangeAccountId,
+    ) -> bool {
+        *self
+            .trade_sources
+            .entry(market_id)
+            .or_insert(exchange_account_id)
+            == exchange_account_id
+    }
+
+    fn push_to_history(&mut self, candle: Candle) {
+        let history = self
+            .history
+            .entry((candle.market_id, candle.interval_secs))
+            .or_default();
+        history.push_back(candle);
+        while history.len() > self.history_size {
+            let _ = history.pop_front();
+        }
+    }
+
+    /// Adds late trade to closed candle of its interval or creates candle for the interval if there
+    /// were no trades during it. Returns the amended candle
+    fn amend_history(
+        &mut self,
+        key: CandleKey,
+        open_time: DateTime,
+        price: Price,
+        amount: Amount,
+    ) -> Option<Candle> {
+        let history_size = self.history_size;
+        let history = self.history.entry(key).or_default();
+        let index = history.partition_point(|x| x.open_time < open_time);
+        match history.get_mut(index) {
+            Some(candle) if candle.open_time == open_time => {
+                candle.add_late_trade(price, amount);
+                return Some(candle.clone());
+            }
+            _ => {}
+        }
+
+        if index == 0 && history.len() >= history_size {
+            self.dropped_trades_count += 1;
+            log::warn!(
+                "Trade of {:?} at {open_time} is dropped because it's older than {}s candles history",
+                key.0,
+                key.1
+            );
+            return None;
+        }
+
+        let (market_id, interval_secs) = key;
+        let candle = Candle::new(market_id, interval_secs, open_time, price, amount);
+        history.insert(index, candle.clone());
+        while history.len() > history_size {
+            let _ = history.pop_front();
+        }
+        Some(candle)
+    }
+
+    /// Returns candles which should be saved: closed by the trade or amended by late trade.
+    /// Amended candles are saved again, so the latest saved version of candle is actual
+    fn add_trade(
+        &mut self,
+        market_id: MarketId,
+        price: Price,
+        amount: Amount,
+        time: DateTime,
+    ) -> Vec<Candle> {
+        let mut closed_candles = Vec::new();
+
+        for interval_secs in self.intervals_secs.clone() {
+            let key = (market_id, interval_secs);
+            let open_time = get_open_time(time, interval_secs);
+
+            let last_closed_open_time = self
+                .history
+                .get(&key)
+                .and_then(|x| x.back())
+                .map(|x| x.open_time);
+            let is_late = match self.current.get_mut(&key) {
+                Some(candle) if candle.open_time == open_time => {
+                    candle.add_trade(price, amount);
+                    continue;
+                }
+                Some(candle) => candle.open_time > open_time,
+                None => last_closed_open_time.map_or(false, |x| x >= open_time),
+            };
+            if is_late {
+                closed_candles.extend(self.amend_history(key, open_time, price, amount));
+                continue;
+            }
+
+            let new_candle = Candle::new(market_id, interval_secs, open_time, price, amount);
+            if let Some(closed_candle) = self.current.insert(key, new_candle) {
+                self.push_to_history(closed_candle.clone());
+                closed_candles.push(closed_candle);
+            }
+        }
+
+        closed_candles
+    }
+
+    /// Closes candles which intervals ended before current time of exchange server.
+    /// Trades time is set by exchange, so local clock can't be used for closing
+    fn close_expired(&mut self, server_now: impl Fn(ExchangeAccountId) -> DateTime) -> Vec<Candle> {
+        let expired_keys = self
+            .current
+            .iter()
+            .filter(|((market_id, _), candle)| {
+                let now = match self.trade_sources.get(market_id) {
+                    Some(&exchange_account_id) => server_now(exchange_account_id),
+                    None => return false,
+                };
+                candle.close_time() + ChronoDuration::seconds(CLOSING_DELAY_SECS) <= now
+            })
+            .map(|(key, _)| *key)
+            .collect_vec();
+
+        let mut closed_candles = Vec::with_capacity(expired_keys.len());
+        for key in expired_keys {
+            if let Some(candle) = self.current.remove(&key) {
+                self.push_to_history(candle.clone());
+                closed_candles.push(candle);
+            }
+        }
+
+        closed_candles.sort_by_key(|x| x.open_time);
+        closed_candles
+    }
+
+    /// Merges candles received from exchange into history. Candles which intervals are already
+    /// in history or are built from trades now are skipped
+    fn add_history(&mut self, market_id: MarketId, interval_secs: u32, candles: Vec<Candle>) {
+        let key = (market_id, interval_secs);
+        let current_open_time = self.current.get(&key).map(|x| x.open_time);
+
+        let history = self.history.entry(key).or_default();
+        let merged = history
+            .drain(..)
+            .merge_by(candles, |a, b| a.open_time <= b.open_time)
+            .dedup_by(|a, b| a.open_time == b.open_time)
+            .filter(|x| current_open_time.map_or(true, |open_time| x.open_time < open_time))
+            .collect_vec();
+
+        let skipped_count = merged.len().saturating_sub(self.history_size);
+        history.extend(merged.into_iter().skip(skipped_count));
+    }
+
+    fn get_candles(&self, market_id: MarketId, interval_secs: u32, limit: usize) -> Vec<Candle> {
+        match self.history.get(&(market_id, interval_secs)) {
+            Some(history) => history
+                .iter()
+                .skip(history.len().saturating_sub(limit))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Builds OHLCV candles of all markets from trades for configured intervals.
+/// Closed candles are saved to database and kept in memory for strategies
+pub struct CandlesService {
+    builder: Mutex<CandlesBuilder>,
+    event_recorder: Arc<EventRecorder>,
+    timeout_manager: Arc<TimeoutManager>,
+}
+
+impl Service for CandlesService {
+    fn name(&self) -> &str {
+        "CandlesService"
+    }
+
+    fn graceful_shutdown(self: Arc<Self>) -> Option<Receiver<Result<()>>> {
+        None
+    }
+}
+
+impl CandlesService {
+    pub fn new(
+        settings: &CandlesSettings,
+        event_recorder: Arc<EventRecorder>,
+        timeout_manager: Arc<TimeoutManager>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            builder: Mutex::new(CandlesBuilder::new(settings)),
+            event_recorder,
+            timeout_manager,
+        })
+    }
+
+    /// Count of trades which weren't added to candles because they are older than candles history
+    pub fn dropped_trades_count(&self) -> u64 {
+        self.builder.lock().dropped_trades_count
+    }
+
+    pub fn intervals_secs(&self) -> Vec<u32> {
+        self.builder.lock().intervals_secs.clone()
+    }
+
+    /// Last closed candles of market, oldest first
+    pub fn get_candles(
+        &self,
+        market_id: MarketId,
+        interval_secs: u32,
+        limit: usize,
+    ) -> Vec<Candle> {
+        self.builder
+            .lock()
+            .get_candles(market_id, interval_secs, limit)
+    }
+
+    /// Candle of current interval, `None` if there were no trades during it
+    pub fn get_current_candle(&self, market_id: MarketId, interval_secs: u32) -> Option<Candle> {
+        self.builder
+            .lock()
+            .current
+            .get(&(market_id, interval_secs))
+            .cloned()
+    }
+
+    pub async fn start(
+        self: Arc<Self>,
+        mut events_receiver: broadcast::Receiver<ExchangeEvent>,
+    ) -> Result<()> {
+        loop {
+            match events_receiver.recv().await {
+                Ok(ExchangeEvent::Trades(trades_event)) => self.handle_trades(&trades_event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped_count)) => {
+                    log::warn!("CandlesService skipped {skipped_count} events, so some trades aren't included in candles")
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    fn handle_trades(&self, trades_event: &TradesEvent) {
+        let market_id = MarketId::new(
+            trades_event.exchange_account_id.exchange_id,
+            trades_event.currency_pair,
+        );
+
+        let closed_candles = {
+            let mut builder = self.builder.lock();
+            if !builder.is_trade_source(market_id, trades_event.exchange_account_id) {
+                return;
+            }
+
+            trades_event
+                .trades
+                .iter()
+                .flat_map(|trade| {
+                    builder.add_trade(
+                        market_id,
+                        trade.price,
+                        trade.quantity,
+                        trade.transaction_time,
+                    )
+                })
+                .collect_vec()
+        };
+
+        self.save_candles(closed_candles);
+    }
+
+    /// Closes candles of finished intervals even if there are no new trades
+    pub async fn close_expired_candles(self: Arc<Self>) {
+        let closed_candles = self.builder.lock().close_expired(|exchange_account_id| {
+            self.timeout_manager.server_clock(exchange_account_id).now()
+        });
+        self.save_candles(closed_candles);
+    }
+
+    fn save_candles(&self, candles: Vec<Candle>) {
+        for candle in candles {
+            if let Err(err) = self.event_recorder.save(candle) {
+                log::error!("Failed to save candle: {err:?}");
+            }
+        }
+    }
+
+    /// Requests history of candles for subscribed currency pairs from exchanges which support klines.
+    /// Only one account of each exchange is used because accounts of the same exchange share market data
+    pub async fn load_history(
+        self: Arc<Self>,
+        exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+    ) -> Result<()> {
+        let history_size = self.builder.lock().history_size;
+        let limit = u32::try_from(history_size).unwrap_or(u32::MAX);
+
+        let exchanges = exchanges
+            .iter()
+            .filter(|x| x.is_klines_supported())
+            .map(|x| (x.value().clone(), x.subscribed_currency_pairs()))
+            .filter(|(_, currency_pairs)| !currency_pairs.is_empty())
+            .unique_by(|(x, _)| x.exchange_account_id.exchange_id)
+            .collect_vec();
+
+        for (exchange, currency_pairs) in exchanges {
+            for currency_pair in currency_pairs {
+                let market_id =
+                    MarketId::new(exchange.exchange_account_id.exchange_id, currency_pair);
+                for interval_secs in self.intervals_secs() {
+                    match exchange.get_candles(currency_pair, interval_secs, limit).await {
+                        Ok(candles) => {
+                            self.builder
+                                .lock()
+                                .add_history(market_id, interval_secs, candles)
+                        }
+                        Err(err) => log::warn!(
+                            "Failed to load {interval_secs}s candles of {} {currency_pair}: {err:?}",
+                            exchange.exchange_account_id
+                        ),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmb_domain::market::CurrencyPair;
+    use rust_decimal_macros::dec;
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("exchange_test_id", 0)
+    }
+
+    fn market_id() -> MarketId {
+        MarketId::new(
+            exchange_account_id().exchange_id,
+            CurrencyPair::from_codes("btc".into(), "usdt".into()),
+        )
+    }
+
+    fn time(secs: i64) -> DateTime {
+        Utc.timestamp(secs, 0)
+    }
+
+    fn builder() -> CandlesBuilder {
+        CandlesBuilder::new(&CandlesSettings {
+            intervals_secs: vec![60, 300],
+            history_size: Some(2),
+        })
+    }
+
+    #[test]
+    fn trades_are_aggregated_into_candles_of_all_intervals() {
+        let mut builder = builder();
+
+        assert!(builder
+            .add_trade(market_id(), dec!(10), dec!(1), time(30))
+            .is_empty());
+        assert!(builder
+            .add_trade(market_id(), dec!(12), dec!(2), time(40))
+            .is_empty());
+        assert!(builder
+            .add_trade(market_id(), dec!(9), dec!(1), time(50))
+            .is_empty());
+
+        let closed = builder.add_trade(market_id(), dec!(11), dec!(3), time(70));
+        assert_eq!(
+            closed,
+            vec![Candle {
+                market_id: market_id(),
+                interval_secs: 60,
+                open_time: time(0),
+                open: dec!(10),
+                high: dec!(12),
+                low: dec!(9),
+                close: dec!(9),
+                volume: dec!(4),
+                trades_count: 3,
+            }]
+        );
+
+        // late trade amends closed 1 minute candle, so it's saved again
+        let amended = builder.add_trade(market_id(), dec!(100), dec!(1), time(59));
+        assert_eq!(
+            amended,
+            vec![Candle {
+                high: dec!(100),
+                volume: dec!(5),
+                trades_count: 4,
+                ..closed[0].clone()
+            }]
+        );
+        assert_eq!(builder.get_candles(market_id(), 60, 1), amended);
+
+        // but it's still in interval of 5 minutes candle
+        let current = builder.current.get(&(market_id(), 300)).expect("in test");
+        assert_eq!(current.open_time, time(0));
+        assert_eq!(current.high, dec!(100));
+        assert_eq!(current.volume, dec!(8));
+        assert_eq!(current.trades_count, 5);
+    }
+
+    #[test]
+    fn expired_candles_are_closed_and_history_is_merged() {
+        let mut builder = builder();
+        assert!(builder.is_trade_source(market_id(), exchange_account_id()));
+        builder.add_trade(market_id(), dec!(10), dec!(1), time(125));
+
+        let server_now = |now| {
+            move |exchange_account_id| {
+                assert_eq!(exchange_account_id, self::exchange_account_id());
+                time(now)
+            }
+        };
+        assert!(builder.close_expired(server_now(181)).is_empty());
+        let closed = builder.close_expired(server_now(182));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].open_time, time(120));
+
+        builder.add_trade(market_id(), dec!(11), dec!(1), time(250));
+
+        let exchange_candle =
+            |open_time| Candle::new(market_id(), 60, time(open_time), dec!(1), dec!(1));
+        builder.add_history(
+            market_id(),
+            60,
+            vec![
+                exchange_candle(0),
+                exchange_candle(60),
+                exchange_candle(120),
+                exchange_candle(180),
+                exchange_candle(240),
+            ],
+        );
+
+        // history is limited by its size and candle built from trades isn't replaced
+        let history = builder.get_candles(market_id(), 60, 10);
+        let open_times = history.iter().map(|x| x.open_time).collect_vec();
+        assert_eq!(open_times, vec![time(120), time(180)]);
+        assert_eq!(history[0].close, dec!(10));
+        assert_eq!(
+            builder.get_candles(market_id(), 60, 1)[0].open_time,
+            time(180)
+        );
+    }
+
+    #[test]
+    fn late_trades_fill_gaps_and_too_old_trades_are_counted() {
+        let mut builder = builder();
+        builder.add_trade(market_id(), dec!(10), dec!(1), time(10));
+        builder.add_trade(market_id(), dec!(11), dec!(1), time(130));
+        builder.add_trade(market_id(), dec!(12), dec!(1), time(250));
+
+        // there were no trades during the 2nd minute, so candle is created for it
+        let amended = builder.add_trade(market_id(), dec!(9), dec!(2), time(70));
+        assert_eq!(
+            amended,
+            vec![Candle::new(market_id(), 60, time(60), dec!(9), dec!(2))]
+        );
+        let open_times = builder
+            .get_candles(market_id(), 60, 10)
+            .iter()
+            .map(|x| x.open_time)
+            .collect_vec();
+        assert_eq!(open_times, vec![time(60), time(120)]);
+        assert_eq!(builder.dropped_trades_count, 0);
+
+        // the 1st minute is out of history of 2 candles
+        assert!(builder
+            .add_trade(market_id(), dec!(8), dec!(1), time(5))
+            .is_empty());
+        assert_eq!(builder.dropped_trades_count, 1);
+    }
+
+    #[test]
+    fn trades_are_taken_from_one_account_of_exchange() {
+        let mut builder = builder();
+        let other_account_id = ExchangeAccountId::new("exchange_test_id", 1);
+        let other_market_id = MarketId::new(
+            exchange_account_id().exchange_id,
+            CurrencyPair::from_codes("eth".into(), "usdt".into()),
+        );
+
+        assert!(builder.is_trade_source(market_id(), exchange_account_id()));
+        assert!(!builder.is_trade_source(market_id(), other_account_id));
+        assert!(builder.is_trade_source(market_id(), exchange_account_id()));
+
+        assert!(builder.is_trade_source(other_market_id, other_account_id));
+        assert!(!builder.is_trade_source(other_market_id, exchange_account_id()));
+    }
+}
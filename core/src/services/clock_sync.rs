@@ -0,0 +1,137 @@
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::server_clock::{ClockSample, ServerClock};
+use crate::exchanges::timeouts::timeout_manager;
+use crate::lifecycle::trading_engine::Service;
+use crate::settings::ClockSyncSettings;
+use crate::statistic_service::StatisticService;
+use dashmap::DashMap;
+use mmb_domain::market::ExchangeAccountId;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::Receiver;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_SAMPLES_COUNT: usize = 3;
+const DEFAULT_MAX_OFFSET_MS: u64 = 1000;
+
+/// Periodically requests exchange server time and updates `ServerClock` of exchange, so signed
+/// requests aren't rejected by exchange because of drift of local clock
+pub struct ClockSyncService {
+    exchange: Arc<Exchange>,
+    server_clock: Arc<ServerClock>,
+    statistics: Arc<StatisticService>,
+    interval: Duration,
+    samples_count: usize,
+    max_offset_ms: u64,
+}
+
+impl Service for ClockSyncService {
+    fn name(&self) -> &str {
+        "ClockSyncService"
+    }
+
+    fn graceful_shutdown(self: Arc<Self>) -> Option<Receiver<anyhow::Result<()>>> {
+        None
+    }
+}
+
+impl ClockSyncService {
+    pub fn new(
+        exchange: Arc<Exchange>,
+        settings: &ClockSyncSettings,
+        statistics: Arc<StatisticService>,
+    ) -> Self {
+        Self {
+            server_clock: exchange.server_clock(),
+            exchange,
+            statistics,
+            interval: settings
+                .interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_INTERVAL),
+            samples_count: settings
+                .samples_count
+                .unwrap_or(DEFAULT_SAMPLES_COUNT)
+                .max(1),
+            max_offset_ms: settings.max_offset_ms.unwrap_or(DEFAULT_MAX_OFFSET_MS),
+        }
+    }
+
+    /// Creates services for exchanges which are able to request server time
+    pub fn create_for_exchanges(
+        exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>,
+        statistics: Arc<StatisticService>,
+    ) -> Vec<Arc<Self>> {
+        exchanges
+            .iter()
+            .filter(|exchange| exchange.is_server_time_supported())
+            .map(|exchange| {
+                let settings = exchange
+                    .exchange_client
+                    .get_settings()
+                    .clock_sync
+                    .clone()
+                    .unwrap_or_default();
+
+                Arc::new(Self::new(exchange.clone(), &settings, statistics.clone()))
+            })
+            .collect()
+    }
+
+    pub fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange.exchange_account_id
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub async fn sync(self: Arc<Self>) {
+        let exchange_account_id = self.exchange.exchange_account_id;
+
+        let mut samples = Vec::with_capacity(self.samples_count);
+        for _ in 0..self.samples_count {
+            let request_time = timeout_manager::now();
+            match self.exchange.get_server_time().await {
+                Ok(server_time) => samples.push(ClockSample::new(
+                    request_time,
+                    server_time,
+                    timeout_manager::now(),
+                )),
+                Err(err) => {
+                    log::warn!("Failed to request server time of {exchange_account_id}: {err:?}")
+                }
+            }
+        }
+
+        let state = match self
+            .server_clock
+            .synchronize(&samples, timeout_manager::now())
+        {
+            Some(state) => state,
+            None => {
+                log::error!("Clock of {exchange_account_id} isn't synchronized because server time wasn't received");
+                return;
+            }
+        };
+
+        self.statistics
+            .register_server_clock(exchange_account_id, state);
+
+        if state.offset_ms.unsigned_abs() > self.max_offset_ms {
+            log::warn!(
+                "Local clock differs from {exchange_account_id} server clock by {}ms (round trip {}ms, drift {}ms since previous synchronization)",
+                state.offset_ms,
+                state.round_trip_ms,
+                state.drift_ms
+            );
+        } else {
+            log::trace!(
+                "Clock of {exchange_account_id} is synchronized: offset {}ms, round trip {}ms, drift {}ms",
+                state.offset_ms,
+                state.round_trip_ms,
+                state.drift_ms
+            );
+        }
+    }
+}
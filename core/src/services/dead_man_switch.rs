@@ -0,0 +1,141 @@
+use crate::exchanges::general::exchange::Exchange;
+use crate::lifecycle::trading_engine::Service;
+use crate::services::kill_switch::KillSwitch;
+use crate::settings::DeadManSwitchSettings;
+use dashmap::DashMap;
+use mmb_domain::market::ExchangeAccountId;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::Receiver;
+
+/// Periodically rearms exchange-side timer which cancels all orders on exchange, so if engine hangs
+/// or loses connectivity our orders don't stay on exchange until restart.
+/// Timer isn't disarmed on graceful shutdown because all orders are cancelled anyway and the
+/// next engine run rearms it
+pub struct DeadManSwitchService {
+    exchange: Arc<Exchange>,
+    timeout: Duration,
+    refresh_interval: Duration,
+    kill_switch: Arc<KillSwitch>,
+}
+
+impl Service for DeadManSwitchService {
+    fn name(&self) -> &str {
+        "DeadManSwitchService"
+    }
+
+    fn graceful_shutdown(self: Arc<Self>) -> Option<Receiver<anyhow::Result<()>>> {
+        None
+    }
+}
+
+impl DeadManSwitchService {
+    pub fn new(
+        exchange: Arc<Exchange>,
+        settings: &DeadManSwitchSettings,
+        kill_switch: Arc<KillSwitch>,
+    ) -> Self {
+        let (timeout, refresh_interval) = timeout_and_refresh_interval(settings);
+        Self {
+            exchange,
+            timeout,
+            refresh_interval,
+            kill_switch,
+        }
+    }
+
+    /// Creates services for exchanges with configured dead man's switch
+    pub fn create_for_exchanges(
+        exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>,
+        kill_switch: Arc<KillSwitch>,
+    ) -> Vec<Arc<Self>> {
+        exchanges
+            .iter()
+            .filter_map(|exchange| {
+                let settings = exchange.exchange_client.get_settings();
+                let dead_man_switch = settings.dead_man_switch.as_ref()?;
+
+                if !exchange.is_cancel_all_after_supported() {
+                    log::warn!(
+                        "Dead man's switch is configured but isn't supported by {}",
+                        exchange.exchange_account_id
+                    );
+                    return None;
+                }
+
+                Some(Arc::new(Self::new(
+                    exchange.clone(),
+                    dead_man_switch,
+                    kill_switch.clone(),
+                )))
+            })
+            .collect()
+    }
+
+    pub fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange.exchange_account_id
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    pub async fn refresh(self: Arc<Self>) {
+        // Let exchange cancel orders by itself in addition to kill switch cancellation
+        if self.kill_switch.is_triggered() {
+            log::warn!(
+                "Dead man's switch isn't refreshed for {} because kill switch is triggered",
+                self.exchange.exchange_account_id
+            );
+            return;
+        }
+
+        if let Err(err) = self.exchange.cancel_all_orders_after(self.timeout).await {
+            log::error!(
+                "Failed to refresh dead man's switch for {}: {err:?}",
+                self.exchange.exchange_account_id
+            );
+        }
+    }
+}
+
+fn timeout_and_refresh_interval(settings: &DeadManSwitchSettings) -> (Duration, Duration) {
+    let timeout = Duration::from_millis(settings.timeout_ms);
+    let refresh_interval = settings
+        .refresh_interval_ms
+        .map(Duration::from_millis)
+        .unwrap_or(timeout / 3);
+
+    (timeout, refresh_interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_refresh_interval() {
+        let settings = DeadManSwitchSettings {
+            timeout_ms: 60_000,
+            refresh_interval_ms: None,
+        };
+
+        let (timeout, refresh_interval) = timeout_and_refresh_interval(&settings);
+
+        assert_eq!(timeout, Duration::from_secs(60));
+        assert_eq!(refresh_interval, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn explicit_refresh_interval() {
+        let settings = DeadManSwitchSettings {
+            timeout_ms: 60_000,
+            refresh_interval_ms: Some(5_000),
+        };
+
+        let (timeout, refresh_interval) = timeout_and_refresh_interval(&settings);
+
+        assert_eq!(timeout, Duration::from_secs(60));
+        assert_eq!(refresh_interval, Duration::from_secs(5));
+    }
+}
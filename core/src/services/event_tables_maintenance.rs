@@ -0,0 +1,141 @@
+use crate::lifecycle::trading_engine::Service;
+use crate::settings::{EventTableRetentionSettings, EventTablesMaintenanceSettings};
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use mmb_database::postgres_db::partitions::{
+    create_partition, drop_partition, export_partition, load_default_partition_days,
+    load_partitions, Partition,
+};
+use mmb_database::postgres_db::PgPool;
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::oneshot::Receiver;
+
+const DEFAULT_PARTITIONS_AHEAD_DAYS: u32 = 2;
+
+/// Creates daily partitions of event tables in advance and archives or drops
+/// partitions older than retention of the table
+pub struct EventTablesMaintenanceService {
+    pool: PgPool,
+    settings: EventTablesMaintenanceSettings,
+}
+
+impl Service for EventTablesMaintenanceService {
+    fn name(&self) -> &str {
+        "EventTablesMaintenanceService"
+    }
+
+    fn graceful_shutdown(self: Arc<Self>) -> Option<Receiver<Result<()>>> {
+        None
+    }
+}
+
+impl EventTablesMaintenanceService {
+    pub fn new(pool: PgPool, settings: EventTablesMaintenanceSettings) -> Self {
+        Self { pool, settings }
+    }
+
+    pub async fn maintain(self: Arc<Self>) {
+        let today = Utc::now().naive_utc().date();
+        for table in &self.settings.tables {
+            if let Err(err) = self.maintain_table(table, today).await {
+                log::error!(
+                    "Failed maintenance of event table {}: {err:?}",
+                    table.table_name
+                );
+            }
+        }
+    }
+
+    async fn maintain_table(
+        &self,
+        table: &EventTableRetentionSettings,
+        today: NaiveDate,
+    ) -> Result<()> {
+        let table_name = table.table_name.as_str();
+
+        // Rows are saved to the default partition if daily partition wasn't created in time
+        for day in load_default_partition_days(&self.pool, table_name).await? {
+            create_partition(&self.pool, table_name, day).await?;
+        }
+
+        let partitions_ahead_days = self
+            .settings
+            .partitions_ahead_days
+            .unwrap_or(DEFAULT_PARTITIONS_AHEAD_DAYS);
+        for days in 0..=partitions_ahead_days {
+            create_partition(&self.pool, table_name, today + Duration::days(days.into())).await?;
+        }
+
+        let partitions = load_partitions(&self.pool, table_name).await?;
+        for partition in expired_partitions(partitions, today, table.retention_days) {
+            if table.archive {
+                self.archive_partition(table_name, &partition).await?;
+            }
+
+            drop_partition(&self.pool, &partition.name).await?;
+            log::info!("Partition {} of event table was dropped", partition.name);
+        }
+
+        Ok(())
+    }
+
+    async fn archive_partition(&self, table_name: &str, partition: &Partition) -> Result<()> {
+        let archive_dir = self
+            .settings
+            .archive_dir
+            .as_ref()
+            .context("`archive_dir` should be set for archiving event tables")?
+            .join(table_name);
+        fs::create_dir_all(&archive_dir)
+            .with_context(|| format!("creating directory {}", archive_dir.display()))?;
+
+        let path = archive_dir.join(format!("{}.csv.gz", partition.name));
+        export_partition(&self.pool, &partition.name, &path).await?;
+        log::info!(
+            "Partition {} of event table was archived to {}",
+            partition.name,
+            path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Partitions with all rows older than `retention_days` before start of `today`
+fn expired_partitions(
+    partitions: Vec<Partition>,
+    today: NaiveDate,
+    retention_days: u32,
+) -> impl Iterator<Item = Partition> {
+    let first_retained_day = today - Duration::days(retention_days.into());
+    partitions
+        .into_iter()
+        .filter(move |x| x.day < first_retained_day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn expired_partitions_by_retention() {
+        let today = NaiveDate::from_ymd(2022, 10, 24);
+        let partitions = (18..=26)
+            .map(|day| {
+                let day = NaiveDate::from_ymd(2022, 10, day);
+                Partition {
+                    name: day.to_string(),
+                    day,
+                }
+            })
+            .collect_vec();
+
+        let expired = expired_partitions(partitions, today, 3)
+            .map(|x| x.name)
+            .collect_vec();
+
+        assert_eq!(expired, vec!["2022-10-18", "2022-10-19", "2022-10-20"]);
+    }
+}
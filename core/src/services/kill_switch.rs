@@ -0,0 +1,496 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use dashmap::DashMap;
+use futures::future::join_all;
+use mmb_domain::market::ExchangeAccountId;
+use mmb_domain::order::snapshot::Amount;
+use mmb_utils::cancellation_token::CancellationToken;
+use mmb_utils::infrastructure::SpawnFutureFlags;
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::exchanges::exchange_blocker::{BlockReason, BlockType, ExchangeBlocker};
+use crate::exchanges::general::engine_api::EngineApi;
+use crate::exchanges::general::exchange::Exchange;
+use crate::infrastructure::spawn_future;
+use crate::settings::{CoreSettings, KillSwitchSettings};
+
+pub static KILL_SWITCH_BLOCK_REASON: BlockReason = BlockReason::new("KillSwitch");
+
+const DEFAULT_TRIGGER_FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KillSwitchTrigger {
+    Rpc {
+        reason: String,
+    },
+    ProfitLoss {
+        market: String,
+        usd_change: Amount,
+        limit: Amount,
+    },
+    ExternalFile {
+        path: PathBuf,
+    },
+    Signal,
+    TooManyErrors {
+        errors_count: usize,
+        last_error: String,
+    },
+}
+
+impl Display for KillSwitchTrigger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KillSwitchTrigger::Rpc { reason } => write!(f, "RPC call: {reason}"),
+            KillSwitchTrigger::ProfitLoss {
+                market,
+                usd_change,
+                limit,
+            } => write!(
+                f,
+                "usd change {usd_change} for {market} exceeded limit {limit}"
+            ),
+            KillSwitchTrigger::ExternalFile { path } => {
+                write!(f, "trigger file {} exists", path.display())
+            }
+            KillSwitchTrigger::Signal => write!(f, "SIGUSR1 received"),
+            KillSwitchTrigger::TooManyErrors {
+                errors_count,
+                last_error,
+            } => write!(
+                f,
+                "{errors_count} errors happened, the last one: {last_error}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KillSwitchStatus {
+    pub trigger: KillSwitchTrigger,
+    pub triggered_at: DateTime,
+}
+
+/// Stops all trading at once: blocks every exchange account, cancels all opened orders and
+/// optionally closes derivative positions. Trading stays blocked until an operator
+/// acknowledges the trigger through `acknowledge` and stopping trading is finished
+pub struct KillSwitch {
+    settings: KillSwitchSettings,
+    exchange_account_ids: Vec<ExchangeAccountId>,
+    margin_exchange_account_ids: Vec<ExchangeAccountId>,
+    exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+    exchange_blocker: Arc<ExchangeBlocker>,
+    cancellation_token: CancellationToken,
+    status: Mutex<Option<KillSwitchStatus>>,
+    errors: Mutex<VecDeque<DateTime>>,
+    is_stopping_trading: AtomicBool,
+    trading_stopped: Notify,
+}
+
+impl KillSwitch {
+    pub fn new(
+        core_settings: &CoreSettings,
+        exchanges: DashMap<ExchangeAccountId, Arc<Exchange>>,
+        exchange_blocker: Arc<ExchangeBlocker>,
+        cancellation_token: CancellationToken,
+    ) -> Arc<Self> {
+        let exchange_account_ids = core_settings
+            .exchanges
+            .iter()
+            .map(|x| x.exchange_account_id)
+            .collect();
+        let margin_exchange_account_ids = core_settings
+            .exchanges
+            .iter()
+            .filter(|x| x.is_margin_trading)
+            .map(|x| x.exchange_account_id)
+            .collect();
+
+        Arc::new(KillSwitch {
+            settings: core_settings.kill_switch.clone().unwrap_or_default(),
+            exchange_account_ids,
+            margin_exchange_account_ids,
+            exchanges,
+            exchange_blocker,
+            cancellation_token,
+            status: Mutex::new(None),
+            errors: Default::default(),
+            is_stopping_trading: AtomicBool::new(false),
+            trading_stopped: Notify::new(),
+        })
+    }
+
+    pub fn status(&self) -> Option<KillSwitchStatus> {
+        self.status.lock().clone()
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.status.lock().is_some()
+    }
+
+    /// Returns `false` if kill switch has already been triggered
+    pub fn trigger(self: &Arc<Self>, trigger: KillSwitchTrigger) -> bool {
+        {
+            let mut status = self.status.lock();
+            if let Some(status) = &*status {
+                log::warn!(
+                    "Kill switch is already triggered by {}, new trigger {trigger} is ignored",
+                    status.trigger
+                );
+                return false;
+            }
+
+            log::error!("Kill switch is triggered by {trigger}");
+            *status = Some(KillSwitchStatus {
+                trigger,
+                triggered_at: Utc::now(),
+            });
+        }
+
+        for &exchange_account_id in &self.exchange_account_ids {
+            self.exchange_blocker.block(
+                exchange_account_id,
+                KILL_SWITCH_BLOCK_REASON,
+                BlockType::Manual,
+            );
+        }
+
+        self.is_stopping_trading.store(true, Ordering::SeqCst);
+        let this = self.clone();
+        let _ = spawn_future(
+            "Kill switch stop trading",
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            async move {
+                this.stop_trading().await;
+                this.is_stopping_trading.store(false, Ordering::SeqCst);
+                this.trading_stopped.notify_waiters();
+                Ok(())
+            },
+        );
+
+        true
+    }
+
+    async fn stop_trading(&self) {
+        join_all(self.exchanges.iter().map(|x| {
+            x.clone()
+                .cancel_opened_orders(self.cancellation_token.clone(), true)
+        }))
+        .await;
+
+        if !self.settings.close_positions {
+            return;
+        }
+
+        join_all(
+            self.margin_exchange_account_ids
+                .iter()
+                .filter_map(|x| self.exchanges.get(x).map(|x| x.clone()))
+                .map(|exchange| async move {
+                    let closed_positions = EngineApi::new(exchange.clone())
+                        .close_active_positions(self.cancellation_token.clone())
+                        .await;
+                    log::warn!(
+                        "Kill switch closed {} positions on {}",
+                        closed_positions.len(),
+                        exchange.exchange_account_id
+                    );
+                }),
+        )
+        .await;
+    }
+
+    /// Wait until orders cancelling and positions closing started by trigger are finished
+    async fn wait_trading_stopped(&self) -> Result<()> {
+        loop {
+            let trading_stopped = self.trading_stopped.notified();
+            if !self.is_stopping_trading.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            tokio::select! {
+                _ = trading_stopped => {},
+                _ = self.cancellation_token.when_cancelled() => {
+                    bail!("Stopping trading by kill switch was cancelled")
+                }
+            }
+        }
+    }
+
+    /// Unblock trading after the operator checked the reason of triggering.
+    /// Waits for stopping trading so unblocked exchanges don't race with orders cancellation
+    pub async fn acknowledge(&self, operator: &str) -> Result<KillSwitchStatus> {
+        if operator.trim().is_empty() {
+            bail!("Operator name should be specified to acknowledge kill switch");
+        }
+
+        if let Some(path) = &self.settings.trigger_file {
+            if path.exists() {
+                bail!(
+                    "Trigger file {} should be removed before acknowledging kill switch",
+                    path.display()
+                );
+            }
+        }
+
+        if !self.is_triggered() {
+            bail!("Kill switch isn't triggered");
+        }
+
+        self.wait_trading_stopped().await?;
+
+        let status = match self.status.lock().take() {
+            None => bail!("Kill switch isn't triggered"),
+            Some(status) => status,
+        };
+
+        log::warn!(
+            "Kill switch triggered by {} is acknowledged by {operator}",
+            status.trigger
+        );
+
+        self.errors.lock().clear();
+        for &exchange_account_id in &self.exchange_account_ids {
+            self.exchange_blocker
+                .unblock(exchange_account_id, KILL_SWITCH_BLOCK_REASON);
+        }
+
+        Ok(status)
+    }
+
+    /// Count error and trigger kill switch if there were too many errors during configured period
+    pub fn register_error(self: &Arc<Self>, error: &str) {
+        let limit = match &self.settings.errors_limit {
+            None => return,
+            Some(limit) => limit,
+        };
+
+        let errors_count = {
+            let now = Utc::now();
+            let period = chrono::Duration::milliseconds(limit.period_ms as i64);
+
+            let mut errors = self.errors.lock();
+            errors.push_back(now);
+            while matches!(errors.front(), Some(&time) if time + period < now) {
+                let _ = errors.pop_front();
+            }
+
+            errors.len()
+        };
+
+        if errors_count >= limit.max_errors_count && !self.is_triggered() {
+            let _ = self.trigger(KillSwitchTrigger::TooManyErrors {
+                errors_count,
+                last_error: error.to_owned(),
+            });
+        }
+    }
+
+    /// Poll the configured trigger file and trigger kill switch when it appears
+    pub async fn watch_trigger_file(self: Arc<Self>) -> Result<()> {
+        let path = match &self.settings.trigger_file {
+            None => return Ok(()),
+            Some(path) => path.clone(),
+        };
+
+        let interval = self
+            .settings
+            .trigger_file_check_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_TRIGGER_FILE_CHECK_INTERVAL);
+
+        loop {
+            if path.exists() && !self.is_triggered() {
+                let _ = self.trigger(KillSwitchTrigger::ExternalFile { path: path.clone() });
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {},
+                _ = self.cancellation_token.when_cancelled() => return Ok(()),
+            }
+        }
+    }
+
+    /// Trigger kill switch on SIGUSR1
+    #[cfg(unix)]
+    pub async fn watch_signal(self: Arc<Self>) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut user_signal = signal(SignalKind::user_defined1())?;
+        loop {
+            tokio::select! {
+                _ = user_signal.recv() => {
+                    let _ = self.trigger(KillSwitchTrigger::Signal);
+                }
+                _ = self.cancellation_token.when_cancelled() => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::init_lifetime_manager;
+    use crate::settings::{ErrorsLimitSettings, ExchangeSettings};
+
+    fn exchange_account_id() -> ExchangeAccountId {
+        ExchangeAccountId::new("exchange_test_id", 0)
+    }
+
+    fn kill_switch(settings: KillSwitchSettings) -> (Arc<KillSwitch>, Arc<ExchangeBlocker>) {
+        let _ = init_lifetime_manager();
+        let exchange_blocker = ExchangeBlocker::new(vec![exchange_account_id()]);
+        let core_settings = CoreSettings {
+            exchanges: vec![ExchangeSettings {
+                exchange_account_id: exchange_account_id(),
+                ..Default::default()
+            }],
+            kill_switch: Some(settings),
+            ..Default::default()
+        };
+
+        let kill_switch = KillSwitch::new(
+            &core_settings,
+            DashMap::new(),
+            exchange_blocker.clone(),
+            CancellationToken::new(),
+        );
+        (kill_switch, exchange_blocker)
+    }
+
+    fn rpc_trigger() -> KillSwitchTrigger {
+        KillSwitchTrigger::Rpc {
+            reason: "test".to_owned(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn trigger_blocks_all_accounts_until_acknowledge() {
+        let (kill_switch, exchange_blocker) = kill_switch(KillSwitchSettings::default());
+
+        assert!(kill_switch.trigger(rpc_trigger()));
+        assert!(
+            exchange_blocker.is_blocked_by_reason(exchange_account_id(), KILL_SWITCH_BLOCK_REASON)
+        );
+        assert_eq!(kill_switch.status().map(|x| x.trigger), Some(rpc_trigger()));
+
+        assert!(!kill_switch.trigger(KillSwitchTrigger::Signal));
+        assert_eq!(kill_switch.status().map(|x| x.trigger), Some(rpc_trigger()));
+
+        assert!(kill_switch.acknowledge("").await.is_err());
+        assert!(kill_switch.is_triggered());
+
+        let status = kill_switch.acknowledge("operator").await.expect("in test");
+        assert_eq!(status.trigger, rpc_trigger());
+        assert!(!kill_switch.is_triggered());
+        exchange_blocker
+            .wait_unblock(exchange_account_id(), CancellationToken::new())
+            .await;
+        assert!(!exchange_blocker.is_blocked(exchange_account_id()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn acknowledge_without_trigger_is_error() {
+        let (kill_switch, _exchange_blocker) = kill_switch(KillSwitchSettings::default());
+
+        assert!(kill_switch.acknowledge("operator").await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn acknowledge_waits_trading_stopped() {
+        let (kill_switch, exchange_blocker) = kill_switch(KillSwitchSettings::default());
+
+        assert!(kill_switch.trigger(rpc_trigger()));
+        kill_switch.wait_trading_stopped().await.expect("in test");
+        // emulate long orders cancellation
+        kill_switch
+            .is_stopping_trading
+            .store(true, Ordering::SeqCst);
+
+        let acknowledge = tokio::spawn({
+            let kill_switch = kill_switch.clone();
+            async move { kill_switch.acknowledge("operator").await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!acknowledge.is_finished());
+        assert!(kill_switch.is_triggered());
+        assert!(
+            exchange_blocker.is_blocked_by_reason(exchange_account_id(), KILL_SWITCH_BLOCK_REASON)
+        );
+
+        kill_switch
+            .is_stopping_trading
+            .store(false, Ordering::SeqCst);
+        kill_switch.trading_stopped.notify_waiters();
+
+        let status = acknowledge.await.expect("in test").expect("in test");
+        assert_eq!(status.trigger, rpc_trigger());
+        assert!(!kill_switch.is_triggered());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn too_many_errors() {
+        let (kill_switch, _exchange_blocker) = kill_switch(KillSwitchSettings {
+            errors_limit: Some(ErrorsLimitSettings {
+                max_errors_count: 3,
+                period_ms: 60_000,
+            }),
+            ..Default::default()
+        });
+
+        kill_switch.register_error("first");
+        kill_switch.register_error("second");
+        assert!(!kill_switch.is_triggered());
+
+        kill_switch.register_error("third");
+        assert_eq!(
+            kill_switch.status().map(|x| x.trigger),
+            Some(KillSwitchTrigger::TooManyErrors {
+                errors_count: 3,
+                last_error: "third".to_owned(),
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn trigger_file() {
+        let path = std::env::temp_dir().join(format!("kill_switch_{}", std::process::id()));
+        let (kill_switch, _exchange_blocker) = kill_switch(KillSwitchSettings {
+            trigger_file: Some(path.clone()),
+            trigger_file_check_interval_ms: Some(10),
+            ..Default::default()
+        });
+
+        let _ = spawn_future(
+            "watch trigger file in test",
+            SpawnFutureFlags::STOP_BY_TOKEN,
+            kill_switch.clone().watch_trigger_file(),
+        );
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!kill_switch.is_triggered());
+
+        std::fs::write(&path, "").expect("in test");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(kill_switch.is_triggered());
+        assert!(kill_switch.acknowledge("operator").await.is_err());
+
+        std::fs::remove_file(&path).expect("in test");
+        assert!(kill_switch.acknowledge("operator").await.is_ok());
+
+        kill_switch.cancellation_token.cancel();
+    }
+}
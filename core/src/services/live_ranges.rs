@@ -1,12 +1,11 @@
 use crate::lifecycle::trading_engine::Service;
-use mmb_database::postgres_db::live_ranges::save_live_range_to_db;
-use mmb_database::postgres_db::PgPool;
+use mmb_database::storage::Storage;
 use mmb_utils::infrastructure::WithExpect;
 use std::sync::Arc;
 use tokio::sync::oneshot::Receiver;
 
 pub struct LiveRangesService {
-    pool: PgPool,
+    storage: Arc<dyn Storage>,
     session_id: String,
 }
 
@@ -21,12 +20,16 @@ impl Service for LiveRangesService {
 }
 
 impl LiveRangesService {
-    pub fn new(session_id: String, pool: PgPool) -> Self {
-        Self { pool, session_id }
+    pub fn new(session_id: String, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            session_id,
+        }
     }
 
     pub async fn push(self: Arc<Self>) {
-        let _ = save_live_range_to_db(&self.pool, &self.session_id)
+        self.storage
+            .save_live_range(&self.session_id)
             .await
             .with_expect(|| format!("Failed push live range. Session ID: {}", &self.session_id));
     }
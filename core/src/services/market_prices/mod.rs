@@ -1 +1,2 @@
 pub mod market_currency_code_price;
+pub mod usd_price_providers;
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use futures::future::join_all;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use mmb_domain::market::CurrencyCode;
+use mmb_domain::order::snapshot::Price;
+use mmb_utils::DateTime;
+use mockall_double::double;
+use parking_lot::Mutex;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+
+#[double]
+use crate::misc::time::time_manager;
+
+use crate::misc::traits::market_service::GetMarketCurrencyCodePrice;
+use crate::services::market_prices::market_currency_code_price::MarketCurrencyCodePrice;
+use crate::settings::{UsdPriceProviderSettings, UsdPriceProvidersMode, UsdPriceProvidersSettings};
+
+/// Source of currencies prices in USD
+#[async_trait]
+pub trait UsdPriceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn get_prices(&self) -> Result<HashMap<CurrencyCode, Price>>;
+}
+
+type HttpClient = Client<HttpsConnector<HttpConnector>>;
+
+fn create_http_client() -> HttpClient {
+    let connector = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    Client::builder().build::<_, Body>(connector)
+}
+
+async fn http_get(client: &HttpClient, uri: &Uri) -> Result<bytes::Bytes> {
+    let response = client
+        .get(uri.clone())
+        .await
+        .with_context(|| format!("Failed to send request to {uri}"))?;
+
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .with_context(|| format!("Failed to read response from {uri}"))?;
+
+    if !status.is_success() {
+        bail!(
+            "Request to {uri} failed with status {status}: {}",
+            String::from_utf8_lossy(&body)
+        );
+    }
+
+    Ok(body)
+}
+
+#[derive(Deserialize)]
+struct TickerPrice {
+    symbol: String,
+    price: Price,
+}
+
+/// Prices from exchange ticker endpoint with list of prices for all symbols.
+/// Only symbols quoted in USD-like currency with `quote_suffix` are used
+pub struct ExchangeTickerPriceProvider {
+    name: String,
+    uri: Uri,
+    quote_suffix: String,
+    client: HttpClient,
+}
+
+impl ExchangeTickerPriceProvider {
+    pub fn new(url: &str, quote_suffix: String) -> Result<Self> {
+        Ok(Self {
+            name: format!("ExchangeTicker({url})"),
+            uri: url.parse().with_context(|| format!("Invalid url {url}"))?,
+            quote_suffix,
+            client: create_http_client(),
+        })
+    }
+
+    fn parse_prices(&self, body: &[u8]) -> Result<HashMap<CurrencyCode, Price>> {
+        let tickers: Vec<TickerPrice> =
+            serde_json::from_slice(body).context("Failed to parse ticker prices")?;
+
+        let mut prices: HashMap<_, _> = tickers
+            .into_iter()
+            .filter_map(|ticker| {
+                let base = ticker.symbol.strip_suffix(&self.quote_suffix)?;
+                if base.is_empty() {
+                    return None;
+                }
+                Some((base.to_lowercase().as_str().into(), ticker.price))
+            })
+            .collect();
+
+        let quote = self.quote_suffix.to_lowercase();
+        prices.entry(quote.as_str().into()).or_insert(dec!(1));
+
+        Ok(prices)
+    }
+}
+
+#[async_trait]
+impl UsdPriceProvider for ExchangeTickerPriceProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_prices(&self) -> Result<HashMap<CurrencyCode, Price>> {
+        let body = http_get(&self.client, &self.uri).await?;
+        self.parse_prices(&body)
+    }
+}
+
+/// Prices from JSON file with object like `{ "btc": "20000.5" }`.
+/// File is read on each refreshing, so it can be updated without restart
+pub struct StaticFilePriceProvider {
+    name: String,
+    path: PathBuf,
+}
+
+impl StaticFilePriceProvider {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            name: format!("StaticFile({})", path.display()),
+            path,
+        }
+    }
+}
+
+#[async_trait]
+impl UsdPriceProvider for StaticFilePriceProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_prices(&self) -> Result<HashMap<CurrencyCode, Price>> {
+        let content = tokio::fs::read(&self.path)
+            .await
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse prices from {}", self.path.display()))
+    }
+}
+
+/// Prices from HTTP endpoint with object like `{ "btc": "20000.5" }`
+pub struct HttpOraclePriceProvider {
+    name: String,
+    uri: Uri,
+    client: HttpClient,
+}
+
+impl HttpOraclePriceProvider {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            name: format!("HttpOracle({url})"),
+            uri: url.parse().with_context(|| format!("Invalid url {url}"))?,
+            client: create_http_client(),
+        })
+    }
+}
+
+#[async_trait]
+impl UsdPriceProvider for HttpOraclePriceProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_prices(&self) -> Result<HashMap<CurrencyCode, Price>> {
+        let body = http_get(&self.client, &self.uri).await?;
+        serde_json::from_slice(&body)
+            .with_context(|| format!("Failed to parse prices from {}", self.uri))
+    }
+}
+
+pub fn create_usd_price_provider(
+    settings: &UsdPriceProviderSettings,
+) -> Result<Box<dyn UsdPriceProvider>> {
+    Ok(match settings {
+        UsdPriceProviderSettings::ExchangeTicker { url, quote_suffix } => {
+            Box::new(ExchangeTickerPriceProvider::new(url, quote_suffix.clone())?)
+        }
+        UsdPriceProviderSettings::StaticFile { path } => {
+            Box::new(StaticFilePriceProvider::new(path.clone()))
+        }
+        UsdPriceProviderSettings::HttpOracle { url } => {
+            Box::new(HttpOraclePriceProvider::new(url)?)
+        }
+    })
+}
+
+struct ProviderPrices {
+    prices: HashMap<CurrencyCode, Price>,
+    updated_at: DateTime,
+}
+
+/// Combines prices of several providers. Prices of provider which wasn't successfully refreshed
+/// during `max_price_age` are treated as stale and aren't used
+pub struct UsdPriceProviders {
+    providers: Vec<Box<dyn UsdPriceProvider>>,
+    mode: UsdPriceProvidersMode,
+    max_price_age: Duration,
+    /// Last successfully received prices by index of provider
+    last_prices: Mutex<Vec<Option<ProviderPrices>>>,
+}
+
+impl UsdPriceProviders {
+    pub fn new(
+        providers: Vec<Box<dyn UsdPriceProvider>>,
+        mode: UsdPriceProvidersMode,
+        max_price_age: Duration,
+    ) -> Self {
+        let last_prices = providers.iter().map(|_| None).collect();
+        Self {
+            providers,
+            mode,
+            max_price_age,
+            last_prices: Mutex::new(last_prices),
+        }
+    }
+
+    pub fn from_settings(
+        settings: &UsdPriceProvidersSettings,
+        max_price_age: Duration,
+    ) -> Result<Self> {
+        if settings.providers.is_empty() {
+            bail!("At least one USD price provider should be configured");
+        }
+
+        let providers = settings
+            .providers
+            .iter()
+            .map(create_usd_price_provider)
+            .collect::<Result<_>>()?;
+
+        Ok(Self::new(providers, settings.mode, max_price_age))
+    }
+
+    async fn refresh(&self) {
+        let results = join_all(self.providers.iter().map(|x| x.get_prices())).await;
+
+        let now = time_manager::now();
+        let mut last_prices = self.last_prices.lock();
+        for ((provider, result), last) in self
+            .providers
+            .iter()
+            .zip(results)
+            .zip(last_prices.iter_mut())
+        {
+            match result {
+                Ok(prices) => {
+                    *last = Some(ProviderPrices {
+                        prices,
+                        updated_at: now,
+                    })
+                }
+                Err(err) => {
+                    log::warn!("Failed to get USD prices from {}: {err:?}", provider.name())
+                }
+            }
+        }
+    }
+
+    fn actual_prices(&self, now: DateTime) -> HashMap<CurrencyCode, Price> {
+        let last_prices = self.last_prices.lock();
+        let max_price_age = chrono::Duration::from_std(self.max_price_age)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+
+        let fresh_prices = self
+            .providers
+            .iter()
+            .zip(last_prices.iter())
+            .filter_map(|(provider, last)| match last {
+                Some(last) if now - last.updated_at <= max_price_age => Some(&last.prices),
+                Some(last) => {
+                    log::warn!(
+                        "USD prices from {} are stale: last update at {}",
+                        provider.name(),
+                        last.updated_at
+                    );
+                    None
+                }
+                None => None,
+            })
+            .collect::<Vec<_>>();
+
+        match self.mode {
+            UsdPriceProvidersMode::Fallback => {
+                let mut result = HashMap::new();
+                // providers are in priority order, so don't override prices of previous providers
+                for prices in fresh_prices {
+                    for (currency_code, price) in prices {
+                        result.entry(*currency_code).or_insert(*price);
+                    }
+                }
+                result
+            }
+            UsdPriceProvidersMode::Median => {
+                let mut all_prices: HashMap<CurrencyCode, Vec<Price>> = HashMap::new();
+                for prices in fresh_prices {
+                    for (currency_code, price) in prices {
+                        all_prices.entry(*currency_code).or_default().push(*price);
+                    }
+                }
+
+                all_prices
+                    .into_iter()
+                    .map(|(currency_code, prices)| (currency_code, median(prices)))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn median(mut prices: Vec<Price>) -> Price {
+    prices.sort();
+    let middle = prices.len() / 2;
+    match prices.len() % 2 {
+        0 => (prices[middle - 1] + prices[middle]) / dec!(2),
+        _ => prices[middle],
+    }
+}
+
+#[async_trait]
+impl GetMarketCurrencyCodePrice for UsdPriceProviders {
+    async fn get_market_currency_code_price(&self) -> Vec<MarketCurrencyCodePrice> {
+        self.refresh().await;
+
+        self.actual_prices(time_manager::now())
+            .into_iter()
+            .map(|(currency_code, price)| MarketCurrencyCodePrice::new(currency_code, Some(price)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use mmb_utils::hashmap;
+
+    struct TestProvider {
+        prices: Result<HashMap<CurrencyCode, Price>, String>,
+    }
+
+    #[async_trait]
+    impl UsdPriceProvider for TestProvider {
+        fn name(&self) -> &str {
+            "TestProvider"
+        }
+
+        async fn get_prices(&self) -> Result<HashMap<CurrencyCode, Price>> {
+            self.prices.clone().map_err(anyhow::Error::msg)
+        }
+    }
+
+    fn btc() -> CurrencyCode {
+        "btc".into()
+    }
+
+    fn eth() -> CurrencyCode {
+        "eth".into()
+    }
+
+    fn providers(
+        mode: UsdPriceProvidersMode,
+        prices: Vec<(HashMap<CurrencyCode, Price>, chrono::Duration)>,
+    ) -> (UsdPriceProviders, DateTime) {
+        let now = Utc::now();
+        let providers = UsdPriceProviders::new(
+            prices
+                .iter()
+                .map(|_| {
+                    Box::new(TestProvider {
+                        prices: Err("unused".to_owned()),
+                    }) as Box<dyn UsdPriceProvider>
+                })
+                .collect(),
+            mode,
+            Duration::from_secs(60),
+        );
+
+        *providers.last_prices.lock() = prices
+            .into_iter()
+            .map(|(prices, age)| {
+                Some(ProviderPrices {
+                    prices,
+                    updated_at: now - age,
+                })
+            })
+            .collect();
+
+        (providers, now)
+    }
+
+    #[test]
+    fn fallback_uses_first_provider_with_price() {
+        let (providers, now) = providers(
+            UsdPriceProvidersMode::Fallback,
+            vec![
+                (hashmap![btc() => dec!(20000)], chrono::Duration::zero()),
+                (
+                    hashmap![btc() => dec!(21000), eth() => dec!(1500)],
+                    chrono::Duration::zero(),
+                ),
+            ],
+        );
+
+        let prices = providers.actual_prices(now);
+
+        assert_eq!(prices, hashmap![btc() => dec!(20000), eth() => dec!(1500)]);
+    }
+
+    #[test]
+    fn stale_provider_is_skipped() {
+        let (providers, now) = providers(
+            UsdPriceProvidersMode::Fallback,
+            vec![
+                (hashmap![btc() => dec!(20000)], chrono::Duration::minutes(5)),
+                (hashmap![btc() => dec!(21000)], chrono::Duration::seconds(5)),
+            ],
+        );
+
+        let prices = providers.actual_prices(now);
+
+        assert_eq!(prices, hashmap![btc() => dec!(21000)]);
+    }
+
+    #[test]
+    fn no_prices_if_all_providers_are_stale() {
+        let (providers, now) = providers(
+            UsdPriceProvidersMode::Median,
+            vec![(hashmap![btc() => dec!(20000)], chrono::Duration::minutes(5))],
+        );
+
+        assert!(providers.actual_prices(now).is_empty());
+    }
+
+    #[test]
+    fn median_of_sources() {
+        let (providers, now) = providers(
+            UsdPriceProvidersMode::Median,
+            vec![
+                (
+                    hashmap![btc() => dec!(20000), eth() => dec!(1500)],
+                    chrono::Duration::zero(),
+                ),
+                (
+                    hashmap![btc() => dec!(30000), eth() => dec!(1600)],
+                    chrono::Duration::zero(),
+                ),
+                (hashmap![btc() => dec!(21000)], chrono::Duration::zero()),
+            ],
+        );
+
+        let prices = providers.actual_prices(now);
+
+        assert_eq!(prices, hashmap![btc() => dec!(21000), eth() => dec!(1550)]);
+    }
+
+    #[test]
+    fn parse_exchange_ticker() {
+        let provider = ExchangeTickerPriceProvider::new(
+            "https://api.binance.com/api/v3/ticker/price",
+            "USDT".to_owned(),
+        )
+        .expect("in test");
+        let body = br#"[
+            {"symbol": "BTCUSDT", "price": "20000.50"},
+            {"symbol": "ETHBTC", "price": "0.07"},
+            {"symbol": "ETHUSDT", "price": "1500.1"}
+        ]"#;
+
+        let prices = provider.parse_prices(body).expect("in test");
+
+        assert_eq!(
+            prices,
+            hashmap![btc() => dec!(20000.50), eth() => dec!(1500.1), "usdt".into() => dec!(1)]
+        );
+    }
+}
@@ -1,4 +1,11 @@
+pub mod candles;
 pub mod cleanup_orders;
+pub mod clock_sync;
+pub mod dead_man_switch;
+pub mod event_tables_maintenance;
+pub mod kill_switch;
 pub mod live_ranges;
 pub(crate) mod market_prices;
+pub mod order_recovery;
+pub mod stale_feed;
 pub mod usd_convertion;
@@ -0,0 +1,139 @@
+use crate::balance::manager::balance_manager::BalanceManager;
+use crate::balance::manager::balances::Balances;
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::general::order::recover::RecoveredOrder;
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use itertools::Itertools;
+use mmb_database::postgres_db::events::Event;
+use mmb_database::storage::Storage;
+use mmb_domain::market::ExchangeAccountId;
+use mmb_domain::order::pool::OrderRef;
+use mmb_domain::order::snapshot::OrderSnapshot;
+use mmb_utils::cancellation_token::CancellationToken;
+use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Restores orders which weren't finished before engine restart and balance reservations for them.
+/// Reservations which aren't used by recovered open orders are released, because nobody
+/// in a new engine run owns them
+pub async fn recover_orders_state(
+    storage: &dyn Storage,
+    exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>,
+    balance_manager: &Arc<Mutex<BalanceManager>>,
+    cancellation_token: CancellationToken,
+) -> Result<()> {
+    restore_balances(storage, balance_manager).await?;
+
+    let exchange_account_ids = exchanges.iter().map(|x| x.key().to_string()).collect_vec();
+    let mut snapshots_by_exchange_account_id = storage
+        .load_unfinished_orders(OrderRef::TABLE_NAME, &exchange_account_ids)
+        .await
+        .context("loading unfinished orders")?
+        .into_iter()
+        .filter_map(parse_order_snapshot)
+        .into_group_map_by(|x| x.header.exchange_account_id);
+
+    let mut recovered_orders = Vec::new();
+    for exchange in exchanges.iter() {
+        let exchange_snapshots = snapshots_by_exchange_account_id
+            .remove(&exchange.exchange_account_id)
+            .unwrap_or_default();
+
+        match exchange
+            .recover_orders(exchange_snapshots, cancellation_token.clone())
+            .await
+        {
+            Ok(orders) => recovered_orders.extend(orders),
+            Err(err) => log::error!(
+                "Failed to recover orders on {}: {err:?}",
+                exchange.exchange_account_id
+            ),
+        }
+    }
+
+    apply_recovered_orders(&mut balance_manager.lock(), &recovered_orders);
+
+    Ok(())
+}
+
+async fn restore_balances(
+    storage: &dyn Storage,
+    balance_manager: &Arc<Mutex<BalanceManager>>,
+) -> Result<()> {
+    let event = match storage
+        .load_last_event(Balances::TABLE_NAME)
+        .await
+        .context("loading balances")?
+    {
+        None => return Ok(()),
+        Some(event) => event,
+    };
+
+    let mut balances: Balances =
+        serde_json::from_value(event.json).context("parsing saved balances")?;
+
+    // Exchange balances are requested after start, so saved virtual diffs are outdated
+    balances.virtual_diff_balances = None;
+
+    balance_manager
+        .lock()
+        .restore_balance_state(&balances, false);
+
+    Ok(())
+}
+
+fn parse_order_snapshot(json: JsonValue) -> Option<OrderSnapshot> {
+    serde_json::from_value(json)
+        .map_err(|err| log::error!("Failed to parse saved order: {err:?}"))
+        .ok()
+}
+
+fn apply_recovered_orders(balance_manager: &mut BalanceManager, orders: &[RecoveredOrder]) {
+    let mut live_reservation_ids = HashSet::new();
+
+    for RecoveredOrder {
+        order,
+        missed_fills,
+    } in orders
+    {
+        let snapshot = order.deep_clone();
+        let reservation = snapshot
+            .header
+            .reservation_id
+            .and_then(|reservation_id| balance_manager.get_reservation(reservation_id));
+
+        let configuration_descriptor = match reservation {
+            Some(reservation) => reservation.configuration_descriptor,
+            None => {
+                if !missed_fills.is_empty() {
+                    log::warn!(
+                        "Missed fills of order {} aren't applied to balances because there is no reservation for it",
+                        snapshot.header.client_order_id
+                    );
+                }
+                continue;
+            }
+        };
+
+        for fill in missed_fills {
+            balance_manager.order_was_filled_with_fill(configuration_descriptor, &snapshot, fill);
+        }
+
+        if !snapshot.is_finished() {
+            live_reservation_ids.extend(snapshot.header.reservation_id);
+        }
+    }
+
+    for reservation_id in balance_manager.get_reservation_ids() {
+        if live_reservation_ids.contains(&reservation_id) {
+            continue;
+        }
+
+        if let Err(err) = balance_manager.unreserve_rest(reservation_id) {
+            log::error!("Failed to release restored reservation {reservation_id}: {err:?}");
+        }
+    }
+}
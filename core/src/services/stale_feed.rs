@@ -0,0 +1,330 @@
+use crate::exchanges::block_reasons::STALE_MARKET_DATA;
+use crate::exchanges::exchange_blocker::{BlockType, ExchangeBlocker};
+use crate::exchanges::general::exchange::Exchange;
+use crate::exchanges::general::feed_liveness::{Feed, FeedKind};
+use crate::exchanges::timeouts::timeout_manager;
+use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
+use crate::lifecycle::trading_engine::Service;
+use crate::settings::StaleFeedSettings;
+use dashmap::DashMap;
+use itertools::Itertools;
+use mmb_domain::market::ExchangeAccountId;
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::Receiver;
+
+const DEFAULT_WEBSOCKET_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Result of feeds checking
+#[derive(Debug, Default, PartialEq, Eq)]
+struct StaleFeedsCheck {
+    newly_stale: Vec<Feed>,
+    recovered: Vec<Feed>,
+    has_stale_feeds: bool,
+    need_reconnect: bool,
+}
+
+/// Keeps set of stale feeds between checks and decides when websocket should be reconnected
+struct StaleFeedTracker {
+    websocket_timeout: Duration,
+    order_book_timeout: Option<Duration>,
+    trades_timeout: Option<Duration>,
+    reconnect_interval: Duration,
+    stale_feeds: HashSet<Feed>,
+    last_reconnect_time: Option<DateTime>,
+}
+
+impl StaleFeedTracker {
+    fn new(settings: &StaleFeedSettings) -> Self {
+        Self {
+            websocket_timeout: settings
+                .websocket_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_WEBSOCKET_TIMEOUT),
+            order_book_timeout: settings.order_book_timeout_ms.map(Duration::from_millis),
+            trades_timeout: settings.trades_timeout_ms.map(Duration::from_millis),
+            reconnect_interval: settings
+                .reconnect_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_RECONNECT_INTERVAL),
+            stale_feeds: HashSet::new(),
+            last_reconnect_time: None,
+        }
+    }
+
+    fn timeout(&self, kind: FeedKind) -> Option<Duration> {
+        match kind {
+            FeedKind::Websocket => Some(self.websocket_timeout),
+            FeedKind::OrderBook => self.order_book_timeout,
+            FeedKind::Trades => self.trades_timeout,
+        }
+    }
+
+    fn check(&mut self, last_message_times: &[(Feed, DateTime)], now: DateTime) -> StaleFeedsCheck {
+        let stale_feeds: HashSet<Feed> = last_message_times
+            .iter()
+            .filter(|(feed, last_message_time)| match self.timeout(feed.kind) {
+                Some(timeout) => (now - *last_message_time)
+                    .to_std()
+                    .map_or(false, |elapsed| elapsed > timeout),
+                None => false,
+            })
+            .map(|(feed, _)| *feed)
+            .collect();
+
+        let newly_stale = stale_feeds
+            .difference(&self.stale_feeds)
+            .copied()
+            .collect_vec();
+        let recovered = self
+            .stale_feeds
+            .difference(&stale_feeds)
+            .copied()
+            .collect_vec();
+        self.stale_feeds = stale_feeds;
+
+        let has_stale_feeds = !self.stale_feeds.is_empty();
+        let need_reconnect = has_stale_feeds
+            && self
+                .last_reconnect_time
+                .map_or(true, |last_reconnect_time| {
+                    (now - last_reconnect_time)
+                        .to_std()
+                        .map_or(false, |elapsed| elapsed >= self.reconnect_interval)
+                });
+
+        if need_reconnect {
+            self.last_reconnect_time = Some(now);
+        } else if !has_stale_feeds {
+            self.last_reconnect_time = None;
+        }
+
+        StaleFeedsCheck {
+            newly_stale,
+            recovered,
+            has_stale_feeds,
+            need_reconnect,
+        }
+    }
+}
+
+/// Watches time of last messages of websocket and market data feeds of exchange. When some feed
+/// stops receiving messages, exchange account is blocked, orders of affected market are cancelled
+/// and websocket is reconnected. Account is unblocked after all feeds receive messages again
+pub struct StaleFeedService {
+    exchange: Arc<Exchange>,
+    exchange_blocker: Arc<ExchangeBlocker>,
+    lifetime_manager: Arc<AppLifetimeManager>,
+    check_interval: Duration,
+    tracker: Mutex<StaleFeedTracker>,
+}
+
+impl Service for StaleFeedService {
+    fn name(&self) -> &str {
+        "StaleFeedService"
+    }
+
+    fn graceful_shutdown(self: Arc<Self>) -> Option<Receiver<anyhow::Result<()>>> {
+        None
+    }
+}
+
+impl StaleFeedService {
+    pub fn new(
+        exchange: Arc<Exchange>,
+        settings: &StaleFeedSettings,
+        exchange_blocker: Arc<ExchangeBlocker>,
+        lifetime_manager: Arc<AppLifetimeManager>,
+    ) -> Self {
+        Self {
+            exchange,
+            exchange_blocker,
+            lifetime_manager,
+            check_interval: settings
+                .check_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_CHECK_INTERVAL),
+            tracker: Mutex::new(StaleFeedTracker::new(settings)),
+        }
+    }
+
+    /// Creates services for exchanges with configured stale feed detection
+    pub fn create_for_exchanges(
+        exchanges: &DashMap<ExchangeAccountId, Arc<Exchange>>,
+        exchange_blocker: Arc<ExchangeBlocker>,
+        lifetime_manager: Arc<AppLifetimeManager>,
+    ) -> Vec<Arc<Self>> {
+        exchanges
+            .iter()
+            .filter_map(|exchange| {
+                let settings = exchange.exchange_client.get_settings();
+                let stale_feed = settings.stale_feed.as_ref()?;
+
+                Some(Arc::new(Self::new(
+                    exchange.clone(),
+                    stale_feed,
+                    exchange_blocker.clone(),
+                    lifetime_manager.clone(),
+                )))
+            })
+            .collect()
+    }
+
+    pub fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange.exchange_account_id
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        self.check_interval
+    }
+
+    pub async fn check(self: Arc<Self>) {
+        let exchange_account_id = self.exchange.exchange_account_id;
+
+        let check = self.tracker.lock().check(
+            &self.exchange.feed_liveness.last_message_times(),
+            timeout_manager::now(),
+        );
+
+        for feed in &check.recovered {
+            log::info!("Feed {feed} of {exchange_account_id} receives messages again");
+        }
+
+        if !check.has_stale_feeds {
+            if self
+                .exchange_blocker
+                .is_blocked_by_reason(exchange_account_id, STALE_MARKET_DATA)
+            {
+                self.exchange_blocker
+                    .unblock(exchange_account_id, STALE_MARKET_DATA);
+            }
+            return;
+        }
+
+        if !check.newly_stale.is_empty() {
+            log::warn!(
+                "Feeds of {exchange_account_id} are stale: {}",
+                check.newly_stale.iter().join(", ")
+            );
+
+            if !self
+                .exchange_blocker
+                .is_blocked_by_reason(exchange_account_id, STALE_MARKET_DATA)
+            {
+                self.exchange_blocker.block(
+                    exchange_account_id,
+                    STALE_MARKET_DATA,
+                    BlockType::Manual,
+                );
+            }
+
+            self.cancel_orders_of_stale_markets(&check.newly_stale)
+                .await;
+        }
+
+        if check.need_reconnect {
+            log::warn!("Reconnecting websocket of {exchange_account_id} because of stale feeds");
+            if let Err(err) = self.exchange.reconnect_ws().await {
+                log::error!("Failed to reconnect websocket of {exchange_account_id}: {err:?}");
+            }
+        }
+    }
+
+    async fn cancel_orders_of_stale_markets(&self, stale_feeds: &[Feed]) {
+        // Stale websocket affects all markets of exchange
+        let currency_pairs = match stale_feeds.iter().any(|x| x.currency_pair.is_none()) {
+            true => vec![None],
+            false => stale_feeds
+                .iter()
+                .map(|x| x.currency_pair)
+                .unique()
+                .collect(),
+        };
+
+        for currency_pair in currency_pairs {
+            self.exchange
+                .clone()
+                .cancel_opened_orders_by_currency_pair(
+                    currency_pair,
+                    self.lifetime_manager.stop_token(),
+                )
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use mmb_domain::market::CurrencyPair;
+
+    fn time(millis: i64) -> DateTime {
+        Utc.timestamp_millis(millis)
+    }
+
+    fn tracker() -> StaleFeedTracker {
+        StaleFeedTracker::new(&StaleFeedSettings {
+            websocket_timeout_ms: Some(5_000),
+            order_book_timeout_ms: Some(2_000),
+            trades_timeout_ms: None,
+            check_interval_ms: None,
+            reconnect_interval_ms: Some(10_000),
+        })
+    }
+
+    #[test]
+    fn feeds_without_timeout_are_not_checked() {
+        let currency_pair = CurrencyPair::from_codes("eth".into(), "btc".into());
+        let feeds = [
+            (Feed::websocket(), time(0)),
+            (Feed::market(FeedKind::Trades, currency_pair), time(0)),
+        ];
+
+        let check = tracker().check(&feeds, time(3_000));
+
+        assert_eq!(check, StaleFeedsCheck::default());
+    }
+
+    #[test]
+    fn stale_feed_reconnects_by_interval_and_recovers() {
+        let currency_pair = CurrencyPair::from_codes("eth".into(), "btc".into());
+        let order_book = Feed::market(FeedKind::OrderBook, currency_pair);
+        let mut tracker = tracker();
+
+        let check = tracker.check(&[(order_book, time(0))], time(3_000));
+        assert_eq!(
+            check,
+            StaleFeedsCheck {
+                newly_stale: vec![order_book],
+                recovered: vec![],
+                has_stale_feeds: true,
+                need_reconnect: true,
+            }
+        );
+
+        let check = tracker.check(&[(order_book, time(0))], time(8_000));
+        assert_eq!(check.newly_stale, vec![]);
+        assert!(check.has_stale_feeds);
+        assert!(!check.need_reconnect);
+
+        let check = tracker.check(&[(order_book, time(0))], time(13_000));
+        assert!(check.need_reconnect);
+
+        let check = tracker.check(&[(order_book, time(13_500))], time(14_000));
+        assert_eq!(
+            check,
+            StaleFeedsCheck {
+                newly_stale: vec![],
+                recovered: vec![order_book],
+                has_stale_feeds: false,
+                need_reconnect: false,
+            }
+        );
+    }
+}
@@ -85,15 +85,24 @@ impl PriceSourceEventLoop {
                     convert_amount.task_finished_sender.send_expected(result);
                 },
                 core_event_res = self.rx_core.recv() => {
-                    let event = core_event_res.context("Error during receiving event on rx_core")?;
+                    let event = match core_event_res {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped_count)) => {
+                            // Prices are updated by the next order book events
+                            log::warn!("PriceSourceService lagged behind events channel by {skipped_count} events");
+                            continue;
+                        }
+                        Err(err) => return Err(err).context("Error during receiving event on rx_core"),
+                    };
                     match event {
                         ExchangeEvent::OrderBookEvent(order_book_event) => {
                             let market_id = MarketId::new(
                                 order_book_event.exchange_account_id.exchange_id,
                                 order_book_event.currency_pair,
                             );
-                            if self.all_market_ids.contains(&market_id) {
-                                let _ = self.local_snapshot_service.update(&order_book_event);
+                            if self.all_market_ids.contains(&market_id)
+                                && self.local_snapshot_service.update(&order_book_event).is_some()
+                            {
                                 self.update_cache_and_save(market_id);
                             }
                         },
@@ -369,16 +378,19 @@ impl PriceSourceService {
         time_in_past: DateTime,
         cancellation_token: CancellationToken,
     ) -> Option<Amount> {
-        let price_sources = self
+        let price_sources = match self
             .price_sources_loader
             .load(time_in_past, cancellation_token.clone())
             .await
-            .with_expect(|| {
-                format!(
-                    "Failed to get price_sources for {} from database",
-                    time_in_past
-                )
-            });
+        {
+            Ok(price_sources) => price_sources,
+            Err(err) => {
+                log::error!(
+                    "Failed to get price_sources for {time_in_past} from database: {err:?}"
+                );
+                return None;
+            }
+        };
 
         let convert_currency_direction = ConvertCurrencyDirection::new(from, to);
 
@@ -1,46 +1,95 @@
+use anyhow::{bail, Context, Result};
+use mmb_database::postgres_db::events::Event;
+use mmb_database::postgres_db::price_sources::load_latest_price_sources;
+use mmb_database::storage::Storage;
 use mmb_domain::market::MarketId;
+use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use mmb_utils::{cancellation_token::CancellationToken, DateTime};
 
 use mmb_domain::order::snapshot::PriceByOrderSide;
 
+use crate::misc::price_source_model::PriceSourceModel;
+
+/// Loads prices saved by `PriceSourcesSaver`
 #[derive(Default)]
 pub struct PriceSourcesLoader {
-    // TODO: fix when DatabaseManager will be added
-    //database_manager: DatabaseManager
+    storage: Option<Arc<dyn Storage>>,
 }
 
 impl PriceSourcesLoader {
-    pub fn new(//database_manager: DatabaseManager
-    ) -> Self {
-        Self{
-            //database_manager: DatabaseManager
-        }
+    pub fn new(storage: Option<Arc<dyn Storage>>) -> Self {
+        Self { storage }
     }
 
+    /// Returns the latest prices for each market at or before `time`
     pub async fn load(
         &self,
-        _save_time: DateTime,
-        _cancellation_token: CancellationToken,
-    ) -> Option<HashMap<MarketId, PriceByOrderSide>> {
-        //     const string sqlQuery =
-        //         "SELECT a.* FROM public.\"PriceSources\" a " +
-        //         "JOIN ( " +
-        //         "SELECT \"ExchangeName\", \"CurrencyCodePair\", max(\"DateTime\") \"DateTime\" " +
-        //         "FROM public.\"PriceSources\" " +
-        //         "WHERE \"DateTime\" <= {0} " +
-        //         "GROUP BY \"ExchangeName\", \"CurrencyCodePair\" " +
-        //         ") b ON a.\"ExchangeName\" = b.\"ExchangeName\" AND a.\"CurrencyCodePair\" = b.\"CurrencyCodePair\" AND a.\"DateTime\" = b.\"DateTime\"";
-
-        //     await using var session = _databaseManager.Sql;
-        //     return await session.Set<PriceSourceModel>()
-        //         .FromSqlRaw(sqlQuery, dateTime)
-        //         .ToDictionaryAsync(
-        //             x => new ExchangeNameSymbol(x.ExchangeName, x.CurrencyCodePair),
-        //             x => new PricesBySide(x.Ask, x.Bid),
-        //             cancellationToken);
-
-        Some(HashMap::new())
+        time: DateTime,
+        cancellation_token: CancellationToken,
+    ) -> Result<HashMap<MarketId, PriceByOrderSide>> {
+        let pool = self
+            .storage
+            .as_ref()
+            .context("Database isn't configured for loading price sources")?
+            .postgres_pool()
+            .context("Loading price sources is supported only for Postgres database")?;
+
+        let price_sources = tokio::select! {
+            price_sources = load_latest_price_sources(pool, PriceSourceModel::TABLE_NAME, time) => price_sources?,
+            _ = cancellation_token.when_cancelled() => bail!("Loading price sources was cancelled"),
+        };
+
+        to_prices_by_market(price_sources)
+    }
+}
+
+fn to_prices_by_market(
+    price_sources: Vec<JsonValue>,
+) -> Result<HashMap<MarketId, PriceByOrderSide>> {
+    price_sources
+        .into_iter()
+        .map(|json| {
+            let price_source = serde_json::from_value::<PriceSourceModel>(json)
+                .context("Failed to parse price source")?;
+
+            Ok((
+                MarketId::new(price_source.exchange_id, price_source.currency_pair),
+                PriceByOrderSide::new(price_source.bid, price_source.ask),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use mmb_domain::market::{CurrencyPair, ExchangeId};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn prices_by_market_from_saved_price_sources() {
+        let market_id = MarketId::new(
+            ExchangeId::new("Binance"),
+            CurrencyPair::from_codes("btc".into(), "usdt".into()),
+        );
+        let price_source = PriceSourceModel::new(
+            Utc::now(),
+            market_id.exchange_id,
+            market_id.currency_pair,
+            Some(dec!(100)),
+            None,
+        );
+        let json = price_source.get_json().expect("in test");
+
+        let prices = to_prices_by_market(vec![json]).expect("in test");
+
+        assert_eq!(prices.len(), 1);
+        let price = prices.get(&market_id).expect("in test");
+        assert_eq!(price.top_bid, Some(dec!(100)));
+        assert_eq!(price.top_ask, None);
     }
 }
@@ -2,6 +2,7 @@ use crate::database::events::recorder::EventRecorder;
 use mmb_domain::market::MarketId;
 use mmb_domain::order::snapshot::PriceByOrderSide;
 use mockall_double::double;
+use std::sync::Arc;
 
 #[double]
 use crate::misc::time::time_manager;
@@ -9,11 +10,11 @@ use crate::misc::time::time_manager;
 use crate::misc::price_source_model::PriceSourceModel;
 
 pub struct PriceSourcesSaver {
-    event_recorder: EventRecorder,
+    event_recorder: Arc<EventRecorder>,
 }
 
 impl PriceSourcesSaver {
-    pub fn new(event_recorder: EventRecorder) -> Self {
+    pub fn new(event_recorder: Arc<EventRecorder>) -> Self {
         Self { event_recorder }
     }
 
@@ -15,7 +15,7 @@ use super::{
 };
 
 pub struct UsdConverter {
-    price_source_service: PriceSourceService,
+    price_source_service: Arc<PriceSourceService>,
     usd_currency_code: CurrencyCode,
     denominator_usd_converter: DenominatorUsdConverter,
 }
@@ -24,7 +24,7 @@ pub struct UsdConverter {
 impl UsdConverter {
     pub fn new(
         currencies: &[CurrencyCode],
-        price_source_service: PriceSourceService,
+        price_source_service: Arc<PriceSourceService>,
         usd_denominator: Arc<UsdDenominator>,
     ) -> Self {
         let usd = "USD".into();
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::lifecycle::app_lifetime_manager::AppLifetimeManager;
+use anyhow::Result;
 use itertools::Itertools;
 use mmb_domain::market::CurrencyCode;
 use mmb_domain::market::CurrencyId;
@@ -12,8 +13,12 @@ use crate::{
     infrastructure::spawn_by_timer,
     misc::traits::market_service::{CreateMarketService, GetMarketCurrencyCodePrice},
     services::market_prices::market_currency_code_price::MarketCurrencyCodePrice,
+    services::market_prices::usd_price_providers::UsdPriceProviders,
+    settings::UsdPriceProvidersSettings,
 };
 
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(7200); // 2 hours
+
 pub struct UsdDenominator {
     market_service: Arc<dyn GetMarketCurrencyCodePrice>,
     lifetime_manager: Arc<AppLifetimeManager>,
@@ -45,7 +50,7 @@ impl UsdDenominator {
     fn new(
         market_service: Arc<dyn GetMarketCurrencyCodePrice>,
         market_prices: Vec<MarketCurrencyCodePrice>,
-        auto_refresh_data: bool,
+        refresh_interval: Option<Duration>,
         lifetime_manager: Arc<AppLifetimeManager>,
     ) -> Arc<Self> {
         let this = Arc::new(Self {
@@ -57,12 +62,12 @@ impl UsdDenominator {
             price_update_callback: Box::new(|| ()),
         });
 
-        if auto_refresh_data {
+        if let Some(refresh_interval) = refresh_interval {
             let this = this.clone();
             let _ = spawn_by_timer(
                 "UsdDenominator::refresh_data()",
                 Duration::ZERO,
-                Duration::from_secs(7200), // 2 hours
+                refresh_interval,
                 SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
                 move || Self::refresh_data(this.clone()),
             );
@@ -90,11 +95,36 @@ impl UsdDenominator {
         UsdDenominator::new(
             service as Arc<dyn GetMarketCurrencyCodePrice>,
             market_prices,
-            auto_refresh_data,
+            auto_refresh_data.then_some(DEFAULT_REFRESH_INTERVAL),
             lifetime_manager,
         )
     }
 
+    /// Create denominator with prices from configured providers
+    pub async fn create_with_providers(
+        settings: &UsdPriceProvidersSettings,
+        lifetime_manager: Arc<AppLifetimeManager>,
+    ) -> Result<Arc<Self>> {
+        let refresh_interval = settings
+            .refresh_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+        let max_price_age = settings
+            .max_price_age_ms
+            .map(Duration::from_millis)
+            .unwrap_or(refresh_interval * 3);
+
+        let service = Arc::new(UsdPriceProviders::from_settings(settings, max_price_age)?);
+        let market_prices = service.get_market_currency_code_price().await;
+
+        Ok(UsdDenominator::new(
+            service,
+            market_prices,
+            Some(refresh_interval),
+            lifetime_manager,
+        ))
+    }
+
     pub fn get_non_refreshing_usd_denominator(&self) -> Arc<Self> {
         UsdDenominator::new(
             self.market_service.clone(),
@@ -103,7 +133,7 @@ impl UsdDenominator {
                 .values()
                 .cloned()
                 .collect_vec(),
-            false,
+            None,
             self.lifetime_manager.clone(),
         )
     }
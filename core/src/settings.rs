@@ -1,6 +1,10 @@
+use mmb_database::postgres_db::connection::PgPoolSettings;
 use mmb_domain::market::{CurrencyCode, CurrencyPair, ExchangeAccountId};
 use mmb_domain::order::snapshot::Amount;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
 
 pub trait BaseStrategySettings {
@@ -22,15 +26,161 @@ pub struct AppSettings<StrategySettings: BaseStrategySettings + Clone> {
 pub struct CoreSettings {
     pub database: Option<DbSettings>,
     pub exchanges: Vec<ExchangeSettings>,
+    pub kill_switch: Option<KillSwitchSettings>,
+    pub usd_price_providers: Option<UsdPriceProvidersSettings>,
+    /// Chains of markets for converting amounts between currencies, e.g. to USD
+    #[serde(default)]
+    pub price_sources: Vec<CurrencyPriceSourceSettings>,
+    /// Blocks trading on the strategy market when USD loss exceeds limit. Triggers kill switch too
+    /// if `KillSwitchSettings::trigger_on_profit_loss` is set.
+    /// Requires `price_sources` and `usd_price_providers`
+    pub profit_loss_stopper: Option<ProfitLossStopperSettings>,
+    pub candles: Option<CandlesSettings>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct DbSettings {
+    /// Postgres connection string or `sqlite:<path to file>` for embedded SQLite database
     pub url: String,
     pub migrations: Vec<PathBuf>,
     /// Path to directory for creating temporary directory for save events that was not saved to
     /// database by any reason and will be resaved to db late
     pub postponed_events_dir: Option<PathBuf>,
+    /// Connection pool, timeouts and TLS settings for Postgres
+    #[serde(default)]
+    pub pool: PgPoolSettings,
+    /// Partitioning and retention of event tables, Postgres only
+    pub maintenance: Option<EventTablesMaintenanceSettings>,
+    /// Queue of `EventRecorder` and limits for postponed events
+    #[serde(default)]
+    pub event_recorder: EventRecorderSettings,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventRecorderSettings {
+    /// Max number of events waiting for saving to database. Default is 20 000
+    pub queue_capacity: Option<usize>,
+    /// What to do with new events when the queue is full
+    #[serde(default)]
+    pub overflow_policy: EventRecorderOverflowPolicy,
+    /// Max time caller of `save()` waits for free place in the queue with `block` policy, after that
+    /// the event is spilled to disk. Default is 1000
+    pub block_timeout_ms: Option<u64>,
+    /// Priorities of tables for `drop_oldest` policy and disk quota. Events of tables with lower
+    /// priority are dropped first. Default priority is 0
+    #[serde(default)]
+    pub table_priorities: HashMap<String, i32>,
+    /// Max size of postponed events files. When it's exceeded, the oldest files of tables with
+    /// lower priority are removed and then new files are dropped. Unlimited by default
+    pub postponed_events_quota_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventRecorderOverflowPolicy {
+    /// Caller of `save()` waits until the recorder frees place in the queue. The event is spilled
+    /// to disk if the recorder doesn't catch up during `block_timeout_ms`
+    Block,
+    /// Drop the oldest event of table with the lowest priority
+    DropOldest,
+    /// Write events which don't fit into the queue to postponed events files
+    SpillToDisk,
+}
+
+impl Default for EventRecorderOverflowPolicy {
+    fn default() -> Self {
+        EventRecorderOverflowPolicy::SpillToDisk
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventTablesMaintenanceSettings {
+    /// Event tables partitioned by day of `insert_time`
+    pub tables: Vec<EventTableRetentionSettings>,
+    /// Daily partitions are created in advance for this number of days. Default is 2
+    pub partitions_ahead_days: Option<u32>,
+    /// Directory for gzip compressed CSV files of archived partitions
+    pub archive_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventTableRetentionSettings {
+    pub table_name: String,
+    /// Partitions older than this number of days are dropped
+    pub retention_days: u32,
+    /// Export partition to `archive_dir` before dropping
+    #[serde(default)]
+    pub archive: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KillSwitchSettings {
+    /// Close active positions on margin trading accounts after cancelling orders
+    #[serde(default)]
+    pub close_positions: bool,
+    /// Kill switch is triggered when this file appears
+    pub trigger_file: Option<PathBuf>,
+    pub trigger_file_check_interval_ms: Option<u64>,
+    /// Kill switch is triggered when too many errors happened during period
+    pub errors_limit: Option<ErrorsLimitSettings>,
+    /// Kill switch is triggered when `profit_loss_stopper` limit is exceeded. Otherwise only
+    /// the strategy market is blocked temporarily
+    #[serde(default)]
+    pub trigger_on_profit_loss: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UsdPriceProvidersSettings {
+    /// Sources of USD prices in fallback order
+    pub providers: Vec<UsdPriceProviderSettings>,
+    #[serde(default)]
+    pub mode: UsdPriceProvidersMode,
+    /// Default is 2 hours
+    pub refresh_interval_ms: Option<u64>,
+    /// Prices of provider aren't used if they weren't updated during this period.
+    /// Default is triple refresh interval
+    pub max_price_age_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UsdPriceProviderSettings {
+    /// Endpoint with list of `{ "symbol": "BTCUSDT", "price": "20000.5" }` like Binance ticker
+    ExchangeTicker { url: String, quote_suffix: String },
+    /// JSON file with object like `{ "btc": "20000.5" }`
+    StaticFile { path: PathBuf },
+    /// Endpoint with object like `{ "btc": "20000.5" }`
+    HttpOracle { url: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsdPriceProvidersMode {
+    /// Price of the first provider in order which has it
+    Fallback,
+    /// Median of prices of all providers which have it
+    Median,
+}
+
+impl Default for UsdPriceProvidersMode {
+    fn default() -> Self {
+        UsdPriceProvidersMode::Fallback
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CandlesSettings {
+    /// Intervals of candles built from trades, e.g. `[60, 3600]` for 1 minute and 1 hour candles
+    pub intervals_secs: Vec<u32>,
+    /// Number of closed candles kept in memory and backfilled from exchange at startup for each
+    /// market and interval. Default is 1000
+    pub history_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ErrorsLimitSettings {
+    pub max_errors_count: usize,
+    pub period_ms: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -58,6 +208,71 @@ pub struct ExchangeSettings {
     pub subscribe_to_market_data: bool,
     pub websocket_channels: Vec<String>,
     pub currency_pairs: Option<Vec<CurrencyPairSetting>>,
+    /// Pre-trade checks of orders created by disposition executor
+    pub risk_checks: Option<RiskChecksSettings>,
+    /// Exchange-side cancelling of all orders if engine stops refreshing timer
+    pub dead_man_switch: Option<DeadManSwitchSettings>,
+    /// Named environment of exchange like `mainnet` or `testnet`. Default is the main environment
+    /// of exchange
+    pub environment: Option<String>,
+    /// Overrides of environment hosts, e.g. for colocated endpoints or a local mock server
+    pub hosts: Option<HostsSettings>,
+    /// Proxy and local addresses of REST and websocket connections to exchange
+    pub connectivity: Option<ConnectivitySettings>,
+    /// Synchronization with exchange server clock if exchange supports it. Default settings are
+    /// used if it isn't specified
+    pub clock_sync: Option<ClockSyncSettings>,
+    /// Detection of websocket and market data feeds which stopped receiving messages. Detection
+    /// is disabled if it isn't specified
+    pub stale_feed: Option<StaleFeedSettings>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClockSyncSettings {
+    /// Period of server time requesting. Default is 60000
+    pub interval_ms: Option<u64>,
+    /// Count of server time requests per synchronization. Offset is taken from the request with
+    /// the least round trip. Default is 3
+    pub samples_count: Option<usize>,
+    /// Offset between local and server clocks which is reported as warning. Default is 1000
+    pub max_offset_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StaleFeedSettings {
+    /// Time without any websocket message after which connection is considered stale.
+    /// Default is 30000
+    pub websocket_timeout_ms: Option<u64>,
+    /// Time without order book updates of currency pair after which its order book is considered
+    /// stale. Order book feeds aren't checked if it isn't specified
+    pub order_book_timeout_ms: Option<u64>,
+    /// Time without trades of currency pair after which its trades feed is considered stale.
+    /// Trades feeds aren't checked if it isn't specified
+    pub trades_timeout_ms: Option<u64>,
+    /// Period of feeds checking. Default is 1000
+    pub check_interval_ms: Option<u64>,
+    /// Minimal period between websocket reconnections while feeds stay stale. Default is 30000
+    pub reconnect_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ConnectivitySettings {
+    /// `http://[user:password@]host:port` or `socks5://[user:password@]host:port`
+    pub proxy: Option<String>,
+    /// Local IP addresses for outbound connections. Requests are spread over them in round-robin,
+    /// addresses which reached requests limit of exchange per IP are skipped. Limits per exchange
+    /// account aren't changed
+    #[serde(default)]
+    pub local_addresses: Vec<IpAddr>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HostsSettings {
+    /// URL like `https://api.binance.com`, `http` scheme is allowed for mock servers
+    pub rest_host: Option<String>,
+    pub web_socket_host: Option<String>,
+    /// Websocket for private data if exchange has separate one
+    pub web_socket2_host: Option<String>,
 }
 
 impl ExchangeSettings {
@@ -78,6 +293,13 @@ impl ExchangeSettings {
             currency_pairs: None,
             subscribe_to_market_data: true,
             is_reducing_market_data: None,
+            risk_checks: None,
+            dead_man_switch: None,
+            environment: None,
+            hosts: None,
+            connectivity: None,
+            clock_sync: None,
+            stale_feed: None,
         }
     }
 }
@@ -94,10 +316,45 @@ impl Default for ExchangeSettings {
             currency_pairs: None,
             subscribe_to_market_data: true,
             is_reducing_market_data: None,
+            risk_checks: None,
+            dead_man_switch: None,
+            environment: None,
+            hosts: None,
+            connectivity: None,
+            clock_sync: None,
+            stale_feed: None,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeadManSwitchSettings {
+    /// Time after which exchange cancels all orders if timer isn't refreshed
+    pub timeout_ms: u64,
+    /// Period of timer refreshing. Default is third part of `timeout_ms`
+    pub refresh_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RiskChecksSettings {
+    /// Max order cost in quote currency
+    pub max_order_notional: Option<Amount>,
+    /// Max relative deviation of order price from middle price of order book, e.g. 0.05 for 5%
+    pub max_price_deviation_from_mid: Option<Decimal>,
+    pub max_open_orders_per_market: Option<usize>,
+    pub max_order_rate_per_strategy: Option<OrderRateSettings>,
+    /// Max balance of currency after order is filled
+    #[serde(default)]
+    pub max_positions: HashMap<CurrencyCode, Amount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OrderRateSettings {
+    pub max_orders_count: usize,
+    pub period_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct CurrencyPriceSourceSettings {
     pub start_currency_code: CurrencyCode,
     pub end_currency_code: CurrencyCode,
@@ -119,22 +376,27 @@ impl CurrencyPriceSourceSettings {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ExchangeIdCurrencyPairSettings {
     pub exchange_account_id: ExchangeAccountId,
     pub currency_pair: CurrencyPair,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TimePeriodKind {
     Hour,
     Day,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct StopperCondition {
     pub period_kind: TimePeriodKind,
     pub period_value: i64,
     pub limit: Amount,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ProfitLossStopperSettings {
     pub conditions: Vec<StopperCondition>,
 }
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::Duration;
 use mmb_domain::order::event::OrderEventType;
 use mmb_utils::infrastructure::SpawnFutureFlags;
 use mmb_utils::nothing_to_do;
@@ -6,7 +7,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use mmb_domain::events::ExchangeEvent;
-use mmb_domain::market::MarketAccountId;
+use mmb_domain::market::{ExchangeAccountId, MarketAccountId};
 use mmb_domain::order::snapshot::ClientOrderId;
 use mmb_domain::order::snapshot::{Amount, Price};
 use parking_lot::{Mutex, RwLock};
@@ -14,6 +15,9 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
 use super::infrastructure::spawn_future;
+use crate::exchanges::conflating_events_receiver::PushResult;
+use crate::exchanges::server_clock::ServerClockState;
+use crate::exchanges::timeouts::timeout_manager::TimeoutManager;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct MarketAccountIdStatistic {
@@ -25,6 +29,8 @@ pub struct MarketAccountIdStatistic {
     summary_filled_amount: Amount,
     // Calculated only for completely filled orders
     summary_commission: Amount,
+    // Count of orders rejected by pre-trade risk checks by check name
+    rejected_by_risk_checks: HashMap<String, u64>,
 }
 
 impl MarketAccountIdStatistic {
@@ -59,6 +65,39 @@ impl MarketAccountIdStatistic {
     fn add_summary_commission(&mut self, commission: Price) {
         self.summary_commission += commission;
     }
+
+    fn register_risk_check_rejection(&mut self, check_name: &str) {
+        *self
+            .rejected_by_risk_checks
+            .entry(check_name.to_owned())
+            .or_default() += 1;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LatencyStatistic {
+    events_count: u64,
+    average_ms: f64,
+    max_ms: i64,
+    last_ms: i64,
+}
+
+impl LatencyStatistic {
+    fn register(&mut self, latency: Duration) {
+        let latency_ms = latency.num_milliseconds();
+
+        self.events_count += 1;
+        self.average_ms += (latency_ms as f64 - self.average_ms) / self.events_count as f64;
+        self.max_ms = self.max_ms.max(latency_ms);
+        self.last_ms = latency_ms;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExchangeStatistic {
+    server_clock: Option<ServerClockState>,
+    // Time from trade on exchange to its receipt by server clock
+    market_data_latency: LatencyStatistic,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -66,10 +105,65 @@ pub struct DispositionExecutorStatistic {
     skipped_events_amount: u64,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EventsReceiverStatistic {
+    received_events_count: u64,
+    delivered_events_count: u64,
+    // Order book events merged into later state of the same market
+    conflated_events_count: u64,
+    // Order book events which can't be applied to local snapshot, e.g. while it's resynchronized
+    dropped_order_book_events_count: u64,
+    // Market data events lost because of overflow of queue of the receiver
+    overflowed_events_count: u64,
+    // Events lost because the receiver lagged behind events channel.
+    // Order, balance and liquidation price events can be lost too
+    lagged_events_count: u64,
+    pending_events_count: usize,
+    max_pending_events_count: usize,
+    // Time from receipt to delivery of order, balance and liquidation price events
+    priority_delivery_delay: LatencyStatistic,
+    // Time from receipt to delivery of market data events
+    delivery_delay: LatencyStatistic,
+}
+
+impl EventsReceiverStatistic {
+    fn set_pending_events_count(&mut self, pending_events_count: usize) {
+        self.pending_events_count = pending_events_count;
+        self.max_pending_events_count = self.max_pending_events_count.max(pending_events_count);
+    }
+
+    fn register_received_event(&mut self, push_result: PushResult, pending_events_count: usize) {
+        self.received_events_count += 1;
+        match push_result {
+            PushResult::Queued => nothing_to_do(),
+            PushResult::Conflated => self.conflated_events_count += 1,
+            PushResult::Dropped => self.dropped_order_book_events_count += 1,
+            PushResult::Overflowed => self.overflowed_events_count += 1,
+        }
+        self.set_pending_events_count(pending_events_count);
+    }
+
+    fn register_delivered_event(
+        &mut self,
+        is_priority: bool,
+        delay: Duration,
+        pending_events_count: usize,
+    ) {
+        self.delivered_events_count += 1;
+        match is_priority {
+            true => self.priority_delivery_delay.register(delay),
+            false => self.delivery_delay.register(delay),
+        }
+        self.set_pending_events_count(pending_events_count);
+    }
+}
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub(crate) struct StatisticServiceState {
     market_account_id_stats: RwLock<HashMap<MarketAccountId, MarketAccountIdStatistic>>,
+    exchange_stats: RwLock<HashMap<ExchangeAccountId, ExchangeStatistic>>,
     disposition_executor_stats: Mutex<DispositionExecutorStatistic>,
+    events_receivers_stats: RwLock<HashMap<String, EventsReceiverStatistic>>,
 }
 
 impl StatisticServiceState {
@@ -137,9 +231,81 @@ impl StatisticServiceState {
             .add_summary_commission(commission);
     }
 
+    pub(crate) fn register_risk_check_rejection(
+        &self,
+        market_account_id: MarketAccountId,
+        check_name: &str,
+    ) {
+        self.market_account_id_stats
+            .write()
+            .entry(market_account_id)
+            .or_default()
+            .register_risk_check_rejection(check_name);
+    }
+
     pub(crate) fn register_skipped_event(&self) {
         (*self.disposition_executor_stats.lock()).skipped_events_amount += 1;
     }
+
+    pub(crate) fn register_server_clock(
+        &self,
+        exchange_account_id: ExchangeAccountId,
+        state: ServerClockState,
+    ) {
+        self.exchange_stats
+            .write()
+            .entry(exchange_account_id)
+            .or_default()
+            .server_clock = Some(state);
+    }
+
+    pub(crate) fn register_market_data_latency(
+        &self,
+        exchange_account_id: ExchangeAccountId,
+        latency: Duration,
+    ) {
+        self.exchange_stats
+            .write()
+            .entry(exchange_account_id)
+            .or_default()
+            .market_data_latency
+            .register(latency);
+    }
+
+    pub(crate) fn register_received_event(
+        &self,
+        receiver_name: &str,
+        push_result: PushResult,
+        pending_events_count: usize,
+    ) {
+        self.events_receivers_stats
+            .write()
+            .entry(receiver_name.to_owned())
+            .or_default()
+            .register_received_event(push_result, pending_events_count);
+    }
+
+    pub(crate) fn register_lagged_events(&self, receiver_name: &str, lagged_events_count: u64) {
+        self.events_receivers_stats
+            .write()
+            .entry(receiver_name.to_owned())
+            .or_default()
+            .lagged_events_count += lagged_events_count;
+    }
+
+    pub(crate) fn register_delivered_event(
+        &self,
+        receiver_name: &str,
+        is_priority: bool,
+        delay: Duration,
+        pending_events_count: usize,
+    ) {
+        self.events_receivers_stats
+            .write()
+            .entry(receiver_name.to_owned())
+            .or_default()
+            .register_delivered_event(is_priority, delay, pending_events_count);
+    }
 }
 
 #[derive(Default, Debug)]
@@ -222,18 +388,83 @@ impl StatisticService {
     pub(crate) fn register_skipped_event(&self) {
         self.statistic_service_state.register_skipped_event();
     }
+
+    pub(crate) fn register_server_clock(
+        &self,
+        exchange_account_id: ExchangeAccountId,
+        state: ServerClockState,
+    ) {
+        self.statistic_service_state
+            .register_server_clock(exchange_account_id, state);
+    }
+
+    pub(crate) fn register_market_data_latency(
+        &self,
+        exchange_account_id: ExchangeAccountId,
+        latency: Duration,
+    ) {
+        self.statistic_service_state
+            .register_market_data_latency(exchange_account_id, latency);
+    }
+
+    pub(crate) fn register_risk_check_rejection(
+        &self,
+        market_account_id: MarketAccountId,
+        check_name: &str,
+    ) {
+        self.statistic_service_state
+            .register_risk_check_rejection(market_account_id, check_name);
+    }
+
+    pub(crate) fn register_received_event(
+        &self,
+        receiver_name: &str,
+        push_result: PushResult,
+        pending_events_count: usize,
+    ) {
+        self.statistic_service_state.register_received_event(
+            receiver_name,
+            push_result,
+            pending_events_count,
+        );
+    }
+
+    pub(crate) fn register_lagged_events(&self, receiver_name: &str, lagged_events_count: u64) {
+        self.statistic_service_state
+            .register_lagged_events(receiver_name, lagged_events_count);
+    }
+
+    pub(crate) fn register_delivered_event(
+        &self,
+        receiver_name: &str,
+        is_priority: bool,
+        delay: Duration,
+        pending_events_count: usize,
+    ) {
+        self.statistic_service_state.register_delivered_event(
+            receiver_name,
+            is_priority,
+            delay,
+            pending_events_count,
+        );
+    }
 }
 
 pub struct StatisticEventHandler {
     pub(crate) stats: Arc<StatisticService>,
+    timeout_manager: Arc<TimeoutManager>,
 }
 
 impl StatisticEventHandler {
     pub fn new(
         events_receiver: broadcast::Receiver<ExchangeEvent>,
         stats: Arc<StatisticService>,
+        timeout_manager: Arc<TimeoutManager>,
     ) -> Arc<Self> {
-        let statistic_event_handler = Arc::new(Self { stats });
+        let statistic_event_handler = Arc::new(Self {
+            stats,
+            timeout_manager,
+        });
 
         spawn_future(
             "Start statistic service",
@@ -303,6 +534,20 @@ impl StatisticEventHandler {
                     _ => nothing_to_do(),
                 }
             }
+            ExchangeEvent::Trades(trades_event) => {
+                let last_trade_time = trades_event.trades.iter().map(|x| x.transaction_time).max();
+                if let Some(last_trade_time) = last_trade_time {
+                    let receipt_time = self
+                        .timeout_manager
+                        .server_clock(trades_event.exchange_account_id)
+                        .to_server_time(trades_event.receipt_time);
+
+                    self.stats.register_market_data_latency(
+                        trades_event.exchange_account_id,
+                        receipt_time - last_trade_time,
+                    );
+                }
+            }
             _ => nothing_to_do(),
         }
 
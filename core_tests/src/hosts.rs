@@ -0,0 +1,22 @@
+use mmb_core::settings::{ExchangeSettings, HostsSettings};
+use std::env;
+
+/// Applies environment and hosts of exchange from environment variables `<PREFIX>_ENVIRONMENT`,
+/// `<PREFIX>_REST_HOST`, `<PREFIX>_WEB_SOCKET_HOST` and `<PREFIX>_WEB_SOCKET2_HOST`, so
+/// integration tests can be run against testnet or a local mock server
+pub fn apply_hosts_from_env(settings: &mut ExchangeSettings, prefix: &str) {
+    let var = |name: &str| env::var(format!("{prefix}_{name}")).ok();
+
+    if let Some(environment) = var("ENVIRONMENT") {
+        settings.environment = Some(environment);
+    }
+
+    let hosts = HostsSettings {
+        rest_host: var("REST_HOST"),
+        web_socket_host: var("WEB_SOCKET_HOST"),
+        web_socket2_host: var("WEB_SOCKET2_HOST"),
+    };
+    if hosts != HostsSettings::default() {
+        settings.hosts = Some(hosts);
+    }
+}
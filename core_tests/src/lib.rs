@@ -16,4 +16,5 @@
     clippy::unwrap_used
 )]
 
+pub mod hosts;
 pub mod order;
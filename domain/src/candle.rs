@@ -0,0 +1,68 @@
+use chrono::Duration;
+use mmb_database::impl_event;
+use mmb_utils::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::market::MarketId;
+use crate::order::snapshot::{Amount, Price};
+
+/// OHLCV bar of trades of market during interval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candle {
+    pub market_id: MarketId,
+    pub interval_secs: u32,
+    /// Start of interval aligned to multiple of interval since unix epoch
+    pub open_time: DateTime,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Amount,
+    pub trades_count: u64,
+}
+
+impl_event!(Candle, "candles");
+
+impl Candle {
+    /// Creates candle with the first trade of interval
+    pub fn new(
+        market_id: MarketId,
+        interval_secs: u32,
+        open_time: DateTime,
+        price: Price,
+        amount: Amount,
+    ) -> Self {
+        Candle {
+            market_id,
+            interval_secs,
+            open_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: amount,
+            trades_count: 1,
+        }
+    }
+
+    pub fn add_trade(&mut self, price: Price, amount: Amount) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += amount;
+        self.trades_count += 1;
+    }
+
+    /// Adds trade delivered after newer trades of the interval, so open and close aren't changed
+    pub fn add_late_trade(&mut self, price: Price, amount: Amount) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.volume += amount;
+        self.trades_count += 1;
+    }
+
+    /// End of interval (exclusive)
+    pub fn close_time(&self) -> DateTime {
+        self.open_time + Duration::seconds(self.interval_secs.into())
+    }
+}
@@ -1,8 +1,6 @@
 use anyhow::Result;
-use itertools::Itertools;
 use mmb_utils::infrastructure::WithExpect;
 use mmb_utils::{impl_table_type, impl_table_type_raw};
-use regex::Regex;
 use rust_decimal::{Decimal, MathematicalOps};
 use serde::de::{self, Deserializer, Visitor};
 use serde::ser::Serializer;
@@ -44,26 +42,23 @@ impl FromStr for ExchangeAccountId {
     type Err = ExchangeIdParseError;
 
     fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
-        let regex = Regex::new(r"(^[A-Za-z0-9\-\.]+)_(\d+$)")
-            .map_err(|err| ExchangeIdParseError(err.to_string()))?;
-
-        let captures = regex
-            .captures(text)
-            .ok_or_else(|| ExchangeIdParseError("Invalid format".into()))?
-            .iter()
-            .collect_vec();
+        // exchange id can contain '_' so account number is separated by the last one
+        let (exchange_id, number) = text
+            .rsplit_once('_')
+            .ok_or_else(|| ExchangeIdParseError("Invalid format".into()))?;
+
+        let is_valid_exchange_id = !exchange_id.is_empty()
+            && exchange_id
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || matches!(x, '-' | '.' | '_'));
+        if !is_valid_exchange_id || number.is_empty() || !number.chars().all(|x| x.is_ascii_digit())
+        {
+            return Err(ExchangeIdParseError("Invalid format".into()));
+        }
 
-        let exchange_id = captures[1]
-            .ok_or_else(|| ExchangeIdParseError("Invalid format".into()))?
-            .as_str();
-
-        let number = captures[2]
-            .ok_or_else(|| ExchangeIdParseError("Invalid format".into()))?
-            .as_str()
-            .parse()
-            .map_err(|x| {
-                ExchangeIdParseError(format!("Can't parse exchange account number: {}", x))
-            })?;
+        let number = number.parse().map_err(|x| {
+            ExchangeIdParseError(format!("Can't parse exchange account number: {}", x))
+        })?;
 
         Ok(ExchangeAccountId::new(exchange_id, number))
     }
@@ -145,7 +140,7 @@ impl MarketId {
 }
 
 /// Exchange account id and currency pair
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct MarketAccountId {
     pub exchange_account_id: ExchangeAccountId,
     pub currency_pair: CurrencyPair,
@@ -174,6 +169,64 @@ impl Serialize for MarketAccountId {
     }
 }
 
+struct MarketAccountIdVisitor;
+
+impl<'de> Visitor<'de> for MarketAccountIdVisitor {
+    type Value = MarketAccountId;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "string or struct for MarketAccountId")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let invalid_value = || {
+            de::Error::invalid_value(
+                de::Unexpected::Str(v),
+                &"MarketAccountId as a string with ExchangeAccountId and CurrencyPair separated by a '|' character",
+            )
+        };
+
+        let (exchange_account_id, currency_pair) = v.split_once('|').ok_or_else(invalid_value)?;
+        let exchange_account_id = exchange_account_id.parse().map_err(|_| invalid_value())?;
+
+        Ok(MarketAccountId::new(
+            exchange_account_id,
+            CurrencyPair::from_raw(currency_pair),
+        ))
+    }
+
+    fn visit_map<A>(self, map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        // Struct form was used before MarketAccountId became serialized as a string
+        #[derive(Deserialize)]
+        struct MarketAccountIdStruct {
+            exchange_account_id: ExchangeAccountId,
+            currency_pair: CurrencyPair,
+        }
+
+        let MarketAccountIdStruct {
+            exchange_account_id,
+            currency_pair,
+        } = Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+        Ok(MarketAccountId::new(exchange_account_id, currency_pair))
+    }
+}
+
+impl<'de> Deserialize<'de> for MarketAccountId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(MarketAccountIdVisitor)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum ExchangeErrorType {
     Unknown,
@@ -253,6 +306,48 @@ mod tests {
         }
     }
 
+    mod market_account_id_serde {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        pub fn roundtrip() {
+            let market_account_id = MarketAccountId::new(
+                ExchangeAccountId::new("Binance", 1),
+                CurrencyPair::from_codes("btc".into(), "usdt".into()),
+            );
+
+            let serialized = serde_json::to_string(&market_account_id).expect("in test");
+            let deserialized: MarketAccountId = serde_json::from_str(&serialized).expect("in test");
+
+            assert_eq!(serialized, r#""Binance_1|btc/usdt""#);
+            assert_eq!(deserialized, market_account_id);
+        }
+
+        #[test]
+        pub fn struct_format() {
+            let deserialized: MarketAccountId = serde_json::from_str(
+                r#"{"exchange_account_id":"Binance_1","currency_pair":"btc/usdt"}"#,
+            )
+            .expect("in test");
+
+            assert_eq!(
+                deserialized,
+                MarketAccountId::new(
+                    ExchangeAccountId::new("Binance", 1),
+                    CurrencyPair::from_codes("btc".into(), "usdt".into()),
+                )
+            );
+        }
+
+        #[test]
+        pub fn invalid_format() {
+            let result = serde_json::from_str::<MarketAccountId>(r#""Binance_1""#);
+
+            assert!(result.is_err());
+        }
+    }
+
     mod parse_exchange_account_id {
         use super::*;
         use pretty_assertions::assert_eq;
@@ -266,6 +361,15 @@ mod tests {
             );
         }
 
+        #[test]
+        pub fn exchange_id_with_underscore() {
+            let exchange_account_id = "local_exchange_id_2".parse::<ExchangeAccountId>();
+            assert_eq!(
+                exchange_account_id,
+                Ok(ExchangeAccountId::new("local_exchange_id", 2))
+            );
+        }
+
         #[test]
         pub fn failed_because_no_exchange_name() {
             let exchange_account_id = "123".parse::<ExchangeAccountId>();
@@ -2,7 +2,9 @@ use mmb_utils::DateTime;
 
 use crate::market::CurrencyPair;
 use crate::market::*;
+use crate::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
 use crate::order_book::order_book_data::OrderBookData;
+use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 
 /// Possible variants of OrderBookEvent
@@ -14,6 +16,52 @@ pub enum EventType {
     Update,
 }
 
+/// Exchange update ids covered by event. Snapshot covers all updates up to `last_update_id`
+/// so for it `first_update_id` is equal to `last_update_id`.
+/// Update can be applied only if it continues previous one: `first_update_id <= previous last_update_id + 1`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OrderBookSequence {
+    pub first_update_id: u64,
+    pub last_update_id: u64,
+}
+
+impl OrderBookSequence {
+    pub fn new(first_update_id: u64, last_update_id: u64) -> Self {
+        Self {
+            first_update_id,
+            last_update_id,
+        }
+    }
+
+    pub fn snapshot(last_update_id: u64) -> Self {
+        Self::new(last_update_id, last_update_id)
+    }
+}
+
+/// Exchange specific calculation of order book checksum
+pub type ChecksumCalculator = fn(&LocalOrderBookSnapshot) -> u32;
+
+/// Checksum of order book after applying event
+#[derive(Copy, Clone)]
+pub struct OrderBookChecksum {
+    pub expected: u32,
+    pub calculator: ChecksumCalculator,
+}
+
+impl Debug for OrderBookChecksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderBookChecksum")
+            .field("expected", &self.expected)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OrderBookChecksum {
+    pub fn is_valid(&self, snapshot: &LocalOrderBookSnapshot) -> bool {
+        (self.calculator)(snapshot) == self.expected
+    }
+}
+
 /// Event to update local snapshot
 #[derive(Debug, Clone)]
 pub struct OrderBookEvent {
@@ -22,7 +70,9 @@ pub struct OrderBookEvent {
     pub exchange_account_id: ExchangeAccountId,
     pub currency_pair: CurrencyPair,
 
-    _event_id: String,
+    /// `None` if exchange doesn't provide update ids
+    pub sequence: Option<OrderBookSequence>,
+    pub checksum: Option<OrderBookChecksum>,
 
     pub event_type: EventType,
     pub data: Arc<OrderBookData>,
@@ -33,7 +83,7 @@ impl OrderBookEvent {
         creation_time: DateTime,
         exchange_account_id: ExchangeAccountId,
         currency_pair: CurrencyPair,
-        _event_id: String,
+        sequence: Option<OrderBookSequence>,
         event_type: EventType,
         data: Arc<OrderBookData>,
     ) -> OrderBookEvent {
@@ -42,12 +92,18 @@ impl OrderBookEvent {
             creation_time,
             exchange_account_id,
             currency_pair,
-            _event_id,
+            sequence,
+            checksum: None,
             event_type,
             data,
         }
     }
 
+    pub fn with_checksum(mut self, checksum: OrderBookChecksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
     pub fn market_account_id(&self) -> MarketAccountId {
         MarketAccountId::new(self.exchange_account_id, self.currency_pair)
     }
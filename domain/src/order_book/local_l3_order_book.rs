@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mmb_utils::DateTime;
+use rust_decimal::Decimal;
+
+use crate::order::snapshot::{Amount, ExchangeOrderId, OrderSide, Price, SortedOrderData};
+use crate::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
+
+/// Resting order in per-order (L3) market data of exchange
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L3Order {
+    pub exchange_order_id: ExchangeOrderId,
+    pub side: OrderSide,
+    pub price: Price,
+    pub amount: Amount,
+}
+
+/// Change of per-order order book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum L3OrderBookUpdate {
+    /// Order is placed to the end of queue of its price level
+    Add(L3Order),
+    /// Decreasing of amount keeps priority of order, changing of price or increasing of amount
+    /// moves order to the end of queue
+    Modify {
+        exchange_order_id: ExchangeOrderId,
+        price: Price,
+        amount: Amount,
+    },
+    Remove {
+        exchange_order_id: ExchangeOrderId,
+    },
+}
+
+/// Place of order in queue of its price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuePosition {
+    pub orders_ahead: usize,
+    /// Amount which should be filled before order starts filling
+    pub amount_ahead: Amount,
+    pub level_amount: Amount,
+}
+
+/// Order book with individual orders in time priority for venues which publish per-order data.
+/// Allows to find position of own orders in queues of price levels
+#[derive(Debug, Clone)]
+pub struct LocalL3OrderBook {
+    asks: BTreeMap<Price, Vec<L3Order>>,
+    bids: BTreeMap<Price, Vec<L3Order>>,
+    order_locations: HashMap<ExchangeOrderId, (OrderSide, Price)>,
+    pub last_update_time: DateTime,
+}
+
+impl LocalL3OrderBook {
+    /// Orders of snapshot should be sorted by priority inside price levels
+    pub fn new(orders: impl IntoIterator<Item = L3Order>, last_update_time: DateTime) -> Self {
+        let mut order_book = Self {
+            asks: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            order_locations: HashMap::new(),
+            last_update_time,
+        };
+
+        for order in orders {
+            order_book.add_order(order);
+        }
+
+        order_book
+    }
+
+    fn levels(&self, side: OrderSide) -> &BTreeMap<Price, Vec<L3Order>> {
+        match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, side: OrderSide) -> &mut BTreeMap<Price, Vec<L3Order>> {
+        match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        }
+    }
+
+    /// Returns `false` if update refers to unknown order, so order book is out of sync
+    pub fn apply_update(&mut self, update: &L3OrderBookUpdate, update_time: DateTime) -> bool {
+        self.last_update_time = update_time;
+
+        match update {
+            L3OrderBookUpdate::Add(order) => {
+                let _ = self.remove_order(&order.exchange_order_id);
+                self.add_order(order.clone());
+                true
+            }
+            L3OrderBookUpdate::Modify {
+                exchange_order_id,
+                price,
+                amount,
+            } => {
+                let (side, current_price) = match self.order_locations.get(exchange_order_id) {
+                    Some(location) => *location,
+                    None => return false,
+                };
+
+                let level = match self.levels_mut(side).get_mut(&current_price) {
+                    Some(level) => level,
+                    None => return false,
+                };
+                let index = match level
+                    .iter()
+                    .position(|x| &x.exchange_order_id == exchange_order_id)
+                {
+                    Some(index) => index,
+                    None => return false,
+                };
+
+                if *price == current_price && *amount <= level[index].amount {
+                    level[index].amount = *amount;
+                    return true;
+                }
+
+                let mut order = match self.remove_order(exchange_order_id) {
+                    Some(order) => order,
+                    None => return false,
+                };
+                order.price = *price;
+                order.amount = *amount;
+                self.add_order(order);
+                true
+            }
+            L3OrderBookUpdate::Remove { exchange_order_id } => {
+                self.remove_order(exchange_order_id).is_some()
+            }
+        }
+    }
+
+    fn add_order(&mut self, order: L3Order) {
+        let _ = self
+            .order_locations
+            .insert(order.exchange_order_id.clone(), (order.side, order.price));
+        self.levels_mut(order.side)
+            .entry(order.price)
+            .or_default()
+            .push(order);
+    }
+
+    fn remove_order(&mut self, exchange_order_id: &ExchangeOrderId) -> Option<L3Order> {
+        let (side, price) = self.order_locations.remove(exchange_order_id)?;
+
+        let levels = self.levels_mut(side);
+        let level = levels.get_mut(&price)?;
+        let index = level
+            .iter()
+            .position(|x| &x.exchange_order_id == exchange_order_id)?;
+        let order = level.remove(index);
+        if level.is_empty() {
+            let _ = levels.remove(&price);
+        }
+
+        Some(order)
+    }
+
+    pub fn get_order(&self, exchange_order_id: &ExchangeOrderId) -> Option<&L3Order> {
+        let (side, price) = self.order_locations.get(exchange_order_id)?;
+        self.get_level_orders(*side, *price)
+            .iter()
+            .find(|x| &x.exchange_order_id == exchange_order_id)
+    }
+
+    pub fn orders_count(&self) -> usize {
+        self.order_locations.len()
+    }
+
+    /// Orders of price level in time priority
+    pub fn get_level_orders(&self, side: OrderSide, price: Price) -> &[L3Order] {
+        self.levels(side)
+            .get(&price)
+            .map(|x| x.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Total amount of price level. It's the amount ahead of a new order placed at this price
+    pub fn get_level_amount(&self, side: OrderSide, price: Price) -> Amount {
+        self.get_level_orders(side, price)
+            .iter()
+            .map(|x| x.amount)
+            .sum()
+    }
+
+    /// Position of order in queue of its price level, `None` if order isn't in order book
+    pub fn get_queue_position(&self, exchange_order_id: &ExchangeOrderId) -> Option<QueuePosition> {
+        let (side, price) = self.order_locations.get(exchange_order_id)?;
+        let level = self.get_level_orders(*side, *price);
+        let orders_ahead = level
+            .iter()
+            .position(|x| &x.exchange_order_id == exchange_order_id)?;
+
+        Some(QueuePosition {
+            orders_ahead,
+            amount_ahead: level[..orders_ahead].iter().map(|x| x.amount).sum(),
+            level_amount: level.iter().map(|x| x.amount).sum(),
+        })
+    }
+
+    /// Aggregated order book with amounts of price levels
+    pub fn to_local_order_book_snapshot(&self) -> LocalOrderBookSnapshot {
+        let aggregate = |levels: &BTreeMap<Price, Vec<L3Order>>| -> SortedOrderData {
+            levels
+                .iter()
+                .map(|(price, orders)| (*price, orders.iter().map(|x| x.amount).sum::<Decimal>()))
+                .collect()
+        };
+
+        LocalOrderBookSnapshot::new(
+            aggregate(&self.asks),
+            aggregate(&self.bids),
+            self.last_update_time,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    fn order(id: &str, side: OrderSide, price: Price, amount: Amount) -> L3Order {
+        L3Order {
+            exchange_order_id: id.into(),
+            side,
+            price,
+            amount,
+        }
+    }
+
+    fn order_book() -> LocalL3OrderBook {
+        LocalL3OrderBook::new(
+            [
+                order("1", OrderSide::Buy, dec!(10), dec!(1)),
+                order("2", OrderSide::Buy, dec!(10), dec!(2)),
+                order("3", OrderSide::Buy, dec!(10), dec!(3)),
+                order("4", OrderSide::Sell, dec!(11), dec!(5)),
+            ],
+            Utc::now(),
+        )
+    }
+
+    #[test]
+    fn queue_position_changes_with_updates() {
+        let mut order_book = order_book();
+        let own_order_id: ExchangeOrderId = "3".into();
+
+        assert_eq!(
+            order_book.get_queue_position(&own_order_id),
+            Some(QueuePosition {
+                orders_ahead: 2,
+                amount_ahead: dec!(3),
+                level_amount: dec!(6),
+            })
+        );
+
+        // partial fill of order ahead keeps its priority
+        let decrease = L3OrderBookUpdate::Modify {
+            exchange_order_id: "1".into(),
+            price: dec!(10),
+            amount: dec!(0.5),
+        };
+        assert!(order_book.apply_update(&decrease, Utc::now()));
+        let position = order_book
+            .get_queue_position(&own_order_id)
+            .expect("in test");
+        assert_eq!(position.orders_ahead, 2);
+        assert_eq!(position.amount_ahead, dec!(2.5));
+
+        // increasing of amount moves order to the end of queue
+        let increase = L3OrderBookUpdate::Modify {
+            exchange_order_id: "2".into(),
+            price: dec!(10),
+            amount: dec!(4),
+        };
+        assert!(order_book.apply_update(&increase, Utc::now()));
+        let position = order_book
+            .get_queue_position(&own_order_id)
+            .expect("in test");
+        assert_eq!(position.orders_ahead, 1);
+        assert_eq!(position.amount_ahead, dec!(0.5));
+        assert_eq!(position.level_amount, dec!(7.5));
+
+        let remove = L3OrderBookUpdate::Remove {
+            exchange_order_id: "1".into(),
+        };
+        assert!(order_book.apply_update(&remove, Utc::now()));
+        assert_eq!(
+            order_book
+                .get_queue_position(&own_order_id)
+                .expect("in test")
+                .orders_ahead,
+            0
+        );
+        assert!(!order_book.apply_update(&remove, Utc::now()));
+    }
+
+    #[test]
+    fn aggregated_snapshot() {
+        let mut order_book = order_book();
+        let change_price = L3OrderBookUpdate::Modify {
+            exchange_order_id: "2".into(),
+            price: dec!(9),
+            amount: dec!(2),
+        };
+        assert!(order_book.apply_update(&change_price, Utc::now()));
+
+        let snapshot = order_book.to_local_order_book_snapshot();
+
+        assert_eq!(snapshot.get_top_bid(), Some((dec!(10), dec!(4))));
+        assert_eq!(snapshot.get_bids().get(&dec!(9)), Some(&dec!(2)));
+        assert_eq!(snapshot.get_top_ask(), Some((dec!(11), dec!(5))));
+        assert_eq!(order_book.orders_count(), 4);
+        assert_eq!(
+            order_book.get_level_amount(OrderSide::Buy, dec!(9)),
+            dec!(2)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use mmb_utils::DateTime;
+use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
 use crate::market::*;
@@ -23,28 +24,135 @@ impl DataToExcludeOrder {
     }
 }
 
+/// Statistics of spread between top ask and top bid since snapshot creation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpreadStatistics {
+    pub last: Option<Price>,
+    pub min: Option<Price>,
+    pub max: Option<Price>,
+    pub updates_count: u64,
+    sum: Decimal,
+}
+
+impl SpreadStatistics {
+    fn register(&mut self, spread: Price) {
+        self.last = Some(spread);
+        self.min = Some(self.min.map_or(spread, |min| min.min(spread)));
+        self.max = Some(self.max.map_or(spread, |max| max.max(spread)));
+        self.updates_count += 1;
+        self.sum += spread;
+    }
+
+    pub fn average(&self) -> Option<Price> {
+        (self.updates_count > 0).then(|| self.sum / Decimal::from(self.updates_count))
+    }
+}
+
+/// Parameters of metrics maintained by snapshot on every update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrderBookMetricsSettings {
+    /// Distance from mid price in basis points for depth
+    pub depth_bps: Decimal,
+    /// Count of top levels for imbalance
+    pub imbalance_levels_count: usize,
+    /// Amount taken from the top for VWAP
+    pub vwap_amount: Amount,
+}
+
+/// Values of `get_depth_within_bps`, `get_imbalance`, `get_microprice` and `get_vwap`
+/// for `OrderBookMetricsSettings`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OrderBookMetrics {
+    pub asks_depth: Option<Amount>,
+    pub bids_depth: Option<Amount>,
+    pub imbalance: Option<Decimal>,
+    pub microprice: Option<Price>,
+    pub asks_vwap: Option<Price>,
+    pub bids_vwap: Option<Price>,
+}
+
+#[derive(Clone, Debug)]
+struct MaintainedMetrics {
+    settings: OrderBookMetricsSettings,
+    metrics: OrderBookMetrics,
+    /// The deepest price of asks that metrics depend on, `None` means all levels
+    asks_reach: Option<Price>,
+    /// The deepest price of bids that metrics depend on, `None` means all levels
+    bids_reach: Option<Price>,
+}
+
 /// Snapshot of certain ask and bids collection
 /// Identified by ExchangeId
+/// Total amounts, spread statistics and metrics are updated incrementally, so `asks` and `bids`
+/// can be changed only through methods of snapshot
 #[derive(Clone, Debug)]
 pub struct LocalOrderBookSnapshot {
-    pub asks: SortedOrderData,
-    pub bids: SortedOrderData,
+    asks: SortedOrderData,
+    bids: SortedOrderData,
     pub last_update_time: DateTime,
+    asks_total_amount: Amount,
+    bids_total_amount: Amount,
+    spread_statistics: SpreadStatistics,
+    metrics: Option<MaintainedMetrics>,
 }
 
 impl LocalOrderBookSnapshot {
     pub fn new(asks: SortedOrderData, bids: SortedOrderData, last_update_time: DateTime) -> Self {
-        Self {
+        let mut snapshot = Self {
+            asks_total_amount: asks.values().sum(),
+            bids_total_amount: bids.values().sum(),
             asks,
             bids,
             last_update_time,
-        }
+            spread_statistics: SpreadStatistics::default(),
+            metrics: None,
+        };
+        snapshot.register_spread();
+        snapshot
     }
 
     /// Update inner asks and bids
     pub fn apply_update(&mut self, update: &OrderBookData, update_time: DateTime) {
+        self.asks_total_amount += Self::amount_change(&self.asks, &update.asks);
+        self.bids_total_amount += Self::amount_change(&self.bids, &update.bids);
+
         OrderBookData::apply_update(&mut self.asks, &mut self.bids, update);
         self.last_update_time = update_time;
+        self.register_spread();
+
+        // Levels deeper than reach of metrics don't change them
+        let are_metrics_affected = self.metrics.as_ref().is_some_and(|x| {
+            Self::is_reached(OrderSide::Sell, &update.asks, x.asks_reach)
+                || Self::is_reached(OrderSide::Buy, &update.bids, x.bids_reach)
+        });
+        if are_metrics_affected {
+            self.update_metrics();
+        }
+    }
+
+    fn is_reached(book_side: OrderSide, update: &SortedOrderData, reach: Option<Price>) -> bool {
+        let reach = match reach {
+            None => return !update.is_empty(),
+            Some(reach) => reach,
+        };
+
+        match book_side {
+            OrderSide::Buy => update.keys().next_back().is_some_and(|&x| x >= reach),
+            OrderSide::Sell => update.keys().next().is_some_and(|&x| x <= reach),
+        }
+    }
+
+    fn amount_change(book_side: &SortedOrderData, update: &SortedOrderData) -> Amount {
+        update
+            .iter()
+            .map(|(price, amount)| amount - book_side.get(price).copied().unwrap_or_default())
+            .sum()
+    }
+
+    fn register_spread(&mut self) {
+        if let Some(spread) = self.get_spread() {
+            self.spread_statistics.register(spread);
+        }
     }
 
     pub fn exclude_orders<T>(&mut self, orders: T)
@@ -54,6 +162,73 @@ impl LocalOrderBookSnapshot {
         for price_level in orders.into_iter() {
             self.try_remove_order(price_level);
         }
+
+        if self.metrics.is_some() {
+            self.update_metrics();
+        }
+    }
+
+    /// Start maintaining metrics for `settings` on every change of snapshot
+    pub fn set_metrics_settings(&mut self, settings: OrderBookMetricsSettings) {
+        self.metrics = Some(self.calculate_metrics(settings));
+    }
+
+    /// Metrics for settings passed to `set_metrics_settings`
+    pub fn get_metrics(&self) -> Option<&OrderBookMetrics> {
+        self.metrics.as_ref().map(|x| &x.metrics)
+    }
+
+    fn update_metrics(&mut self) {
+        if let Some(settings) = self.metrics.as_ref().map(|x| x.settings) {
+            self.metrics = Some(self.calculate_metrics(settings));
+        }
+    }
+
+    fn calculate_metrics(&self, settings: OrderBookMetricsSettings) -> MaintainedMetrics {
+        let asks_vwap = self.take_amount(OrderSide::Sell, settings.vwap_amount);
+        let bids_vwap = self.take_amount(OrderSide::Buy, settings.vwap_amount);
+
+        let depth_bounds = self
+            .get_mid_price()
+            .map(|mid_price| Self::depth_bounds(mid_price, settings.depth_bps));
+
+        let side_reach = |book_side, vwap: Option<(Price, Price)>, depth_bound| {
+            let levels_count = settings.imbalance_levels_count.max(1);
+            let imbalance_reach = self.get_price_levels(book_side).nth(levels_count - 1);
+            let is_deeper = |x: Price, y: Price| match book_side {
+                OrderSide::Buy => x < y,
+                OrderSide::Sell => x > y,
+            };
+
+            [
+                imbalance_reach.map(|(&price, _)| price),
+                vwap.map(|(_, last_price)| last_price),
+                depth_bound,
+            ]
+            .into_iter()
+            .try_fold(None, |deepest: Option<Price>, price| {
+                let price = price?;
+                Some(Some(match deepest {
+                    Some(deepest) if !is_deeper(price, deepest) => deepest,
+                    _ => price,
+                }))
+            })
+            .flatten()
+        };
+
+        MaintainedMetrics {
+            settings,
+            metrics: OrderBookMetrics {
+                asks_depth: self.get_depth_within_bps(OrderSide::Sell, settings.depth_bps),
+                bids_depth: self.get_depth_within_bps(OrderSide::Buy, settings.depth_bps),
+                imbalance: self.get_imbalance(settings.imbalance_levels_count),
+                microprice: self.get_microprice(),
+                asks_vwap: asks_vwap.map(|(vwap, _)| vwap),
+                bids_vwap: bids_vwap.map(|(vwap, _)| vwap),
+            },
+            asks_reach: side_reach(OrderSide::Sell, asks_vwap, depth_bounds.map(|x| x.1)),
+            bids_reach: side_reach(OrderSide::Buy, bids_vwap, depth_bounds.map(|x| x.0)),
+        }
     }
 
     /// Return value with minimum price
@@ -93,7 +268,7 @@ impl LocalOrderBookSnapshot {
     fn try_remove_order(&mut self, order: DataToExcludeOrder) {
         let book_side = self.get_order_book_side(order.side);
 
-        if let Some(amount) = book_side.get(&order.price) {
+        if let Some(&amount) = book_side.get(&order.price) {
             let new_amount = amount - order.amount;
 
             if new_amount.is_sign_negative() || new_amount.is_zero() {
@@ -101,6 +276,12 @@ impl LocalOrderBookSnapshot {
             } else {
                 let _ = book_side.insert(order.price, new_amount);
             }
+
+            let removed_amount = amount - new_amount.max(Decimal::ZERO);
+            match order.side {
+                OrderSide::Buy => self.bids_total_amount -= removed_amount,
+                OrderSide::Sell => self.asks_total_amount -= removed_amount,
+            }
         }
     }
 
@@ -111,6 +292,14 @@ impl LocalOrderBookSnapshot {
         }
     }
 
+    pub fn get_asks(&self) -> &SortedOrderData {
+        &self.asks
+    }
+
+    pub fn get_bids(&self) -> &SortedOrderData {
+        &self.bids
+    }
+
     pub fn get_top_prices(&self) -> PriceByOrderSide {
         let top_bid = self.get_top_bid().map(|(price, _)| price);
         let top_ask = self.get_top_ask().map(|(price, _)| price);
@@ -118,6 +307,111 @@ impl LocalOrderBookSnapshot {
         PriceByOrderSide::new(top_bid, top_ask)
     }
 
+    /// Return levels of asks or bids starting from the top
+    pub fn get_price_levels(
+        &self,
+        book_side: OrderSide,
+    ) -> Box<dyn Iterator<Item = (&Price, &Amount)> + '_> {
+        match book_side {
+            OrderSide::Buy => Box::new(self.get_bids_price_levels()),
+            OrderSide::Sell => Box::new(self.get_asks_price_levels()),
+        }
+    }
+
+    /// Sum of amounts of all levels of asks or bids
+    pub fn get_total_amount(&self, book_side: OrderSide) -> Amount {
+        match book_side {
+            OrderSide::Buy => self.bids_total_amount,
+            OrderSide::Sell => self.asks_total_amount,
+        }
+    }
+
+    pub fn get_spread(&self) -> Option<Price> {
+        Some(self.get_top_ask()?.0 - self.get_top_bid()?.0)
+    }
+
+    pub fn get_spread_statistics(&self) -> &SpreadStatistics {
+        &self.spread_statistics
+    }
+
+    fn get_mid_price(&self) -> Option<Price> {
+        Some((self.get_top_ask()?.0 + self.get_top_bid()?.0) * dec!(0.5))
+    }
+
+    /// Lowest and highest prices within `bps` basis points from `mid_price`
+    fn depth_bounds(mid_price: Price, bps: Decimal) -> (Price, Price) {
+        let deviation = mid_price * bps / dec!(10000);
+        (mid_price - deviation, mid_price + deviation)
+    }
+
+    /// Cumulative amount of asks or bids with prices within `bps` basis points from mid price
+    pub fn get_depth_within_bps(&self, book_side: OrderSide, bps: Decimal) -> Option<Amount> {
+        let (low, high) = Self::depth_bounds(self.get_mid_price()?, bps);
+
+        let depth = self
+            .get_price_levels(book_side)
+            .take_while(|(&price, _)| low <= price && price <= high)
+            .map(|(_, amount)| amount)
+            .sum();
+
+        Some(depth)
+    }
+
+    /// `(bids amount - asks amount) / (bids amount + asks amount)` within `levels_count` top levels.
+    /// Positive value means buying pressure
+    pub fn get_imbalance(&self, levels_count: usize) -> Option<Decimal> {
+        let sum_amount = |book_side| -> Amount {
+            self.get_price_levels(book_side)
+                .take(levels_count)
+                .map(|(_, amount)| amount)
+                .sum()
+        };
+
+        let bids_amount = sum_amount(OrderSide::Buy);
+        let asks_amount = sum_amount(OrderSide::Sell);
+        let total_amount = bids_amount + asks_amount;
+
+        (!total_amount.is_zero()).then(|| (bids_amount - asks_amount) / total_amount)
+    }
+
+    /// Mid price weighted by amounts of top levels: it moves towards top ask when bids amount
+    /// is bigger and vice versa
+    pub fn get_microprice(&self) -> Option<Price> {
+        let (ask_price, ask_amount) = self.get_top_ask()?;
+        let (bid_price, bid_amount) = self.get_top_bid()?;
+
+        let total_amount = ask_amount + bid_amount;
+        (!total_amount.is_zero())
+            .then(|| (ask_price * bid_amount + bid_price * ask_amount) / total_amount)
+    }
+
+    /// Average price of taking `amount` from asks or bids starting from the top.
+    /// Returns `None` if there isn't enough amount in order book
+    pub fn get_vwap(&self, book_side: OrderSide, amount: Amount) -> Option<Price> {
+        self.take_amount(book_side, amount).map(|(vwap, _)| vwap)
+    }
+
+    /// Average price and price of the last level taken
+    fn take_amount(&self, book_side: OrderSide, amount: Amount) -> Option<(Price, Price)> {
+        if amount <= Decimal::ZERO {
+            return None;
+        }
+
+        let mut remaining_amount = amount;
+        let mut cost = Decimal::ZERO;
+        for (&price, &level_amount) in self.get_price_levels(book_side) {
+            let taken_amount = remaining_amount.min(level_amount);
+            cost += price * taken_amount;
+            remaining_amount -= taken_amount;
+
+            if remaining_amount.is_zero() {
+                return Some((cost / amount, price));
+            }
+        }
+
+        None
+    }
+
     pub fn calculate_middle_price(&self, market_id: MarketId) -> Option<Price> {
         let prices = self.get_top_prices();
         let top_ask = match prices.top_ask {
@@ -314,4 +608,105 @@ mod tests {
         // Still exists
         assert_eq!(asks.next().expect("in test"), (&dec!(3.0), &dec!(4.2)));
     }
+
+    fn analytics_snapshot() -> LocalOrderBookSnapshot {
+        let mut asks = SortedOrderData::new();
+        asks.insert(dec!(101), dec!(1));
+        asks.insert(dec!(102), dec!(2));
+        asks.insert(dec!(110), dec!(5));
+        let mut bids = SortedOrderData::new();
+        bids.insert(dec!(99), dec!(3));
+        bids.insert(dec!(98), dec!(1));
+
+        LocalOrderBookSnapshot::new(asks, bids, Utc::now())
+    }
+
+    #[test]
+    fn totals_and_spread_statistics_are_updated_incrementally() {
+        let mut snapshot = analytics_snapshot();
+        assert_eq!(snapshot.get_total_amount(OrderSide::Sell), dec!(8));
+        assert_eq!(snapshot.get_total_amount(OrderSide::Buy), dec!(4));
+
+        let mut update = OrderBookData::default();
+        update.asks.insert(dec!(101), dec!(0));
+        update.asks.insert(dec!(102), dec!(0.5));
+        update.bids.insert(dec!(100), dec!(2));
+        snapshot.apply_update(&update, Utc::now());
+
+        snapshot.exclude_orders([DataToExcludeOrder::new(dec!(99), dec!(5), OrderSide::Buy)]);
+
+        assert_eq!(snapshot.get_total_amount(OrderSide::Sell), dec!(5.5));
+        assert_eq!(snapshot.get_total_amount(OrderSide::Buy), dec!(3));
+
+        let spread_statistics = snapshot.get_spread_statistics();
+        assert_eq!(spread_statistics.last, Some(dec!(2)));
+        assert_eq!(spread_statistics.min, Some(dec!(2)));
+        assert_eq!(spread_statistics.max, Some(dec!(2)));
+        assert_eq!(spread_statistics.updates_count, 2);
+        assert_eq!(spread_statistics.average(), Some(dec!(2)));
+    }
+
+    #[test]
+    fn depth_imbalance_microprice_and_vwap() {
+        let snapshot = analytics_snapshot();
+
+        // Mid price is 100, so 200 bps is [98, 102]
+        assert_eq!(
+            snapshot.get_depth_within_bps(OrderSide::Sell, dec!(200)),
+            Some(dec!(3))
+        );
+        assert_eq!(
+            snapshot.get_depth_within_bps(OrderSide::Buy, dec!(100)),
+            Some(dec!(3))
+        );
+
+        assert_eq!(snapshot.get_imbalance(1), Some(dec!(0.5)));
+        assert_eq!(snapshot.get_imbalance(2), Some(dec!(1) / dec!(7)));
+
+        assert_eq!(snapshot.get_microprice(), Some(dec!(100.5)));
+
+        assert_eq!(
+            snapshot.get_vwap(OrderSide::Sell, dec!(2)),
+            Some(dec!(101.5))
+        );
+        assert_eq!(
+            snapshot.get_vwap(OrderSide::Buy, dec!(4)),
+            Some(dec!(98.75))
+        );
+        assert_eq!(snapshot.get_vwap(OrderSide::Buy, dec!(5)), None);
+    }
+
+    #[test]
+    fn metrics_are_updated_only_when_reached() {
+        let settings = OrderBookMetricsSettings {
+            depth_bps: dec!(200),
+            imbalance_levels_count: 1,
+            vwap_amount: dec!(2),
+        };
+        let mut snapshot = analytics_snapshot();
+        snapshot.set_metrics_settings(settings);
+
+        let metrics = *snapshot.get_metrics().expect("in test");
+        assert_eq!(metrics.asks_depth, Some(dec!(3)));
+        assert_eq!(metrics.imbalance, Some(dec!(0.5)));
+        assert_eq!(metrics.microprice, Some(dec!(100.5)));
+        assert_eq!(metrics.asks_vwap, Some(dec!(101.5)));
+
+        // Levels deeper than depth band, imbalance levels and VWAP amount don't affect metrics
+        let mut deep_update = OrderBookData::default();
+        deep_update.asks.insert(dec!(110), dec!(1));
+        deep_update.bids.insert(dec!(90), dec!(7));
+        snapshot.apply_update(&deep_update, Utc::now());
+        assert_eq!(snapshot.get_metrics(), Some(&metrics));
+
+        let mut near_update = OrderBookData::default();
+        near_update.asks.insert(dec!(101), dec!(3));
+        near_update.bids.insert(dec!(98), dec!(2));
+        snapshot.apply_update(&near_update, Utc::now());
+
+        let mut expected = snapshot.clone();
+        expected.set_metrics_settings(settings);
+        assert_ne!(snapshot.get_metrics(), Some(&metrics));
+        assert_eq!(snapshot.get_metrics(), expected.get_metrics());
+    }
 }
@@ -1,3 +1,4 @@
 pub mod event;
+pub mod local_l3_order_book;
 pub mod local_order_book_snapshot;
 pub mod order_book_data;
@@ -20,27 +20,44 @@ use anyhow::Result;
 use binance::binance::BinanceBuilder;
 use itertools::Itertools;
 use mmb_core::config::{CONFIG_PATH, CREDENTIALS_PATH};
+use mmb_core::disposition_execution::wasm_plugin::{WasmStrategy, WasmStrategySettings};
 use mmb_core::lifecycle::app_lifetime_manager::ActionAfterGracefulShutdown;
 use mmb_core::lifecycle::launcher::{launch_trading_engine, EngineBuildConfig, InitSettings};
 use mmb_core::settings::BaseStrategySettings;
 use std::env;
 use strategies::example_strategy::{ExampleStrategy, ExampleStrategySettings};
+use strategies::scripted_strategy::{ScriptedStrategy, ScriptedStrategySettings};
+
+const SCRIPTED_CONFIG_PATH: &str = "config_scripted.toml";
+const WASM_CONFIG_PATH: &str = "config_wasm.toml";
+
+enum Demo {
+    Spot,
+    Futures,
+    /// Strategy from Lua script
+    Scripted,
+    /// Strategy from WebAssembly plugin
+    Wasm,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let engine_config = EngineBuildConfig::new(vec![Box::new(BinanceBuilder)]);
+    match get_demo() {
+        Demo::Spot => run_example_strategy(CONFIG_PATH, CREDENTIALS_PATH).await,
+        Demo::Futures => {
+            run_example_strategy("config_futures.toml", "credentials_futures.toml").await
+        }
+        Demo::Scripted => run_scripted_strategy().await,
+        Demo::Wasm => run_wasm_strategy().await,
+    }
+}
 
-    let (config_path, credentials_path) = match is_futures_demo() {
-        true => (
-            "config_futures.toml".to_owned(),
-            "credentials_futures.toml".to_owned(),
-        ),
-        false => (CONFIG_PATH.to_owned(), CREDENTIALS_PATH.to_owned()),
-    };
+async fn run_example_strategy(config_path: &str, credentials_path: &str) -> Result<()> {
+    let engine_config = EngineBuildConfig::new(vec![Box::new(BinanceBuilder)]);
 
     let init_settings = InitSettings::<ExampleStrategySettings>::Load {
-        config_path,
-        credentials_path,
+        config_path: config_path.to_owned(),
+        credentials_path: credentials_path.to_owned(),
     };
     loop {
         let engine = launch_trading_engine(&engine_config, init_settings.clone()).await?;
@@ -64,8 +81,58 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn is_futures_demo() -> bool {
+async fn run_scripted_strategy() -> Result<()> {
+    let engine_config = EngineBuildConfig::new(vec![Box::new(BinanceBuilder)]);
+
+    let init_settings = InitSettings::<ScriptedStrategySettings>::Load {
+        config_path: SCRIPTED_CONFIG_PATH.to_owned(),
+        credentials_path: CREDENTIALS_PATH.to_owned(),
+    };
+    loop {
+        let engine = launch_trading_engine(&engine_config, init_settings.clone()).await?;
+
+        let strategy = ScriptedStrategy::new(&engine.settings().strategy, engine.context())?;
+        engine.start_disposition_executor(strategy);
+
+        match engine.run().await {
+            ActionAfterGracefulShutdown::Nothing => break,
+            ActionAfterGracefulShutdown::Restart => continue,
+        }
+    }
+    Ok(())
+}
+
+async fn run_wasm_strategy() -> Result<()> {
+    let engine_config = EngineBuildConfig::new(vec![Box::new(BinanceBuilder)]);
+
+    let init_settings = InitSettings::<WasmStrategySettings>::Load {
+        config_path: WASM_CONFIG_PATH.to_owned(),
+        credentials_path: CREDENTIALS_PATH.to_owned(),
+    };
+    loop {
+        let engine = launch_trading_engine(&engine_config, init_settings.clone()).await?;
+
+        let strategy = WasmStrategy::new(&engine.settings().strategy, engine.context())?;
+        engine.start_disposition_executor(strategy);
+
+        match engine.run().await {
+            ActionAfterGracefulShutdown::Nothing => break,
+            ActionAfterGracefulShutdown::Restart => continue,
+        }
+    }
+    Ok(())
+}
+
+fn get_demo() -> Demo {
     let args = env::args().collect_vec();
+    if args.len() != 2 {
+        return Demo::Spot;
+    }
 
-    2 == args.len() && "--futures" == args[1]
+    match args[1].as_str() {
+        "--futures" => Demo::Futures,
+        "--scripted" => Demo::Scripted,
+        "--wasm" => Demo::Wasm,
+        _ => Demo::Spot,
+    }
 }
@@ -0,0 +1,4 @@
+DROP INDEX price_sources__market_idx;
+
+ALTER INDEX price_sources__insert_time_idx RENAME TO prices_sources__insert_time_idx;
+ALTER TABLE price_sources RENAME TO prices_sources;
@@ -0,0 +1,5 @@
+-- Events of PriceSourceModel are saved to `price_sources` table
+ALTER TABLE prices_sources RENAME TO price_sources;
+ALTER INDEX prices_sources__insert_time_idx RENAME TO price_sources__insert_time_idx;
+
+CREATE INDEX price_sources__market_idx ON price_sources USING btree ((json ->> 'exchange_id'), (json ->> 'currency_pair'));
@@ -0,0 +1,11 @@
+CREATE TABLE audit_events (
+    id bigint PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    insert_time timestamp WITH TIME ZONE NOT NULL DEFAULT now(),
+    version int,
+    json jsonb NOT NULL
+);
+
+CREATE INDEX audit_events__insert_time_idx ON audit_events USING btree (insert_time);
+CREATE INDEX audit_events__order_client_order_id_idx ON audit_events USING btree (((json #>> '{record, order, header, client_order_id}')::text));
+CREATE INDEX audit_events__fill_exchange_order_id_idx ON audit_events USING btree (((json #>> '{record, fill, exchange_order_id}')::text));
+CREATE INDEX audit_events__reservation_id_idx ON audit_events USING btree (((json #>> '{record, reservation_id}')::text));
@@ -0,0 +1,36 @@
+CREATE FUNCTION unpartition_event_table(table_name text) RETURNS void AS $$
+BEGIN
+    EXECUTE format('ALTER TABLE %I RENAME TO %I', table_name, table_name || '_partitioned');
+    EXECUTE format('ALTER INDEX %I RENAME TO %I', table_name || '_pkey', table_name || '_partitioned_pkey');
+    EXECUTE format('ALTER SEQUENCE %I RENAME TO %I', table_name || '_id_seq', table_name || '_partitioned_id_seq');
+
+    EXECUTE format('CREATE TABLE %I (
+        id bigint PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+        insert_time timestamp WITH TIME ZONE NOT NULL DEFAULT now(),
+        version int,
+        json jsonb NOT NULL
+    )', table_name);
+
+    EXECUTE format('INSERT INTO %I SELECT id, insert_time, version, json FROM %I', table_name, table_name || '_partitioned');
+    EXECUTE format('SELECT setval(%L, COALESCE(max(id), 0) + 1, false) FROM %I', table_name || '_id_seq', table_name);
+    EXECUTE format('DROP TABLE %I CASCADE', table_name || '_partitioned');
+END
+$$ LANGUAGE plpgsql;
+
+SELECT unpartition_event_table('liquidity_order_books');
+CREATE INDEX liquidity_order_books__insert_time_idx ON liquidity_order_books USING btree (insert_time);
+CREATE INDEX liquidity_order_books__exchange_id_idx ON liquidity_order_books USING btree (((json ->> 'exchange_id')::text));
+CREATE INDEX liquidity_order_books__currency_pair_idx ON liquidity_order_books USING btree (((json ->> 'currency_pair')::text));
+
+SELECT unpartition_event_table('disposition_explanations');
+CREATE INDEX disposition_explanations__insert_time_idx ON disposition_explanations USING btree (insert_time);
+CREATE INDEX disposition_explanations__exchange_id_idx ON disposition_explanations ((json ->> 'exchange_id'::text));
+CREATE INDEX disposition_explanations__currency_pair_idx ON disposition_explanations ((json ->> 'currency_pair'::text));
+
+SELECT unpartition_event_table('balances');
+CREATE INDEX balances__insert_time_idx ON balances USING btree (insert_time);
+CREATE INDEX balances__market_id_exchange_id_idx ON balances USING btree (((json #>> '{market_id, exchange_id}')::text));
+CREATE INDEX balances__market_id_currency_pair_idx ON balances USING btree (((json #>> '{market_id, currency_pair}')::text));
+CREATE INDEX balances__balance_creation_time_idx ON balances USING btree (((json ->> 'balance_creation_time')::text));
+
+DROP FUNCTION unpartition_event_table(text);
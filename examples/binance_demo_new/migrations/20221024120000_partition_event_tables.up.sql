@@ -0,0 +1,39 @@
+-- Event tables with high insert rate are partitioned by day of `insert_time`.
+-- Rows which don't fit to any daily partition are stored in `<table>_default`
+-- and moved to daily partitions by the maintenance service
+CREATE FUNCTION partition_event_table(table_name text) RETURNS void AS $$
+BEGIN
+    EXECUTE format('ALTER TABLE %I RENAME TO %I', table_name, table_name || '_unpartitioned');
+    EXECUTE format('ALTER INDEX %I RENAME TO %I', table_name || '_pkey', table_name || '_unpartitioned_pkey');
+    EXECUTE format('ALTER SEQUENCE %I RENAME TO %I', table_name || '_id_seq', table_name || '_unpartitioned_id_seq');
+
+    EXECUTE format('CREATE TABLE %I (
+        id bigint GENERATED BY DEFAULT AS IDENTITY,
+        insert_time timestamp WITH TIME ZONE NOT NULL DEFAULT now(),
+        version int,
+        json jsonb NOT NULL,
+        PRIMARY KEY (id, insert_time)
+    ) PARTITION BY RANGE (insert_time)', table_name);
+    EXECUTE format('CREATE TABLE %I PARTITION OF %I DEFAULT', table_name || '_default', table_name);
+
+    EXECUTE format('INSERT INTO %I SELECT id, insert_time, version, json FROM %I', table_name, table_name || '_unpartitioned');
+    EXECUTE format('SELECT setval(%L, COALESCE(max(id), 0) + 1, false) FROM %I', table_name || '_id_seq', table_name);
+    EXECUTE format('DROP TABLE %I', table_name || '_unpartitioned');
+END
+$$ LANGUAGE plpgsql;
+
+SELECT partition_event_table('liquidity_order_books');
+CREATE INDEX liquidity_order_books__insert_time_idx ON liquidity_order_books USING btree (insert_time);
+CREATE INDEX liquidity_order_books__exchange_id_idx ON liquidity_order_books USING btree (((json ->> 'exchange_id')::text));
+CREATE INDEX liquidity_order_books__currency_pair_idx ON liquidity_order_books USING btree (((json ->> 'currency_pair')::text));
+
+SELECT partition_event_table('disposition_explanations');
+CREATE INDEX disposition_explanations__insert_time_idx ON disposition_explanations USING btree (insert_time);
+
+SELECT partition_event_table('balances');
+CREATE INDEX balances__insert_time_idx ON balances USING btree (insert_time);
+CREATE INDEX balances__market_id_exchange_id_idx ON balances USING btree (((json #>> '{market_id, exchange_id}')::text));
+CREATE INDEX balances__market_id_currency_pair_idx ON balances USING btree (((json #>> '{market_id, currency_pair}')::text));
+CREATE INDEX balances__balance_creation_time_idx ON balances USING btree (((json ->> 'balance_creation_time')::text));
+
+DROP FUNCTION partition_event_table(text);
@@ -0,0 +1 @@
+DROP TABLE candles;
\ No newline at end of file
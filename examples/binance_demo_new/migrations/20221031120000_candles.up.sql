@@ -0,0 +1,11 @@
+CREATE TABLE candles (
+    id bigint PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    insert_time timestamp WITH TIME ZONE NOT NULL DEFAULT now(),
+    version int,
+    json jsonb NOT NULL
+);
+
+CREATE INDEX candles__insert_time_idx ON candles USING btree (insert_time);
+CREATE INDEX candles__market_id_exchange_id_idx ON candles USING btree (((json #>> '{market_id, exchange_id}')::text));
+CREATE INDEX candles__market_id_currency_pair_idx ON candles USING btree (((json #>> '{market_id, currency_pair}')::text));
+CREATE INDEX candles__interval_secs_idx ON candles USING btree (((json ->> 'interval_secs')::text));
@@ -0,0 +1,2 @@
+DROP INDEX disposition_explanations__exchange_id_idx;
+DROP INDEX disposition_explanations__currency_pair_idx;
@@ -0,0 +1,2 @@
+CREATE INDEX disposition_explanations__exchange_id_idx ON disposition_explanations ((json ->> 'exchange_id'::text));
+CREATE INDEX disposition_explanations__currency_pair_idx ON disposition_explanations ((json ->> 'currency_pair'::text));
@@ -0,0 +1,70 @@
+#![deny(
+    non_ascii_idents,
+    non_shorthand_field_patterns,
+    no_mangle_generic_items,
+    overflowing_literals,
+    path_statements,
+    unused_allocation,
+    unused_comparisons,
+    unused_parens,
+    while_true,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use,
+    clippy::unwrap_used
+)]
+
+//! Rebuilds state of the engine from audit log.
+//!
+//! Usage:
+//!   audit_replay <database_url> order <client_order_id>
+//!   audit_replay <database_url> balances <rfc3339 time>
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use mmb_core::database::events::audit::{load_balances_at, load_order_timeline};
+use mmb_database::postgres_db::PgPool;
+use mmb_domain::order::snapshot::ClientOrderId;
+
+const USAGE: &str = "Usage:
+  audit_replay <database_url> order <client_order_id>
+  audit_replay <database_url> balances <rfc3339 time>";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (database_url, command, argument) = match args.as_slice() {
+        [database_url, command, argument] => (database_url, command, argument),
+        _ => bail!(USAGE),
+    };
+
+    let pool = PgPool::create(database_url, 1)
+        .await
+        .context("connecting to database")?;
+
+    match command.as_str() {
+        "order" => {
+            let timeline = load_order_timeline(&pool, &ClientOrderId::from(argument.as_str()))
+                .await
+                .context("rebuilding order timeline")?;
+            println!("{}", serde_json::to_string_pretty(&timeline)?);
+        }
+        "balances" => {
+            let time = chrono::DateTime::parse_from_rfc3339(argument)
+                .with_context(|| format!("parsing time {argument}"))?
+                .with_timezone(&Utc);
+            match load_balances_at(&pool, time)
+                .await
+                .context("rebuilding balances")?
+            {
+                None => println!("There are no saved balances before {time}"),
+                Some(balances) => println!("{}", serde_json::to_string_pretty(&balances)?),
+            }
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
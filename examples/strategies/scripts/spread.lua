@@ -0,0 +1,28 @@
+-- Places orders around the middle of the order book with fixed spread,
+-- the same logic as in ExampleStrategy.
+-- The script is reloaded by ScriptedStrategy after the file was saved.
+
+local SPREAD = 1000
+local AMOUNT = "0.001"
+
+function calculate(market)
+    local top_ask = market.asks[1]
+    local top_bid = market.bids[1]
+    if top_ask == nil or top_bid == nil then
+        return { buy = {}, sell = {} }
+    end
+
+    local buy_price = top_bid.price
+    local sell_price = top_ask.price
+    if top_ask.price - top_bid.price < SPREAD then
+        local middle = (top_ask.price + top_bid.price) / 2
+        buy_price = middle - SPREAD / 2
+        sell_price = middle + SPREAD / 2
+    end
+
+    local reason = "spread " .. SPREAD
+    return {
+        buy = { { price = buy_price, amount = AMOUNT, reason = reason } },
+        sell = { { price = sell_price, amount = AMOUNT, reason = reason } },
+    }
+end
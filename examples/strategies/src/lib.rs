@@ -17,3 +17,4 @@
 )]
 
 pub mod example_strategy;
+pub mod scripted_strategy;
@@ -0,0 +1,335 @@
+pub mod script;
+
+use crate::scripted_strategy::script::{MarketView, ScriptLevel, ScriptLimits, StrategyScript};
+use anyhow::Result;
+use mmb_core::disposition_execution::strategy::DispositionStrategy;
+use mmb_core::disposition_execution::{
+    PriceSlot, TradeCycle, TradeDisposition, TradingContext, TradingContextBySide,
+    PRICE_SLOTS_COUNT,
+};
+use mmb_core::explanation::{Explanation, WithExplanation};
+use mmb_core::lifecycle::trading_engine::EngineContext;
+use mmb_core::order_book::local_snapshot_service::LocalSnapshotsService;
+use mmb_core::service_configuration::configuration_descriptor::ConfigurationDescriptor;
+use mmb_core::settings::{BaseStrategySettings, CurrencyPairSetting};
+use mmb_domain::events::{ExchangeEvent, Trade};
+use mmb_domain::exchanges::symbol::{Round, Symbol};
+use mmb_domain::market::{CurrencyPair, ExchangeAccountId, MarketAccountId, MarketId};
+use mmb_domain::order::snapshot::{Amount, OrderRole, OrderSide, OrderSnapshot};
+use mmb_utils::cancellation_token::CancellationToken;
+use mmb_utils::infrastructure::WithExpect;
+use mmb_utils::DateTime;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_ORDER_BOOK_DEPTH: usize = 20;
+const DEFAULT_RECENT_TRADES_COUNT: usize = 100;
+const DEFAULT_MAX_INSTRUCTIONS: u32 = 1_000_000;
+const DEFAULT_MAX_MEMORY: usize = 64 * 1024 * 1024;
+const SCRIPT_CHANGES_CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ScriptedStrategySettings {
+    /// Path to Lua script with global `calculate(market)` function.
+    /// Script is reloaded automatically after the file was changed (it is checked every second)
+    pub script_path: PathBuf,
+    pub currency_pair: CurrencyPairSetting,
+    pub max_amount: Decimal,
+    pub exchange_account_id: ExchangeAccountId,
+    /// Count of order book levels by side passed to script
+    pub order_book_depth: Option<usize>,
+    /// Count of last market trades passed to script
+    pub recent_trades_count: Option<usize>,
+    /// Limit of Lua VM instructions for one script call to avoid hanging on infinite loops
+    pub max_instructions: Option<u32>,
+    /// Limit of memory in bytes for script
+    pub max_memory: Option<usize>,
+}
+
+impl BaseStrategySettings for ScriptedStrategySettings {
+    fn exchange_account_id(&self) -> ExchangeAccountId {
+        self.exchange_account_id
+    }
+
+    fn currency_pair(&self) -> CurrencyPair {
+        if let CurrencyPairSetting::Ordinary { base, quote } = self.currency_pair {
+            CurrencyPair::from_codes(base, quote)
+        } else {
+            panic!(
+                "Incorrect currency pair setting enum type {:?}",
+                self.currency_pair
+            );
+        }
+    }
+
+    fn max_amount(&self) -> Amount {
+        self.max_amount
+    }
+}
+
+/// Strategy that delegates calculation of price levels to Lua script, so pricing logic can be
+/// changed without recompilation.
+///
+/// Script receives read-only view of market:
+/// ```text
+/// {
+///     asks = { { price, amount }, ... },   -- starting from the best price
+///     bids = { { price, amount }, ... },   -- starting from the best price
+///     balances = { <currency code> = amount or nil },
+///     trades = { { price, amount, side = "buy" | "sell", time }, ... },
+///     now,                                 -- unix time in milliseconds
+/// }
+/// ```
+/// and should return price levels by side:
+/// ```text
+/// {
+///     buy = { { price, amount, reason } }, -- `reason` is optional
+///     sell = { { price, amount, reason } },
+/// }
+/// ```
+/// Prices and amounts can be returned as numbers or as strings for exact decimal values.
+/// They are rounded by symbol precision before placing orders.
+pub struct ScriptedStrategy {
+    target_eai: ExchangeAccountId,
+    currency_pair: CurrencyPair,
+    symbol: Arc<Symbol>,
+    engine_context: Arc<EngineContext>,
+    configuration_descriptor: ConfigurationDescriptor,
+    max_amount: Decimal,
+    order_book_depth: usize,
+    recent_trades_count: usize,
+    recent_trades: VecDeque<Trade>,
+    script: StrategyScript,
+}
+
+impl ScriptedStrategy {
+    pub fn new(
+        settings: &ScriptedStrategySettings,
+        engine_context: Arc<EngineContext>,
+    ) -> Result<Box<Self>> {
+        let target_eai = settings.exchange_account_id();
+        let currency_pair = settings.currency_pair();
+        let max_amount = settings.max_amount;
+
+        let mut script = StrategyScript::load(
+            &settings.script_path,
+            ScriptLimits {
+                max_instructions: settings
+                    .max_instructions
+                    .unwrap_or(DEFAULT_MAX_INSTRUCTIONS),
+                max_memory: settings.max_memory.unwrap_or(DEFAULT_MAX_MEMORY),
+            },
+        )?;
+        script.watch_changes(SCRIPT_CHANGES_CHECK_PERIOD);
+
+        let configuration_descriptor = ConfigurationDescriptor::new(
+            Self::strategy_name().into(),
+            format!("{target_eai};{currency_pair}").as_str().into(),
+        );
+
+        let symbol = engine_context
+            .exchanges
+            .get(&target_eai)
+            .with_expect(|| format!("failed to get exchange from trading_engine for {target_eai}"))
+            .symbols
+            .get(&currency_pair)
+            .with_expect(|| format!("failed to get symbol from exchange for {currency_pair}"))
+            .clone();
+
+        // the same limit as in ExampleStrategy: an order can change a position from a limit by
+        // sells to a limit by buys
+        let amount_limit = max_amount * dec!(0.5);
+        engine_context
+            .balance_manager
+            .lock()
+            .set_target_amount_limit(
+                configuration_descriptor,
+                target_eai,
+                symbol.clone(),
+                amount_limit,
+            );
+
+        Ok(Box::new(ScriptedStrategy {
+            target_eai,
+            currency_pair,
+            symbol,
+            engine_context,
+            configuration_descriptor,
+            max_amount,
+            order_book_depth: settings
+                .order_book_depth
+                .unwrap_or(DEFAULT_ORDER_BOOK_DEPTH),
+            recent_trades_count: settings
+                .recent_trades_count
+                .unwrap_or(DEFAULT_RECENT_TRADES_COUNT),
+            recent_trades: VecDeque::new(),
+            script,
+        }))
+    }
+
+    fn strategy_name() -> &'static str {
+        "ScriptedStrategy"
+    }
+
+    fn market_account_id(&self) -> MarketAccountId {
+        MarketAccountId::new(self.target_eai, self.currency_pair)
+    }
+
+    fn market_id(&self) -> MarketId {
+        self.market_account_id().market_id()
+    }
+
+    fn remember_trades(&mut self, event: &ExchangeEvent) {
+        let trades_event = match event {
+            ExchangeEvent::Trades(trades_event) => trades_event,
+            _ => return,
+        };
+
+        if trades_event.exchange_account_id != self.target_eai
+            || trades_event.currency_pair != self.currency_pair
+        {
+            return;
+        }
+
+        self.recent_trades
+            .extend(trades_event.trades.iter().cloned());
+        while self.recent_trades.len() > self.recent_trades_count {
+            let _ = self.recent_trades.pop_front();
+        }
+    }
+
+    fn get_balances(&self) -> Vec<(String, Option<Amount>)> {
+        let balance_manager = self.engine_context.balance_manager.lock();
+        [
+            self.symbol.base_currency_code(),
+            self.symbol.quote_currency_code(),
+        ]
+        .into_iter()
+        .map(|currency_code| {
+            let balance = balance_manager.get_exchange_balance(
+                self.target_eai,
+                self.symbol.clone(),
+                currency_code,
+            );
+            (currency_code.as_str().to_owned(), balance)
+        })
+        .collect()
+    }
+
+    fn to_trading_context_by_side(
+        &self,
+        side: OrderSide,
+        levels: Vec<ScriptLevel>,
+        explanation: &Explanation,
+    ) -> TradingContextBySide {
+        if levels.len() > PRICE_SLOTS_COUNT {
+            log::warn!(
+                "Script {} returned {} levels for {side} side, only {PRICE_SLOTS_COUNT} will be used",
+                self.script.path().display(),
+                levels.len(),
+            );
+        }
+
+        let mut empty_slot_explanation = explanation.clone();
+        empty_slot_explanation.add_reason("Script didn't return price level for slot");
+
+        TradingContextBySide::from_trade_cycles(
+            self.max_amount,
+            levels
+                .into_iter()
+                .take(PRICE_SLOTS_COUNT)
+                .map(|level| self.to_trade_cycle(side, level, explanation.clone())),
+            &empty_slot_explanation,
+        )
+    }
+
+    fn to_trade_cycle(
+        &self,
+        side: OrderSide,
+        level: ScriptLevel,
+        mut explanation: Explanation,
+    ) -> WithExplanation<Option<TradeCycle>> {
+        let price_round = match side {
+            OrderSide::Buy => Round::Floor,
+            OrderSide::Sell => Round::Ceiling,
+        };
+        let price = self.symbol.price_round(level.price, price_round);
+        let amount = self.symbol.amount_round(level.amount, Round::Floor);
+
+        explanation.add_reason(format!(
+            "Script {} returned price {} and amount {} (rounded to {price} and {amount})",
+            self.script.path().display(),
+            level.price,
+            level.amount,
+        ));
+        explanation.add_reason(level.reason);
+
+        WithExplanation {
+            value: Some(TradeCycle {
+                order_role: OrderRole::Maker,
+                strategy_name: Self::strategy_name().to_string(),
+                disposition: TradeDisposition::new(self.market_account_id(), side, price, amount),
+            }),
+            explanation,
+        }
+    }
+}
+
+impl DispositionStrategy for ScriptedStrategy {
+    fn calculate_trading_context(
+        &mut self,
+        event: &ExchangeEvent,
+        now: DateTime,
+        local_snapshots_service: &LocalSnapshotsService,
+        explanation: &mut Explanation,
+    ) -> Option<TradingContext> {
+        self.remember_trades(event);
+        self.script.reload_if_changed();
+
+        let snapshot = local_snapshots_service.get_snapshot(self.market_id())?;
+        let balances = self.get_balances();
+
+        let view = MarketView {
+            snapshot,
+            order_book_depth: self.order_book_depth,
+            balances: &balances,
+            trades: self.recent_trades.make_contiguous(),
+            now,
+        };
+
+        let (buy_levels, sell_levels) = match self.script.calculate(&view) {
+            Ok(levels) => levels,
+            Err(err) => {
+                log::error!(
+                    "Failed to calculate trading context by script {}: {err:?}",
+                    self.script.path().display()
+                );
+                return None;
+            }
+        };
+
+        Some(TradingContext::new(
+            self.to_trading_context_by_side(OrderSide::Buy, buy_levels, explanation),
+            self.to_trading_context_by_side(OrderSide::Sell, sell_levels, explanation),
+        ))
+    }
+
+    fn handle_order_fill(
+        &self,
+        _cloned_order: &Arc<OrderSnapshot>,
+        _price_slot: &PriceSlot,
+        _target_eai: ExchangeAccountId,
+        _cancellation_token: CancellationToken,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn configuration_descriptor(&self) -> ConfigurationDescriptor {
+        self.configuration_descriptor
+    }
+}
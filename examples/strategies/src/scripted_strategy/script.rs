@@ -0,0 +1,473 @@
+use anyhow::{anyhow, bail, Context, Result};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, StdLib, Table, Value};
+use mmb_core::infrastructure::spawn_by_timer;
+use mmb_domain::events::Trade;
+use mmb_domain::order::snapshot::{Amount, OrderSide, Price};
+use mmb_domain::order_book::local_order_book_snapshot::LocalOrderBookSnapshot;
+use mmb_utils::infrastructure::{FutureOutcome, SpawnFutureFlags};
+use mmb_utils::DateTime;
+use parking_lot::Mutex;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// Name of the global function which script should define to calculate price levels
+const CALCULATE_FN_NAME: &str = "calculate";
+
+/// Read-only data passed to the script on every trading context calculation
+pub struct MarketView<'a> {
+    pub snapshot: &'a LocalOrderBookSnapshot,
+    pub order_book_depth: usize,
+    pub balances: &'a [(String, Option<Amount>)],
+    pub trades: &'a [Trade],
+    pub now: DateTime,
+}
+
+impl MarketView<'_> {
+    fn to_lua_table<'lua>(&self, lua: &'lua Lua) -> mlua::Result<Table<'lua>> {
+        let price_levels = |levels: &mut dyn Iterator<Item = (&Price, &Amount)>| {
+            let table = lua.create_table()?;
+            for (&price, &amount) in levels.take(self.order_book_depth) {
+                let level = lua.create_table()?;
+                level.set("price", to_number(price))?;
+                level.set("amount", to_number(amount))?;
+                table.push(level)?;
+            }
+            Ok::<_, mlua::Error>(table)
+        };
+
+        let balances = lua.create_table()?;
+        for (currency_code, balance) in self.balances {
+            balances.set(currency_code.as_str(), balance.map(to_number))?;
+        }
+
+        let trades = lua.create_table()?;
+        for trade in self.trades {
+            let value = lua.create_table()?;
+            value.set("price", to_number(trade.price))?;
+            value.set("amount", to_number(trade.quantity))?;
+            value.set("side", side_name(trade.side))?;
+            value.set("time", trade.transaction_time.timestamp_millis())?;
+            trades.push(value)?;
+        }
+
+        let view = lua.create_table()?;
+        view.set(
+            "asks",
+            price_levels(&mut self.snapshot.get_asks_price_levels())?,
+        )?;
+        view.set(
+            "bids",
+            price_levels(&mut self.snapshot.get_bids_price_levels())?,
+        )?;
+        view.set("balances", balances)?;
+        view.set("trades", trades)?;
+        view.set("now", self.now.timestamp_millis())?;
+        Ok(view)
+    }
+}
+
+fn to_number(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
+
+fn side_name(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+/// Price level returned by the script for one side of the order book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLevel {
+    pub price: Price,
+    pub amount: Amount,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptLimits {
+    /// Max count of Lua VM instructions for one script call
+    pub max_instructions: u32,
+    /// Max memory in bytes which Lua state can allocate
+    pub max_memory: usize,
+}
+
+/// Modification time of script file which is checked by timer outside of trading context calculation
+struct ScriptFileState {
+    modified: Mutex<Option<SystemTime>>,
+    is_changed: AtomicBool,
+}
+
+impl ScriptFileState {
+    fn check_changes(&self, path: &Path) {
+        let modified = get_modified_time(path);
+        let mut last_modified = self.modified.lock();
+        if modified != *last_modified {
+            *last_modified = modified;
+            self.is_changed.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Wrapper over Lua state with loaded script that reloads it when the file on disk was changed.
+/// Lua state has only `table`, `string` and `math` libraries, so script can't access
+/// file system or OS
+pub struct StrategyScript {
+    lua: Mutex<Lua>,
+    path: PathBuf,
+    limits: ScriptLimits,
+    file_state: Arc<ScriptFileState>,
+    changes_watcher: Option<JoinHandle<FutureOutcome>>,
+}
+
+impl StrategyScript {
+    pub fn load(path: &Path, limits: ScriptLimits) -> Result<Self> {
+        let modified = get_modified_time(path);
+        let lua = create_lua(path, limits)?;
+
+        Ok(StrategyScript {
+            lua: Mutex::new(lua),
+            path: path.to_path_buf(),
+            limits,
+            file_state: Arc::new(ScriptFileState {
+                modified: Mutex::new(modified),
+                is_changed: AtomicBool::new(false),
+            }),
+            changes_watcher: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Start timer which checks modification time of script file with `period`.
+    /// Timer is stopped when script is dropped
+    pub fn watch_changes(&mut self, period: Duration) {
+        let path = self.path.clone();
+        let file_state = self.file_state.clone();
+        let changes_watcher = spawn_by_timer(
+            "Checking changes of strategy script",
+            period,
+            period,
+            SpawnFutureFlags::STOP_BY_TOKEN | SpawnFutureFlags::DENY_CANCELLATION,
+            move || {
+                file_state.check_changes(&path);
+                async {}
+            },
+        );
+
+        if let Some(previous_watcher) = self.changes_watcher.replace(changes_watcher) {
+            previous_watcher.abort();
+        }
+    }
+
+    /// Reload script if its file was modified according to the last check of `watch_changes` timer.
+    /// The previous version of script stays active if the new one can't be loaded
+    pub fn reload_if_changed(&mut self) {
+        if !self.file_state.is_changed.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        match create_lua(&self.path, self.limits) {
+            Ok(lua) => {
+                log::info!("Strategy script {} reloaded", self.path.display());
+                *self.lua.get_mut() = lua;
+            }
+            Err(err) => log::error!(
+                "Failed to reload strategy script {}, previous version is used: {err:?}",
+                self.path.display()
+            ),
+        }
+    }
+
+    /// Call `calculate` function of script and parse returned price levels for both sides
+    pub fn calculate(&self, view: &MarketView) -> Result<(Vec<ScriptLevel>, Vec<ScriptLevel>)> {
+        let lua = self.lua.lock();
+
+        let max_instructions = self.limits.max_instructions;
+        lua.set_hook(
+            HookTriggers {
+                every_nth_instruction: Some(max_instructions),
+                ..Default::default()
+            },
+            move |_, _| {
+                Err(mlua::Error::RuntimeError(format!(
+                    "script exceeded limit of {max_instructions} instructions"
+                )))
+            },
+        )?;
+
+        let result = call_calculate(&lua, view);
+        lua.remove_hook();
+        result
+    }
+}
+
+impl Drop for StrategyScript {
+    fn drop(&mut self) {
+        if let Some(changes_watcher) = self.changes_watcher.take() {
+            changes_watcher.abort();
+        }
+    }
+}
+
+fn call_calculate(lua: &Lua, view: &MarketView) -> Result<(Vec<ScriptLevel>, Vec<ScriptLevel>)> {
+    let calculate: Function = lua
+        .globals()
+        .get(CALCULATE_FN_NAME)
+        .with_context(|| format!("Script should define function '{CALCULATE_FN_NAME}'"))?;
+
+    let result: Table = calculate
+        .call(view.to_lua_table(lua)?)
+        .with_context(|| format!("Failed to call '{CALCULATE_FN_NAME}' in script"))?;
+
+    let buy = parse_levels(result.get("buy")?).context("Failed to parse 'buy' levels")?;
+    let sell = parse_levels(result.get("sell")?).context("Failed to parse 'sell' levels")?;
+    Ok((buy, sell))
+}
+
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+fn create_lua(path: &Path, limits: ScriptLimits) -> Result<Lua> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read script {}", path.display()))?;
+
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+        LuaOptions::default(),
+    )?;
+    let _ = lua.set_memory_limit(limits.max_memory)?;
+
+    lua.load(&source)
+        .set_name(&path.display().to_string())?
+        .exec()
+        .with_context(|| format!("Failed to load script {}", path.display()))?;
+
+    Ok(lua)
+}
+
+fn parse_levels(levels: Option<Table>) -> Result<Vec<ScriptLevel>> {
+    let levels = match levels {
+        None => return Ok(vec![]),
+        Some(levels) => levels,
+    };
+
+    levels
+        .sequence_values::<Table>()
+        .map(|level| {
+            let level = level.context("Price level should be a table")?;
+
+            let price = to_decimal(level.get("price")?).context("Invalid 'price' value")?;
+            let amount = to_decimal(level.get("amount")?).context("Invalid 'amount' value")?;
+            let reason = level.get::<_, Option<String>>("reason")?;
+
+            Ok(ScriptLevel {
+                price,
+                amount,
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// Numbers are converted through their shortest decimal representation to avoid
+/// float artifacts like `0.29999999999999998`. Strings are parsed as is for exact values
+fn to_decimal(value: Value) -> Result<Decimal> {
+    match value {
+        Value::Integer(value) => Ok(Decimal::from(value)),
+        Value::Number(value) if value.is_finite() => Ok(Decimal::from_str(&value.to_string())?),
+        Value::String(value) => Ok(Decimal::from_str(value.to_str()?)?),
+        Value::Nil => bail!("Value is missing"),
+        value => Err(anyhow!("Unsupported value {value:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use mmb_domain::events::TradeId;
+    use mmb_domain::order::snapshot::SortedOrderData;
+    use rust_decimal_macros::dec;
+    use std::ops::Deref;
+
+    const LIMITS: ScriptLimits = ScriptLimits {
+        max_instructions: 100_000,
+        max_memory: 16 * 1024 * 1024,
+    };
+
+    /// Script file in temp dir which is removed when test finishes, even if it fails
+    struct ScriptFile(PathBuf);
+
+    impl Deref for ScriptFile {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for ScriptFile {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for ScriptFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn write_script(name: &str, text: &str) -> ScriptFile {
+        let path = std::env::temp_dir().join(format!("{name}_{}.lua", std::process::id()));
+        fs::write(&path, text).expect("in test");
+        ScriptFile(path)
+    }
+
+    fn snapshot() -> LocalOrderBookSnapshot {
+        let mut asks = SortedOrderData::new();
+        asks.insert(dec!(101), dec!(1));
+        asks.insert(dec!(102), dec!(2));
+        let mut bids = SortedOrderData::new();
+        bids.insert(dec!(99), dec!(3));
+        bids.insert(dec!(98), dec!(4));
+        LocalOrderBookSnapshot::new(asks, bids, Utc::now())
+    }
+
+    fn calculate(script: &StrategyScript) -> Result<(Vec<ScriptLevel>, Vec<ScriptLevel>)> {
+        let snapshot = snapshot();
+        let balances = vec![("btc".to_owned(), Some(dec!(2))), ("usdt".to_owned(), None)];
+        let trades = vec![Trade {
+            trade_id: TradeId::Number(1),
+            price: dec!(100),
+            quantity: dec!(0.5),
+            side: OrderSide::Buy,
+            transaction_time: Utc::now(),
+        }];
+        let view = MarketView {
+            snapshot: &snapshot,
+            order_book_depth: 1,
+            balances: &balances,
+            trades: &trades,
+            now: Utc::now(),
+        };
+
+        script.calculate(&view)
+    }
+
+    #[test]
+    fn calculate_levels_from_market_view() {
+        let path = write_script(
+            "calculate_levels_from_market_view",
+            r#"
+            function calculate(market)
+                assert(#market.asks == 1 and #market.bids == 1)
+                assert(market.balances.usdt == nil)
+                local mid = (market.asks[1].price + market.bids[1].price) / 2
+                return {
+                    buy = { { price = mid - 5, amount = market.balances.btc / 10, reason = "mid - 5" } },
+                    sell = { { price = mid + market.trades[1].amount, amount = "0.3" } },
+                }
+            end
+            "#,
+        );
+
+        let script = StrategyScript::load(&path, LIMITS).expect("in test");
+        let (buy, sell) = calculate(&script).expect("in test");
+
+        assert_eq!(
+            buy,
+            vec![ScriptLevel {
+                price: dec!(95),
+                amount: dec!(0.2),
+                reason: Some("mid - 5".to_owned()),
+            }]
+        );
+        assert_eq!(
+            sell,
+            vec![ScriptLevel {
+                price: dec!(100.5),
+                amount: dec!(0.3),
+                reason: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn reload_after_changes_check() {
+        let path = write_script(
+            "reload_after_changes_check",
+            "function calculate(market) return { buy = { { price = 1, amount = 1 } } } end",
+        );
+        let mut script = StrategyScript::load(&path, LIMITS).expect("in test");
+
+        fs::write(
+            &path,
+            "function calculate(market) return { sell = { { price = 2, amount = 1 } } } end",
+        )
+        .expect("in test");
+        // file system can have low resolution of modification time
+        let modified = SystemTime::now() + Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(modified))
+            .expect("in test");
+
+        script.reload_if_changed();
+        assert_eq!(calculate(&script).expect("in test").1.len(), 0);
+
+        script.file_state.check_changes(&path);
+        script.reload_if_changed();
+        let (buy, sell) = calculate(&script).expect("in test");
+        assert_eq!((buy.len(), sell.len()), (0, 1));
+    }
+
+    #[test]
+    fn infinite_loop_is_interrupted() {
+        let path = write_script(
+            "infinite_loop_is_interrupted",
+            "function calculate(market) while true do end end",
+        );
+
+        let script = StrategyScript::load(&path, LIMITS).expect("in test");
+
+        assert!(calculate(&script).is_err());
+    }
+
+    #[test]
+    fn os_library_is_unavailable() {
+        let path = write_script(
+            "os_library_is_unavailable",
+            "function calculate(market) os.exit(1) end",
+        );
+
+        let script = StrategyScript::load(&path, LIMITS).expect("in test");
+
+        assert!(calculate(&script).is_err());
+    }
+
+    #[test]
+    fn invalid_price_is_error() {
+        let path = write_script(
+            "invalid_price_is_error",
+            r#"function calculate(market) return { buy = { { price = "abc", amount = 1 } } } end"#,
+        );
+
+        let script = StrategyScript::load(&path, LIMITS).expect("in test");
+
+        assert!(calculate(&script).is_err());
+    }
+}
@@ -11,7 +11,7 @@ use hmac::{Hmac, Mac};
 use hyper::http::request::Builder;
 use hyper::Uri;
 use itertools::Itertools;
-use mmb_utils::time::{get_current_milliseconds, u64_to_date_time};
+use mmb_utils::time::u64_to_date_time;
 use mmb_utils::DateTime;
 use parking_lot::{Mutex, RwLock};
 use serde_json::Value;
@@ -20,6 +20,7 @@ use tokio::sync::broadcast;
 
 use super::support::{BinanceOrderInfo, BinanceSpotBalances};
 use crate::support::{BinanceAccountInfo, BinanceMarginBalances};
+use mmb_core::connectivity::transport::Transport;
 use mmb_core::exchanges::general::exchange::BoxExchangeClient;
 use mmb_core::exchanges::general::exchange::Exchange;
 use mmb_core::exchanges::general::features::{
@@ -28,10 +29,11 @@ use mmb_core::exchanges::general::features::{
 use mmb_core::exchanges::general::handlers::handle_order_filled::FillAmount;
 use mmb_core::exchanges::general::handlers::handle_order_filled::FillEvent;
 use mmb_core::exchanges::general::order::get_order_trades::OrderTrade;
-use mmb_core::exchanges::hosts::Hosts;
+use mmb_core::exchanges::hosts::{Hosts, HostsEnvironment, MAINNET, TESTNET};
 use mmb_core::exchanges::rest_client::{
     ErrorHandler, ErrorHandlerData, RequestType, RestClient, RestHeaders, RestResponse, UriBuilder,
 };
+use mmb_core::exchanges::server_clock::ServerClock;
 use mmb_core::exchanges::timeouts::timeout_manager::TimeoutManager;
 use mmb_core::exchanges::traits::{ExchangeClientBuilder, ExchangeError};
 use mmb_core::exchanges::traits::{
@@ -44,21 +46,25 @@ use mmb_core::exchanges::{
 };
 use mmb_core::lifecycle::app_lifetime_manager::AppLifetimeManager;
 use mmb_core::settings::ExchangeSettings;
+use mmb_domain::candle::Candle;
 use mmb_domain::events::AllowedEventSourceType;
 use mmb_domain::events::{ExchangeBalance, ExchangeBalancesAndPositions, ExchangeEvent, TradeId};
 use mmb_domain::exchanges::symbol::{Precision, Symbol};
 use mmb_domain::market::{CurrencyCode, CurrencyId, CurrencyPair, ExchangeErrorType, ExchangeId};
-use mmb_domain::market::{ExchangeAccountId, SpecificCurrencyPair};
+use mmb_domain::market::{ExchangeAccountId, MarketId, SpecificCurrencyPair};
 use mmb_domain::order::fill::{EventSourceType, OrderFillType};
 use mmb_domain::order::pool::{OrderRef, OrdersPool};
 use mmb_domain::order::snapshot::*;
 use mmb_domain::order::snapshot::{Amount, Price};
 use mmb_domain::position::ActivePosition;
 use mmb_utils::value_to_decimal::GetOrErr;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
 use sha2::digest::generic_array::GenericArray;
 
 const LISTEN_KEY: &str = "listenKey";
+/// Max depth of REST order book snapshot of Binance
+const ORDER_BOOK_SNAPSHOT_LIMIT: u32 = 1000;
 
 #[derive(Default)]
 pub struct ErrorHandlerBinance;
@@ -130,6 +136,44 @@ impl ErrorHandler for ErrorHandlerBinance {
 }
 
 const EMPTY_RESPONSE_IS_OK: bool = false;
+/// Request weight limit per IP
+const REQUESTS_PER_MINUTE: usize = 1200;
+
+const SPOT_ENVIRONMENTS: &[HostsEnvironment] = &[
+    HostsEnvironment {
+        name: MAINNET,
+        web_socket_host: "wss://stream.binance.com:9443",
+        web_socket2_host: "wss://stream.binance.com:9443",
+        rest_host: "https://api.binance.com",
+    },
+    HostsEnvironment {
+        name: TESTNET,
+        web_socket_host: "wss://testnet.binance.vision",
+        web_socket2_host: "wss://testnet.binance.vision",
+        rest_host: "https://testnet.binance.vision",
+    },
+    HostsEnvironment {
+        name: "us",
+        web_socket_host: "wss://stream.binance.us:9443",
+        web_socket2_host: "wss://stream.binance.us:9443",
+        rest_host: "https://api.binance.us",
+    },
+];
+
+const FUTURES_ENVIRONMENTS: &[HostsEnvironment] = &[
+    HostsEnvironment {
+        name: MAINNET,
+        web_socket_host: "wss://fstream.binance.com",
+        web_socket2_host: "wss://fstream.binance.com",
+        rest_host: "https://fapi.binance.com",
+    },
+    HostsEnvironment {
+        name: TESTNET,
+        web_socket_host: "wss://stream.binancefuture.com",
+        web_socket2_host: "wss://stream.binancefuture.com",
+        rest_host: "https://testnet.binancefuture.com",
+    },
+];
 
 pub struct Binance {
     pub settings: ExchangeSettings,
@@ -147,6 +191,7 @@ pub struct Binance {
     // currencies specified in settings for exchange
     pub working_currencies_ids: RwLock<Vec<CurrencyId>>,
     pub(super) timeout_manager: Arc<TimeoutManager>,
+    pub(super) server_clock: Arc<ServerClock>,
 
     // Currencies used for trading according to user settings
     pub(super) traded_specific_currencies: Mutex<Vec<SpecificCurrencyPair>>,
@@ -197,15 +242,21 @@ impl Binance {
         lifetime_manager: Arc<AppLifetimeManager>,
         timeout_manager: Arc<TimeoutManager>,
         is_reducing_market_data: bool,
-    ) -> Self {
+    ) -> Result<Self> {
         let is_reducing_market_data = settings
             .is_reducing_market_data
             .unwrap_or(is_reducing_market_data);
 
-        let hosts = Self::make_hosts(settings.is_margin_trading);
+        let hosts = Self::make_hosts(&settings)?;
+        let transport = Transport::new(settings.connectivity.as_ref())
+            .context("Invalid Binance connectivity settings")?
+            .with_ip_limit(&RequestTimeoutArguments::from_requests_per_minute(
+                REQUESTS_PER_MINUTE,
+            ));
         let exchange_account_id = settings.exchange_account_id;
+        let server_clock = timeout_manager.server_clock(exchange_account_id);
 
-        Self {
+        Ok(Self {
             id,
             order_created_callback: Box::new(|_, _, _| {}),
             order_cancelled_callback: Box::new(|_, _, _| {}),
@@ -218,7 +269,7 @@ impl Binance {
             traded_specific_currencies: Default::default(),
             last_trade_ids: Default::default(),
             subscribe_to_market_data: settings.subscribe_to_market_data,
-            rest_client: RestClient::new(
+            rest_client: RestClient::with_transport(
                 ErrorHandlerData::new(
                     EMPTY_RESPONSE_IS_OK,
                     exchange_account_id,
@@ -227,31 +278,26 @@ impl Binance {
                 RestHeadersBinance {
                     api_key: settings.api_key.clone(),
                 },
+                Arc::new(transport),
+                hosts.is_http_rest_host(),
             ),
             timeout_manager,
+            server_clock,
             is_reducing_market_data,
             settings,
             hosts,
             events_channel,
             lifetime_manager,
             listen_key: Default::default(),
-        }
+        })
     }
 
-    pub fn make_hosts(is_margin_trading: bool) -> Hosts {
-        if is_margin_trading {
-            Hosts {
-                web_socket_host: "wss://fstream.binance.com",
-                web_socket2_host: "wss://fstream.binance.com",
-                rest_host: "https://fapi.binance.com",
-            }
-        } else {
-            Hosts {
-                web_socket_host: "wss://stream.binance.com:9443",
-                web_socket2_host: "wss://stream.binance.com:9443",
-                rest_host: "https://api.binance.com",
-            }
-        }
+    pub fn make_hosts(settings: &ExchangeSettings) -> Result<Hosts> {
+        let environments = match settings.is_margin_trading {
+            true => FUTURES_ENVIRONMENTS,
+            false => SPOT_ENVIRONMENTS,
+        };
+        Hosts::from_settings(settings, environments).context("Invalid Binance hosts settings")
     }
 
     #[named]
@@ -330,7 +376,8 @@ impl Binance {
     }
 
     pub(super) fn add_authentification(&self, builder: &mut UriBuilder) {
-        let time_stamp = get_current_milliseconds();
+        // Binance rejects requests with timestamp out of `recvWindow` by server clock
+        let time_stamp = self.server_clock.now().timestamp_millis();
         builder.add_kv("timestamp", time_stamp);
 
         self.write_signature_to_builder(builder);
@@ -896,6 +943,26 @@ impl Binance {
             .await
     }
 
+    #[named]
+    pub(super) async fn request_countdown_cancel_all(
+        &self,
+        specific_currency_pair: &SpecificCurrencyPair,
+        timeout: Duration,
+    ) -> Result<RestResponse, ExchangeError> {
+        let mut builder = UriBuilder::from_path("/fapi/v1/countdownCancelAll");
+        builder.add_kv("symbol", specific_currency_pair);
+        builder.add_kv("countdownTime", timeout.as_millis());
+        self.add_authentification(&mut builder);
+
+        let (uri, query) = builder.build_uri_and_query(self.hosts.rest_uri_host(), false);
+
+        let log_args =
+            format!("Countdown cancel all for {specific_currency_pair} after {timeout:?}");
+        self.rest_client
+            .post(uri, Some(query), function_name!(), log_args)
+            .await
+    }
+
     #[named]
     pub(super) async fn request_all_symbols(&self) -> Result<RestResponse, ExchangeError> {
         let path = self.get_uri_path("/fapi/v1/exchangeInfo", "/api/v3/exchangeInfo");
@@ -907,6 +974,135 @@ impl Binance {
             .await
     }
 
+    #[named]
+    pub(super) async fn request_server_time(&self) -> Result<RestResponse, ExchangeError> {
+        let path = self.get_uri_path("/fapi/v1/time", "/api/v3/time");
+        let builder = UriBuilder::from_path(path);
+        let uri = builder.build_uri(self.hosts.rest_uri_host(), false);
+
+        self.rest_client
+            .get(uri, function_name!(), "".to_string())
+            .await
+    }
+
+    #[named]
+    pub(super) async fn request_order_book_snapshot(
+        &self,
+        currency_pair: CurrencyPair,
+    ) -> Result<RestResponse, ExchangeError> {
+        let specific_currency_pair = self.get_specific_currency_pair(currency_pair);
+
+        let path = self.get_uri_path("/fapi/v1/depth", "/api/v3/depth");
+        let mut builder = UriBuilder::from_path(path);
+        builder.add_kv("symbol", &specific_currency_pair);
+        builder.add_kv("limit", ORDER_BOOK_SNAPSHOT_LIMIT);
+        let uri = builder.build_uri(self.hosts.rest_uri_host(), true);
+
+        let log_args = format!("currency pair {currency_pair}");
+        self.rest_client.get(uri, function_name!(), log_args).await
+    }
+
+    #[named]
+    pub(super) async fn request_candles(
+        &self,
+        currency_pair: CurrencyPair,
+        interval: &str,
+        limit: u32,
+    ) -> Result<RestResponse, ExchangeError> {
+        let specific_currency_pair = self.get_specific_currency_pair(currency_pair);
+
+        let path = self.get_uri_path("/fapi/v1/klines", "/api/v3/klines");
+        let mut builder = UriBuilder::from_path(path);
+        builder.add_kv("symbol", &specific_currency_pair);
+        builder.add_kv("interval", interval);
+        builder.add_kv("limit", limit);
+        let uri = builder.build_uri(self.hosts.rest_uri_host(), true);
+
+        let log_args = format!("currency pair {currency_pair}, interval {interval}");
+        self.rest_client.get(uri, function_name!(), log_args).await
+    }
+
+    pub(super) fn parse_server_time(response: &RestResponse) -> Result<DateTime> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ServerTime {
+            server_time: u64,
+        }
+
+        let deserialized: ServerTime = serde_json::from_str(&response.content)
+            .context("Unable to parse server time from Binance response")?;
+
+        Ok(u64_to_date_time(deserialized.server_time))
+    }
+
+    /// Binance interval code for interval in seconds. Intervals longer than a day aren't
+    /// supported because Binance aligns them to weeks and months instead of unix epoch
+    pub(super) fn get_kline_interval(interval_secs: u32) -> Option<&'static str> {
+        let interval = match interval_secs {
+            60 => "1m",
+            180 => "3m",
+            300 => "5m",
+            900 => "15m",
+            1_800 => "30m",
+            3_600 => "1h",
+            7_200 => "2h",
+            14_400 => "4h",
+            21_600 => "6h",
+            28_800 => "8h",
+            43_200 => "12h",
+            86_400 => "1d",
+            _ => return None,
+        };
+
+        Some(interval)
+    }
+
+    pub(super) fn parse_candles(
+        &self,
+        currency_pair: CurrencyPair,
+        interval_secs: u32,
+        response: &RestResponse,
+    ) -> Result<Vec<Candle>> {
+        /// Open time, open, high, low, close, volume, close time, quote volume, trades count,
+        /// taker buy volume, taker buy quote volume and unused field
+        #[derive(Deserialize)]
+        struct BinanceKline(
+            u64,
+            Price,
+            Price,
+            Price,
+            Price,
+            Amount,
+            IgnoredAny,
+            IgnoredAny,
+            u64,
+            IgnoredAny,
+            IgnoredAny,
+            IgnoredAny,
+        );
+
+        let klines: Vec<BinanceKline> = serde_json::from_str(&response.content)
+            .context("Unable to parse klines from Binance response")?;
+
+        let market_id = MarketId::new(self.id.exchange_id, currency_pair);
+        let candles = klines
+            .into_iter()
+            .map(|kline| Candle {
+                market_id,
+                interval_secs,
+                open_time: u64_to_date_time(kline.0),
+                open: kline.1,
+                high: kline.2,
+                low: kline.3,
+                close: kline.4,
+                volume: kline.5,
+                trades_count: kline.8,
+            })
+            .collect();
+
+        Ok(candles)
+    }
+
     pub(super) fn parse_all_symbols(&self, response: &RestResponse) -> Result<Vec<Arc<Symbol>>> {
         let deserialized: Value = serde_json::from_str(&response.content)
             .expect("Unable to deserialize response from Binance");
@@ -1080,10 +1276,12 @@ impl ExchangeClientBuilder for BinanceBuilder {
         lifetime_manager: Arc<AppLifetimeManager>,
         timeout_manager: Arc<TimeoutManager>,
         _orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult {
+    ) -> Result<ExchangeClientBuilderResult> {
         let exchange_account_id = exchange_settings.exchange_account_id;
+        // Countdown cancel is available only for futures
+        let supports_cancel_all_after = exchange_settings.is_margin_trading;
 
-        ExchangeClientBuilderResult {
+        Ok(ExchangeClientBuilderResult {
             client: Box::new(Binance::new(
                 exchange_account_id,
                 exchange_settings,
@@ -1091,26 +1289,31 @@ impl ExchangeClientBuilder for BinanceBuilder {
                 lifetime_manager,
                 timeout_manager,
                 false,
-            )) as BoxExchangeClient,
-            features: ExchangeFeatures::new(
-                OpenOrdersType::AllCurrencyPair,
-                RestFillsFeatures::new(RestFillsType::None),
-                OrderFeatures {
-                    supports_get_order_info_by_client_order_id: true,
-                    ..OrderFeatures::default()
-                },
-                OrderTradeOption::default(),
-                WebSocketOptions::default(),
-                EMPTY_RESPONSE_IS_OK,
-                AllowedEventSourceType::All,
-                AllowedEventSourceType::All,
-                AllowedEventSourceType::All,
-            ),
-        }
+            )?) as BoxExchangeClient,
+            features: ExchangeFeatures {
+                supports_server_time: true,
+                supports_klines: true,
+                ..ExchangeFeatures::new(
+                    OpenOrdersType::AllCurrencyPair,
+                    RestFillsFeatures::new(RestFillsType::None),
+                    OrderFeatures {
+                        supports_get_order_info_by_client_order_id: true,
+                        supports_cancel_all_after,
+                        ..OrderFeatures::default()
+                    },
+                    OrderTradeOption::default(),
+                    WebSocketOptions::default(),
+                    EMPTY_RESPONSE_IS_OK,
+                    AllowedEventSourceType::All,
+                    AllowedEventSourceType::All,
+                    AllowedEventSourceType::All,
+                )
+            },
+        })
     }
 
     fn get_timeout_arguments(&self) -> RequestTimeoutArguments {
-        RequestTimeoutArguments::from_requests_per_minute(1200)
+        RequestTimeoutArguments::from_requests_per_minute(REQUESTS_PER_MINUTE)
     }
 
     fn get_exchange_id(&self) -> ExchangeId {
@@ -1123,8 +1326,10 @@ mod tests {
     use super::*;
     use mmb_core::exchanges::timeouts::requests_timeout_manager_factory::RequestsTimeoutManagerFactory;
     use mmb_core::lifecycle::launcher::EngineBuildConfig;
+    use mmb_domain::order_book::event::{EventType, OrderBookSequence};
     use mmb_utils::cancellation_token::CancellationToken;
     use mmb_utils::hashmap;
+    use rust_decimal_macros::dec;
 
     pub(crate) fn get_timeout_manager(
         exchange_account_id: ExchangeAccountId,
@@ -1162,7 +1367,8 @@ mod tests {
             AppLifetimeManager::new(CancellationToken::default()),
             get_timeout_manager(exchange_account_id),
             false,
-        );
+        )
+        .expect("in test");
 
         let mut builder = UriBuilder::from_path("/test");
         builder.add_kv("symbol", "LTCBTC");
@@ -1184,4 +1390,61 @@ mod tests {
 
         assert_eq!(signature_value, expected);
     }
+
+    #[test]
+    fn depth_updates_contain_update_ids() {
+        let exchange_account_id: ExchangeAccountId = "Binance_0".parse().expect("in test");
+        let currency_pair = CurrencyPair::from_codes("bnb".into(), "btc".into());
+
+        let receive_sequence = |is_margin_trading: bool, data: Value| {
+            let settings = ExchangeSettings::new_short(
+                exchange_account_id,
+                "".into(),
+                "".into(),
+                is_margin_trading,
+            );
+            let (tx, mut rx) = broadcast::channel(10);
+            let binance = Binance::new(
+                exchange_account_id,
+                settings,
+                tx,
+                AppLifetimeManager::new(CancellationToken::default()),
+                get_timeout_manager(exchange_account_id),
+                false,
+            )
+            .expect("in test");
+
+            binance
+                .process_depth_update(currency_pair, &data)
+                .expect("in test");
+
+            match rx.try_recv().expect("in test") {
+                ExchangeEvent::OrderBookEvent(event) => {
+                    assert!(matches!(event.event_type, EventType::Update));
+                    assert_eq!(event.data.bids.get(&dec!(0.0024)), Some(&dec!(0)));
+                    event.sequence
+                }
+                _ => panic!("Order book event expected"),
+            }
+        };
+
+        let spot_update = serde_json::json!({
+            "e": "depthUpdate", "E": 123456789, "s": "BNBBTC", "U": 157, "u": 160,
+            "b": [["0.0024", "0"]], "a": [["0.0026", "100"]]
+        });
+        assert_eq!(
+            receive_sequence(false, spot_update),
+            Some(OrderBookSequence::new(157, 160))
+        );
+
+        let futures_update = serde_json::json!({
+            "e": "depthUpdate", "E": 123456789, "T": 123456788, "s": "BNBBTC",
+            "U": 157, "u": 160, "pu": 149,
+            "b": [["0.0024", "0"]], "a": [["0.0026", "100"]]
+        });
+        assert_eq!(
+            receive_sequence(true, futures_update),
+            Some(OrderBookSequence::new(150, 160))
+        );
+    }
 }
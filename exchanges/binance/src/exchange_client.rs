@@ -1,6 +1,6 @@
 use super::binance::Binance;
 use crate::support::{BinanceOrderInfo, BinancePosition};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use function_name::named;
 use itertools::Itertools;
@@ -11,6 +11,7 @@ use mmb_core::exchanges::general::order::get_order_trades::OrderTrade;
 use mmb_core::exchanges::general::request_type::RequestType;
 use mmb_core::exchanges::rest_client::UriBuilder;
 use mmb_core::exchanges::traits::{ExchangeClient, ExchangeError, Support};
+use mmb_domain::candle::Candle;
 use mmb_domain::events::ExchangeBalancesAndPositions;
 use mmb_domain::exchanges::symbol::Symbol;
 use mmb_domain::market::CurrencyPair;
@@ -18,9 +19,14 @@ use mmb_domain::order::fill::EventSourceType;
 use mmb_domain::order::pool::OrderRef;
 use mmb_domain::order::snapshot::Price;
 use mmb_domain::order::snapshot::*;
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::position::{ActivePosition, ClosedPosition};
 use mmb_utils::DateTime;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Max number of klines in one response of spot API
+const MAX_KLINES_LIMIT: u32 = 1000;
 
 #[async_trait]
 impl ExchangeClient for Binance {
@@ -64,6 +70,27 @@ impl ExchangeClient for Binance {
         Ok(())
     }
 
+    async fn cancel_all_orders_after(&self, timeout: Duration) -> Result<()> {
+        if !self.settings.is_margin_trading {
+            bail!("Countdown cancel all is supported only for Binance futures");
+        }
+
+        let currency_pairs = self.traded_specific_currencies.lock().clone();
+        if currency_pairs.is_empty() {
+            bail!("Countdown cancel all isn't armed because there are no traded currency pairs");
+        }
+
+        for specific_currency_pair in currency_pairs {
+            self.request_countdown_cancel_all(&specific_currency_pair, timeout)
+                .await
+                .with_context(|| {
+                    format!("Failed to arm countdown cancel all for {specific_currency_pair}")
+                })?;
+        }
+
+        Ok(())
+    }
+
     async fn get_open_orders(&self) -> Result<Vec<OrderInfo>> {
         let response = self.request_open_orders().await?;
 
@@ -143,6 +170,38 @@ impl ExchangeClient for Binance {
         let response = &self.request_all_symbols().await?;
         self.parse_all_symbols(response)
     }
+
+    async fn get_server_time(&self) -> Result<DateTime> {
+        let response = self.request_server_time().await?;
+        Binance::parse_server_time(&response)
+    }
+
+    async fn get_order_book_snapshot(&self, currency_pair: CurrencyPair) -> Result<OrderBookEvent> {
+        let response = self.request_order_book_snapshot(currency_pair).await?;
+        self.parse_order_book_snapshot(currency_pair, &response)
+    }
+
+    async fn get_candles(
+        &self,
+        currency_pair: CurrencyPair,
+        interval_secs: u32,
+        limit: u32,
+    ) -> Result<Vec<Candle>> {
+        let interval = match Binance::get_kline_interval(interval_secs) {
+            Some(interval) => interval,
+            None => bail!("Candles with interval {interval_secs}s aren't supported by Binance"),
+        };
+
+        // The last kline in response is still open, so it's requested additionally and skipped
+        let request_limit = (limit + 1).min(MAX_KLINES_LIMIT);
+        let response = self
+            .request_candles(currency_pair, interval, request_limit)
+            .await?;
+
+        let mut candles = self.parse_candles(currency_pair, interval_secs, &response)?;
+        let _ = candles.pop();
+        Ok(candles)
+    }
 }
 
 impl Binance {
@@ -20,6 +20,7 @@ use super::binance::Binance;
 use mmb_core::connectivity::WebSocketRole;
 use mmb_core::exchanges::common::send_event;
 use mmb_core::exchanges::general::exchange::Exchange;
+use mmb_core::exchanges::rest_client::RestResponse;
 use mmb_core::exchanges::traits::Support;
 use mmb_core::exchanges::traits::{
     HandleOrderFilledCb, HandleTradeCb, OrderCancelledCb, OrderCreatedCb, SendWebsocketMessageCb,
@@ -31,7 +32,7 @@ use mmb_domain::market::{CurrencyCode, CurrencyPair};
 use mmb_domain::market::{CurrencyId, SpecificCurrencyPair};
 use mmb_domain::order::snapshot::SortedOrderData;
 use mmb_domain::order::snapshot::*;
-use mmb_domain::order_book::event::{EventType, OrderBookEvent};
+use mmb_domain::order_book::event::{EventType, OrderBookEvent, OrderBookSequence};
 use mmb_domain::order_book::order_book_data::OrderBookData;
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
@@ -127,15 +128,13 @@ impl Support for Binance {
                     return Ok(());
                 }
 
-                // TODO handle public stream
-                let stream_tail = &stream[byte_index + 1..];
-                if stream_tail.starts_with("depth1000") {
-                    log::warn!("depth1000 is unsuported for Binance in current implementation");
-                    return Ok(());
-                }
-
-                if stream_tail.starts_with("depth") {
-                    self.process_snapshot_update(currency_pair, data)?;
+                if let Some(depth_tail) = stream[byte_index + 1..].strip_prefix("depth") {
+                    // Partial book depth streams like `depth20@100ms` contain top levels only,
+                    // diff depth streams like `depth@100ms` contain changes of full order book
+                    match depth_tail.starts_with(|c: char| c.is_ascii_digit()) {
+                        true => self.process_snapshot_update(currency_pair, data)?,
+                        false => self.process_depth_update(currency_pair, data)?,
+                    }
                     return Ok(());
                 }
             }
@@ -306,60 +305,102 @@ impl Binance {
 
     pub fn process_snapshot_update(&self, currency_pair: CurrencyPair, data: &Value) -> Result<()> {
         let (last_update_id, raw_asks, raw_bids) = match self.settings.is_margin_trading {
-            true => {
-                let last_update_id = data["u"].to_string();
-                let raw_asks = data["a"]
-                    .as_array()
-                    .ok_or_else(|| anyhow!("Unable to parse 'asks' in Binance"))?;
-                let raw_bids = data["b"]
-                    .as_array()
-                    .ok_or_else(|| anyhow!("Unable to parse 'bids' in Binance"))?;
-                (last_update_id, raw_asks, raw_bids)
-            }
-            false => {
-                let last_update_id = data["lastUpdateId"].to_string();
-                let raw_asks = data["asks"]
-                    .as_array()
-                    .ok_or_else(|| anyhow!("Unable to parse 'asks' in Binance"))?;
-                let raw_bids = data["bids"]
-                    .as_array()
-                    .ok_or_else(|| anyhow!("Unable to parse 'bids' in Binance"))?;
-                (last_update_id, raw_asks, raw_bids)
-            }
+            true => (&data["u"], &data["a"], &data["b"]),
+            false => (&data["lastUpdateId"], &data["asks"], &data["bids"]),
         };
 
-        let asks = get_order_book_side(raw_asks)?;
-        let bids = get_order_book_side(raw_bids)?;
+        let order_book_event = self.create_order_book_snapshot_event(
+            currency_pair,
+            last_update_id,
+            raw_asks,
+            raw_bids,
+        )?;
+        self.handle_order_book_event(order_book_event)
+    }
 
-        let order_book_data = OrderBookData::new(asks, bids);
-        self.handle_order_book_snapshot(currency_pair, &last_update_id, order_book_data, None)
+    /// Diff depth update is applied to local order book built from REST snapshot. The snapshot is
+    /// requested automatically when the first update without snapshot or a gap in update ids is
+    /// detected. Futures updates continue previous update with id `pu`, so it's used as the first id
+    pub fn process_depth_update(&self, currency_pair: CurrencyPair, data: &Value) -> Result<()> {
+        let last_update_id = data["u"]
+            .as_u64()
+            .context("Unable to get u64 from 'u' field of Binance depth update")?;
+        let first_update_id = match data["pu"].as_u64() {
+            Some(previous_update_id) => previous_update_id + 1,
+            None => data["U"]
+                .as_u64()
+                .context("Unable to get u64 from 'U' field of Binance depth update")?,
+        };
+
+        let raw_asks = data["a"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Unable to parse 'a' in Binance depth update"))?;
+        let raw_bids = data["b"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Unable to parse 'b' in Binance depth update"))?;
+
+        let order_book_event = OrderBookEvent::new(
+            Utc::now(),
+            self.id,
+            currency_pair,
+            Some(OrderBookSequence::new(first_update_id, last_update_id)),
+            EventType::Update,
+            Arc::new(OrderBookData::new(
+                get_order_book_side(raw_asks)?,
+                get_order_book_side(raw_bids)?,
+            )),
+        );
+        self.handle_order_book_event(order_book_event)
     }
 
-    fn handle_order_book_snapshot(
+    /// Parses REST order book snapshot requested for resynchronization of local order book
+    pub(super) fn parse_order_book_snapshot(
         &self,
         currency_pair: CurrencyPair,
-        event_id: &str,
-        mut order_book_data: OrderBookData,
-        order_book_update: Option<Vec<OrderBookData>>,
-    ) -> Result<()> {
-        if !self.subscribe_to_market_data {
-            return Ok(());
-        }
+        response: &RestResponse,
+    ) -> Result<OrderBookEvent> {
+        let data: Value = serde_json::from_str(&response.content)
+            .context("Unable to parse order book snapshot response for Binance")?;
 
-        //Some exchanges like Binance don't give us Snapshot in Web Socket, so we have to request Snapshot using Rest
-        //and then update it with orderBookUpdates that we received while Rest request was being executed
-        if let Some(updates) = order_book_update {
-            order_book_data.update(updates)
-        }
+        self.create_order_book_snapshot_event(
+            currency_pair,
+            &data["lastUpdateId"],
+            &data["asks"],
+            &data["bids"],
+        )
+    }
 
-        let order_book_event = OrderBookEvent::new(
+    fn create_order_book_snapshot_event(
+        &self,
+        currency_pair: CurrencyPair,
+        last_update_id: &Value,
+        raw_asks: &Value,
+        raw_bids: &Value,
+    ) -> Result<OrderBookEvent> {
+        let raw_asks = raw_asks
+            .as_array()
+            .ok_or_else(|| anyhow!("Unable to parse 'asks' in Binance"))?;
+        let raw_bids = raw_bids
+            .as_array()
+            .ok_or_else(|| anyhow!("Unable to parse 'bids' in Binance"))?;
+
+        let asks = get_order_book_side(raw_asks)?;
+        let bids = get_order_book_side(raw_bids)?;
+
+        Ok(OrderBookEvent::new(
             Utc::now(),
             self.id,
             currency_pair,
-            event_id.to_string(),
+            last_update_id.as_u64().map(OrderBookSequence::snapshot),
             EventType::Snapshot,
-            Arc::new(order_book_data),
-        );
+            Arc::new(OrderBookData::new(asks, bids)),
+        ))
+    }
+
+    fn handle_order_book_event(&self, order_book_event: OrderBookEvent) -> Result<()> {
+        if !self.subscribe_to_market_data {
+            return Ok(());
+        }
 
         let event = ExchangeEvent::OrderBookEvent(order_book_event);
 
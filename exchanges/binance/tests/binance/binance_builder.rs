@@ -1,5 +1,6 @@
 use anyhow::Result;
 use binance::binance::Binance;
+use core_tests::hosts::apply_hosts_from_env;
 use core_tests::order::OrderProxy;
 use mmb_core::balance::manager::balance_manager::BalanceManager;
 use mmb_core::database::events::recorder::EventRecorder;
@@ -173,15 +174,19 @@ impl BinanceBuilder {
         let (tx, rx) = broadcast::channel(10);
 
         settings.websocket_channels = vec!["depth".into(), "trade".into()];
+        apply_hosts_from_env(&mut settings, "BINANCE");
 
-        let binance = Box::new(Binance::new(
-            exchange_account_id,
-            settings.clone(),
-            tx.clone(),
-            lifetime_manager.clone(),
-            get_timeout_manager(exchange_account_id),
-            false,
-        ));
+        let binance = Box::new(
+            Binance::new(
+                exchange_account_id,
+                settings.clone(),
+                tx.clone(),
+                lifetime_manager.clone(),
+                get_timeout_manager(exchange_account_id),
+                false,
+            )
+            .expect("in test"),
+        );
 
         let hosts = binance.hosts.clone();
 
@@ -204,7 +209,8 @@ impl BinanceBuilder {
             Arc::downgrade(&exchange_blocker),
             commission,
             event_recorder,
-        );
+        )
+        .expect("in test");
         exchange.connect_ws().await.with_expect(move || {
             format!("Failed to connect to websockets on exchange {exchange_account_id}")
         });
@@ -99,6 +99,7 @@ async fn orders_cancelled() {
 
     let is_margin_trading = exchange_settings.is_margin_trading;
     let api_key = exchange_settings.api_key.clone();
+    let hosts = Binance::make_hosts(exchange_settings).expect("in test");
 
     let init_settings = InitSettings::Directly(settings.clone());
     let engine = launch_trading_engine(&config, init_settings)
@@ -125,7 +126,7 @@ async fn orders_cancelled() {
         let _ = exchange.cancel_all_orders(test_currency_pair).await;
         let price = get_default_price(
             get_specific_currency_pair_for_tests(&exchange, test_currency_pair),
-            &Binance::make_hosts(is_margin_trading),
+            &hosts,
             &api_key,
             exchange_account_id,
             is_margin_trading,
@@ -141,7 +142,7 @@ async fn orders_cancelled() {
 
         let amount = get_min_amount(
             get_specific_currency_pair_for_tests(&exchange, test_currency_pair),
-            &Binance::make_hosts(is_margin_trading),
+            &hosts,
             &api_key,
             price,
             &symbol,
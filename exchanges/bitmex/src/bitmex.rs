@@ -1,19 +1,23 @@
 use crate::types::{BitmexOrderInfo, BitmexSymbol};
 use anyhow::{Context, Result};
 use arrayvec::{ArrayString, ArrayVec};
+use chrono::Utc;
 use dashmap::DashMap;
 use function_name::named;
 use hmac::{Hmac, Mac};
+use hyper::header::DATE;
 use hyper::http::request::Builder;
 use hyper::{StatusCode, Uri};
+use mmb_core::connectivity::transport::Transport;
 use mmb_core::exchanges::general::features::{
     BalancePositionOption, ExchangeFeatures, OpenOrdersType, OrderFeatures, OrderTradeOption,
     RestFillsFeatures, RestFillsType, WebSocketOptions,
 };
-use mmb_core::exchanges::hosts::Hosts;
+use mmb_core::exchanges::hosts::{Hosts, HostsEnvironment, MAINNET, TESTNET};
 use mmb_core::exchanges::rest_client::{
     ErrorHandler, ErrorHandlerData, RequestType, RestClient, RestHeaders, RestResponse, UriBuilder,
 };
+use mmb_core::exchanges::server_clock::ServerClock;
 use mmb_core::exchanges::timeouts::requests_timeout_manager_factory::RequestTimeoutArguments;
 use mmb_core::exchanges::timeouts::timeout_manager::TimeoutManager;
 use mmb_core::exchanges::traits::{
@@ -32,6 +36,7 @@ use mmb_domain::order::snapshot::{
     ExchangeOrderId, OrderCancelling, OrderExecutionType, OrderInfo, OrderSide, OrderStatus,
     OrderType, Price,
 };
+use mmb_utils::DateTime;
 use parking_lot::{Mutex, RwLock};
 use rust_decimal_macros::dec;
 use serde::Deserialize;
@@ -41,7 +46,7 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::io::Write;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use tinyvec::Array;
 use tokio::sync::broadcast;
 use urlencoding::encode;
@@ -68,17 +73,20 @@ impl ErrorHandler for ErrorHandlerBitmex {
 struct RestHeadersBitmex {
     api_key: String,
     secret_key: String,
+    server_clock: Arc<ServerClock>,
 }
 
 impl RestHeadersBitmex {
-    pub fn new(api_key: String, secret_key: String) -> Self {
+    pub fn new(api_key: String, secret_key: String, server_clock: Arc<ServerClock>) -> Self {
         Self {
             api_key,
             secret_key,
+            server_clock,
         }
     }
 
     pub fn create_signature_message(
+        &self,
         path_and_query: &str,
         request_type: RequestType,
     ) -> (ArrayString<128>, u64) {
@@ -86,7 +94,7 @@ impl RestHeadersBitmex {
         message.push_str(request_type.as_str());
         message.push_str(path_and_query);
 
-        let expire_time = Bitmex::get_key_expire_time(60);
+        let expire_time = Bitmex::get_key_expire_time(&self.server_clock, 60);
 
         (message, expire_time)
     }
@@ -103,8 +111,7 @@ impl RestHeaders for RestHeadersBitmex {
             Some(path_and_query) => path_and_query.as_str(),
             None => uri.path(),
         };
-        let (message, expire_time) =
-            RestHeadersBitmex::create_signature_message(path_and_query, request_type);
+        let (message, expire_time) = self.create_signature_message(path_and_query, request_type);
 
         builder
             .header("api-expires", expire_time)
@@ -118,6 +125,23 @@ impl RestHeaders for RestHeadersBitmex {
 }
 
 const EMPTY_RESPONSE_IS_OK: bool = false;
+/// Requests limit per IP
+const REQUESTS_PER_MINUTE: usize = 60;
+
+const ENVIRONMENTS: &[HostsEnvironment] = &[
+    HostsEnvironment {
+        name: MAINNET,
+        web_socket_host: "wss://www.bitmex.com/realtime",
+        web_socket2_host: "wss://www.bitmex.com/realtime",
+        rest_host: "https://www.bitmex.com",
+    },
+    HostsEnvironment {
+        name: TESTNET,
+        web_socket_host: "wss://ws.testnet.bitmex.com/realtime",
+        web_socket2_host: "wss://ws.testnet.bitmex.com/realtime",
+        rest_host: "https://testnet.bitmex.com",
+    },
+];
 
 pub struct Bitmex {
     pub(crate) settings: ExchangeSettings,
@@ -129,6 +153,7 @@ pub struct Bitmex {
     // Currencies used for trading according to user settings
     pub(super) traded_specific_currencies: Mutex<Vec<SpecificCurrencyPair>>,
     pub(super) lifetime_manager: Arc<AppLifetimeManager>,
+    pub(super) server_clock: Arc<ServerClock>,
     pub(super) events_channel: broadcast::Sender<ExchangeEvent>,
     pub(crate) order_created_callback: OrderCreatedCb,
     pub(crate) order_cancelled_callback: OrderCancelledCb,
@@ -143,39 +168,50 @@ impl Bitmex {
         settings: ExchangeSettings,
         events_channel: broadcast::Sender<ExchangeEvent>,
         lifetime_manager: Arc<AppLifetimeManager>,
-    ) -> Bitmex {
-        Self {
-            rest_client: RestClient::new(
+        server_clock: Arc<ServerClock>,
+    ) -> Result<Bitmex> {
+        let hosts = Self::make_hosts(&settings)?;
+        let transport = Transport::new(settings.connectivity.as_ref())
+            .context("Invalid Bitmex connectivity settings")?
+            .with_ip_limit(&RequestTimeoutArguments::from_requests_per_minute(
+                REQUESTS_PER_MINUTE,
+            ));
+
+        Ok(Self {
+            rest_client: RestClient::with_transport(
                 ErrorHandlerData::new(
                     EMPTY_RESPONSE_IS_OK,
                     settings.exchange_account_id,
                     ErrorHandlerBitmex::default(),
                 ),
-                RestHeadersBitmex::new(settings.api_key.clone(), settings.secret_key.clone()),
+                RestHeadersBitmex::new(
+                    settings.api_key.clone(),
+                    settings.secret_key.clone(),
+                    server_clock.clone(),
+                ),
+                Arc::new(transport),
+                hosts.is_http_rest_host(),
             ),
+            hosts,
             settings,
-            hosts: Self::make_hosts(),
             unified_to_specific: Default::default(),
             specific_to_unified: Default::default(),
             supported_currencies: Default::default(),
             traded_specific_currencies: Default::default(),
             events_channel,
             lifetime_manager,
+            server_clock,
             order_created_callback: Box::new(|_, _, _| {}),
             order_cancelled_callback: Box::new(|_, _, _| {}),
             handle_order_filled_callback: Box::new(|_| {}),
             handle_trade_callback: Box::new(|_, _| {}),
             websocket_message_callback: Box::new(|_, _| Ok(())),
             order_book_ids: Default::default(),
-        }
+        })
     }
 
-    fn make_hosts() -> Hosts {
-        Hosts {
-            web_socket_host: "wss://www.bitmex.com/realtime",
-            web_socket2_host: "wss://www.bitmex.com/realtime",
-            rest_host: "https://www.bitmex.com",
-        }
+    fn make_hosts(settings: &ExchangeSettings) -> Result<Hosts> {
+        Hosts::from_settings(settings, ENVIRONMENTS).context("Invalid Bitmex hosts settings")
     }
 
     #[named]
@@ -188,6 +224,31 @@ impl Bitmex {
             .await
     }
 
+    #[named]
+    pub(super) async fn request_server_time(&self) -> Result<RestResponse, ExchangeError> {
+        let builder = UriBuilder::from_path("/api/v1");
+        let uri = builder.build_uri(self.hosts.rest_uri_host(), false);
+
+        self.rest_client
+            .get(uri, function_name!(), "".to_string())
+            .await
+    }
+
+    /// Server time is taken from `Date` header of response
+    pub(super) fn parse_server_time(response: &RestResponse) -> Result<DateTime> {
+        let date = response
+            .headers
+            .get(DATE)
+            .context("No Date header in Bitmex response")?
+            .to_str()
+            .context("Unable to read Date header of Bitmex response")?;
+        let date = chrono::DateTime::parse_from_rfc2822(date)
+            .with_context(|| format!("Unable to parse Date header {date} of Bitmex response"))?;
+
+        // Date header is truncated to seconds so the middle of the second is the best estimation
+        Ok(date.with_timezone(&Utc) + chrono::Duration::milliseconds(500))
+    }
+
     pub(super) fn parse_all_symbols(&self, response: &RestResponse) -> Result<Vec<Arc<Symbol>>> {
         let symbols: Vec<BitmexSymbol> = serde_json::from_str(&response.content)
             .expect("Unable to deserialize response from Bitmex");
@@ -505,6 +566,22 @@ impl Bitmex {
             .await
     }
 
+    #[named]
+    pub(super) async fn do_cancel_all_orders_after(
+        &self,
+        timeout: Duration,
+    ) -> Result<RestResponse, ExchangeError> {
+        let mut builder = UriBuilder::from_path("/api/v1/order/cancelAllAfter");
+        builder.add_kv("timeout", timeout.as_millis());
+
+        let uri = builder.build_uri(self.hosts.rest_uri_host(), true);
+        let log_args = format!("Cancel all orders after {timeout:?}");
+
+        self.rest_client
+            .post(uri, None, function_name!(), log_args)
+            .await
+    }
+
     pub(super) fn create_signature(secret_key: &str, message: &str, expire_time: u64) -> [u8; 64] {
         let mut hmac = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes())
             .expect("Unable to calculate hmac for Bitmex signature");
@@ -523,11 +600,9 @@ impl Bitmex {
         hex_array
     }
 
-    pub(super) fn get_key_expire_time(secs: u64) -> u64 {
-        let current_unix_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System Time before UNIX EPOCH!")
-            .as_secs();
+    pub(super) fn get_key_expire_time(server_clock: &ServerClock, secs: u64) -> u64 {
+        // Bitmex rejects requests which are expired by server clock
+        let current_unix_time = server_clock.now().timestamp() as u64;
 
         current_unix_time + secs
     }
@@ -541,15 +616,18 @@ impl ExchangeClientBuilder for BitmexBuilder {
         exchange_settings: ExchangeSettings,
         events_channel: broadcast::Sender<ExchangeEvent>,
         lifetime_manager: Arc<AppLifetimeManager>,
-        _timeout_manager: Arc<TimeoutManager>,
+        timeout_manager: Arc<TimeoutManager>,
         _orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult {
-        ExchangeClientBuilderResult {
+    ) -> Result<ExchangeClientBuilderResult> {
+        let server_clock = timeout_manager.server_clock(exchange_settings.exchange_account_id);
+
+        Ok(ExchangeClientBuilderResult {
             client: Box::new(Bitmex::new(
                 exchange_settings,
                 events_channel,
                 lifetime_manager,
-            )),
+                server_clock,
+            )?),
             features: ExchangeFeatures {
                 open_orders_type: OpenOrdersType::AllCurrencyPair,
                 rest_fills_features: RestFillsFeatures::new(RestFillsType::MyTrades),
@@ -561,6 +639,7 @@ impl ExchangeClientBuilder for BitmexBuilder {
                     order_was_completed_error_for_cancellation: true,
                     supports_already_cancelled_order: true,
                     supports_stop_loss_order: true,
+                    supports_cancel_all_after: true,
                 },
                 trade_option: OrderTradeOption {
                     supports_trade_time: true,
@@ -578,15 +657,17 @@ impl ExchangeClientBuilder for BitmexBuilder {
                 },
                 empty_response_is_ok: EMPTY_RESPONSE_IS_OK,
                 balance_position_option: BalancePositionOption::NonDerivative,
+                supports_server_time: true,
+                supports_klines: false,
                 allowed_create_event_source_type: AllowedEventSourceType::All,
                 allowed_fill_event_source_type: AllowedEventSourceType::All,
                 allowed_cancel_event_source_type: AllowedEventSourceType::All,
             },
-        }
+        })
     }
 
     fn get_timeout_arguments(&self) -> RequestTimeoutArguments {
-        RequestTimeoutArguments::from_requests_per_minute(60)
+        RequestTimeoutArguments::from_requests_per_minute(REQUESTS_PER_MINUTE)
     }
 
     fn get_exchange_id(&self) -> ExchangeId {
@@ -598,6 +679,9 @@ impl ExchangeClientBuilder for BitmexBuilder {
 mod tests {
     use super::*;
     use bstr::ByteSlice;
+    use chrono::TimeZone;
+    use hyper::header::HeaderValue;
+    use mmb_domain::market::ExchangeAccountId;
 
     #[test]
     fn generate_signature() {
@@ -610,9 +694,10 @@ mod tests {
         let rest_header = RestHeadersBitmex {
             api_key,
             secret_key,
+            server_clock: ServerClock::new(ExchangeAccountId::new("Bitmex", 0)),
         };
 
-        let (message, _) = RestHeadersBitmex::create_signature_message(path, RequestType::Get);
+        let (message, _) = rest_header.create_signature_message(path, RequestType::Get);
 
         let signature_hash =
             Bitmex::create_signature(&rest_header.secret_key, message.as_str(), expire_time);
@@ -624,4 +709,18 @@ mod tests {
             "e2f422547eecb5b3cb29ade2127e21b858b235b386bfa45e1c1756eb3383919f"
         );
     }
+
+    #[test]
+    fn parse_server_time_from_date_header() {
+        let mut response = RestResponse::new("{}".to_owned(), StatusCode::OK);
+        response.headers.insert(
+            DATE,
+            HeaderValue::from_static("Tue, 18 Oct 2022 10:20:30 GMT"),
+        );
+
+        assert_eq!(
+            Bitmex::parse_server_time(&response).expect("in test"),
+            Utc.ymd(2022, 10, 18).and_hms_milli(10, 20, 30, 500)
+        );
+    }
 }
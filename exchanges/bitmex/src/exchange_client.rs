@@ -1,20 +1,23 @@
 use crate::bitmex::Bitmex;
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use mmb_core::exchanges::general::exchange::RequestResult;
 use mmb_core::exchanges::general::order::cancel::CancelOrderResult;
 use mmb_core::exchanges::general::order::create::CreateOrderResult;
 use mmb_core::exchanges::general::order::get_order_trades::OrderTrade;
 use mmb_core::exchanges::traits::{ExchangeClient, ExchangeError};
+use mmb_domain::candle::Candle;
 use mmb_domain::events::ExchangeBalancesAndPositions;
 use mmb_domain::exchanges::symbol::Symbol;
 use mmb_domain::market::CurrencyPair;
 use mmb_domain::order::fill::EventSourceType;
 use mmb_domain::order::pool::OrderRef;
 use mmb_domain::order::snapshot::{OrderCancelling, OrderInfo, Price};
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::position::{ActivePosition, ClosedPosition};
 use mmb_utils::DateTime;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[async_trait]
 impl ExchangeClient for Bitmex {
@@ -48,6 +51,14 @@ impl ExchangeClient for Bitmex {
         }
     }
 
+    async fn cancel_all_orders_after(&self, timeout: Duration) -> Result<()> {
+        self.do_cancel_all_orders_after(timeout)
+            .await
+            .with_context(|| format!("Failed to arm cancel all orders after {timeout:?}"))?;
+
+        Ok(())
+    }
+
     async fn get_open_orders(&self) -> Result<Vec<OrderInfo>> {
         let response = self.request_open_orders(None).await?;
 
@@ -106,4 +117,27 @@ impl ExchangeClient for Bitmex {
         let response = self.request_all_symbols().await?;
         self.parse_all_symbols(&response)
     }
+
+    async fn get_server_time(&self) -> Result<DateTime> {
+        let response = self.request_server_time().await?;
+        Bitmex::parse_server_time(&response)
+    }
+
+    async fn get_order_book_snapshot(
+        &self,
+        _currency_pair: CurrencyPair,
+    ) -> Result<OrderBookEvent> {
+        bail!(
+            "Order book events of Bitmex don't have update ids, so resynchronization isn't needed"
+        )
+    }
+
+    async fn get_candles(
+        &self,
+        _currency_pair: CurrencyPair,
+        _interval_secs: u32,
+        _limit: u32,
+    ) -> Result<Vec<Candle>> {
+        bail!("Requesting candles isn't supported by Bitmex")
+    }
 }
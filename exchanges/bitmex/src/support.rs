@@ -67,7 +67,7 @@ impl Support for Bitmex {
 
     fn on_connected(&self) -> Result<()> {
         // First of all we should auth to be able to subscribe to private messages
-        let expire_time = Bitmex::get_key_expire_time(60);
+        let expire_time = Bitmex::get_key_expire_time(&self.server_clock, 60);
         let signature =
             Bitmex::create_signature(&self.settings.secret_key, "GET/realtime", expire_time)
                 .to_str()
@@ -132,7 +132,7 @@ impl Support for Bitmex {
     }
 
     async fn create_ws_url(&self, role: WebSocketRole) -> Result<Url> {
-        Url::parse(self.hosts.web_socket_host)
+        Url::parse(&self.hosts.web_socket_host)
             .with_context(|| format!("Unable parse websocket {role:?} uri"))
     }
 
@@ -314,7 +314,7 @@ impl Bitmex {
             Utc::now(),
             self.settings.exchange_account_id,
             currency_pair,
-            String::default(),
+            None,
             update_type,
             Arc::new(order_book),
         );
@@ -1,6 +1,7 @@
 use crate::bitmex::common::{get_bitmex_credentials, get_timeout_manager};
 use anyhow::{bail, Result};
 use bitmex::bitmex::Bitmex;
+use core_tests::hosts::apply_hosts_from_env;
 use mmb_core::balance::manager::balance_manager::BalanceManager;
 use mmb_core::database::events::recorder::EventRecorder;
 use mmb_core::exchanges::exchange_blocker::ExchangeBlocker;
@@ -156,7 +157,7 @@ impl BitmexBuilder {
     }
 
     async fn try_new_with_settings(
-        settings: ExchangeSettings,
+        mut settings: ExchangeSettings,
         cancellation_token: CancellationToken,
         features: ExchangeFeatures,
         commission: Commission,
@@ -165,11 +166,18 @@ impl BitmexBuilder {
         let lifetime_manager = init_lifetime_manager();
         let (tx, rx) = broadcast::channel(10);
 
-        let bitmex = Box::new(Bitmex::new(
-            settings.clone(),
-            tx.clone(),
-            lifetime_manager.clone(),
-        ));
+        apply_hosts_from_env(&mut settings, "BITMEX");
+
+        let timeout_manager = get_timeout_manager(settings.exchange_account_id);
+        let bitmex = Box::new(
+            Bitmex::new(
+                settings.clone(),
+                tx.clone(),
+                lifetime_manager.clone(),
+                timeout_manager.server_clock(settings.exchange_account_id),
+            )
+            .expect("in test"),
+        );
 
         let hosts = bitmex.hosts.clone();
 
@@ -178,7 +186,6 @@ impl BitmexBuilder {
             .await
             .expect("Failure start EventRecorder");
 
-        let timeout_manager = get_timeout_manager(settings.exchange_account_id);
         let exchange = Exchange::new(
             settings.exchange_account_id,
             bitmex,
@@ -191,7 +198,8 @@ impl BitmexBuilder {
             Arc::downgrade(&exchange_blocker),
             commission,
             event_recorder,
-        );
+        )
+        .expect("in test");
         exchange.build_symbols(&settings.currency_pairs).await;
         exchange.connect_ws().await.with_expect(move || {
             format!(
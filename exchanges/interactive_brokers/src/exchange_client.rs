@@ -7,18 +7,21 @@ use mmb_core::exchanges::general::order::cancel::CancelOrderResult;
 use mmb_core::exchanges::general::order::create::CreateOrderResult;
 use mmb_core::exchanges::general::order::get_order_trades::OrderTrade;
 use mmb_core::exchanges::traits::{ExchangeClient, ExchangeError};
+use mmb_domain::candle::Candle;
 use mmb_domain::events::ExchangeBalancesAndPositions;
 use mmb_domain::exchanges::symbol::{Precision, Symbol};
 use mmb_domain::market::{CurrencyCode, CurrencyId, CurrencyPair, ExchangeErrorType};
 use mmb_domain::order::fill::EventSourceType;
 use mmb_domain::order::pool::OrderRef;
 use mmb_domain::order::snapshot::{OrderCancelling, OrderInfo, Price};
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::position::{ActivePosition, ClosedPosition};
 use mmb_utils::DateTime;
 use rust_decimal_macros::dec;
 use std::fs::File;
 use std::io::BufReader;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[async_trait]
 impl ExchangeClient for InteractiveBrokers {
@@ -67,6 +70,12 @@ impl ExchangeClient for InteractiveBrokers {
         Ok(())
     }
 
+    async fn cancel_all_orders_after(&self, _timeout: Duration) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "Cancel all orders after timeout isn't supported by Interactive Brokers"
+        ))
+    }
+
     async fn get_open_orders(&self) -> anyhow::Result<Vec<OrderInfo>> {
         self.get_open_orders_inner().await
     }
@@ -258,4 +267,30 @@ impl ExchangeClient for InteractiveBrokers {
 
         Ok(symbols)
     }
+
+    async fn get_server_time(&self) -> anyhow::Result<DateTime> {
+        Err(anyhow!(
+            "Requesting server time isn't supported by Interactive Brokers"
+        ))
+    }
+
+    async fn get_order_book_snapshot(
+        &self,
+        _currency_pair: CurrencyPair,
+    ) -> anyhow::Result<OrderBookEvent> {
+        Err(anyhow!(
+            "Requesting order book snapshot isn't supported by Interactive Brokers"
+        ))
+    }
+
+    async fn get_candles(
+        &self,
+        _currency_pair: CurrencyPair,
+        _interval_secs: u32,
+        _limit: u32,
+    ) -> anyhow::Result<Vec<Candle>> {
+        Err(anyhow!(
+            "Requesting candles isn't supported by Interactive Brokers"
+        ))
+    }
 }
@@ -1,4 +1,5 @@
 use crate::interactive_brokers::InteractiveBrokers;
+use anyhow::Result;
 use mmb_core::exchanges::general::features::{
     ExchangeFeatures, OpenOrdersType, OrderFeatures, OrderTradeOption, RestFillsFeatures,
     RestFillsType, WebSocketOptions,
@@ -24,10 +25,10 @@ impl ExchangeClientBuilder for InteractiveBrokersBuilder {
         _lifetime_manager: Arc<AppLifetimeManager>,
         _timeout_manager: Arc<TimeoutManager>,
         _orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult {
+    ) -> Result<ExchangeClientBuilderResult> {
         let empty_response_is_ok = false;
 
-        ExchangeClientBuilderResult {
+        Ok(ExchangeClientBuilderResult {
             client: Box::new(InteractiveBrokers::new()),
             features: ExchangeFeatures::new(
                 OpenOrdersType::AllCurrencyPair,
@@ -43,7 +44,7 @@ impl ExchangeClientBuilder for InteractiveBrokersBuilder {
                 AllowedEventSourceType::All,
                 AllowedEventSourceType::All,
             ),
-        }
+        })
     }
 
     /// TODO: Check if it is right
@@ -1,5 +1,5 @@
 use crate::serum::Serum;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use futures::future::join_all;
 use futures::try_join;
@@ -10,16 +10,19 @@ use solana_program::account_info::IntoAccountInfo;
 use solana_program::pubkey::Pubkey;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use mmb_core::exchanges::general::exchange::RequestResult;
 use mmb_core::exchanges::general::order::cancel::CancelOrderResult;
 use mmb_core::exchanges::general::order::create::CreateOrderResult;
 use mmb_core::exchanges::general::order::get_order_trades::OrderTrade;
 use mmb_core::exchanges::traits::{ExchangeClient, ExchangeError};
+use mmb_domain::candle::Candle;
 use mmb_domain::events::ExchangeBalancesAndPositions;
 use mmb_domain::exchanges::symbol::Symbol;
 use mmb_domain::market::{CurrencyCode, CurrencyPair};
 use mmb_domain::order::fill::EventSourceType;
+use mmb_domain::order_book::event::OrderBookEvent;
 use mmb_domain::order::pool::OrderRef;
 use mmb_domain::order::snapshot::{OrderCancelling, OrderInfo, Price};
 use mmb_domain::position::{ActivePosition, ClosedPosition};
@@ -51,6 +54,10 @@ impl ExchangeClient for Serum {
         self.cancel_all_orders_core(currency_pair).await
     }
 
+    async fn cancel_all_orders_after(&self, _timeout: Duration) -> Result<()> {
+        bail!("Cancel all orders after timeout isn't supported by Serum")
+    }
+
     async fn get_open_orders(&self) -> Result<Vec<OrderInfo>> {
         let currency_pairs = self.markets_data.read().keys().cloned().collect_vec();
 
@@ -171,4 +178,24 @@ impl ExchangeClient for Serum {
 
         symbols
     }
+
+    async fn get_server_time(&self) -> Result<DateTime> {
+        bail!("Requesting server time isn't supported by Serum")
+    }
+
+    async fn get_order_book_snapshot(
+        &self,
+        _currency_pair: CurrencyPair,
+    ) -> Result<OrderBookEvent> {
+        bail!("Order book events of Serum don't have update ids, so resynchronization isn't needed")
+    }
+
+    async fn get_candles(
+        &self,
+        _currency_pair: CurrencyPair,
+        _interval_secs: u32,
+        _limit: u32,
+    ) -> Result<Vec<Candle>> {
+        bail!("Requesting candles isn't supported by Serum")
+    }
 }
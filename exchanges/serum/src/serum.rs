@@ -936,11 +936,11 @@ impl ExchangeClientBuilder for SerumBuilder {
         lifetime_manager: Arc<AppLifetimeManager>,
         _timeout_manager: Arc<TimeoutManager>,
         orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult {
+    ) -> Result<ExchangeClientBuilderResult> {
         let exchange_account_id = exchange_settings.exchange_account_id;
         let empty_response_is_ok = false;
 
-        ExchangeClientBuilderResult {
+        Ok(ExchangeClientBuilderResult {
             client: Box::new(Serum::new(
                 exchange_account_id,
                 exchange_settings,
@@ -964,7 +964,7 @@ impl ExchangeClientBuilder for SerumBuilder {
                 AllowedEventSourceType::All,
                 AllowedEventSourceType::All,
             ),
-        }
+        })
     }
 
     fn get_timeout_arguments(&self) -> RequestTimeoutArguments {
@@ -186,7 +186,7 @@ impl Serum {
             Utc::now(),
             self.id,
             currency_pair,
-            "".to_string(),
+            None,
             EventType::Snapshot,
             Arc::new(OrderBookData::new(asks, bids)),
         );
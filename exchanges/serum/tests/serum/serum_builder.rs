@@ -136,7 +136,8 @@ impl SerumBuilder {
             Arc::downgrade(&exchange_blocker),
             commission,
             event_recorder,
-        );
+        )
+        .expect("in test");
         exchange.connect_ws().await?;
         exchange.build_symbols(&settings.currency_pairs).await;
 
@@ -159,12 +160,12 @@ impl ExchangeClientBuilder for ExchangeSerumBuilder {
         lifetime_manager: Arc<AppLifetimeManager>,
         _timeout_manager: Arc<TimeoutManager>,
         orders: Arc<OrdersPool>,
-    ) -> ExchangeClientBuilderResult {
+    ) -> Result<ExchangeClientBuilderResult> {
         let exchange_account_id = exchange_settings.exchange_account_id;
         let empty_response_is_ok = false;
 
         let network_type = get_network_type().expect("Get network type");
-        ExchangeClientBuilderResult {
+        Ok(ExchangeClientBuilderResult {
             client: Box::new(Serum::new(
                 exchange_account_id,
                 exchange_settings,
@@ -188,7 +189,7 @@ impl ExchangeClientBuilder for ExchangeSerumBuilder {
                 AllowedEventSourceType::All,
                 AllowedEventSourceType::All,
             ),
-        }
+        })
     }
 
     fn get_timeout_arguments(&self) -> RequestTimeoutArguments {
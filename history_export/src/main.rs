@@ -0,0 +1,132 @@
+#![deny(
+    non_ascii_idents,
+    non_shorthand_field_patterns,
+    no_mangle_generic_items,
+    overflowing_literals,
+    path_statements,
+    unused_allocation,
+    unused_comparisons,
+    unused_parens,
+    while_true,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use,
+    clippy::unwrap_used
+)]
+
+//! Exports trading history to CSV or Parquet files with stable schema for analysis in notebooks.
+//!
+//! Usage:
+//!   history_export (--database-url <url> | --fallback-dir <dir>) --records <kind>
+//!       --format <csv|parquet> --output <path> [--from <rfc3339 time>] [--to <rfc3339 time>]
+//!
+//! Kinds of records: fills, orders, transactions, balances, profit_loss_balance_changes.
+//! Time range filters by time of saving records, it's [from, to) and whole history by default.
+
+mod records;
+mod schema;
+mod source;
+mod writer;
+
+use crate::records::RecordKind;
+use crate::source::Source;
+use crate::writer::{write_csv, write_parquet, OutputFormat};
+use anyhow::{bail, Context, Result};
+use chrono::{TimeZone, Utc};
+use mmb_database::postgres_db::connection::PgPoolSettings;
+use mmb_database::storage::open_storage;
+use mmb_utils::DateTime;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage:
+  history_export (--database-url <url> | --fallback-dir <dir>) --records <kind>
+      --format <csv|parquet> --output <path> [--from <rfc3339 time>] [--to <rfc3339 time>]
+
+Kinds of records: fills, orders, transactions, balances, profit_loss_balance_changes";
+
+struct Args {
+    source: Source,
+    records: RecordKind,
+    format: OutputFormat,
+    output: PathBuf,
+    from: DateTime,
+    to: DateTime,
+}
+
+async fn parse_args(args: &[String]) -> Result<Args> {
+    let mut source = None;
+    let mut records = None;
+    let mut format = None;
+    let mut output = None;
+    let mut from = Utc.timestamp(0, 0);
+    let mut to = Utc::now();
+
+    for pair in args.chunks(2) {
+        let (name, value) = match pair {
+            [name, value] => (name.as_str(), value.as_str()),
+            _ => bail!(USAGE),
+        };
+        match name {
+            "--database-url" => {
+                let storage = open_storage(value, &PgPoolSettings::with_max_size(1))
+                    .await
+                    .context("connecting to database")?;
+                source = Some(Source::Database(storage));
+            }
+            "--fallback-dir" => source = Some(Source::FallbackFiles(value.into())),
+            "--records" => records = Some(value.parse()?),
+            "--format" => format = Some(value.parse()?),
+            "--output" => output = Some(value.into()),
+            "--from" => from = parse_time(value)?,
+            "--to" => to = parse_time(value)?,
+            _ => bail!("Unknown argument `{name}`\n{USAGE}"),
+        }
+    }
+
+    match (source, records, format, output) {
+        (Some(source), Some(records), Some(format), Some(output)) => Ok(Args {
+            source,
+            records,
+            format,
+            output,
+            from,
+            to,
+        }),
+        _ => bail!(USAGE),
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime> {
+    let time = chrono::DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("parsing time `{value}`"))?;
+    Ok(time.with_timezone(&Utc))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = parse_args(&args).await?;
+
+    let time_range = args.from..args.to;
+    let events = args
+        .source
+        .load_events(args.records.table_name(), &time_range)
+        .await
+        .context("loading events")?;
+    let rows = args.records.to_rows(events, &time_range)?;
+
+    let file = File::create(&args.output)
+        .with_context(|| format!("creating file {}", args.output.display()))?;
+    let columns = args.records.columns();
+    match args.format {
+        OutputFormat::Csv => write_csv(BufWriter::new(file), columns, &rows)?,
+        OutputFormat::Parquet => write_parquet(file, columns, &rows)?,
+    }
+
+    println!("Exported {} rows to {}", rows.len(), args.output.display());
+    Ok(())
+}
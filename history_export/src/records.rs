@@ -0,0 +1,487 @@
+use crate::schema::{column, Column, ColumnType, Row, Value};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use mmb_core::balance::changes::profit_loss_balance_change::ProfitLossBalanceChange;
+use mmb_core::balance::manager::balances::Balances;
+use mmb_database::postgres_db::events::{Event, TableName};
+use mmb_domain::order::pool::OrderRef;
+use mmb_domain::order::snapshot::OrderSnapshot;
+use mmb_utils::DateTime;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+use std::str::FromStr;
+use vis_robot_integration::transaction::TransactionSnapshot;
+
+/// Saved event with time of inserting to database or saving to postponed events file
+#[derive(Debug, Clone)]
+pub struct SourceEvent {
+    pub insert_time: DateTime,
+    pub json: JsonValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    /// Fills of orders received in the time range
+    Fills,
+    /// The latest snapshot of orders saved in the time range
+    Orders,
+    /// The latest revision of transactions saved in the time range
+    Transactions,
+    /// Exchange balances of all snapshots saved in the time range
+    Balances,
+    ProfitLossBalanceChanges,
+}
+
+impl FromStr for RecordKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Ok(match value {
+            "fills" => RecordKind::Fills,
+            "orders" => RecordKind::Orders,
+            "transactions" => RecordKind::Transactions,
+            "balances" => RecordKind::Balances,
+            "profit_loss_balance_changes" => RecordKind::ProfitLossBalanceChanges,
+            _ => bail!("Unknown kind of records `{value}`"),
+        })
+    }
+}
+
+const FILL_COLUMNS: &[Column] = &[
+    column("receive_time", ColumnType::Timestamp),
+    column("fill_id", ColumnType::Utf8),
+    column("client_order_fill_id", ColumnType::Utf8),
+    column("client_order_id", ColumnType::Utf8),
+    column("exchange_order_id", ColumnType::Utf8),
+    column("exchange_account_id", ColumnType::Utf8),
+    column("currency_pair", ColumnType::Utf8),
+    column("strategy_name", ColumnType::Utf8),
+    column("trade_id", ColumnType::Utf8),
+    column("fill_type", ColumnType::Utf8),
+    column("side", ColumnType::Utf8),
+    column("role", ColumnType::Utf8),
+    column("price", ColumnType::Decimal),
+    column("amount", ColumnType::Decimal),
+    column("cost", ColumnType::Decimal),
+    column("commission_currency_code", ColumnType::Utf8),
+    column("commission_amount", ColumnType::Decimal),
+    column("converted_commission_currency_code", ColumnType::Utf8),
+    column("converted_commission_amount", ColumnType::Decimal),
+    column("referral_reward_amount", ColumnType::Decimal),
+    column("is_incremental_fill", ColumnType::Boolean),
+];
+
+const ORDER_COLUMNS: &[Column] = &[
+    column("insert_time", ColumnType::Timestamp),
+    column("client_order_id", ColumnType::Utf8),
+    column("exchange_order_id", ColumnType::Utf8),
+    column("exchange_account_id", ColumnType::Utf8),
+    column("currency_pair", ColumnType::Utf8),
+    column("strategy_name", ColumnType::Utf8),
+    column("signal_id", ColumnType::Utf8),
+    column("order_type", ColumnType::Utf8),
+    column("execution_type", ColumnType::Utf8),
+    column("side", ColumnType::Utf8),
+    column("role", ColumnType::Utf8),
+    column("price", ColumnType::Decimal),
+    column("amount", ColumnType::Decimal),
+    column("filled_amount", ColumnType::Decimal),
+    column("status", ColumnType::Utf8),
+    column("init_time", ColumnType::Timestamp),
+    column("finished_time", ColumnType::Timestamp),
+];
+
+const TRANSACTION_COLUMNS: &[Column] = &[
+    column("insert_time", ColumnType::Timestamp),
+    column("transaction_id", ColumnType::Utf8),
+    column("revision", ColumnType::Int64),
+    column("creation_time", ColumnType::Timestamp),
+    column("exchange_id", ColumnType::Utf8),
+    column("currency_pair", ColumnType::Utf8),
+    column("strategy_name", ColumnType::Utf8),
+    column("side", ColumnType::Utf8),
+    column("price", ColumnType::Decimal),
+    column("amount", ColumnType::Decimal),
+    column("status", ColumnType::Utf8),
+    column("hedged", ColumnType::Decimal),
+    column("profit_loss_pct", ColumnType::Decimal),
+    column("trades_count", ColumnType::Int64),
+];
+
+const BALANCE_COLUMNS: &[Column] = &[
+    column("insert_time", ColumnType::Timestamp),
+    column("init_time", ColumnType::Timestamp),
+    column("exchange_account_id", ColumnType::Utf8),
+    column("currency_code", ColumnType::Utf8),
+    column("balance", ColumnType::Decimal),
+];
+
+const PROFIT_LOSS_BALANCE_CHANGE_COLUMNS: &[Column] = &[
+    column("change_date", ColumnType::Timestamp),
+    column("id", ColumnType::Utf8),
+    column("client_order_fill_id", ColumnType::Utf8),
+    column("service_name", ColumnType::Utf8),
+    column("service_configuration_key", ColumnType::Utf8),
+    column("exchange_id", ColumnType::Utf8),
+    column("exchange_account_id", ColumnType::Utf8),
+    column("currency_pair", ColumnType::Utf8),
+    column("currency_code", ColumnType::Utf8),
+    column("balance_change", ColumnType::Decimal),
+    column("usd_price", ColumnType::Decimal),
+    column("usd_balance_change", ColumnType::Decimal),
+];
+
+impl RecordKind {
+    pub fn table_name(self) -> TableName {
+        match self {
+            RecordKind::Fills | RecordKind::Orders => OrderRef::TABLE_NAME,
+            RecordKind::Transactions => <&mut TransactionSnapshot>::TABLE_NAME,
+            RecordKind::Balances => Balances::TABLE_NAME,
+            RecordKind::ProfitLossBalanceChanges => ProfitLossBalanceChange::TABLE_NAME,
+        }
+    }
+
+    /// Exported columns. Columns can be added to the end only to keep schema stable
+    pub fn columns(self) -> &'static [Column] {
+        match self {
+            RecordKind::Fills => FILL_COLUMNS,
+            RecordKind::Orders => ORDER_COLUMNS,
+            RecordKind::Transactions => TRANSACTION_COLUMNS,
+            RecordKind::Balances => BALANCE_COLUMNS,
+            RecordKind::ProfitLossBalanceChanges => PROFIT_LOSS_BALANCE_CHANGE_COLUMNS,
+        }
+    }
+
+    /// Convert events ordered by insert time to rows of exported columns
+    pub fn to_rows(
+        self,
+        events: Vec<SourceEvent>,
+        time_range: &Range<DateTime>,
+    ) -> Result<Vec<Row>> {
+        match self {
+            RecordKind::Fills => {
+                let orders = latest_by_key(parse_events::<OrderSnapshot>(events)?, |x| {
+                    x.header.client_order_id.clone()
+                });
+                Ok(orders
+                    .iter()
+                    .flat_map(|(_, order)| fill_rows(order, time_range))
+                    .sorted_by_key(|x| match x.first() {
+                        Some(Value::Timestamp(receive_time)) => Some(*receive_time),
+                        _ => None,
+                    })
+                    .collect())
+            }
+            RecordKind::Orders => {
+                let orders = latest_by_key(parse_events::<OrderSnapshot>(events)?, |x| {
+                    x.header.client_order_id.clone()
+                });
+                Ok(orders
+                    .iter()
+                    .map(|(insert_time, order)| order_row(*insert_time, order))
+                    .collect())
+            }
+            RecordKind::Transactions => {
+                let transactions =
+                    latest_by_key(parse_events::<TransactionSnapshot>(events)?, |x| {
+                        x.transaction_id()
+                    });
+                Ok(transactions
+                    .iter()
+                    .map(|(insert_time, transaction)| transaction_row(*insert_time, transaction))
+                    .collect())
+            }
+            RecordKind::Balances => Ok(parse_events::<Balances>(events)?
+                .iter()
+                .flat_map(|(insert_time, balances)| balance_rows(*insert_time, balances))
+                .collect()),
+            RecordKind::ProfitLossBalanceChanges => {
+                Ok(parse_events::<ProfitLossBalanceChange>(events)?
+                    .iter()
+                    .map(|(_, change)| profit_loss_balance_change_row(change))
+                    .collect())
+            }
+        }
+    }
+}
+
+fn parse_events<T: DeserializeOwned>(events: Vec<SourceEvent>) -> Result<Vec<(DateTime, T)>> {
+    events
+        .into_iter()
+        .map(|event| {
+            let record = serde_json::from_value(event.json).with_context(|| {
+                format!(
+                    "parsing {} saved at {}",
+                    std::any::type_name::<T>(),
+                    event.insert_time
+                )
+            })?;
+            Ok((event.insert_time, record))
+        })
+        .collect()
+}
+
+/// The latest record for each key in order of the first appearance of key
+fn latest_by_key<T, K: Hash + Eq>(
+    records: Vec<(DateTime, T)>,
+    get_key: impl Fn(&T) -> K,
+) -> Vec<(DateTime, T)> {
+    let mut indexes = HashMap::new();
+    let mut latest = Vec::<(DateTime, T)>::new();
+    for record in records {
+        match indexes.get(&get_key(&record.1)) {
+            Some(&index) => latest[index] = record,
+            None => {
+                indexes.insert(get_key(&record.1), latest.len());
+                latest.push(record);
+            }
+        }
+    }
+    latest
+}
+
+/// Name of enum variant as it's saved to database
+fn enum_name<T: Serialize>(value: &T) -> Value {
+    match serde_json::to_value(value) {
+        Ok(JsonValue::String(name)) => Value::Utf8(name),
+        Ok(JsonValue::Null) | Err(_) => Value::Null,
+        Ok(other) => Value::Utf8(other.to_string()),
+    }
+}
+
+fn fill_rows(order: &OrderSnapshot, time_range: &Range<DateTime>) -> Vec<Row> {
+    let header = &order.header;
+    order
+        .fills
+        .fills
+        .iter()
+        .filter(|fill| time_range.contains(&fill.receive_time()))
+        .map(|fill| {
+            vec![
+                fill.receive_time().into(),
+                fill.id().to_string().into(),
+                fill.client_order_fill_id()
+                    .as_ref()
+                    .map(|x| x.as_str())
+                    .into(),
+                header.client_order_id.as_str().into(),
+                order
+                    .props
+                    .exchange_order_id
+                    .as_ref()
+                    .map(|x| x.as_str())
+                    .into(),
+                header.exchange_account_id.to_string().into(),
+                header.currency_pair.to_string().into(),
+                header.strategy_name.as_str().into(),
+                fill.trade_id().map(|x| x.to_string()).into(),
+                enum_name(&fill.fill_type()),
+                enum_name(&fill.side().unwrap_or(header.side)),
+                enum_name(&fill.role()),
+                fill.price().into(),
+                fill.amount().into(),
+                fill.cost().into(),
+                fill.commission_currency_code().to_string().into(),
+                fill.commission_amount().into(),
+                fill.converted_commission_currency_code().to_string().into(),
+                fill.converted_commission_amount().into(),
+                fill.referral_reward_amount().into(),
+                fill.is_incremental_fill().into(),
+            ]
+        })
+        .collect()
+}
+
+fn order_row(insert_time: DateTime, order: &OrderSnapshot) -> Row {
+    let header = &order.header;
+    let props = &order.props;
+    vec![
+        insert_time.into(),
+        header.client_order_id.as_str().into(),
+        props.exchange_order_id.as_ref().map(|x| x.as_str()).into(),
+        header.exchange_account_id.to_string().into(),
+        header.currency_pair.to_string().into(),
+        header.strategy_name.as_str().into(),
+        header.signal_id.as_deref().into(),
+        enum_name(&header.order_type),
+        enum_name(&header.execution_type),
+        enum_name(&header.side),
+        enum_name(&props.role),
+        props.raw_price.into(),
+        header.amount.into(),
+        order.fills.filled_amount.into(),
+        enum_name(&props.status),
+        props.init_time.into(),
+        props.finished_time.into(),
+    ]
+}
+
+fn transaction_row(insert_time: DateTime, transaction: &TransactionSnapshot) -> Row {
+    vec![
+        insert_time.into(),
+        transaction.transaction_id().to_string().into(),
+        (transaction.revisions() as i64).into(),
+        transaction.creation_time().into(),
+        transaction.market_id.exchange_id.to_string().into(),
+        transaction.market_id.currency_pair.to_string().into(),
+        transaction.strategy_name.as_str().into(),
+        enum_name(&transaction.side),
+        transaction.price.into(),
+        transaction.amount.into(),
+        enum_name(&transaction.status),
+        transaction.hedged.into(),
+        transaction.profit_loss_pct.into(),
+        (transaction.trades.len() as i64).into(),
+    ]
+}
+
+fn balance_rows(insert_time: DateTime, balances: &Balances) -> Vec<Row> {
+    balances
+        .balances_by_exchange_id
+        .iter()
+        .flatten()
+        .sorted_by_key(|(exchange_account_id, _)| exchange_account_id.to_string())
+        .flat_map(|(exchange_account_id, balances_by_currency_code)| {
+            balances_by_currency_code
+                .iter()
+                .sorted_by_key(|(currency_code, _)| currency_code.as_str())
+                .map(move |(currency_code, balance)| {
+                    vec![
+                        insert_time.into(),
+                        balances.init_time.into(),
+                        exchange_account_id.to_string().into(),
+                        currency_code.as_str().into(),
+                        (*balance).into(),
+                    ]
+                })
+        })
+        .collect()
+}
+
+fn profit_loss_balance_change_row(change: &ProfitLossBalanceChange) -> Row {
+    vec![
+        change.change_date.into(),
+        change.id.to_string().into(),
+        change.client_order_fill_id.as_str().into(),
+        change.configuration_descriptor.service_name.as_str().into(),
+        change
+            .configuration_descriptor
+            .service_configuration_key
+            .as_str()
+            .into(),
+        change.exchange_id.to_string().into(),
+        change
+            .market_account_id
+            .exchange_account_id
+            .to_string()
+            .into(),
+        change.market_account_id.currency_pair.to_string().into(),
+        change.currency_code.as_str().into(),
+        change.balance_change.into(),
+        change.usd_price.into(),
+        change.usd_balance_change.into(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use mmb_domain::market::{CurrencyPair, ExchangeAccountId};
+    use mmb_domain::order::fill::{OrderFill, OrderFillType};
+    use mmb_domain::order::snapshot::{
+        ClientOrderId, OrderFillRole, OrderRole, OrderSide, OrderType,
+    };
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn order_event(insert_time: DateTime, fills_count: i64) -> SourceEvent {
+        let mut order = OrderSnapshot::with_params(
+            ClientOrderId::from("test_order"),
+            OrderType::Limit,
+            Some(OrderRole::Maker),
+            ExchangeAccountId::new("Binance", 0),
+            CurrencyPair::from_codes("btc".into(), "usdt".into()),
+            dec!(20000),
+            dec!(2),
+            OrderSide::Buy,
+            None,
+            "test_strategy",
+        );
+        for i in 0..fills_count {
+            order.add_fill(OrderFill::new(
+                Uuid::new_v4(),
+                None,
+                insert_time - Duration::minutes(fills_count - i),
+                OrderFillType::UserTrade,
+                None,
+                dec!(20000),
+                dec!(1),
+                dec!(20000),
+                OrderFillRole::Maker,
+                "usdt".into(),
+                dec!(0.1),
+                dec!(0),
+                "usdt".into(),
+                dec!(0.1),
+                dec!(0.1),
+                false,
+                None,
+                None,
+            ));
+        }
+
+        SourceEvent {
+            insert_time,
+            json: serde_json::to_value(&order).expect("in test"),
+        }
+    }
+
+    #[test]
+    fn fills_of_the_latest_order_snapshot() {
+        let now = Utc::now();
+        let events = vec![
+            order_event(now - Duration::minutes(1), 1),
+            order_event(now, 2),
+        ];
+        let time_range = now - Duration::minutes(1)..now;
+
+        let rows = RecordKind::Fills
+            .to_rows(events, &time_range)
+            .expect("in test");
+
+        // the first fill received before the time range
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.len(), RecordKind::Fills.columns().len());
+        assert_eq!(row[0], Value::Timestamp(now - Duration::minutes(1)));
+        assert_eq!(row[3], Value::Utf8("test_order".to_owned()));
+        assert_eq!(row[10], Value::Utf8("Buy".to_owned()));
+        assert_eq!(row[12], Value::Decimal(dec!(20000)));
+    }
+
+    #[test]
+    fn the_latest_order_snapshot() {
+        let now = Utc::now();
+        let events = vec![
+            order_event(now - Duration::minutes(1), 1),
+            order_event(now, 2),
+        ];
+
+        let rows = RecordKind::Orders
+            .to_rows(events, &(now - Duration::hours(1)..now))
+            .expect("in test");
+
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.len(), RecordKind::Orders.columns().len());
+        assert_eq!(row[0], Value::Timestamp(now));
+        assert_eq!(row[7], Value::Utf8("Limit".to_owned()));
+        assert_eq!(row[13], Value::Decimal(dec!(2)));
+        assert_eq!(row[16], Value::Null);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::records::SourceEvent;
+use anyhow::Result;
+use mmb_core::database::events::recorder::fallback::read_postponed_events;
+use mmb_database::postgres_db::events::TableNameRef;
+use mmb_database::storage::Storage;
+use mmb_utils::DateTime;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub enum Source {
+    Database(Arc<dyn Storage>),
+    /// Directory with postponed events files of `EventRecorder`
+    FallbackFiles(PathBuf),
+}
+
+impl Source {
+    /// Load events of table saved in the time range ordered by saving time
+    pub async fn load_events(
+        &self,
+        table_name: TableNameRef<'_>,
+        time_range: &Range<DateTime>,
+    ) -> Result<Vec<SourceEvent>> {
+        match self {
+            Source::Database(storage) => Ok(storage
+                .load_events_between(table_name, time_range.start, time_range.end)
+                .await?
+                .into_iter()
+                .map(|event| SourceEvent {
+                    insert_time: event.insert_time,
+                    json: event.json,
+                })
+                .collect()),
+            Source::FallbackFiles(postponed_events_dir) => {
+                Ok(read_postponed_events(postponed_events_dir)?
+                    .into_iter()
+                    .filter(|x| x.table_name == table_name)
+                    .filter(|x| time_range.contains(&x.saving_time))
+                    .flat_map(|x| {
+                        let saving_time = x.saving_time;
+                        x.events.into_iter().map(move |event| SourceEvent {
+                            insert_time: saving_time,
+                            json: event.json,
+                        })
+                    })
+                    .collect())
+            }
+        }
+    }
+}
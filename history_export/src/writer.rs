@@ -0,0 +1,238 @@
+use crate::schema::{Column, ColumnType, Row, Value};
+use anyhow::{bail, Result};
+use chrono::SecondsFormat;
+use parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::MilliSeconds;
+use parquet::schema::types::Type;
+use rust_decimal::prelude::ToPrimitive;
+use std::fs::File;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const PARQUET_ROW_GROUP_SIZE: usize = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Parquet,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Ok(match value {
+            "csv" => OutputFormat::Csv,
+            "parquet" => OutputFormat::Parquet,
+            _ => bail!("Unknown output format `{value}`"),
+        })
+    }
+}
+
+pub fn write_csv(writer: impl Write, columns: &[Column], rows: &[Row]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(columns.iter().map(|x| x.name))?;
+    for row in rows {
+        writer.write_record(row.iter().map(csv_value))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Utf8(value) => value.clone(),
+        Value::Int64(value) => value.to_string(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Decimal(value) => value.to_string(),
+        Value::Timestamp(value) => value.to_rfc3339_opts(SecondsFormat::Micros, true),
+    }
+}
+
+pub fn write_parquet(file: File, columns: &[Column], rows: &[Row]) -> Result<()> {
+    let mut fields = columns
+        .iter()
+        .map(|column| parquet_field(column).map(Arc::new))
+        .collect::<Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("schema")
+        .with_fields(&mut fields)
+        .build()?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))?;
+    for rows in rows.chunks(PARQUET_ROW_GROUP_SIZE) {
+        let mut row_group_writer = writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column_writer) = row_group_writer.next_column()? {
+            write_parquet_column(column_writer.untyped(), rows, index)?;
+            column_writer.close()?;
+            index += 1;
+        }
+        row_group_writer.close()?;
+    }
+    writer.close()?;
+
+    Ok(())
+}
+
+fn parquet_field(column: &Column) -> Result<Type> {
+    let (physical_type, logical_type) = match column.column_type {
+        ColumnType::Utf8 => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        ColumnType::Int64 => (PhysicalType::INT64, None),
+        ColumnType::Boolean => (PhysicalType::BOOLEAN, None),
+        ColumnType::Decimal => (PhysicalType::DOUBLE, None),
+        ColumnType::Timestamp => (
+            PhysicalType::INT64,
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit: TimeUnit::MILLIS(MilliSeconds::new()),
+            }),
+        ),
+    };
+
+    Ok(Type::primitive_type_builder(column.name, physical_type)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_logical_type(logical_type)
+        .build()?)
+}
+
+fn write_parquet_column(writer: &mut ColumnWriter, rows: &[Row], index: usize) -> Result<()> {
+    let values = rows.iter().map(|row| &row[index]);
+    // Definition level 0 means null value of optional column
+    let definition_levels = values
+        .clone()
+        .map(|value| i16::from(*value != Value::Null))
+        .collect::<Vec<_>>();
+
+    match writer {
+        ColumnWriter::ByteArrayColumnWriter(writer) => {
+            let values = values
+                .filter_map(|value| match value {
+                    Value::Utf8(value) => Some(ByteArray::from(value.as_str())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            writer.write_batch(&values, Some(&definition_levels), None)?;
+        }
+        ColumnWriter::Int64ColumnWriter(writer) => {
+            let values = values
+                .filter_map(|value| match value {
+                    Value::Int64(value) => Some(*value),
+                    Value::Timestamp(value) => Some(value.timestamp_millis()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            writer.write_batch(&values, Some(&definition_levels), None)?;
+        }
+        ColumnWriter::BoolColumnWriter(writer) => {
+            let values = values
+                .filter_map(|value| match value {
+                    Value::Boolean(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            writer.write_batch(&values, Some(&definition_levels), None)?;
+        }
+        ColumnWriter::DoubleColumnWriter(writer) => {
+            let values = values
+                .filter_map(|value| match value {
+                    Value::Decimal(value) => value.to_f64(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            writer.write_batch(&values, Some(&definition_levels), None)?;
+        }
+        _ => bail!("Unexpected parquet column writer for column {index}"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::column;
+    use chrono::{TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use rust_decimal_macros::dec;
+    use std::env;
+    use std::fs;
+
+    const COLUMNS: &[Column] = &[
+        column("time", ColumnType::Timestamp),
+        column("name", ColumnType::Utf8),
+        column("count", ColumnType::Int64),
+        column("price", ColumnType::Decimal),
+        column("is_maker", ColumnType::Boolean),
+    ];
+
+    fn rows() -> Vec<Row> {
+        vec![
+            vec![
+                Utc.timestamp_millis(1_666_000_000_123).into(),
+                "first".into(),
+                1.into(),
+                dec!(0.1).into(),
+                true.into(),
+            ],
+            vec![
+                Utc.timestamp_millis(1_666_000_001_000).into(),
+                Value::Null,
+                2.into(),
+                Value::Null,
+                false.into(),
+            ],
+        ]
+    }
+
+    #[test]
+    fn csv_with_header() {
+        let mut output = Vec::new();
+
+        write_csv(&mut output, COLUMNS, &rows()).expect("in test");
+
+        assert_eq!(
+            String::from_utf8(output).expect("in test"),
+            "time,name,count,price,is_maker
+2022-10-17T09:46:40.123000Z,first,1,0.1,true
+2022-10-17T09:46:41.000000Z,,2,,false
+"
+        );
+    }
+
+    #[test]
+    fn parquet_round_trip() {
+        let path = env::temp_dir().join("history_export_parquet_round_trip.parquet");
+        let file = File::create(&path).expect("in test");
+
+        write_parquet(file, COLUMNS, &rows()).expect("in test");
+
+        let reader = SerializedFileReader::try_from(path.as_path()).expect("in test");
+        let rows = reader
+            .get_row_iter(None)
+            .expect("in test")
+            .collect::<Vec<_>>();
+        fs::remove_file(&path).expect("in test");
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].get_timestamp_millis(0).expect("in test"),
+            1_666_000_000_123
+        );
+        assert_eq!(rows[0].get_string(1).expect("in test"), "first");
+        assert_eq!(rows[0].get_long(2).expect("in test"), 1);
+        assert_eq!(rows[0].get_double(3).expect("in test"), 0.1);
+        assert!(rows[0].get_bool(4).expect("in test"));
+        assert!(rows[1].get_string(1).is_err());
+        assert!(!rows[1].get_bool(4).expect("in test"));
+    }
+}
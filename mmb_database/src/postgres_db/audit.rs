@@ -0,0 +1,245 @@
+use crate::postgres_db::events::{DbEvent, TableNameRef};
+use crate::postgres_db::PgPool;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+/// Load audit events related to the order ordered by their creation time: events of the order
+/// itself, its fills and changes of the balance reservation used by the order
+pub async fn load_order_audit_events(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    client_order_id: &str,
+) -> Result<Vec<DbEvent>> {
+    let sql = format!(
+        "WITH order_ids AS (
+             SELECT DISTINCT
+                 json #>> '{{record, order, props, exchange_order_id}}' AS exchange_order_id,
+                 json #>> '{{record, order, header, reservation_id}}' AS reservation_id
+             FROM {table_name}
+             WHERE json #>> '{{record, order, header, client_order_id}}' = $1
+         )
+         SELECT id, insert_time, version, json
+         FROM {table_name}
+         WHERE json #>> '{{record, order, header, client_order_id}}' = $1
+            OR json #>> '{{record, fill, client_order_id}}' = $1
+            OR json #>> '{{record, fill, exchange_order_id}}' IN (SELECT exchange_order_id FROM order_ids)
+            OR json #>> '{{record, client_order_id}}' = $1
+            OR json #>> '{{record, reservation_id}}' IN (SELECT reservation_id FROM order_ids)
+         ORDER BY (json ->> 'time')::timestamptz, id"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&client_order_id])
+        .await
+        .with_context(|| format!("from `load_order_audit_events` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(DbEvent::from_row).collect())
+}
+
+/// Load audit events of `record_type` created after `from` and at or before `to`
+/// ordered by creation time. Creation time of event is taken from its `time` field, events
+/// created at the same time are ordered by insertion
+pub async fn load_audit_events_between(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    record_type: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<DbEvent>> {
+    // event can't be inserted before it is created, so `insert_time` limits scanned partitions
+    let sql = format!(
+        "SELECT id, insert_time, version, json
+         FROM {table_name}
+         WHERE insert_time > $2
+           AND json #>> '{{record, type}}' = $1
+           AND (json ->> 'time')::timestamptz > $2
+           AND (json ->> 'time')::timestamptz <= $3
+         ORDER BY (json ->> 'time')::timestamptz, id"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&record_type, &from, &to])
+        .await
+        .with_context(|| format!("from `load_audit_events_between` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(DbEvent::from_row).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::postgres_db::audit::{load_audit_events_between, load_order_audit_events};
+    use crate::postgres_db::events::{save_events_batch, InsertEvent};
+    use crate::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use chrono::{DateTime, Duration, Utc};
+    use serde_json::{json, Value as JsonValue};
+
+    const TABLE_NAME: &str = "audit_events_test";
+
+    async fn init_test() -> PgPoolMutex {
+        let pool_mutex = PgPoolMutex::create(&get_database_url(), 1).await;
+        let connection = pool_mutex.pool.get_connection_expected().await;
+        connection
+            .batch_execute(
+                &include_str!("./sql/create_or_truncate_table.sql")
+                    .replace("TABLE_NAME", TABLE_NAME),
+            )
+            .await
+            .expect("TRUNCATE audit_events_test");
+
+        drop(connection);
+        pool_mutex
+    }
+
+    fn order_event(client_order_id: &str, exchange_order_id: Option<&str>) -> JsonValue {
+        json!({
+            "record": {
+                "type": "Order",
+                "order": {
+                    "header": { "client_order_id": client_order_id, "reservation_id": 7 },
+                    "props": { "exchange_order_id": exchange_order_id },
+                },
+            },
+        })
+    }
+
+    fn fill_event(client_order_id: Option<&str>, exchange_order_id: &str) -> JsonValue {
+        json!({
+            "record": {
+                "type": "Fill",
+                "fill": { "client_order_id": client_order_id, "exchange_order_id": exchange_order_id },
+            },
+        })
+    }
+
+    fn reservation_event(reservation_id: u64) -> JsonValue {
+        json!({
+            "record": { "type": "Reservation", "reservation_id": reservation_id, "client_order_id": null },
+        })
+    }
+
+    fn timed_event(record_type: &str, time: DateTime<Utc>) -> JsonValue {
+        json!({
+            "time": time,
+            "record": { "type": record_type },
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_events_related_to_order() {
+        let pool = init_test().await;
+
+        // arrange
+        let related = [
+            reservation_event(7),
+            order_event("1", None),
+            order_event("1", Some("100")),
+            fill_event(None, "100"),
+            fill_event(Some("1"), "100"),
+        ];
+        let unrelated = [
+            reservation_event(8),
+            order_event("2", Some("200")),
+            fill_event(Some("2"), "200"),
+        ];
+        let items = related
+            .iter()
+            .chain(&unrelated)
+            .cloned()
+            .map(|json| InsertEvent { version: 1, json })
+            .collect::<Vec<_>>();
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_order_audit_events(&pool.pool, TABLE_NAME, "1")
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json)
+            .collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(loaded, related.to_vec());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_events_between_times() {
+        let pool = init_test().await;
+
+        // arrange
+        let from = Utc::now() - Duration::minutes(10);
+        let to = from + Duration::minutes(5);
+        let expected = [
+            timed_event("Reservation", from + Duration::minutes(1)),
+            timed_event("Reservation", to),
+        ];
+        let unexpected = [
+            timed_event("Reservation", from),
+            timed_event("Order", from + Duration::minutes(2)),
+            timed_event("Reservation", to + Duration::seconds(1)),
+        ];
+        let items = unexpected
+            .iter()
+            .chain(&expected)
+            .cloned()
+            .map(|json| InsertEvent { version: 1, json })
+            .collect::<Vec<_>>();
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_audit_events_between(&pool.pool, TABLE_NAME, "Reservation", from, to)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json)
+            .collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(loaded, expected.to_vec());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_events_between_times_ordered_by_creation_time() {
+        let pool = init_test().await;
+
+        // arrange
+        let from = Utc::now() - Duration::minutes(10);
+        let to = from + Duration::minutes(5);
+        let events = [
+            timed_event("Reservation", from + Duration::minutes(3)),
+            timed_event("Reservation", from + Duration::minutes(1)),
+            timed_event("Reservation", from + Duration::minutes(2)),
+        ];
+        let items = events
+            .iter()
+            .cloned()
+            .map(|json| InsertEvent { version: 1, json })
+            .collect::<Vec<_>>();
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_audit_events_between(&pool.pool, TABLE_NAME, "Reservation", from, to)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json)
+            .collect::<Vec<_>>();
+
+        // assert
+        let expected = [events[1].clone(), events[2].clone(), events[0].clone()];
+        assert_eq!(loaded, expected.to_vec());
+    }
+}
@@ -1,15 +1,13 @@
-use crate::postgres_db::PgPool;
+use crate::postgres_db::{PgConnection, PgPool};
 use anyhow::{bail, Context, Result};
-use bb8_postgres::bb8::PooledConnection;
-use bb8_postgres::PostgresConnectionManager;
 use chrono::{DateTime, Utc};
 use futures::pin_mut;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::fmt::{Display, Formatter};
 use tokio_postgres::binary_copy::BinaryCopyInWriter;
-use tokio_postgres::types::Type;
-use tokio_postgres::{NoTls, Statement};
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Row, Statement};
 pub type TableName = &'static str;
 pub type TableNameRef<'a> = &'a str;
 
@@ -45,6 +43,18 @@ pub struct DbEvent {
     pub json: JsonValue,
 }
 
+impl DbEvent {
+    /// Expects row with `id`, `insert_time`, `version` and `json` columns
+    pub fn from_row(row: &Row) -> Self {
+        DbEvent {
+            id: row.get::<_, i64>("id") as u64,
+            insert_time: row.get("insert_time"),
+            version: row.get::<_, Option<i32>>("version").unwrap_or(1),
+            json: row.get("json"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct InsertEvent {
     pub version: i32,
@@ -104,10 +114,7 @@ pub async fn save_events_one_by_one(
     async fn prepare_connection<'a>(
         pool: &'a PgPool,
         table_name: &'_ str,
-    ) -> Result<(
-        PooledConnection<'a, PostgresConnectionManager<NoTls>>,
-        Statement,
-    )> {
+    ) -> Result<(PgConnection<'a>, Statement)> {
         let sql = format!("INSERT INTO {table_name} (version, json) VALUES($1, $2)");
 
         let connection = pool
@@ -159,10 +166,182 @@ pub async fn save_events_one_by_one(
     (Ok(()), failed_events)
 }
 
+/// Load events inserted since `from` ordered by insert time
+pub async fn load_events_since(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    from: DateTime<Utc>,
+) -> Result<Vec<DbEvent>> {
+    let sql = format!(
+        "SELECT id, insert_time, version, json FROM {table_name} WHERE insert_time >= $1 ORDER BY insert_time"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&from])
+        .await
+        .with_context(|| format!("from `load_events_since` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(DbEvent::from_row).collect())
+}
+
+/// Load events inserted in range [`from`, `to`) ordered by insert time
+pub async fn load_events_between(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<DbEvent>> {
+    let sql = format!(
+        "SELECT id, insert_time, version, json FROM {table_name} WHERE insert_time >= $1 AND insert_time < $2 ORDER BY insert_time, id"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&from, &to])
+        .await
+        .with_context(|| format!("from `load_events_between` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(DbEvent::from_row).collect())
+}
+
+/// Load the latest inserted event
+pub async fn load_last_event(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+) -> Result<Option<DbEvent>> {
+    let sql =
+        format!("SELECT id, insert_time, version, json FROM {table_name} ORDER BY id DESC LIMIT 1");
+
+    let row = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query_opt(&sql, &[])
+        .await
+        .with_context(|| format!("from `load_last_event` on select from {table_name}"))?;
+
+    Ok(row.as_ref().map(DbEvent::from_row))
+}
+
+/// Load the latest event inserted at or before `time`
+pub async fn load_last_event_before(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    time: DateTime<Utc>,
+) -> Result<Option<DbEvent>> {
+    let sql = format!(
+        "SELECT id, insert_time, version, json FROM {table_name} WHERE insert_time <= $1 ORDER BY id DESC LIMIT 1"
+    );
+
+    let row = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query_opt(&sql, &[&time])
+        .await
+        .with_context(|| format!("from `load_last_event_before` on select from {table_name}"))?;
+
+    Ok(row.as_ref().map(DbEvent::from_row))
+}
+
+/// Condition on text value of json field located by `path`
+#[derive(Debug, Clone, Copy)]
+pub struct JsonFilter<'a> {
+    pub path: &'a [&'a str],
+    pub value: &'a str,
+}
+
+impl<'a> JsonFilter<'a> {
+    pub fn new(path: &'a [&'a str], value: &'a str) -> Self {
+        JsonFilter { path, value }
+    }
+}
+
+/// Load up to `limit` the latest events matching all `filters`, newest first
+fn filter_conditions(filters: &[JsonFilter<'_>]) -> String {
+    filters
+        .iter()
+        .enumerate()
+        .map(|(i, filter)| format!(" AND json #>> '{{{}}}' = ${}", filter.path.join(","), i + 1))
+        .collect()
+}
+
+async fn load_filtered_events(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    sql: &str,
+    filters: &[JsonFilter<'_>],
+) -> Result<Vec<DbEvent>> {
+    let values = filters.iter().map(|x| x.value).collect::<Vec<_>>();
+    let params = values
+        .iter()
+        .map(|x| x as &(dyn ToSql + Sync))
+        .collect::<Vec<_>>();
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(sql, &params)
+        .await
+        .with_context(|| format!("from `load_filtered_events` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(DbEvent::from_row).collect())
+}
+
+pub async fn load_last_events(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    filters: &[JsonFilter<'_>],
+    limit: u32,
+) -> Result<Vec<DbEvent>> {
+    let conditions = filter_conditions(filters);
+    let sql = format!(
+        "SELECT id, insert_time, version, json FROM {table_name} WHERE TRUE{conditions} ORDER BY insert_time DESC, id DESC LIMIT {limit}"
+    );
+
+    load_filtered_events(pool, table_name, &sql, filters).await
+}
+
+pub async fn load_last_events_by_key(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    filters: &[JsonFilter<'_>],
+    key_path: &[&str],
+    limit: u32,
+) -> Result<Vec<DbEvent>> {
+    let conditions = filter_conditions(filters);
+    let key = format!("json #>> '{{{}}}'", key_path.join(","));
+    let sql = format!(
+        "SELECT id, insert_time, version, json FROM {table_name} WHERE id IN (
+            SELECT DISTINCT ON ({key}) id FROM {table_name}
+            WHERE TRUE{conditions}
+            ORDER BY {key}, insert_time DESC, id DESC
+        ) ORDER BY {key} DESC LIMIT {limit}"
+    );
+
+    load_filtered_events(pool, table_name, &sql, filters).await
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::postgres_db::events::{save_events_batch, save_events_one_by_one, InsertEvent};
+    use crate::postgres_db::events::{
+        load_events_since, load_last_event, load_last_event_before, load_last_events,
+        load_last_events_by_key, save_events_batch, save_events_one_by_one, InsertEvent,
+        JsonFilter,
+    };
     use crate::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use chrono::{Duration, Utc};
     use serde_json::json;
 
     const TABLE_NAME: &str = "persons";
@@ -253,4 +432,143 @@ mod tests {
         assert_eq!(version, 1);
         assert_eq!(json, expected_json);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_events_since_insert_time() {
+        let pool = init_test().await;
+
+        // arrange
+        let items = [json!({ "name": "first" }), json!({ "name": "second" })]
+            .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_events_since(&pool.pool, TABLE_NAME, Utc::now() - Duration::hours(1))
+            .await
+            .expect("in test");
+        let loaded_in_future =
+            load_events_since(&pool.pool, TABLE_NAME, Utc::now() + Duration::hours(1))
+                .await
+                .expect("in test");
+
+        // assert
+        let loaded_jsons: Vec<_> = loaded.into_iter().map(|x| x.json).collect();
+        let expected_jsons: Vec<_> = items.into_iter().map(|x| x.json).collect();
+        assert_eq!(loaded_jsons, expected_jsons);
+        assert!(loaded_in_future.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_last_inserted_event() {
+        let pool = init_test().await;
+
+        // arrange
+        let not_found = load_last_event(&pool.pool, TABLE_NAME)
+            .await
+            .expect("in test");
+
+        let items = [json!({ "name": "first" }), json!({ "name": "second" })]
+            .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_last_event(&pool.pool, TABLE_NAME)
+            .await
+            .expect("in test")
+            .map(|x| x.json);
+
+        // assert
+        assert!(not_found.is_none());
+        assert_eq!(loaded, Some(json!({ "name": "second" })));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_last_event_before_time() {
+        let pool = init_test().await;
+
+        // arrange
+        let items = [json!({ "name": "first" }), json!({ "name": "second" })]
+            .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_last_event_before(&pool.pool, TABLE_NAME, Utc::now())
+            .await
+            .expect("in test")
+            .map(|x| x.json);
+        let loaded_in_past =
+            load_last_event_before(&pool.pool, TABLE_NAME, Utc::now() - Duration::hours(1))
+                .await
+                .expect("in test");
+
+        // assert
+        assert_eq!(loaded, Some(json!({ "name": "second" })));
+        assert!(loaded_in_past.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_last_events_by_filters() {
+        let pool = init_test().await;
+
+        // arrange
+        let items = [
+            json!({ "market": { "exchange_id": "Binance" }, "name": "first" }),
+            json!({ "market": { "exchange_id": "Bitmex" }, "name": "second" }),
+            json!({ "market": { "exchange_id": "Binance" }, "name": "third" }),
+            json!({ "market": { "exchange_id": "Binance" }, "name": "fourth" }),
+        ]
+        .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let filters = [JsonFilter::new(&["market", "exchange_id"], "Binance")];
+        let loaded = load_last_events(&pool.pool, TABLE_NAME, &filters, 2)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json["name"].clone())
+            .collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(loaded, vec![json!("fourth"), json!("third")]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_last_events_by_key_takes_latest_version() {
+        let pool = init_test().await;
+
+        // arrange
+        let items = [
+            json!({ "key": "1", "name": "first" }),
+            json!({ "key": "3", "name": "third" }),
+            json!({ "key": "2", "name": "second" }),
+            json!({ "key": "1", "name": "first amended" }),
+        ]
+        .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_last_events_by_key(&pool.pool, TABLE_NAME, &[], &["key"], 10)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json["name"].clone())
+            .collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(
+            loaded,
+            vec![json!("third"), json!("second"), json!("first amended")]
+        );
+    }
 }
@@ -1,12 +1,14 @@
+use crate::postgres_db::connection::PgTlsSettings;
 use anyhow::Context;
 use futures::future::{join_all, BoxFuture};
 use futures::FutureExt;
 use itertools::Itertools;
 use sqlx::error::BoxDynError;
 use sqlx::migrate::{Migration, MigrationSource, Migrator};
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
 use sqlx::{Pool, Postgres};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug)]
 struct MigrationSources {
@@ -37,24 +39,40 @@ impl<'s> MigrationSource<'s> for MigrationSources {
     }
 }
 
-/// Run migrations from list of specified sources
+/// Run migrations from list of specified sources.
+/// Note: client certificate from `tls` isn't supported for migrations connection
 pub async fn apply_migrations(
     database_url: &str,
+    tls: Option<&PgTlsSettings>,
     migration_sources: Vec<PathBuf>,
 ) -> anyhow::Result<()> {
     let migrator = Migrator::new(MigrationSources { migration_sources }).await?;
-    let connection_pool = create_connection_pool(database_url, 2).await?;
+    let connection_pool = create_connection_pool(database_url, tls, 2).await?;
     migrator.run(&connection_pool).await?;
     Ok(())
 }
 
 async fn create_connection_pool(
     database_url: &str,
+    tls: Option<&PgTlsSettings>,
     max_connections: u32,
 ) -> anyhow::Result<Pool<Postgres>> {
+    let mut options =
+        PgConnectOptions::from_str(database_url).context("building db connection options")?;
+    options = match tls {
+        Some(tls) => {
+            options = options.ssl_mode(PgSslMode::VerifyFull);
+            match &tls.ca_cert_path {
+                Some(ca_cert_path) => options.ssl_root_cert(ca_cert_path),
+                None => options,
+            }
+        }
+        None => options.ssl_mode(PgSslMode::Disable),
+    };
+
     PgPoolOptions::new()
         .max_connections(max_connections)
-        .connect(database_url)
+        .connect_with(options)
         .await
         .context("Unable to connect to DB")
 }
@@ -89,11 +107,11 @@ mod tests {
         .map(|p| sql_dir.clone().join(p))
         .collect_vec();
 
-        apply_migrations(&get_database_url(), sources)
+        apply_migrations(&get_database_url(), None, sources)
             .await
             .expect("failed apply_migrations in test");
 
-        let pool = create_connection_pool(&get_database_url(), 2)
+        let pool = create_connection_pool(&get_database_url(), None, 2)
             .await
             .expect("failed create_connection_pool in test");
 
@@ -123,7 +141,7 @@ mod tests {
     }
 
     async fn init_test() {
-        let pool = create_connection_pool(&get_database_url(), 2)
+        let pool = create_connection_pool(&get_database_url(), None, 2)
             .await
             .expect("failed create_connection_pool in test");
 
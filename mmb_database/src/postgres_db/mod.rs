@@ -1,43 +1,77 @@
+pub mod audit;
+pub mod connection;
 pub mod events;
 pub mod live_ranges;
 pub mod migrator;
+pub mod orders;
+pub mod partitions;
+pub mod price_sources;
+pub mod settings;
+pub mod storage;
 pub mod tests;
 
+use crate::postgres_db::connection::{
+    make_tls_connect, ConnectionCounters, ConnectionsCounter, CountingErrorSink, PgPoolMetrics,
+    PgPoolSettings,
+};
 use anyhow::{Context, Result};
 use bb8_postgres::bb8::{Pool, PooledConnection};
 use bb8_postgres::PostgresConnectionManager;
 use std::str::FromStr;
-use std::time::Duration;
-use tokio_postgres::{Config, NoTls};
+use std::sync::Arc;
+use tokio_postgres::Config;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+pub type PgConnectionManager = PostgresConnectionManager<MakeRustlsConnect>;
+pub type PgConnection<'a> = PooledConnection<'a, PgConnectionManager>;
 
 #[derive(Clone)]
-pub struct PgPool(Pool<PostgresConnectionManager<NoTls>>);
+pub struct PgPool(Pool<PgConnectionManager>, Arc<ConnectionCounters>);
 
 impl PgPool {
     pub async fn create(database_url: &str, max_size: u32) -> Result<PgPool> {
-        // TODO enable tls
-        let config = Config::from_str(database_url).context("building db connection config")?;
-        let pg_mgr = PostgresConnectionManager::new(config, NoTls);
+        Self::create_with_settings(database_url, &PgPoolSettings::with_max_size(max_size)).await
+    }
+
+    pub async fn create_with_settings(
+        database_url: &str,
+        settings: &PgPoolSettings,
+    ) -> Result<PgPool> {
+        let mut config = Config::from_str(database_url).context("building db connection config")?;
+        settings
+            .configure(&mut config, database_url)
+            .context("configuring db connection")?;
+        let tls = make_tls_connect(settings.tls.as_ref()).context("configuring tls")?;
+
+        let counters = Arc::new(ConnectionCounters::default());
+        let pg_mgr = PostgresConnectionManager::new(config, tls);
         let pool = Pool::builder()
-            .connection_timeout(Duration::from_secs(5))
-            .min_idle(Some(1))
-            .max_size(max_size)
+            .connection_timeout(settings.connection_timeout())
+            .idle_timeout(settings.idle_timeout())
+            .max_lifetime(settings.max_lifetime())
+            .min_idle(settings.min_idle)
+            .max_size(settings.max_size)
+            .error_sink(Box::new(CountingErrorSink(counters.clone())))
+            .connection_customizer(Box::new(ConnectionsCounter(counters.clone())))
             .build(pg_mgr)
             .await
             .context("building postgres connection pool")?;
 
-        Ok(PgPool(pool))
+        Ok(PgPool(pool, counters))
     }
 
     // compilation error without explicit lifetimes
     #[allow(clippy::needless_lifetimes)]
-    pub async fn get_connection_expected<'a>(
-        &'a self,
-    ) -> PooledConnection<'a, PostgresConnectionManager<NoTls>> {
+    pub async fn get_connection_expected<'a>(&'a self) -> PgConnection<'a> {
         self.0.get().await.expect("getting db connection from pool")
     }
 
     pub async fn is_connection_health(&self) -> bool {
         self.0.get().await.is_ok()
     }
+
+    pub fn metrics(&self) -> PgPoolMetrics {
+        let state = self.0.state();
+        self.1.metrics(state.connections, state.idle_connections)
+    }
 }
@@ -0,0 +1,104 @@
+use crate::postgres_db::events::TableNameRef;
+use crate::postgres_db::PgPool;
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+
+/// Load json of the latest saved state for each order of specified exchange accounts
+/// if this state isn't finished
+pub async fn load_unfinished_orders(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    exchange_account_ids: &[String],
+) -> Result<Vec<JsonValue>> {
+    let sql = format!(
+        "SELECT json FROM (
+             SELECT DISTINCT ON (json #>> '{{header, client_order_id}}') json
+             FROM {table_name}
+             WHERE json #>> '{{header, exchange_account_id}}' = ANY($1)
+             ORDER BY json #>> '{{header, client_order_id}}', id DESC
+         ) latest_orders
+         WHERE json #>> '{{props, finished_time}}' IS NULL"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&exchange_account_ids])
+        .await
+        .with_context(|| format!("from `load_unfinished_orders` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(|row| row.get("json")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::postgres_db::events::{save_events_batch, InsertEvent};
+    use crate::postgres_db::orders::load_unfinished_orders;
+    use crate::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use serde_json::{json, Value as JsonValue};
+
+    const TABLE_NAME: &str = "orders_test";
+
+    async fn init_test() -> PgPoolMutex {
+        let pool_mutex = PgPoolMutex::create(&get_database_url(), 1).await;
+        let connection = pool_mutex.pool.get_connection_expected().await;
+        connection
+            .batch_execute(
+                &include_str!("./sql/create_or_truncate_table.sql")
+                    .replace("TABLE_NAME", TABLE_NAME),
+            )
+            .await
+            .expect("TRUNCATE orders_test");
+
+        drop(connection);
+        pool_mutex
+    }
+
+    fn order(
+        client_order_id: &str,
+        exchange_account_id: &str,
+        status: &str,
+        finished_time: Option<&str>,
+    ) -> JsonValue {
+        json!({
+            "header": {
+                "client_order_id": client_order_id,
+                "exchange_account_id": exchange_account_id,
+            },
+            "props": {
+                "status": status,
+                "finished_time": finished_time,
+            },
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_latest_state_of_unfinished_orders() {
+        let pool = init_test().await;
+
+        // arrange
+        let created_order = order("1", "Binance_0", "Created", None);
+        let finished_time = Some("2022-10-10T12:00:00Z");
+        let items = [
+            order("1", "Binance_0", "Creating", None),
+            order("2", "Binance_0", "Creating", None),
+            order("3", "Bitmex_0", "Creating", None),
+            created_order.clone(),
+            order("2", "Binance_0", "Canceled", finished_time),
+        ]
+        .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded = load_unfinished_orders(&pool.pool, TABLE_NAME, &["Binance_0".to_string()])
+            .await
+            .expect("in test");
+
+        // assert
+        assert_eq!(loaded, vec![created_order]);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::postgres_db::events::TableNameRef;
+use crate::postgres_db::PgPool;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{pin_mut, StreamExt};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+const PARTITION_DAY_FORMAT: &str = "%Y%m%d";
+
+/// Daily partition of event table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    pub name: String,
+    pub day: NaiveDate,
+}
+
+pub fn partition_name(table_name: TableNameRef<'_>, day: NaiveDate) -> String {
+    format!("{table_name}_p{}", day.format(PARTITION_DAY_FORMAT))
+}
+
+/// Partition for rows which don't fit to any daily partition
+pub fn default_partition_name(table_name: TableNameRef<'_>) -> String {
+    format!("{table_name}_default")
+}
+
+fn day_start(day: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&day.and_hms(0, 0, 0))
+}
+
+/// Load daily partitions of table ordered by day
+pub async fn load_partitions(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+) -> Result<Vec<Partition>> {
+    let sql = "SELECT child.relname::text FROM pg_inherits
+                     JOIN pg_class parent ON pg_inherits.inhparent = parent.oid
+                     JOIN pg_class child ON pg_inherits.inhrelid = child.oid
+                     WHERE parent.relname = $1";
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(sql, &[&table_name])
+        .await
+        .with_context(|| format!("from `load_partitions` for {table_name}"))?;
+
+    let prefix = format!("{table_name}_p");
+    let mut partitions = rows
+        .iter()
+        .filter_map(|row| {
+            let name: String = row.get(0);
+            let day = NaiveDate::parse_from_str(name.strip_prefix(&prefix)?, PARTITION_DAY_FORMAT)
+                .ok()?;
+            Some(Partition { name, day })
+        })
+        .collect::<Vec<_>>();
+    partitions.sort_by_key(|x| x.day);
+
+    Ok(partitions)
+}
+
+/// Days (UTC) of rows stored in the default partition
+pub async fn load_default_partition_days(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+) -> Result<Vec<NaiveDate>> {
+    let sql = format!(
+        "SELECT DISTINCT (insert_time AT TIME ZONE 'UTC')::date FROM {} ORDER BY 1",
+        default_partition_name(table_name)
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[])
+        .await
+        .with_context(|| format!("from `load_default_partition_days` for {table_name}"))?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Create daily partition if it doesn't exist. Rows of the day stored in the default partition
+/// are moved to the created partition
+pub async fn create_partition(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    day: NaiveDate,
+) -> Result<()> {
+    let partition_name = partition_name(table_name, day);
+    let default_partition_name = default_partition_name(table_name);
+    let from = day_start(day);
+    let to = day_start(day + Duration::days(1));
+
+    let mut connection = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?;
+    let transaction = connection.transaction().await?;
+
+    let exists: bool = transaction
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&partition_name])
+        .await?
+        .get(0);
+    if exists {
+        return Ok(());
+    }
+
+    let has_default_partition: bool = transaction
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&default_partition_name],
+        )
+        .await?
+        .get(0);
+    let has_rows_in_default_partition = has_default_partition
+        && transaction
+            .query_one(
+                &format!("SELECT EXISTS (SELECT 1 FROM {default_partition_name} WHERE insert_time >= $1 AND insert_time < $2)"),
+                &[&from, &to],
+            )
+            .await?
+            .get::<_, bool>(0);
+
+    let create_sql = format!(
+        "CREATE TABLE {partition_name} PARTITION OF {table_name} FOR VALUES FROM ('{}') TO ('{}')",
+        from.to_rfc3339(),
+        to.to_rfc3339()
+    );
+
+    if has_rows_in_default_partition {
+        // Partition can't be created while the default partition contains its rows
+        let sql = format!(
+            "ALTER TABLE {table_name} DETACH PARTITION {default_partition_name};
+             {create_sql};
+             INSERT INTO {table_name} SELECT id, insert_time, version, json FROM {default_partition_name}
+                 WHERE insert_time >= '{from}' AND insert_time < '{to}';
+             DELETE FROM {default_partition_name} WHERE insert_time >= '{from}' AND insert_time < '{to}';
+             ALTER TABLE {table_name} ATTACH PARTITION {default_partition_name} DEFAULT;",
+            from = from.to_rfc3339(),
+            to = to.to_rfc3339(),
+        );
+        transaction.batch_execute(&sql).await
+    } else {
+        transaction.batch_execute(&create_sql).await
+    }
+    .with_context(|| format!("creating partition {partition_name}"))?;
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Export rows of partition to gzip compressed CSV file with header
+pub async fn export_partition(pool: &PgPool, partition_name: &str, path: &Path) -> Result<()> {
+    let sql = format!(
+        "COPY (SELECT id, insert_time, version, json FROM {partition_name} ORDER BY id) TO STDOUT WITH (FORMAT csv, HEADER)"
+    );
+
+    let connection = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?;
+    let stream = connection
+        .copy_out(&sql)
+        .await
+        .with_context(|| format!("from `export_partition` for {partition_name}"))?;
+    pin_mut!(stream);
+
+    // Write to temporary file to not leave incomplete archive on failure
+    let temp_path = path.with_extension("tmp");
+    let file = File::create(&temp_path)
+        .with_context(|| format!("creating file {}", temp_path.display()))?;
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    while let Some(bytes) = stream.next().await {
+        encoder.write_all(&bytes?)?;
+    }
+    encoder.finish()?.sync_all()?;
+
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("renaming file {}", temp_path.display()))?;
+
+    Ok(())
+}
+
+pub async fn drop_partition(pool: &PgPool, partition_name: &str) -> Result<()> {
+    pool.0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .batch_execute(&format!("DROP TABLE IF EXISTS {partition_name}"))
+        .await
+        .with_context(|| format!("dropping partition {partition_name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::postgres_db::partitions::{
+        create_partition, drop_partition, export_partition, load_default_partition_days,
+        load_partitions, Partition,
+    };
+    use crate::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use chrono::NaiveDate;
+    use flate2::read::GzDecoder;
+    use scopeguard::defer;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Read;
+
+    const TABLE_NAME: &str = "partitioned_events";
+
+    async fn init_test() -> PgPoolMutex {
+        let pool_mutex = PgPoolMutex::create(&get_database_url(), 1).await;
+        let connection = pool_mutex.pool.get_connection_expected().await;
+        connection
+            .batch_execute(
+                &include_str!("./sql/create_partitioned_table.sql")
+                    .replace("TABLE_NAME", TABLE_NAME),
+            )
+            .await
+            .expect("recreate partitioned table");
+        drop(connection);
+        pool_mutex
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn create_export_and_drop_partition() {
+        let pool_mutex = init_test().await;
+        let pool = &pool_mutex.pool;
+
+        let day = NaiveDate::from_ymd(2022, 10, 20);
+        let connection = pool.get_connection_expected().await;
+        connection
+            .batch_execute(&format!(
+                "INSERT INTO {TABLE_NAME} (insert_time, version, json) VALUES
+                    ('2022-10-20T10:00:00Z', 1, '{{\"price\":1}}'),
+                    ('2022-10-21T10:00:00Z', 1, '{{\"price\":2}}')"
+            ))
+            .await
+            .expect("in test");
+        drop(connection);
+
+        let default_days = load_default_partition_days(pool, TABLE_NAME)
+            .await
+            .expect("in test");
+        assert_eq!(default_days, vec![day, day.succ()]);
+
+        create_partition(pool, TABLE_NAME, day)
+            .await
+            .expect("in test");
+        // creation is idempotent
+        create_partition(pool, TABLE_NAME, day)
+            .await
+            .expect("in test");
+
+        let partitions = load_partitions(pool, TABLE_NAME).await.expect("in test");
+        let partition_name = format!("{TABLE_NAME}_p20221020");
+        assert_eq!(
+            partitions,
+            vec![Partition {
+                name: partition_name.clone(),
+                day
+            }]
+        );
+        let default_days = load_default_partition_days(pool, TABLE_NAME)
+            .await
+            .expect("in test");
+        assert_eq!(default_days, vec![day.succ()]);
+
+        let path = env::temp_dir().join(format!("{partition_name}.csv.gz"));
+        defer! { let _ = fs::remove_file(&path); }
+        export_partition(pool, &partition_name, &path)
+            .await
+            .expect("in test");
+
+        let mut csv = String::new();
+        GzDecoder::new(File::open(&path).expect("in test"))
+            .read_to_string(&mut csv)
+            .expect("in test");
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "id,insert_time,version,json");
+        assert!(lines[1].ends_with(r#"1,"{""price"": 1}""#), "{}", lines[1]);
+
+        drop_partition(pool, &partition_name)
+            .await
+            .expect("in test");
+        let partitions = load_partitions(pool, TABLE_NAME).await.expect("in test");
+        assert!(partitions.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::postgres_db::events::TableNameRef;
+use crate::postgres_db::PgPool;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+
+/// Load json of the latest price source for each market (`exchange_id` and `currency_pair`)
+/// with `init_time` at or before `time`
+pub async fn load_latest_price_sources(
+    pool: &PgPool,
+    table_name: TableNameRef<'_>,
+    time: DateTime<Utc>,
+) -> Result<Vec<JsonValue>> {
+    let sql = format!(
+        "SELECT DISTINCT ON (json ->> 'exchange_id', json ->> 'currency_pair') json
+         FROM {table_name}
+         WHERE (json ->> 'init_time')::timestamptz <= $1
+         ORDER BY json ->> 'exchange_id', json ->> 'currency_pair', (json ->> 'init_time')::timestamptz DESC"
+    );
+
+    let rows = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query(&sql, &[&time])
+        .await
+        .with_context(|| format!("from `load_latest_price_sources` on select from {table_name}"))?;
+
+    Ok(rows.iter().map(|row| row.get("json")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::postgres_db::events::{save_events_batch, InsertEvent};
+    use crate::postgres_db::price_sources::load_latest_price_sources;
+    use crate::postgres_db::tests::{get_database_url, PgPoolMutex};
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value as JsonValue};
+
+    const TABLE_NAME: &str = "price_sources_test";
+
+    async fn init_test() -> PgPoolMutex {
+        let pool_mutex = PgPoolMutex::create(&get_database_url(), 1).await;
+        let connection = pool_mutex.pool.get_connection_expected().await;
+        connection
+            .batch_execute(
+                &include_str!("./sql/create_or_truncate_table.sql")
+                    .replace("TABLE_NAME", TABLE_NAME),
+            )
+            .await
+            .expect("TRUNCATE price_sources_test");
+
+        drop(connection);
+        pool_mutex
+    }
+
+    fn price_source(exchange_id: &str, currency_pair: &str, minutes_ago: i64) -> JsonValue {
+        json!({
+            "init_time": Utc::now() - Duration::minutes(minutes_ago),
+            "exchange_id": exchange_id,
+            "currency_pair": currency_pair,
+            "bid": minutes_ago.to_string(),
+            "ask": minutes_ago.to_string(),
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn load_latest_price_source_for_each_market() {
+        let pool = init_test().await;
+
+        // arrange
+        let btc_usdt_latest = price_source("Binance", "btc/usdt", 10);
+        let eth_usdt_latest = price_source("Binance", "eth/usdt", 30);
+        let bitmex_btc_usdt_latest = price_source("Bitmex", "btc/usdt", 20);
+        let items = [
+            price_source("Binance", "btc/usdt", 30),
+            btc_usdt_latest.clone(),
+            // after requested time
+            price_source("Binance", "btc/usdt", 1),
+            eth_usdt_latest.clone(),
+            bitmex_btc_usdt_latest.clone(),
+            price_source("Bitmex", "btc/usdt", 40),
+        ]
+        .map(|json| InsertEvent { version: 1, json });
+        save_events_batch(&pool.pool, TABLE_NAME, &items)
+            .await
+            .expect("in test");
+
+        // act
+        let loaded =
+            load_latest_price_sources(&pool.pool, TABLE_NAME, Utc::now() - Duration::minutes(5))
+                .await
+                .expect("in test");
+
+        // assert
+        assert_eq!(
+            loaded,
+            vec![btc_usdt_latest, eth_usdt_latest, bitmex_btc_usdt_latest]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::postgres_db::PgPool;
+use anyhow::{Context, Result};
+
+pub async fn load_setting(pool: &PgPool, code: &str) -> Result<Option<String>> {
+    let row = pool
+        .0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .query_opt("SELECT content FROM settings WHERE code = $1", &[&code])
+        .await
+        .context("from `load_setting` on select from settings")?;
+
+    Ok(row.and_then(|row| row.get("content")))
+}
+
+pub async fn save_setting(pool: &PgPool, code: &str, content: &str) -> Result<()> {
+    let sql = "INSERT INTO settings(code, content)
+               VALUES ($1, $2)
+               ON CONFLICT (code) DO UPDATE SET content = $2";
+
+    pool.0
+        .get()
+        .await
+        .context("getting db connection from pool")?
+        .execute(sql, &[&code, &content])
+        .await
+        .context("from `save_setting` on insert into settings")?;
+
+    Ok(())
+}
@@ -0,0 +1,10 @@
+DROP TABLE IF EXISTS TABLE_NAME CASCADE;
+CREATE TABLE TABLE_NAME(
+    id bigint GENERATED BY DEFAULT AS IDENTITY,
+    insert_time timestamp WITH TIME ZONE NOT NULL DEFAULT now(),
+    version int,
+    json jsonb NOT NULL,
+    PRIMARY KEY (id, insert_time)
+) PARTITION BY RANGE (insert_time);
+
+CREATE TABLE TABLE_NAME_default PARTITION OF TABLE_NAME DEFAULT;
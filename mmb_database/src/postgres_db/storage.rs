@@ -0,0 +1,139 @@
+use crate::postgres_db::connection::{PgPoolSettings, PgTlsSettings};
+use crate::postgres_db::events::{
+    load_events_between, load_events_since, load_last_event, load_last_event_before,
+    load_last_events, load_last_events_by_key, save_events_batch, save_events_one_by_one, DbEvent,
+    InsertEvent, JsonFilter, TableNameRef,
+};
+use crate::postgres_db::live_ranges::save_live_range_to_db;
+use crate::postgres_db::migrator::apply_migrations;
+use crate::postgres_db::orders::load_unfinished_orders;
+use crate::postgres_db::settings::{load_setting, save_setting};
+use crate::postgres_db::PgPool;
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use std::path::PathBuf;
+
+pub struct PostgresStorage {
+    database_url: String,
+    tls: Option<PgTlsSettings>,
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub fn new(database_url: &str, tls: Option<PgTlsSettings>, pool: PgPool) -> Self {
+        PostgresStorage {
+            database_url: database_url.to_string(),
+            tls,
+            pool,
+        }
+    }
+
+    pub async fn create(database_url: &str, settings: &PgPoolSettings) -> Result<Self> {
+        let pool = PgPool::create_with_settings(database_url, settings)
+            .await
+            .with_context(|| format!("connecting to postgres with url: {database_url}"))?;
+
+        Ok(Self::new(database_url, settings.tls.clone(), pool))
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn apply_migrations(&self, migration_sources: Vec<PathBuf>) -> Result<()> {
+        apply_migrations(&self.database_url, self.tls.as_ref(), migration_sources).await
+    }
+
+    async fn is_connection_health(&self) -> bool {
+        self.pool.is_connection_health().await
+    }
+
+    async fn save_events_batch(&self, table_name: &str, events: &[InsertEvent]) -> Result<()> {
+        save_events_batch(&self.pool, table_name, events).await
+    }
+
+    async fn save_events_one_by_one(
+        &self,
+        table_name: &str,
+        events: Vec<InsertEvent>,
+    ) -> (Result<()>, Vec<InsertEvent>) {
+        save_events_one_by_one(&self.pool, table_name, events).await
+    }
+
+    async fn load_events_since(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>> {
+        load_events_since(&self.pool, table_name, from).await
+    }
+
+    async fn load_events_between(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>> {
+        load_events_between(&self.pool, table_name, from, to).await
+    }
+
+    async fn load_last_event(&self, table_name: TableNameRef<'_>) -> Result<Option<DbEvent>> {
+        load_last_event(&self.pool, table_name).await
+    }
+
+    async fn load_last_event_before(
+        &self,
+        table_name: TableNameRef<'_>,
+        time: DateTime<Utc>,
+    ) -> Result<Option<DbEvent>> {
+        load_last_event_before(&self.pool, table_name, time).await
+    }
+
+    async fn load_last_events(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>> {
+        load_last_events(&self.pool, table_name, filters, limit).await
+    }
+
+    async fn load_last_events_by_key(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        key_path: &[&str],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>> {
+        load_last_events_by_key(&self.pool, table_name, filters, key_path, limit).await
+    }
+
+    async fn load_unfinished_orders(
+        &self,
+        table_name: TableNameRef<'_>,
+        exchange_account_ids: &[String],
+    ) -> Result<Vec<JsonValue>> {
+        load_unfinished_orders(&self.pool, table_name, exchange_account_ids).await
+    }
+
+    async fn save_live_range(&self, session_id: &str) -> Result<()> {
+        save_live_range_to_db(&self.pool, session_id)
+            .await
+            .context("saving live range")?;
+        Ok(())
+    }
+
+    async fn load_setting(&self, code: &str) -> Result<Option<String>> {
+        load_setting(&self.pool, code).await
+    }
+
+    async fn save_setting(&self, code: &str, content: &str) -> Result<()> {
+        save_setting(&self.pool, code, content).await
+    }
+
+    fn postgres_pool(&self) -> Option<&PgPool> {
+        Some(&self.pool)
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use itertools::Itertools;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::sqlite_db::format_time;
+
+const UP_MIGRATION_SUFFIX: &str = ".up.sql";
+
+#[derive(Debug, PartialEq, Eq)]
+struct Migration {
+    version: i64,
+    description: String,
+    path: PathBuf,
+}
+
+/// Run not applied migrations from list of specified sources. Migrations have the same
+/// file names as for Postgres (`<version>_<description>.up.sql`) but should be written in SQLite dialect
+pub(crate) fn apply_migrations(
+    connection: &mut Connection,
+    migration_sources: &[PathBuf],
+) -> Result<()> {
+    let migrations = migration_sources
+        .iter()
+        .map(|source| resolve_migrations(source))
+        .flatten_ok()
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .sorted_by_key(|x| x.version)
+        .collect_vec();
+
+    for migration in migrations {
+        let applied = connection
+            .query_row(
+                "SELECT version FROM _migrations WHERE version = ?1",
+                params![migration.version],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .context("checking applied migration")?;
+        if applied.is_some() {
+            continue;
+        }
+
+        let sql = fs::read_to_string(&migration.path)
+            .with_context(|| format!("reading migration {}", migration.path.display()))?;
+
+        let transaction = connection.transaction()?;
+        transaction
+            .execute_batch(&sql)
+            .with_context(|| format!("applying migration {}", migration.path.display()))?;
+        transaction.execute(
+            "INSERT INTO _migrations(version, description, installed_on) VALUES (?1, ?2, ?3)",
+            params![
+                migration.version,
+                migration.description,
+                format_time(Utc::now())
+            ],
+        )?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+fn resolve_migrations(source: &Path) -> Result<Vec<Migration>> {
+    let entries = fs::read_dir(source).with_context(|| {
+        format!(
+            "failed resolving migrations by path: '{}'",
+            source.display()
+        )
+    })?;
+
+    let mut migrations = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let name = match file_name.strip_suffix(UP_MIGRATION_SUFFIX) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let (version, description) = match name.split_once('_') {
+            Some(parts) => parts,
+            None => {
+                bail!("Migration file name {file_name} should be `<version>_<description>.up.sql`")
+            }
+        };
+        let version = version
+            .parse()
+            .with_context(|| format!("parsing version of migration {file_name}"))?;
+
+        migrations.push(Migration {
+            version,
+            description: description.replace('_', " "),
+            path: path.clone(),
+        });
+    }
+
+    Ok(migrations)
+}
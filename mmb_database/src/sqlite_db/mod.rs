@@ -0,0 +1,736 @@
+pub mod migrator;
+
+use crate::postgres_db::events::{DbEvent, InsertEvent, JsonFilter, TableNameRef};
+use crate::storage::Storage;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const IN_MEMORY_PATH: &str = ":memory:";
+const SELECT_EVENT_COLUMNS: &str = "SELECT id, insert_time, version, json";
+
+/// Embedded file-based storage for deployments without database server.
+/// Event tables are created on first access, so migrations are needed for custom tables only
+#[derive(Clone)]
+pub struct SqliteStorage {
+    inner: Arc<Mutex<SqliteConnection>>,
+}
+
+struct SqliteConnection {
+    connection: Connection,
+    event_tables: HashSet<String>,
+}
+
+/// Row of event table before parsing of text columns
+type RawEvent = (i64, String, Option<i32>, String);
+
+impl SqliteStorage {
+    /// Open database file by `path` (it will be created if needed) or in-memory database for `:memory:`
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = if path == IN_MEMORY_PATH {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(path)
+        }
+        .with_context(|| format!("opening sqlite database {path}"))?;
+
+        connection
+            .execute_batch(include_str!("./sql/schema.sql"))
+            .context("creating sqlite schema")?;
+
+        Ok(SqliteStorage {
+            inner: Arc::new(Mutex::new(SqliteConnection {
+                connection,
+                event_tables: HashSet::new(),
+            })),
+        })
+    }
+
+    /// SQLite is synchronous, so queries are executed on blocking threads
+    async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SqliteConnection) -> Result<T> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(&mut inner.lock()))
+            .await
+            .context("joining sqlite query task")?
+    }
+
+    async fn load_events(
+        &self,
+        table_name: TableNameRef<'_>,
+        condition: String,
+        values: Vec<SqlValue>,
+    ) -> Result<Vec<DbEvent>> {
+        let table_name = table_name.to_string();
+        self.call(move |inner| {
+            inner.ensure_event_table(&table_name)?;
+
+            let sql = format!("{SELECT_EVENT_COLUMNS} FROM {table_name} {condition}");
+            let mut statement = inner.connection.prepare(&sql)?;
+            let raw_events = statement
+                .query_map(params_from_iter(values), read_raw_event)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .with_context(|| format!("selecting events from {table_name}"))?;
+
+            raw_events.into_iter().map(parse_event).collect()
+        })
+        .await
+    }
+
+    async fn load_event(
+        &self,
+        table_name: TableNameRef<'_>,
+        condition: String,
+        values: Vec<SqlValue>,
+    ) -> Result<Option<DbEvent>> {
+        let table_name = table_name.to_string();
+        self.call(move |inner| {
+            inner.ensure_event_table(&table_name)?;
+
+            let sql = format!("{SELECT_EVENT_COLUMNS} FROM {table_name} {condition}");
+            inner
+                .connection
+                .query_row(&sql, params_from_iter(values), read_raw_event)
+                .optional()
+                .with_context(|| format!("selecting event from {table_name}"))?
+                .map(parse_event)
+                .transpose()
+        })
+        .await
+    }
+}
+
+impl SqliteConnection {
+    fn ensure_event_table(&mut self, table_name: &str) -> Result<()> {
+        if self.event_tables.contains(table_name) {
+            return Ok(());
+        }
+
+        self.connection
+            .execute_batch(
+                &include_str!("./sql/create_event_table.sql").replace("TABLE_NAME", table_name),
+            )
+            .with_context(|| format!("creating event table {table_name}"))?;
+        self.event_tables.insert(table_name.to_string());
+
+        Ok(())
+    }
+
+    fn insert_event(&self, table_name: &str, event: &InsertEvent) -> Result<()> {
+        let inserted = self.connection.execute(
+            &format!("INSERT INTO {table_name} (insert_time, version, json) VALUES (?1, ?2, ?3)"),
+            params![
+                format_time(Utc::now()),
+                event.version,
+                event.json.to_string()
+            ],
+        )?;
+
+        if inserted != 1 {
+            bail!("Inserted {inserted} events, but should be 1. Event: {event}");
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn apply_migrations(&self, migration_sources: Vec<PathBuf>) -> Result<()> {
+        self.call(move |inner| {
+            migrator::apply_migrations(&mut inner.connection, &migration_sources)
+        })
+        .await
+    }
+
+    async fn is_connection_health(&self) -> bool {
+        self.call(|inner| {
+            inner
+                .connection
+                .query_row("SELECT 1", [], |row| row.get::<_, i64>(0))
+                .context("probing sqlite connection")
+        })
+        .await
+        .is_ok()
+    }
+
+    async fn save_events_batch(&self, table_name: &str, events: &[InsertEvent]) -> Result<()> {
+        let table_name = table_name.to_string();
+        let events = events.to_vec();
+        self.call(move |inner| {
+            inner.ensure_event_table(&table_name)?;
+
+            let transaction = inner.connection.transaction()?;
+            {
+                let sql = format!(
+                    "INSERT INTO {table_name} (insert_time, version, json) VALUES (?1, ?2, ?3)"
+                );
+                let mut statement = transaction.prepare(&sql)?;
+                let insert_time = format_time(Utc::now());
+                for event in &events {
+                    statement
+                        .execute(params![insert_time, event.version, event.json.to_string()])
+                        .context("from `save_events_batch` on insert event")?;
+                }
+            }
+            transaction
+                .commit()
+                .context("from `save_events_batch` on commit")
+        })
+        .await
+    }
+
+    async fn save_events_one_by_one(
+        &self,
+        table_name: &str,
+        events: Vec<InsertEvent>,
+    ) -> (Result<()>, Vec<InsertEvent>) {
+        let table_name = table_name.to_string();
+        // not saved events are returned from the query task to pass them to fallback
+        let result = self
+            .call(move |inner| {
+                if let Err(err) = inner.ensure_event_table(&table_name) {
+                    return Ok((Err(err), events));
+                }
+
+                let mut failed_events = vec![];
+                for event in events {
+                    if let Err(err) = inner.insert_event(&table_name, &event) {
+                        log::error!(
+                            "in `save_events_one_by_one` with error {err:?} failed saving event: {event}"
+                        );
+                        failed_events.push(event);
+                    }
+                }
+
+                Ok((Ok(()), failed_events))
+            })
+            .await;
+
+        match result {
+            Ok(result) => result,
+            // events are lost only if the query task panicked
+            Err(err) => (Err(err), vec![]),
+        }
+    }
+
+    async fn load_events_since(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>> {
+        self.load_events(
+            table_name,
+            "WHERE insert_time >= ?1 ORDER BY insert_time, id".to_string(),
+            vec![SqlValue::Text(format_time(from))],
+        )
+        .await
+    }
+
+    async fn load_events_between(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>> {
+        self.load_events(
+            table_name,
+            "WHERE insert_time >= ?1 AND insert_time < ?2 ORDER BY insert_time, id".to_string(),
+            vec![
+                SqlValue::Text(format_time(from)),
+                SqlValue::Text(format_time(to)),
+            ],
+        )
+        .await
+    }
+
+    async fn load_last_event(&self, table_name: TableNameRef<'_>) -> Result<Option<DbEvent>> {
+        self.load_event(table_name, "ORDER BY id DESC LIMIT 1".to_string(), vec![])
+            .await
+    }
+
+    async fn load_last_event_before(
+        &self,
+        table_name: TableNameRef<'_>,
+        time: DateTime<Utc>,
+    ) -> Result<Option<DbEvent>> {
+        self.load_event(
+            table_name,
+            "WHERE insert_time <= ?1 ORDER BY id DESC LIMIT 1".to_string(),
+            vec![SqlValue::Text(format_time(time))],
+        )
+        .await
+    }
+
+    async fn load_last_events(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>> {
+        let conditions = filter_conditions(filters);
+        self.load_events(
+            table_name,
+            format!("WHERE TRUE{conditions} ORDER BY insert_time DESC, id DESC LIMIT {limit}"),
+            filter_values(filters),
+        )
+        .await
+    }
+
+    async fn load_last_events_by_key(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        key_path: &[&str],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>> {
+        let conditions = filter_conditions(filters);
+        let key = format!(
+            "CAST(json_extract(json, '${}') AS TEXT)",
+            json_path(key_path)
+        );
+        self.load_events(
+            table_name,
+            format!(
+                "WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, ROW_NUMBER() OVER (PARTITION BY {key} ORDER BY insert_time DESC, id DESC) AS row_number
+                        FROM {table_name} WHERE TRUE{conditions}
+                    ) WHERE row_number = 1
+                ) ORDER BY {key} DESC LIMIT {limit}"
+            ),
+            filter_values(filters),
+        )
+        .await
+    }
+
+    async fn load_unfinished_orders(
+        &self,
+        table_name: TableNameRef<'_>,
+        exchange_account_ids: &[String],
+    ) -> Result<Vec<JsonValue>> {
+        let table_name = table_name.to_string();
+        let exchange_account_ids = serde_json::to_string(exchange_account_ids)
+            .context("serializing exchange account ids")?;
+        self.call(move |inner| {
+            inner.ensure_event_table(&table_name)?;
+
+            let sql = format!(
+                "SELECT json FROM {table_name}
+                 WHERE id IN (
+                     SELECT MAX(id) FROM {table_name}
+                     WHERE json_extract(json, '$.header.exchange_account_id')
+                         IN (SELECT value FROM json_each(?1))
+                     GROUP BY json_extract(json, '$.header.client_order_id')
+                 )
+                 AND json_extract(json, '$.props.finished_time') IS NULL
+                 ORDER BY id"
+            );
+            let mut statement = inner.connection.prepare(&sql)?;
+            let jsons = statement
+                .query_map([exchange_account_ids], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .with_context(|| format!("selecting unfinished orders from {table_name}"))?;
+
+            jsons
+                .iter()
+                .map(|json| serde_json::from_str(json).context("parsing saved order json"))
+                .collect()
+        })
+        .await
+    }
+
+    async fn save_live_range(&self, session_id: &str) -> Result<()> {
+        let session_id = session_id.to_string();
+        self.call(move |inner| {
+            let now = format_time(Utc::now());
+            inner
+                .connection
+                .execute(
+                    "INSERT INTO bot_sessions(id, datetime_from, datetime_to) VALUES (?1, ?2, ?2)
+                     ON CONFLICT (id) DO UPDATE
+                     SET datetime_to = ?2",
+                    params![session_id, now],
+                )
+                .context("saving live range")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_setting(&self, code: &str) -> Result<Option<String>> {
+        let code = code.to_string();
+        self.call(move |inner| {
+            let content = inner
+                .connection
+                .query_row(
+                    "SELECT content FROM settings WHERE code = ?1",
+                    params![code],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()
+                .context("from `load_setting` on select from settings")?;
+
+            Ok(content.flatten())
+        })
+        .await
+    }
+
+    async fn save_setting(&self, code: &str, content: &str) -> Result<()> {
+        let code = code.to_string();
+        let content = content.to_string();
+        self.call(move |inner| {
+            inner
+                .connection
+                .execute(
+                    "INSERT INTO settings(code, content) VALUES (?1, ?2)
+                     ON CONFLICT (code) DO UPDATE SET content = ?2",
+                    params![code, content],
+                )
+                .context("from `save_setting` on insert into settings")?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Fixed width UTC format, so stored times are compared correctly as text
+pub(crate) fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+}
+
+fn json_path(path: &[&str]) -> String {
+    path.iter().map(|x| format!(".\"{x}\"")).join("")
+}
+
+fn filter_conditions(filters: &[JsonFilter<'_>]) -> String {
+    filters
+        .iter()
+        .enumerate()
+        .map(|(i, filter)| {
+            format!(
+                " AND CAST(json_extract(json, '${}') AS TEXT) = ?{}",
+                json_path(filter.path),
+                i + 1
+            )
+        })
+        .collect()
+}
+
+fn filter_values(filters: &[JsonFilter<'_>]) -> Vec<SqlValue> {
+    filters
+        .iter()
+        .map(|x| SqlValue::Text(x.value.to_string()))
+        .collect()
+}
+
+fn read_raw_event(row: &Row) -> rusqlite::Result<RawEvent> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+}
+
+fn parse_event((id, insert_time, version, json): RawEvent) -> Result<DbEvent> {
+    Ok(DbEvent {
+        id: id as u64,
+        insert_time: DateTime::parse_from_rfc3339(&insert_time)
+            .with_context(|| format!("parsing insert_time {insert_time}"))?
+            .with_timezone(&Utc),
+        version: version.unwrap_or(1),
+        json: serde_json::from_str(&json).context("parsing event json")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+    use std::fs;
+
+    const TABLE_NAME: &str = "persons";
+
+    fn insert_events(jsons: impl IntoIterator<Item = serde_json::Value>) -> Vec<InsertEvent> {
+        jsons
+            .into_iter()
+            .map(|json| InsertEvent { version: 1, json })
+            .collect()
+    }
+
+    fn open_storage() -> SqliteStorage {
+        SqliteStorage::open(IN_MEMORY_PATH).expect("in test")
+    }
+
+    #[tokio::test]
+    async fn save_and_load_events() {
+        let storage = open_storage();
+        let events = insert_events([json!({ "name": "first" }), json!({ "name": "second" })]);
+
+        storage
+            .save_events_batch(TABLE_NAME, &events)
+            .await
+            .expect("in test");
+        let (result, failed_events) = storage
+            .save_events_one_by_one(TABLE_NAME, insert_events([json!({ "name": "third" })]))
+            .await;
+        result.expect("in test");
+        assert!(failed_events.is_empty());
+
+        let loaded = storage
+            .load_events_since(TABLE_NAME, Utc::now() - Duration::hours(1))
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            loaded,
+            vec![
+                json!({ "name": "first" }),
+                json!({ "name": "second" }),
+                json!({ "name": "third" })
+            ]
+        );
+
+        let now = Utc::now();
+        let loaded = storage
+            .load_events_between(
+                TABLE_NAME,
+                now - Duration::hours(1),
+                now + Duration::hours(1),
+            )
+            .await
+            .expect("in test");
+        assert_eq!(loaded.len(), 3);
+        let loaded = storage
+            .load_events_between(
+                TABLE_NAME,
+                now - Duration::hours(2),
+                now - Duration::hours(1),
+            )
+            .await
+            .expect("in test");
+        assert!(loaded.is_empty());
+
+        let last = storage
+            .load_last_event(TABLE_NAME)
+            .await
+            .expect("in test")
+            .map(|x| x.json);
+        assert_eq!(last, Some(json!({ "name": "third" })));
+
+        let in_past = storage
+            .load_last_event_before(TABLE_NAME, Utc::now() - Duration::hours(1))
+            .await
+            .expect("in test");
+        assert!(in_past.is_none());
+    }
+
+    #[tokio::test]
+    async fn return_not_saved_events_on_error() {
+        let storage = open_storage();
+        let events = insert_events([json!({ "name": "first" }), json!({ "name": "second" })]);
+
+        let (result, failed_events) = storage
+            .save_events_one_by_one("invalid table name", events.clone())
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(failed_events, events);
+    }
+
+    #[tokio::test]
+    async fn load_latest_state_of_unfinished_orders() {
+        let storage = open_storage();
+        let order =
+            |client_order_id: &str, exchange_account_id: &str, finished_time: Option<&str>| {
+                json!({
+                    "header": {
+                        "client_order_id": client_order_id,
+                        "exchange_account_id": exchange_account_id,
+                    },
+                    "props": { "finished_time": finished_time },
+                })
+            };
+        let created_order = order("1", "Binance_0", None);
+        let events = insert_events([
+            order("1", "Binance_0", None),
+            order("2", "Binance_0", None),
+            order("3", "Bitmex_0", None),
+            created_order.clone(),
+            order("2", "Binance_0", Some("2022-10-10T12:00:00Z")),
+        ]);
+        storage
+            .save_events_batch("orders", &events)
+            .await
+            .expect("in test");
+
+        let loaded = storage
+            .load_unfinished_orders("orders", &["Binance_0".to_string()])
+            .await
+            .expect("in test");
+
+        assert_eq!(loaded, vec![created_order]);
+    }
+
+    #[tokio::test]
+    async fn connection_health() {
+        assert!(open_storage().is_connection_health().await);
+    }
+
+    #[tokio::test]
+    async fn load_last_events_by_filters() {
+        let storage = open_storage();
+        let events = insert_events([
+            json!({ "market": { "exchange_id": "Binance" }, "id": 1 }),
+            json!({ "market": { "exchange_id": "Bitmex" }, "id": 2 }),
+            json!({ "market": { "exchange_id": "Binance" }, "id": 3 }),
+            json!({ "market": { "exchange_id": "Binance" }, "id": 4 }),
+        ]);
+        storage
+            .save_events_batch(TABLE_NAME, &events)
+            .await
+            .expect("in test");
+
+        let filters = [JsonFilter::new(&["market", "exchange_id"], "Binance")];
+        let loaded = storage
+            .load_last_events(TABLE_NAME, &filters, 2)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(loaded, vec![json!(4), json!(3)]);
+
+        let filters = [JsonFilter::new(&["id"], "2")];
+        let loaded = storage
+            .load_last_events(TABLE_NAME, &filters, 10)
+            .await
+            .expect("in test");
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn load_last_events_by_key_takes_latest_version() {
+        let storage = open_storage();
+        let events = insert_events([
+            json!({ "key": "1", "id": 1 }),
+            json!({ "key": "3", "id": 2 }),
+            json!({ "key": "2", "id": 3 }),
+            json!({ "key": "1", "id": 4 }),
+        ]);
+        storage
+            .save_events_batch(TABLE_NAME, &events)
+            .await
+            .expect("in test");
+
+        let loaded = storage
+            .load_last_events_by_key(TABLE_NAME, &[], &["key"], 2)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(loaded, vec![json!(2), json!(3)]);
+
+        let loaded = storage
+            .load_last_events_by_key(TABLE_NAME, &[], &["key"], 10)
+            .await
+            .expect("in test")
+            .into_iter()
+            .map(|x| x.json["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(loaded, vec![json!(2), json!(3), json!(4)]);
+    }
+
+    #[tokio::test]
+    async fn save_settings_and_live_ranges() {
+        let storage = open_storage();
+
+        assert_eq!(
+            storage
+                .load_setting("Configuration")
+                .await
+                .expect("in test"),
+            None
+        );
+        storage
+            .save_setting("Configuration", "first")
+            .await
+            .expect("in test");
+        storage
+            .save_setting("Configuration", "second")
+            .await
+            .expect("in test");
+        assert_eq!(
+            storage
+                .load_setting("Configuration")
+                .await
+                .expect("in test"),
+            Some("second".to_string())
+        );
+
+        storage.save_live_range("session").await.expect("in test");
+        storage.save_live_range("session").await.expect("in test");
+        let sessions_count = storage
+            .call(|inner| {
+                Ok(inner
+                    .connection
+                    .query_row("SELECT COUNT(*) FROM bot_sessions", [], |row| {
+                        row.get::<_, i64>(0)
+                    })?)
+            })
+            .await
+            .expect("in test");
+        assert_eq!(sessions_count, 1);
+    }
+
+    #[tokio::test]
+    async fn apply_migrations_once() {
+        let storage = open_storage();
+        let migrations_dir = std::env::temp_dir().join(format!(
+            "mmb_sqlite_migrations_{}",
+            Utc::now().timestamp_nanos()
+        ));
+        fs::create_dir_all(&migrations_dir).expect("in test");
+        fs::write(
+            migrations_dir.join("20220101000000_create_test_table.up.sql"),
+            "CREATE TABLE test_migration (id INTEGER);",
+        )
+        .expect("in test");
+        fs::write(
+            migrations_dir.join("20220101000000_create_test_table.down.sql"),
+            "DROP TABLE test_migration;",
+        )
+        .expect("in test");
+
+        for _ in 0..2 {
+            storage
+                .apply_migrations(vec![migrations_dir.clone()])
+                .await
+                .expect("in test");
+        }
+        fs::remove_dir_all(&migrations_dir).expect("in test");
+
+        let applied = storage
+            .call(|inner| {
+                Ok(inner
+                    .connection
+                    .query_row("SELECT COUNT(*) FROM _migrations", [], |row| {
+                        row.get::<_, i64>(0)
+                    })?)
+            })
+            .await
+            .expect("in test");
+        assert_eq!(applied, 1);
+    }
+}
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS bot_sessions (
+    id TEXT NOT NULL PRIMARY KEY,
+    datetime_from TEXT NOT NULL,
+    datetime_to TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+    code TEXT NOT NULL PRIMARY KEY,
+    content TEXT
+);
+
+CREATE TABLE IF NOT EXISTS _migrations (
+    version INTEGER NOT NULL PRIMARY KEY,
+    description TEXT NOT NULL,
+    installed_on TEXT NOT NULL
+);
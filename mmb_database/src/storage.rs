@@ -0,0 +1,108 @@
+use crate::postgres_db::connection::PgPoolSettings;
+use crate::postgres_db::events::{DbEvent, InsertEvent, JsonFilter, TableNameRef};
+use crate::postgres_db::storage::PostgresStorage;
+use crate::postgres_db::PgPool;
+use crate::sqlite_db::SqliteStorage;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const SQLITE_URL_PREFIX: &str = "sqlite:";
+
+/// Storage of events and service data of the trading engine
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Run migrations from list of specified sources
+    async fn apply_migrations(&self, migration_sources: Vec<PathBuf>) -> Result<()>;
+
+    async fn is_connection_health(&self) -> bool;
+
+    async fn save_events_batch(&self, table_name: &str, events: &[InsertEvent]) -> Result<()>;
+
+    /// Returns events which weren't saved
+    async fn save_events_one_by_one(
+        &self,
+        table_name: &str,
+        events: Vec<InsertEvent>,
+    ) -> (Result<()>, Vec<InsertEvent>);
+
+    /// Load events inserted since `from` ordered by insert time
+    async fn load_events_since(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>>;
+
+    /// Load events inserted in range [`from`, `to`) ordered by insert time
+    async fn load_events_between(
+        &self,
+        table_name: TableNameRef<'_>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DbEvent>>;
+
+    /// Load the latest inserted event
+    async fn load_last_event(&self, table_name: TableNameRef<'_>) -> Result<Option<DbEvent>>;
+
+    /// Load the latest event inserted at or before `time`
+    async fn load_last_event_before(
+        &self,
+        table_name: TableNameRef<'_>,
+        time: DateTime<Utc>,
+    ) -> Result<Option<DbEvent>>;
+
+    /// Load up to `limit` the latest events matching all `filters`, newest first
+    async fn load_last_events(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>>;
+
+    /// Load up to `limit` events matching all `filters` with the greatest values of json field by
+    /// `key_path`, greatest first. Only the latest inserted event is taken for each value of key.
+    /// Values are compared as text, so they should be ordered lexicographically
+    async fn load_last_events_by_key(
+        &self,
+        table_name: TableNameRef<'_>,
+        filters: &[JsonFilter<'_>],
+        key_path: &[&str],
+        limit: u32,
+    ) -> Result<Vec<DbEvent>>;
+
+    /// Load json of the latest saved state for each unfinished order of specified exchange accounts
+    async fn load_unfinished_orders(
+        &self,
+        table_name: TableNameRef<'_>,
+        exchange_account_ids: &[String],
+    ) -> Result<Vec<JsonValue>>;
+
+    /// Prolong live range of the engine session up to now
+    async fn save_live_range(&self, session_id: &str) -> Result<()>;
+
+    async fn load_setting(&self, code: &str) -> Result<Option<String>>;
+
+    async fn save_setting(&self, code: &str, content: &str) -> Result<()>;
+
+    /// Some queries (audit timeline, price sources, tables maintenance) are implemented for Postgres only
+    fn postgres_pool(&self) -> Option<&PgPool> {
+        None
+    }
+}
+
+/// Open storage by url: `sqlite:<path to file>` or `sqlite::memory:` for embedded SQLite
+/// database, Postgres connection string otherwise. Pool settings are used for Postgres only
+pub async fn open_storage(
+    database_url: &str,
+    pool_settings: &PgPoolSettings,
+) -> Result<Arc<dyn Storage>> {
+    match database_url.strip_prefix(SQLITE_URL_PREFIX) {
+        Some(path) => Ok(Arc::new(SqliteStorage::open(path)?)),
+        None => Ok(Arc::new(
+            PostgresStorage::create(database_url, pool_settings).await?,
+        )),
+    }
+}
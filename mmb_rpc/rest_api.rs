@@ -1,4 +1,4 @@
-use jsonrpc_core::{Error, Result};
+use jsonrpc_core::{BoxFuture, Error, Result};
 use jsonrpc_derive::rpc;
 
 #[cfg(unix)]
@@ -22,12 +22,24 @@ pub trait MmbRpc {
 
     #[rpc(name = "stats")]
     fn stats(&self) -> Result<String>;
+
+    #[rpc(name = "kill_switch")]
+    fn kill_switch(&self, reason: String) -> Result<String>;
+
+    #[rpc(name = "kill_switch_status")]
+    fn kill_switch_status(&self) -> Result<String>;
+
+    /// Responds after trading stopping started by kill switch is finished
+    #[rpc(name = "acknowledge_kill_switch")]
+    fn acknowledge_kill_switch(&self, operator: String) -> BoxFuture<Result<String>>;
 }
 
 pub enum ErrorCode {
     StopperIsNone = 1,
     UnableToSendSignal = 2,
     FailedToSaveNewConfig = 3,
+    KillSwitchAcknowledgeRejected = 4,
+    FailedToSerializeResponse = 5,
 }
 
 pub fn server_side_error(code: ErrorCode) -> Error {
@@ -35,6 +47,8 @@ pub fn server_side_error(code: ErrorCode) -> Error {
         ErrorCode::StopperIsNone => "Server stopper is none",
         ErrorCode::UnableToSendSignal => "Unable to send signal",
         ErrorCode::FailedToSaveNewConfig => "Failed to save new config",
+        ErrorCode::KillSwitchAcknowledgeRejected => "Kill switch acknowledge rejected",
+        ErrorCode::FailedToSerializeResponse => "Failed to serialize response",
     };
     log::error!("Rest API error: {}", reason);
     Error::new(jsonrpc_core::ErrorCode::ServerError(code as i64))
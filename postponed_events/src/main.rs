@@ -0,0 +1,136 @@
+#![deny(
+    non_ascii_idents,
+    non_shorthand_field_patterns,
+    no_mangle_generic_items,
+    overflowing_literals,
+    path_statements,
+    unused_allocation,
+    unused_comparisons,
+    unused_parens,
+    while_true,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use,
+    clippy::unwrap_used
+)]
+
+//! Inspects and replays files of events which `EventRecorder` postponed because they weren't saved
+//! to database.
+//!
+//! Usage:
+//!   postponed_events list <dir>
+//!   postponed_events show <dir> <file name>
+//!   postponed_events replay <dir> --database-url <url> [--file <file name>]
+//!
+//! Replayed files are removed. The trading engine replays postponed events by itself when database
+//! is available, so manual replaying is intended for directories of stopped engines.
+
+use anyhow::{bail, Context, Result};
+use mmb_core::database::events::recorder::fallback::{read_postponed_events, PostponedEvents};
+use mmb_database::postgres_db::connection::PgPoolSettings;
+use mmb_database::storage::open_storage;
+use std::fs;
+use std::path::Path;
+
+const USAGE: &str = "Usage:
+  postponed_events list <dir>
+  postponed_events show <dir> <file name>
+  postponed_events replay <dir> --database-url <url> [--file <file name>]";
+
+fn list(dir: &Path) -> Result<()> {
+    let postponed_events = read_postponed_events(dir)?;
+
+    let mut total_size = 0;
+    for PostponedEvents {
+        file_name,
+        saving_time,
+        table_name,
+        events,
+    } in &postponed_events
+    {
+        let size = fs::metadata(dir.join(file_name))
+            .with_context(|| format!("can't read metadata of file {file_name}"))?
+            .len();
+        total_size += size;
+        println!(
+            "{file_name}\t{saving_time}\t{table_name}\t{} events\t{size} bytes",
+            events.len()
+        );
+    }
+
+    println!(
+        "{} files, {total_size} bytes in total",
+        postponed_events.len()
+    );
+    Ok(())
+}
+
+fn show(dir: &Path, file_name: &str) -> Result<()> {
+    let postponed_events = read_postponed_events(dir)?
+        .into_iter()
+        .find(|x| x.file_name == file_name)
+        .with_context(|| format!("postponed events file {file_name} is not found"))?;
+
+    println!("table: {}", postponed_events.table_name);
+    println!("saving time: {}", postponed_events.saving_time);
+    for event in postponed_events.events {
+        println!("{}", serde_json::to_string(&event)?);
+    }
+    Ok(())
+}
+
+async fn replay(dir: &Path, database_url: &str, file_name: Option<&str>) -> Result<()> {
+    let storage = open_storage(database_url, &PgPoolSettings::with_max_size(1))
+        .await
+        .context("connecting to database")?;
+
+    let postponed_events = read_postponed_events(dir)?
+        .into_iter()
+        .filter(|x| file_name.map_or(true, |file_name| x.file_name == file_name))
+        .collect::<Vec<_>>();
+    if let (Some(file_name), true) = (file_name, postponed_events.is_empty()) {
+        bail!("postponed events file {file_name} is not found");
+    }
+
+    for PostponedEvents {
+        file_name,
+        table_name,
+        events,
+        ..
+    } in postponed_events
+    {
+        storage
+            .save_events_batch(&table_name, &events)
+            .await
+            .with_context(|| format!("failed replaying file {file_name}"))?;
+        fs::remove_file(dir.join(&file_name))
+            .with_context(|| format!("can't remove replayed file {file_name}"))?;
+
+        println!(
+            "Replayed {} events of table {table_name} from {file_name}",
+            events.len()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match args.as_slice() {
+        ["list", dir] => list(Path::new(dir)),
+        ["show", dir, file_name] => show(Path::new(dir), file_name),
+        ["replay", dir, "--database-url", database_url] => {
+            replay(Path::new(dir), database_url, None).await
+        }
+        ["replay", dir, "--database-url", database_url, "--file", file_name]
+        | ["replay", dir, "--file", file_name, "--database-url", database_url] => {
+            replay(Path::new(dir), database_url, Some(file_name)).await
+        }
+        _ => bail!(USAGE),
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use actix_web::web::Data;
+use paperclip::actix::{
+    api_v2_operation,
+    web::{self, Json},
+    Apiv2Schema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::services::data_provider::candles::{CandleRecord, CandlesService};
+
+#[derive(Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct CandlesQuery {
+    exchange_name: String,
+    currency_code_pair: String,
+    interval_secs: u32,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct CandlesGetResponse {
+    exchange_name: String,
+    currency_code_pair: String,
+    interval_secs: u32,
+    candles: Vec<CandleRecord>,
+}
+
+#[api_v2_operation(tags(Candles))]
+pub async fn get(
+    query: web::Query<CandlesQuery>,
+    candles_service: Data<Arc<CandlesService>>,
+) -> Result<Json<CandlesGetResponse>, AppError> {
+    let candles = candles_service
+        .list(
+            &query.exchange_name,
+            &query.currency_code_pair,
+            query.interval_secs,
+            query.limit.unwrap_or(500),
+        )
+        .await;
+    match candles {
+        Ok(candles) => {
+            let response = CandlesGetResponse {
+                exchange_name: query.exchange_name.clone(),
+                currency_code_pair: query.currency_code_pair.clone(),
+                interval_secs: query.interval_secs,
+                candles,
+            };
+            Ok(Json(response))
+        }
+        Err(e) => {
+            log::error!("list candles {e:?}");
+            Err(AppError::InternalServerError)
+        }
+    }
+}
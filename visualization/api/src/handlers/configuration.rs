@@ -29,13 +29,10 @@ pub async fn get(
         Ok(configuration) => Ok(Json(GetConfigResponse {
             config: configuration.content,
         })),
-        Err(e) => match e {
-            sqlx::Error::RowNotFound => Ok(Json(GetConfigResponse { config: None })),
-            _ => {
-                log::error!("Get config error: {:?}", e);
-                Err(AppError::InternalServerError)
-            }
-        },
+        Err(e) => {
+            log::error!("Get config error: {:?}", e);
+            Err(AppError::InternalServerError)
+        }
     }
 }
 
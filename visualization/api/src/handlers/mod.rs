@@ -1,4 +1,5 @@
 pub mod account;
+pub mod candles;
 pub mod configuration;
 pub mod explanation;
 pub mod liquidity;
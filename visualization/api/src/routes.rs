@@ -34,6 +34,7 @@ pub(crate) fn http_routes(app: &mut web::ServiceConfig) {
                     .route("/validate", post().to(handlers::configuration::validate)),
             )
             .route("/explanations", get().to(handlers::explanation::get))
+            .route("/candles", get().to(handlers::candles::get))
             .service(web::scope("/liquidity").route(
                 "/supported-exchanges",
                 get().to(handlers::liquidity::supported_exchanges),
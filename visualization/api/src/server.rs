@@ -7,9 +7,10 @@ use actix_web::middleware::Logger;
 use actix_web::web::Data;
 use actix_web::{App, HttpServer};
 use casbin::Enforcer;
+use mmb_database::postgres_db::connection::PgPoolSettings;
+use mmb_database::storage::open_storage;
 use paperclip::actix::OpenApiExt;
 use paperclip::v2::models::DefaultApiRaw;
-use sqlx::postgres::PgPoolOptions;
 use tokio::time;
 
 use crate::config::Market;
@@ -19,6 +20,7 @@ use crate::routes::{http_routes, ws_routes};
 use crate::services::account::AccountService;
 use crate::services::auth::AuthService;
 use crate::services::data_provider::balances::BalancesService;
+use crate::services::data_provider::candles::CandlesService;
 use crate::services::data_provider::explanation::ExplanationService;
 use crate::services::market_settings::MarketSettingsService;
 use crate::services::settings::SettingsService;
@@ -41,14 +43,12 @@ pub async fn start(
     refresh_data_interval_ms: u64,
 ) -> std::io::Result<()> {
     log::info!("Starting server at {address}");
-    let connection_pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
+    let storage = open_storage(database_url, &PgPoolSettings::default())
         .await
         .expect("Unable to connect to DB");
 
-    let liquidity_service = LiquidityService::new(connection_pool.clone());
-    let balances_service = BalancesService::new(connection_pool.clone());
+    let liquidity_service = LiquidityService::new(storage.clone());
+    let balances_service = BalancesService::new(storage.clone());
     let new_data_listener = NewDataListener::default().start();
     let error_listener = ErrorListener::default().start();
     let account_service = AccountService::default();
@@ -61,8 +61,9 @@ pub async fn start(
     let subscription_manager = SubscriptionManager::default().start();
     let auth_service = Arc::new(AuthService::new(enforcer));
     let market_settings_service = Arc::new(MarketSettingsService::from(markets));
-    let settings_service = Arc::new(SettingsService::new(connection_pool.clone()));
-    let explanation_service = Arc::new(ExplanationService::new(connection_pool));
+    let settings_service = Arc::new(SettingsService::new(storage.clone()));
+    let explanation_service = Arc::new(ExplanationService::new(storage.clone()));
+    let candles_service = Arc::new(CandlesService::new(storage));
 
     let data_provider = DataProvider::new(
         subscription_manager,
@@ -99,6 +100,7 @@ pub async fn start(
             .app_data(Data::new(market_settings_service.clone()))
             .app_data(Data::new(settings_service.clone()))
             .app_data(Data::new(explanation_service.clone()))
+            .app_data(Data::new(candles_service.clone()))
             .with_json_spec_at("/swagger-spec")
             .with_swagger_ui_at("/swagger-ui")
             .build()
@@ -1,18 +1,22 @@
 use std::collections::HashMap;
 
+use std::sync::Arc;
+
+use anyhow::Context;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres};
 
+use mmb_database::storage::Storage;
 use mmb_domain::order::snapshot::Amount;
 
-use crate::services::data_provider::model::EventRecord;
 use crate::types::{CurrencyCode, ExchangeId};
 
+const BALANCES_TABLE_NAME: &str = "balances";
+
 /// Data Provider for Balances
 #[derive(Clone)]
 pub struct BalancesService {
-    pool: Pool<Postgres>,
+    storage: Arc<dyn Storage>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -34,15 +38,16 @@ pub struct BalancesData {
 }
 
 impl BalancesService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
     }
 
-    pub async fn get_balances(&self) -> Result<BalancesData, sqlx::Error> {
-        let sql = include_str!("../sql/get_last_balances.sql");
-        let record = sqlx::query_as::<Postgres, EventRecord>(sql)
-            .fetch_one(&self.pool)
-            .await?;
+    pub async fn get_balances(&self) -> anyhow::Result<BalancesData> {
+        let record = self
+            .storage
+            .load_last_event(BALANCES_TABLE_NAME)
+            .await?
+            .context("There are no saved balances")?;
 
         let result: BalancesRecord = serde_json::from_value(record.json)
             .unwrap_or_else(|_| panic!("Incorrect database balances data. ID: {:?}", record.id));
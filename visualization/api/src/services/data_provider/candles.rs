@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use chrono::DateTime;
+use itertools::Itertools;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+use mmb_database::postgres_db::events::JsonFilter;
+use mmb_database::storage::Storage;
+use mmb_domain::candle::Candle;
+use mmb_domain::order::snapshot::{Amount, Price};
+
+use crate::types::{CurrencyPair, ExchangeId};
+
+const CANDLES_TABLE_NAME: &str = "candles";
+
+/// Data Provider for OHLCV candles
+#[derive(Clone)]
+pub struct CandlesService {
+    storage: Arc<dyn Storage>,
+}
+
+#[derive(Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all(deserialize = "snake_case", serialize = "camelCase"))]
+pub struct CandleRecord {
+    pub open_time: DateTime<chrono::Utc>,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Amount,
+    pub trades_count: u64,
+}
+
+impl From<Candle> for CandleRecord {
+    fn from(candle: Candle) -> Self {
+        CandleRecord {
+            open_time: candle.open_time,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            trades_count: candle.trades_count,
+        }
+    }
+}
+
+impl CandlesService {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    pub async fn list(
+        &self,
+        exchange_id: &ExchangeId,
+        currency_pair: &CurrencyPair,
+        interval_secs: u32,
+        limit: u32,
+    ) -> anyhow::Result<Vec<CandleRecord>> {
+        let interval_secs = interval_secs.to_string();
+        let filters = [
+            JsonFilter::new(&["market_id", "exchange_id"], exchange_id),
+            JsonFilter::new(&["market_id", "currency_pair"], currency_pair),
+            JsonFilter::new(&["interval_secs"], &interval_secs),
+        ];
+        // Candles amended by late trades are saved again, so only the latest version is taken
+        let records = self
+            .storage
+            .load_last_events_by_key(CANDLES_TABLE_NAME, &filters, &["open_time"], limit)
+            .await?;
+
+        // Candles are shown in chronological order
+        let list = records
+            .into_iter()
+            .rev()
+            .map(|it| {
+                let candle: Candle = serde_json::from_value(it.json).unwrap_or_else(|_| {
+                    panic!("Incorrect database candle json data. ID: {:?}", it.id)
+                });
+                candle.into()
+            })
+            .collect_vec();
+        Ok(list)
+    }
+}
@@ -1,18 +1,22 @@
+use std::sync::Arc;
+
 use chrono::DateTime;
 use itertools::Itertools;
 use paperclip::actix::Apiv2Schema;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres};
 
+use mmb_database::postgres_db::events::JsonFilter;
+use mmb_database::storage::Storage;
 use mmb_domain::order::snapshot::{Amount, Price};
 
-use crate::services::data_provider::model::EventTimedRecord;
 use crate::types::{CurrencyPair, ExchangeId};
 
+const EXPLANATIONS_TABLE_NAME: &str = "disposition_explanations";
+
 /// Data Provider for Explanations
 #[derive(Clone)]
 pub struct ExplanationService {
-    pool: Pool<Postgres>,
+    storage: Arc<dyn Storage>,
 }
 
 #[derive(Serialize, Deserialize, Apiv2Schema)]
@@ -39,26 +43,29 @@ pub struct Explanation {
 }
 
 impl ExplanationService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
     }
 
     pub async fn list(
         &self,
         exchange_id: &ExchangeId,
         currency_pair: &CurrencyPair,
-        limit: i32,
+        limit: u32,
     ) -> anyhow::Result<Vec<Explanation>> {
-        let sql = include_str!("../sql/get_explanations.sql");
-        let record = sqlx::query_as::<Postgres, EventTimedRecord>(sql)
-            .bind(exchange_id)
-            .bind(currency_pair)
-            .bind(limit)
-            .fetch_all(&self.pool)
+        let filters = [
+            JsonFilter::new(&["exchange_id"], exchange_id),
+            JsonFilter::new(&["currency_pair"], currency_pair),
+        ];
+        let records = self
+            .storage
+            .load_last_events(EXPLANATIONS_TABLE_NAME, &filters, limit)
             .await?;
 
-        let list = record
+        // Explanations are shown in chronological order
+        let list = records
             .into_iter()
+            .rev()
             .map(|it| {
                 let record: ExplanationRecord =
                     serde_json::from_value(it.json).unwrap_or_else(|_| {
@@ -66,7 +73,7 @@ impl ExplanationService {
                     });
 
                 Explanation {
-                    id: it.id,
+                    id: it.id as i64,
                     date_time: it.insert_time,
                     price_levels: record.set,
                 }
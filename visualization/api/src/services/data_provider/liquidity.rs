@@ -1,16 +1,22 @@
+use std::sync::Arc;
+
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres};
 
+use mmb_database::postgres_db::events::JsonFilter;
+use mmb_database::storage::Storage;
 use mmb_domain::order::snapshot::{Amount, Price};
 
-use crate::services::data_provider::model::EventRecord;
 use crate::types::{CurrencyPair, ExchangeId};
 use serde_aux::prelude::deserialize_number_from_string;
 
+const ORDER_BOOKS_TABLE_NAME: &str = "liquidity_order_books";
+const TRANSACTIONS_TABLE_NAME: &str = "transactions";
+
 /// Data Provider for Liquidity
 #[derive(Clone)]
 pub struct LiquidityService {
-    pool: Pool<Postgres>,
+    storage: Arc<dyn Storage>,
 }
 
 #[derive(Clone)]
@@ -111,16 +117,16 @@ pub enum TransactionTradeSide {
 }
 
 impl LiquidityService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
     }
 
     pub async fn get_liquidity_data(
         &self,
         exchange_id: &ExchangeId,
         currency_pair: &CurrencyPair,
-        transaction_limit: i32,
-    ) -> Result<LiquidityData, sqlx::Error> {
+        transaction_limit: u32,
+    ) -> anyhow::Result<LiquidityData> {
         let order_book = self.get_order_book(exchange_id, currency_pair).await?;
 
         let transactions = self
@@ -140,13 +146,20 @@ impl LiquidityService {
         &self,
         exchange_id: &ExchangeId,
         currency_pair: &CurrencyPair,
-    ) -> Result<OrderBookRecord, sqlx::Error> {
-        let sql = include_str!("../sql/get_order_book.sql");
-        let record = sqlx::query_as::<Postgres, EventRecord>(sql)
-            .bind(exchange_id)
-            .bind(currency_pair)
-            .fetch_one(&self.pool)
-            .await?;
+    ) -> anyhow::Result<OrderBookRecord> {
+        let filters = [
+            JsonFilter::new(&["exchange_id"], exchange_id),
+            JsonFilter::new(&["currency_pair"], currency_pair),
+        ];
+        let record = self
+            .storage
+            .load_last_events(ORDER_BOOKS_TABLE_NAME, &filters, 1)
+            .await?
+            .into_iter()
+            .next()
+            .with_context(|| {
+                format!("There is no saved order book for {exchange_id} {currency_pair}")
+            })?;
 
         let result: OrderBookRecord = serde_json::from_value(record.json)
             .unwrap_or_else(|_| panic!("Incorrect database order book data. ID: {:?}", record.id));
@@ -157,14 +170,15 @@ impl LiquidityService {
         &self,
         exchange_id: &ExchangeId,
         currency_pair: &CurrencyPair,
-        limit: i32,
-    ) -> Result<Vec<TransactionRecord>, sqlx::Error> {
-        let sql = include_str!("../sql/get_transactions.sql");
-        let records = sqlx::query_as::<Postgres, EventRecord>(sql)
-            .bind(exchange_id)
-            .bind(currency_pair)
-            .bind(limit)
-            .fetch_all(&self.pool)
+        limit: u32,
+    ) -> anyhow::Result<Vec<TransactionRecord>> {
+        let filters = [
+            JsonFilter::new(&["market_id", "exchange_id"], exchange_id),
+            JsonFilter::new(&["market_id", "currency_pair"], currency_pair),
+        ];
+        let records = self
+            .storage
+            .load_last_events(TRANSACTIONS_TABLE_NAME, &filters, limit)
             .await?;
 
         Ok(records
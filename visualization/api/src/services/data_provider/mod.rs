@@ -1,4 +1,4 @@
 pub mod balances;
+pub mod candles;
 pub mod explanation;
 pub mod liquidity;
-pub(crate) mod model;
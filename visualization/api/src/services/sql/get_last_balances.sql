@@ -1,3 +0,0 @@
-select * from balances b
-order by b.insert_time desc, id desc
-limit 1
\ No newline at end of file
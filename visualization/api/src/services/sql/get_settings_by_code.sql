@@ -1,3 +0,0 @@
-SELECT content
-FROM settings
-WHERE code = $1
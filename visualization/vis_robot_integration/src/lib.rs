@@ -17,7 +17,7 @@
 )]
 
 mod liquidity_order_book;
-mod transaction;
+pub mod transaction;
 
 use crate::transaction::{
     transaction_service, TransactionSnapshot, TransactionStatus, TransactionTrade,